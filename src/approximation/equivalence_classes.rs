@@ -55,20 +55,18 @@ where
     A: Eq + Hash,
     B: Clone + Eq + Hash,
 {
-    pub fn new(map: HashMap<B, HashSet<A>>, default: B) -> Self {
-        match Self::new_safe(map, default) {
-            Ok(relation) => relation,
-            Err(error) => panic!(error),
-        }
-    }
-
-    pub fn new_safe(map: HashMap<B, HashSet<A>>, default: B) -> Result<Self, String> {
+    /// Builds the equivalence relation with the given classes and a `default` class for all
+    /// remaining elements; fails if the classes intersect or if the `default` class is among
+    /// them.
+    pub fn new(map: HashMap<B, HashSet<A>>, default: B) -> Result<Self, ParseError> {
         let mut relation_map = HashMap::new();
         for (class_name, members) in map {
             if class_name == default {
-                return Err(String::from(
-                    "There can only be one default class in the equivalence relation!",
-                ));
+                return Err(ParseError::Inconsistent {
+                    message: String::from(
+                        "There can only be one default class in the equivalence relation!",
+                    ),
+                });
             }
 
             for value in members {
@@ -77,9 +75,11 @@ where
                         v.insert(class_name.clone());
                     }
                     Entry::Occupied(_) => {
-                        return Err(String::from(
-                            "All classes of the equivalence relation must be disjoint!",
-                        ));
+                        return Err(ParseError::Inconsistent {
+                            message: String::from(
+                                "All classes of the equivalence relation must be disjoint!",
+                            ),
+                        });
                     }
                 }
             }
@@ -133,15 +133,20 @@ where
     B: Clone + Eq + Hash + FromStr,
     B::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut map = HashMap::new();
         let mut default = None;
 
-        for l in s.lines() {
-            if !l.is_empty() {
-                match l.trim().parse()? {
+        for (i, l) in s.lines().enumerate() {
+            if !l.trim().is_empty() {
+                let offset = l.chars().count() - l.trim_start().chars().count();
+                match l
+                    .trim()
+                    .parse()
+                    .map_err(|e: ParseError| e.at_line(i + 1, offset))?
+                {
                     EquivalenceClass {
                         label,
                         set: Some(elements),
@@ -155,13 +160,12 @@ where
             }
         }
 
-        if let Some(label) = default {
-            if let Ok(relation) = EquivalenceRelation::new_safe(map, label) {
-                return Ok(relation);
-            }
+        match default {
+            Some(label) => EquivalenceRelation::new(map, label),
+            None => Err(ParseError::Missing {
+                expected: "a default class (`⟨label⟩ *`)".to_owned(),
+            }),
         }
-
-        Err(format!("Could not parse {}", s))
     }
 }
 
@@ -172,13 +176,14 @@ where
     B: FromStr,
     B::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_class(s.as_bytes()) {
-            IResult::Done(_, result) => Ok(result),
-            _ => Err(format!("Could not parse {}", s)),
-        }
+        parse_line(
+            s,
+            parse_class,
+            "an equivalence class of the form `N [A, B]` or `R *`",
+        )
     }
 }
 
@@ -284,7 +289,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "There can only be one default class in the equivalence relation!")]
     fn test_equivalence_relation_new_colliding_default() {
         let mut map = HashMap::new();
         let classes = vec![
@@ -296,11 +300,17 @@ mod tests {
             map.insert(label, elements);
         }
 
-        EquivalenceRelation::new(map, 1);
+        assert_eq!(
+            Err(ParseError::Inconsistent {
+                message: String::from(
+                    "There can only be one default class in the equivalence relation!"
+                ),
+            }),
+            EquivalenceRelation::new(map, 1)
+        );
     }
 
     #[test]
-    #[should_panic(expected = "All classes of the equivalence relation must be disjoint!")]
     fn test_equivalence_relation_new_intersecting_classes() {
         let mut map = HashMap::new();
         let classes = vec![
@@ -312,7 +322,12 @@ mod tests {
             map.insert(label, elements);
         }
 
-        EquivalenceRelation::new(map, 2);
+        assert_eq!(
+            Err(ParseError::Inconsistent {
+                message: String::from("All classes of the equivalence relation must be disjoint!"),
+            }),
+            EquivalenceRelation::new(map, 2)
+        );
     }
 
     #[test]
@@ -1,11 +1,10 @@
-use num_traits::{One,Zero};
 use std::collections::{BTreeMap, BinaryHeap};
-use std::ops::{MulAssign,AddAssign};
 use std::hash::Hash;
 
 use crate::recognisable::automaton::Automaton;
 use crate::recognisable::{Instruction, Transition};
 use crate::util::push_down::Pushdown;
+use crate::util::semiring::Semiring;
 use crate::automata::push_down_automaton::PushDownInstruction;
 
 use std::collections::HashMap;
@@ -27,7 +26,7 @@ where
     Self::A1: Automaton<T, W, I = Self::I1>,
     Self::A2: Automaton<T, W, I = Self::I2> + Sized,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + AddAssign + Zero,
+    W: Semiring,
 {
     type I1;
     type I2;
@@ -53,7 +52,7 @@ where
         let initial2 = instance.approximate_storage(automaton1.initial());
 
 
-        // collect similar transitions and sum up their weights
+        // collect similar transitions and sum up their weights in the semiring `W`
         // see Denkinger 2017 "Approximation of Automata with Storage"
         let mut transition_map = HashMap::new();
 
@@ -84,7 +83,7 @@ pub struct ApproximationInstance<Strategy, T, W>
 where
     Strategy: ApproximationStrategy<T, W>,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + AddAssign + Zero,
+    W: Semiring,
 {
     pub reverse_transition_map:
         BTreeMap<Transition<Strategy::I2, T, W>, Vec<Transition<Strategy::I1, T, W>>>,
@@ -98,7 +97,7 @@ where
     Strategy::I2: Clone + Eq + Ord,
     Strategy::I1: Clone + Eq + Ord,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: Semiring,
 {
     pub fn new(strategy: Strategy) -> Self {
         ApproximationInstance {
@@ -1,9 +1,5 @@
-use num_traits::Zero;
 use std::marker::PhantomData;
-use std::ops::AddAssign;
-use std::ops::MulAssign;
 use std::hash::Hash;
-use num_traits::One;
 use std::collections::HashSet;
 
 
@@ -13,6 +9,7 @@ use crate::approximation::ApproximationStrategy;
 use crate::recognisable::automaton::Automaton;
 use crate::approximation::ApproximationInstance;
 use crate::recognisable::Transition;
+use crate::util::semiring::Semiring;
 
 
 
@@ -36,7 +33,7 @@ impl<A> PDTopKElement<A> {
 impl<A, T, W> ApproximationStrategy<T, W> for PDTopKElement<A>
     where A: Clone + Ord + Hash,
           T: Clone + Eq + Hash + Ord,
-          W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+          W: Semiring,
 {
     type I1 = PushDownInstruction<A>;
     type I2 = PushDownInstruction<A>;
@@ -1,8 +1,7 @@
-use num_traits::Zero;
 use std::hash::Hash;
-use std::ops::{AddAssign, MulAssign};
 
 use crate::approximation::*;
+use crate::util::semiring::Semiring;
 use crate::automata::push_down_automaton::*;
 
 use crate::automata::tree_stack_automaton::tree_stack_instruction::TreeStackInstruction;
@@ -30,7 +29,7 @@ where
     A1: Clone + Hash + Ord,
     A2: Clone + Hash + Ord,
     T: Clone + Eq + Hash + Ord,
-    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+    W: Semiring,
 {
     type I1 = PushDownInstruction<A1>;
     type I2 = PushDownInstruction<A2>;
@@ -81,7 +80,7 @@ where
     A1: Clone + Hash + Ord,
     A2: Clone + Hash + Ord,
     T: Clone + Eq + Hash + Ord,
-    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+    W: Semiring,
 {
     type I1 = TreeStackInstruction<A1>;
     type I2 = TreeStackInstruction<A2>;
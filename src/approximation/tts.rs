@@ -1,9 +1,8 @@
-use num_traits::Zero;
 use std::hash::Hash;
 use std::marker::PhantomData;
-use std::ops::{AddAssign, MulAssign};
 
 use crate::approximation::*;
+use crate::util::semiring::Semiring;
 use crate::automata::push_down_automaton::*;
 use crate::automata::tree_stack_automaton::*;
 
@@ -25,7 +24,7 @@ impl<A, T, W> ApproximationStrategy<T, W> for TTSElement<A>
 where
     A: Clone + Hash + Ord,
     T: Clone + Eq + Hash + Ord,
-    W: AddAssign + Copy + MulAssign + One + Ord + Zero,
+    W: Semiring,
 {
     type I1 = TreeStackInstruction<A>;
     type I2 = PushDownInstruction<A>;
@@ -1,7 +1,6 @@
 use crate::automata::finite_state_automaton::{FiniteStateAutomaton, FiniteStateInstruction};
 use crate::recognisable::Transition;
-use crate::util::parsing::{parse_finals, parse_initial};
-use nom::IResult;
+use crate::util::parsing::{parse_finals, parse_initial, parse_line, ParseError};
 use num_traits::One;
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -17,41 +16,42 @@ where
     W: Clone + Eq + FromStr + One + Ord,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut init = None;
         let mut fin = Vec::new();
         let mut transitions: Vec<Transition<FiniteStateInstruction<Q>, T, W>> = Vec::new();
 
-        let mut it = s.lines();
-
-        while let Some(l) = it.next() {
+        for (i, l) in s.lines().enumerate() {
+            let offset = l.chars().count() - l.trim_start().chars().count();
             if l.trim_start().starts_with("initial:") {
-                match parse_initial(l.trim_start().as_bytes()) {
-                    IResult::Done(_, result) => {
-                        init = Some(result);
-                    }
-                    _ => return Err(format!("Malformed initial declaration: {}", l)),
-                }
+                init = Some(
+                    parse_line(l.trim_start(), parse_initial, "an initial state")
+                        .map_err(|e| e.at_line(i + 1, offset))?,
+                );
             } else if l.trim_start().starts_with("final:") {
-                match parse_finals(l.trim_start().as_bytes()) {
-                    IResult::Done(_, result) => {
-                        fin = result;
-                    }
-                    _ => return Err(format!("Malformed final declaration: {}", l)),
-                }
-            } else if !l.is_empty() && !l.trim_start().starts_with("%") {
-                transitions.push(l.trim().parse()?);
+                fin = parse_line(l.trim_start(), parse_finals, "a list of final states")
+                    .map_err(|e| e.at_line(i + 1, offset))?;
+            } else if !l.trim().is_empty() && !l.trim_start().starts_with("%") {
+                transitions.push(
+                    l.trim()
+                        .parse()
+                        .map_err(|e: ParseError| e.at_line(i + 1, offset))?,
+                );
             }
         }
 
         match (init, fin) {
-            (None, ref r) if r.len() == 0 => {
-                Err(format!("No initial state and no final states found."))
-            }
-            (None, _) => Err(format!("No initial state found.")),
-            (Some(_), ref r) if r.len() == 0 => Err(format!("No final states found.")),
+            (None, ref r) if r.len() == 0 => Err(ParseError::Missing {
+                expected: "an initial state and final states".to_owned(),
+            }),
+            (None, _) => Err(ParseError::Missing {
+                expected: "an initial state (`initial: ⟨state⟩`)".to_owned(),
+            }),
+            (Some(_), ref r) if r.len() == 0 => Err(ParseError::Missing {
+                expected: "final states (`final: [⟨state⟩, …]`)".to_owned(),
+            }),
             (Some(i), r) => Ok(FiniteStateAutomaton::new(transitions, i, r)),
         }
     }
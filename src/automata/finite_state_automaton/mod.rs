@@ -1,6 +1,7 @@
 extern crate bit_set;
 
 use crate::recognisable::{automaton::Automaton, Configuration, Instruction, Item, Transition};
+use crate::util::dot::{transition_label, Digraph, ToDot};
 use crate::util::integerisable::{Integerisable1, Integerisable2};
 use crate::util::push_down::Pushdown;
 use integeriser::{HashIntegeriser, Integeriser};
@@ -56,6 +57,23 @@ impl<Q: Clone + Eq + Hash> Integerisable1 for FiniteStateInstruction<Q> {
     }
 }
 
+impl<Q> FiniteStateInstruction<Q> {
+    pub fn new(source_state: Q, target_state: Q) -> Self {
+        FiniteStateInstruction {
+            source_state,
+            target_state,
+        }
+    }
+
+    pub fn source_state(&self) -> &Q {
+        &self.source_state
+    }
+
+    pub fn target_state(&self) -> &Q {
+        &self.target_state
+    }
+}
+
 impl<Q> Instruction for FiniteStateInstruction<Q>
 where
     Q: Eq + Clone,
@@ -107,6 +125,20 @@ where
         }
     }
 
+    pub fn initial_state(&self) -> Q {
+        self.q_integeriser
+            .find_value(self.initial_state)
+            .unwrap()
+            .clone()
+    }
+
+    pub fn final_states(&self) -> Vec<Q> {
+        self.final_states
+            .iter()
+            .map(|q| self.q_integeriser.find_value(q).unwrap().clone())
+            .collect()
+    }
+
     pub fn list_transitions<'a>(
         &'a self,
     ) -> Box<Iterator<Item = Transition<FiniteStateInstruction<Q>, T, W>> + 'a> {
@@ -243,3 +275,36 @@ where
         write!(f, "{}", buffer)
     }
 }
+
+/// States are drawn as nodes and final states with a double circle; each
+/// transition is an edge labelled with its word and weight.
+impl<Q, T, W> ToDot for FiniteStateAutomaton<Q, T, W>
+where
+    Q: Clone + Display + Hash + Ord,
+    T: Clone + Display + Eq + Hash + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let mut graph = Digraph::new();
+        graph.attribute("rankdir=LR");
+        graph.attribute("node [shape=circle]");
+        graph.node("", &[("shape", "point")]);
+        graph.edge("", &self.initial_state().to_string(), &[]);
+        for q in self.final_states() {
+            graph.node(&q.to_string(), &[("shape", "doublecircle")]);
+        }
+
+        let mut transitions: Vec<_> = self.list_transitions().collect();
+        transitions.sort();
+        for t in transitions {
+            let label = transition_label(&t.word, &t.weight);
+            graph.edge(
+                &t.instruction.source_state.to_string(),
+                &t.instruction.target_state.to_string(),
+                &[("label", label.as_str())],
+            );
+        }
+
+        graph.to_string()
+    }
+}
@@ -1,20 +1,25 @@
 extern crate num_traits;
 
 use crate::recognisable::automaton::Automaton;
-use crate::recognisable::{self, Configuration, Instruction, Item, Recognisable, Transition};
+use crate::recognisable::lattice::Lattice;
+use crate::recognisable::{
+    self, Configuration, Instruction, Item, LatticeRecognisable, Recognisable, Transition,
+};
 use integeriser::{HashIntegeriser, Integeriser};
 use num_traits::{One, Zero};
 use std::collections::{BinaryHeap, HashMap};
 use std::fmt;
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
-use std::ops::{AddAssign, Mul, MulAssign};
+use std::ops::{AddAssign, MulAssign};
 use std::rc::Rc;
 use std::slice::Iter;
 use std::vec::Vec;
 
+use crate::util::dot::{transition_label, Digraph, ToDot};
 use crate::util::integerisable::{Integerisable1, Integerisable2};
 use crate::util::push_down::Pushdown;
+use crate::util::semiring::Semiring;
 
 mod from_cfg;
 // TODO: mod from_str;
@@ -401,7 +406,7 @@ impl<A, T, W> Recognisable<T, W> for PushDownAutomaton<A, T, W>
 where
     A: Ord + PartialEq + Debug + Clone + Hash,
     T: Clone + Debug + Eq + Hash + Ord,
-    W: AddAssign + One + Mul<Output = W> + MulAssign + Clone + Copy + Debug + Eq + Ord + Zero,
+    W: Debug + Semiring,
 {
     type Parse = Item<PushDown<A>, PushDownInstruction<A>, T, W>;
 
@@ -418,6 +423,20 @@ where
     }
 }
 
+impl<A, T, W> LatticeRecognisable<T, W> for PushDownAutomaton<A, T, W>
+where
+    A: Ord + PartialEq + Debug + Clone + Hash,
+    T: Clone + Debug + Eq + Hash + Ord,
+    W: Debug + Semiring,
+{
+    fn recognise_lattice<'a>(
+        &'a self,
+        lattice: &'a Lattice<T, W>,
+    ) -> Box<Iterator<Item = (Self::Parse, Vec<usize>)> + 'a> {
+        Box::new(recognisable::automaton::recognise_lattice(self, lattice))
+    }
+}
+
 impl<A> PushDown<A> {
     pub fn empty(&self) -> &A {
         self.elements.get(0).unwrap()
@@ -597,6 +616,66 @@ where
     }
 }
 
+/// The nodes are the sequences of pushdown symbols that are replaced by the
+/// transitions (the topmost symbol first), and each transition is an edge
+/// from the sequence it pops to the sequence it pushes (the topmost symbol
+/// last), labelled with its word and weight.
+/// The topmost symbol of the initial pushdown is marked by an arrow.
+impl<A, T, W> ToDot for PushDownAutomaton<A, T, W>
+where
+    A: Clone + Display + Hash + Ord,
+    T: Clone + Display + Eq + Hash + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let symbols = |symbols: &[A]| -> String {
+            if symbols.is_empty() {
+                "ε".to_string()
+            } else {
+                let symbols: Vec<String> = symbols.iter().map(ToString::to_string).collect();
+                symbols.join(" ")
+            }
+        };
+
+        let mut graph = Digraph::new();
+        graph.attribute("node [shape=box]");
+        let initial = self
+            .a_integeriser
+            .find_value(*self.initial.current_symbol())
+            .unwrap();
+        graph.node("", &[("shape", "point")]);
+        graph.edge("", &symbols(&[initial.clone()]), &[]);
+
+        let mut transitions: Vec<_> = self.list_transitions().collect();
+        transitions.sort();
+        for t in transitions {
+            let mut label = transition_label(&t.word, &t.weight);
+            let (current_val, new_val) = match t.instruction {
+                PushDownInstruction::Replace {
+                    ref current_val,
+                    ref new_val,
+                } => (current_val, new_val),
+                PushDownInstruction::ReplaceK {
+                    ref current_val,
+                    ref new_val,
+                    limit,
+                    ..
+                } => {
+                    label.push_str(&format!("\nlimit {}", limit));
+                    (current_val, new_val)
+                }
+            };
+            graph.edge(
+                &symbols(current_val),
+                &symbols(new_val),
+                &[("label", label.as_str())],
+            );
+        }
+
+        graph.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -775,4 +854,24 @@ mod tests {
             PushDownInstruction::un_integerise(&integerised_instruction, &integeriser)
         );
     }
+
+    #[test]
+    fn test_to_dot() {
+        use crate::grammars::cfg::CFG;
+        use log_domain::LogDomain;
+
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [T a, Nt S]  # 0.5\n\
+                                                        S → []           # 0.5"
+            .parse()
+            .unwrap();
+        let dot = PushDownAutomaton::from(grammar).to_dot();
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("\"\" -> \"I\";"));
+        assert!(dot.contains("\"I\" -> \"(S)\" [label=\"ε # "));
+        assert!(dot.contains("\"(S)\" -> \"(S) (a)\" [label=\"ε # "));
+        assert!(dot.contains("\"(S)\" -> \"ε\" [label=\"ε # "));
+        assert!(dot.contains("\"(a)\" -> \"ε\" [label=\"a # "));
+    }
 }
@@ -1,4 +1,3 @@
-use nom::IResult;
 use num_traits::One;
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -8,7 +7,7 @@ use std::vec::Vec;
 
 use crate::automata::tree_stack_automaton::{TreeStack, TreeStackAutomaton, TreeStackInstruction};
 use crate::recognisable::Transition;
-use crate::util::parsing::parse_initial;
+use crate::util::parsing::{parse_initial, parse_line, ParseError};
 
 impl<A, T, W> FromStr for TreeStackAutomaton<A, T, W>
 where
@@ -19,30 +18,33 @@ where
     W: Clone + Eq + FromStr + One + Ord,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut initial = None;
         let mut transitions: Vec<Transition<TreeStackInstruction<A>, T, W>> = Vec::new();
 
-        let mut it = s.lines();
-
-        while let Some(l) = it.next() {
+        for (i, l) in s.lines().enumerate() {
+            let offset = l.chars().count() - l.trim_start().chars().count();
             if l.trim_start().starts_with("initial:") {
-                match parse_initial(l.trim_start().as_bytes()) {
-                    IResult::Done(_, result) => {
-                        initial = Some(result);
-                    }
-                    _ => return Err(format!("Malformed initial declaration: {}", l)),
-                }
-            } else if !l.is_empty() && !l.trim_start().starts_with("%") {
-                transitions.push(l.trim().parse()?);
+                initial = Some(
+                    parse_line(l.trim_start(), parse_initial, "an initial stack symbol")
+                        .map_err(|e| e.at_line(i + 1, offset))?,
+                );
+            } else if !l.trim().is_empty() && !l.trim_start().starts_with("%") {
+                transitions.push(
+                    l.trim()
+                        .parse()
+                        .map_err(|e: ParseError| e.at_line(i + 1, offset))?,
+                );
             }
         }
 
         match initial {
             Some(init) => Ok(TreeStackAutomaton::new(transitions, TreeStack::new(init))),
-            None => Err(format!("No initial configuration found.")),
+            None => Err(ParseError::Missing {
+                expected: "an initial stack symbol (`initial: ⟨symbol⟩`)".to_owned(),
+            }),
         }
     }
 }
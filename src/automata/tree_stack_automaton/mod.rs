@@ -10,10 +10,13 @@ use std::vec::Vec;
 
 use num_traits::One;
 
-use crate::recognisable::automaton::{recognise, recognise_beam, Automaton};
-use crate::recognisable::{Configuration, Item, Recognisable, Transition};
+use crate::recognisable::automaton::{recognise, recognise_beam, recognise_lattice, Automaton};
+use crate::recognisable::lattice::Lattice;
+use crate::recognisable::{Configuration, Item, LatticeRecognisable, Recognisable, Transition};
+use crate::util::dot::{transition_label, Digraph, ToDot};
 use crate::util::integerisable::{Integerisable1, Integerisable2};
 use crate::util::push_down::Pushdown;
+use crate::util::semiring::Semiring;
 use integeriser::{HashIntegeriser, Integeriser};
 
 pub mod from_pmcfg;
@@ -127,7 +130,7 @@ impl<A, T, W> Recognisable<T, W> for TreeStackAutomaton<A, T, W>
 where
     A: Ord + PartialEq + Clone + Hash,
     T: Clone + Eq + Hash + Ord,
-    W: One + Mul<Output = W> + MulAssign + Clone + Copy + Eq + Ord,
+    W: Semiring,
 {
     type Parse = Item<TreeStack<A>, TreeStackInstruction<A>, T, W>;
 
@@ -144,6 +147,20 @@ where
     }
 }
 
+impl<A, T, W> LatticeRecognisable<T, W> for TreeStackAutomaton<A, T, W>
+where
+    A: Ord + PartialEq + Clone + Hash,
+    T: Clone + Eq + Hash + Ord,
+    W: Semiring,
+{
+    fn recognise_lattice<'a>(
+        &'a self,
+        lattice: &'a Lattice<T, W>,
+    ) -> Box<Iterator<Item = (Self::Parse, Vec<usize>)> + 'a> {
+        Box::new(recognise_lattice(self, lattice))
+    }
+}
+
 impl<A, T, W> Automaton<T, W> for TreeStackAutomaton<A, T, W>
 where
     A: Clone + Eq + Hash + Ord,
@@ -251,3 +268,57 @@ where
         )
     }
 }
+
+/// The nodes are the symbols of the tree stacks, and each transition is an
+/// edge from the symbol at the stack pointer before to the symbol at the
+/// stack pointer after the transition, labelled with its word, weight, and
+/// the kind of its instruction.
+/// The symbol of the initial tree stack is marked by an arrow.
+impl<A, T, W> ToDot for TreeStackAutomaton<A, T, W>
+where
+    A: Clone + Display + Hash + Ord,
+    T: Clone + Display + Eq + Hash + Ord,
+    W: Clone + Display + Ord,
+{
+    fn to_dot(&self) -> String {
+        let mut graph = Digraph::new();
+        graph.attribute("node [shape=box]");
+        let initial = self
+            .a_integeriser
+            .find_value(*self.initial.current_symbol())
+            .unwrap();
+        graph.node("", &[("shape", "point")]);
+        graph.edge("", &initial.to_string(), &[]);
+
+        let mut transitions: Vec<_> = self.list_transitions().collect();
+        transitions.sort();
+        for t in transitions {
+            let (current_val, new_val, kind) = match t.instruction {
+                TreeStackInstruction::Up {
+                    n,
+                    ref current_val,
+                    ref old_val,
+                    ref new_val,
+                } => (current_val, new_val, format!("up {} ({})", n, old_val)),
+                TreeStackInstruction::Push {
+                    n,
+                    ref current_val,
+                    ref new_val,
+                } => (current_val, new_val, format!("push {}", n)),
+                TreeStackInstruction::Down {
+                    ref current_val,
+                    ref old_val,
+                    ref new_val,
+                } => (current_val, new_val, format!("down ({})", old_val)),
+            };
+            let label = format!("{}\n{}", transition_label(&t.word, &t.weight), kind);
+            graph.edge(
+                &current_val.to_string(),
+                &new_val.to_string(),
+                &[("label", label.as_str())],
+            );
+        }
+
+        graph.to_string()
+    }
+}
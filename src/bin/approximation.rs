@@ -1,18 +1,21 @@
 use clap::{App, Arg, ArgMatches, SubCommand};
 use log_domain::LogDomain;
 use rustomata::approximation::equivalence_classes::EquivalenceRelation;
+use rustomata::approximation::ptk::PDTopKElement;
 use rustomata::approximation::relabel::RlbElement;
 use rustomata::approximation::tts::TTSElement;
 use rustomata::approximation::ApproximationStrategy;
 use rustomata::automata::push_down_automaton::{PushDownAutomaton, PushState};
 use rustomata::automata::tree_stack_automaton::TreeStackAutomaton;
 use rustomata::grammars::cfg::CFG;
-use rustomata::grammars::pmcfg::PMCFG;
 use rustomata::recognisable::Recognisable;
 
-use std::fs::File;
 use std::io::{self, Read};
 
+use crate::output::{format_arg, print_automaton};
+use crate::parse_error::{exit_with_message, parse_or_exit, read_file};
+use crate::pmcfg::{read_grammar, srcg_arg};
+
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("approximation")
         .author("Max Korn <max.korn@tu-dresden.de>")
@@ -48,7 +51,8 @@ pub fn get_sub_command() -> App<'static, 'static> {
                                 .help("cfg-grammar file to use")
                                 .index(2)
                                 .required(true),
-                        ),
+                        )
+                        .arg(format_arg()),
                 ),
         )
         .subcommand(
@@ -82,27 +86,33 @@ pub fn get_sub_command() -> App<'static, 'static> {
                                 .help("size of pushdown")
                                 .index(2)
                                 .required(true),
-                        ),
+                        )
+                        .arg(format_arg()),
                 ),
         )
         .subcommand(
             SubCommand::with_name("tts")
                 .about("approximates tree-stack into pushdown automata")
                 .subcommand(
-                    SubCommand::with_name("parse").arg(
-                        Arg::with_name("grammar")
-                            .help("mcfg-grammar file to use")
-                            .index(1)
-                            .required(true),
-                    ),
+                    SubCommand::with_name("parse")
+                        .arg(
+                            Arg::with_name("grammar")
+                                .help("mcfg-grammar file to use")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(srcg_arg()),
                 )
                 .subcommand(
-                    SubCommand::with_name("automaton").arg(
-                        Arg::with_name("grammar")
-                            .help("mcfg-grammar file to use")
-                            .index(1)
-                            .required(true),
-                    ),
+                    SubCommand::with_name("automaton")
+                        .arg(
+                            Arg::with_name("grammar")
+                                .help("mcfg-grammar file to use")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(srcg_arg())
+                        .arg(format_arg()),
                 ),
         )
 }
@@ -112,18 +122,16 @@ pub fn handle_sub_matches(r_matches: &ArgMatches) {
         ("relabel", Some(relabel_matches)) => match relabel_matches.subcommand() {
             ("parse", Some(parse_matches)) => {
                 let grammar_file_name = parse_matches.value_of("grammar").unwrap();
-                let mut grammar_file = File::open(grammar_file_name).unwrap();
-                let mut grammar_string = String::new();
-                let _ = grammar_file.read_to_string(&mut grammar_string);
-                let g: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                let grammar_string = read_file(grammar_file_name);
+                let g: CFG<String, String, LogDomain<f64>> =
+                    parse_or_exit(&grammar_string, grammar_file_name);
 
                 let a = PushDownAutomaton::from(g);
 
                 let classes_file_name = parse_matches.value_of("classes").unwrap();
-                let mut classes_file = File::open(classes_file_name).unwrap();
-                let mut classes_string = String::new();
-                let _ = classes_file.read_to_string(&mut classes_string);
-                let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                let classes_string = read_file(classes_file_name);
+                let e: EquivalenceRelation<String, String> =
+                    parse_or_exit(&classes_string, classes_file_name);
 
                 let f = |ps: &PushState<_, _>| ps.map(|nt| e.project(nt));
                 let rlb = RlbElement::new(&f);
@@ -144,35 +152,51 @@ pub fn handle_sub_matches(r_matches: &ArgMatches) {
             }
             ("automaton", Some(parse_matches)) => {
                 let grammar_file_name = parse_matches.value_of("grammar").unwrap();
-                let mut grammar_file = File::open(grammar_file_name).unwrap();
-                let mut grammar_string = String::new();
-                let _ = grammar_file.read_to_string(&mut grammar_string);
-                let g: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                let grammar_string = read_file(grammar_file_name);
+                let g: CFG<String, String, LogDomain<f64>> =
+                    parse_or_exit(&grammar_string, grammar_file_name);
 
                 let a = PushDownAutomaton::from(g);
 
                 let classes_file_name = parse_matches.value_of("classes").unwrap();
-                let mut classes_file = File::open(classes_file_name).unwrap();
-                let mut classes_string = String::new();
-                let _ = classes_file.read_to_string(&mut classes_string);
-                let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                let classes_string = read_file(classes_file_name);
+                let e: EquivalenceRelation<String, String> =
+                    parse_or_exit(&classes_string, classes_file_name);
 
                 let f = |ps: &PushState<_, _>| ps.map(|nt| e.project(nt));
                 let rlb = RlbElement::new(&f);
 
                 let (b, _) = rlb.approximate_automaton(&a);
 
-                println!("{}", b);
+                print_automaton(&b, parse_matches);
+            }
+            _ => (),
+        },
+        ("topk", Some(topk_matches)) => match topk_matches.subcommand() {
+            ("automaton", Some(parse_matches)) => {
+                let grammar_file_name = parse_matches.value_of("grammar").unwrap();
+                let grammar_string = read_file(grammar_file_name);
+                let g: CFG<String, String, LogDomain<f64>> =
+                    parse_or_exit(&grammar_string, grammar_file_name);
+
+                let a = PushDownAutomaton::from(g);
+
+                let size = match parse_matches.value_of("size").unwrap().parse::<usize>() {
+                    Ok(size) if size >= 1 => size,
+                    _ => exit_with_message("the size of the pushdown must be a positive integer"),
+                };
+                let ptk = PDTopKElement::new(size);
+
+                let (b, _) = ptk.approximate_automaton(&a);
+
+                print_automaton(&b, parse_matches);
             }
             _ => (),
         },
         ("tts", Some(tts_matches)) => match tts_matches.subcommand() {
             ("parse", Some(parse_matches)) => {
                 let grammar_file_name = parse_matches.value_of("grammar").unwrap();
-                let mut grammar_file = File::open(grammar_file_name).unwrap();
-                let mut grammar_string = String::new();
-                let _ = grammar_file.read_to_string(&mut grammar_string);
-                let g: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                let g = read_grammar(grammar_file_name, parse_matches.is_present("srcg"));
 
                 let a = TreeStackAutomaton::from(g);
 
@@ -196,16 +220,13 @@ pub fn handle_sub_matches(r_matches: &ArgMatches) {
             }
             ("automaton", Some(parse_matches)) => {
                 let grammar_file_name = parse_matches.value_of("grammar").unwrap();
-                let mut grammar_file = File::open(grammar_file_name).unwrap();
-                let mut grammar_string = String::new();
-                let _ = grammar_file.read_to_string(&mut grammar_string);
-                let g: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+                let g = read_grammar(grammar_file_name, parse_matches.is_present("srcg"));
 
                 let a = TreeStackAutomaton::from(g);
                 let tts = TTSElement::new();
 
                 let (b, _) = tts.approximate_automaton(&a);
-                println!("{}", b);
+                print_automaton(&b, parse_matches);
             }
             _ => (),
         },
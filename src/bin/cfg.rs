@@ -4,9 +4,11 @@ use rustomata::automata::push_down_automaton::PushDownAutomaton;
 use rustomata::grammars::cfg::CFG;
 use rustomata::recognisable::Recognisable;
 
-use std::fs::File;
 use std::io::{self, Read};
 
+use crate::output::{format_arg, print_automaton};
+use crate::parse_error::{parse_or_exit, read_file};
+
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("cfg")
         .author("Max Korn <max.korn@tu-dresden.de>")
@@ -47,7 +49,8 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .help("grammar file to use")
                         .index(1)
                         .required(true),
-                ),
+                )
+                .arg(format_arg()),
         )
 }
 
@@ -55,15 +58,14 @@ pub fn handle_sub_matches(cfg_matches: &ArgMatches) {
     match cfg_matches.subcommand() {
         ("parse", Some(cfg_parse_matches)) => {
             let grammar_file_name = cfg_parse_matches.value_of("grammar").unwrap();
-            let mut grammar_file = File::open(grammar_file_name).unwrap();
             let n = cfg_parse_matches
                 .value_of("number-of-parses")
                 .unwrap()
                 .parse()
                 .unwrap();
-            let mut grammar_string = String::new();
-            let _ = grammar_file.read_to_string(&mut grammar_string);
-            let grammar: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+            let grammar_string = read_file(grammar_file_name);
+            let grammar: CFG<String, String, LogDomain<f64>> =
+                parse_or_exit(&grammar_string, grammar_file_name);
 
             let automaton = PushDownAutomaton::from(grammar);
 
@@ -92,13 +94,12 @@ pub fn handle_sub_matches(cfg_matches: &ArgMatches) {
         }
         ("automaton", Some(cfg_automaton_matches)) => {
             let grammar_file_name = cfg_automaton_matches.value_of("grammar").unwrap();
-            let mut grammar_file = File::open(grammar_file_name).unwrap();
-            let mut grammar_string = String::new();
-            let _ = grammar_file.read_to_string(&mut grammar_string);
-            let grammar: CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+            let grammar_string = read_file(grammar_file_name);
+            let grammar: CFG<String, String, LogDomain<f64>> =
+                parse_or_exit(&grammar_string, grammar_file_name);
 
             let automaton = PushDownAutomaton::from(grammar);
-            println!("{}", automaton);
+            print_automaton(&automaton, cfg_automaton_matches);
         }
         _ => (),
     }
@@ -0,0 +1,214 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use flate2::{write, Compression};
+use log_domain::LogDomain;
+use rustomata::grammars::lcfrs::binarization::BinarizationStrategy;
+use rustomata::grammars::lcfrs::from_discodop::{DiscoDopGrammar, ToDiscoDopError};
+use rustomata::grammars::lcfrs::from_rparse::RparseClauses;
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::pmcfg::srcg::SrcgGrammar;
+use rustomata::grammars::pmcfg::PMCFG;
+use std::{
+    fs::File,
+    io::{stdout, Write},
+};
+
+use crate::file_reader::read_or_exit;
+use crate::parse_error::{exit_with_error, exit_with_message, parse_or_exit};
+use crate::pmcfg::print_srcg;
+
+pub type Grammar = Lcfrs<String, String, LogDomain<f64>>;
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("convert")
+        .about("Converts an LCFRS between different file formats.")
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .required(true)
+                .possible_values(&["rustomata", "srcg", "discodop", "rparse"])
+                .help("Format of the input grammar."),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .possible_values(&["rustomata", "srcg", "discodop", "bincode"])
+                .help("Format of the output grammar."),
+        )
+        .arg(
+            Arg::with_name("lexer")
+                .short("l")
+                .long("lexer")
+                .takes_value(true)
+                .help("Provide the lexer file of a disco-dop grammar."),
+        )
+        .arg(
+            Arg::with_name("gzipped")
+                .short("z")
+                .long("zipped")
+                .takes_value(false)
+                .help("if the provided grammar file is Gzipped (default for disco-dop grammars)")
+                .conflicts_with("ungzipped"),
+        )
+        .arg(
+            Arg::with_name("ungzipped")
+                .long("unzipped")
+                .takes_value(false)
+                .help("if the provided grammar file is not Gzipped (default for other grammars)")
+                .conflicts_with("gzipped"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .required_if("to", "discodop")
+                .help(
+                    "Prefix of the output files for disco-dop grammars \
+                     (`⟨prefix⟩.rules.gz` and `⟨prefix⟩.lex.gz`).",
+                ),
+        )
+        .arg(
+            Arg::with_name("grammar")
+                .index(1)
+                .required(false)
+                .help("Grammar file. Reads from stdin if not provided."),
+        )
+}
+
+pub fn handle_sub_matches(params: &ArgMatches) {
+    let from = params.value_of("from").unwrap();
+    let grammar_is_gzipped = (params.is_present("gzipped") || from == "discodop")
+        && !params.is_present("ungzipped");
+    let grammar_string = read_or_exit(params.value_of("grammar"), grammar_is_gzipped);
+
+    let grammar_name = params.value_of("grammar").unwrap_or("the grammar from stdin");
+    let grammar: Grammar = match from {
+        "rustomata" => to_lcfrs(parse_or_exit(&grammar_string, grammar_name), grammar_name),
+        "srcg" => {
+            let SrcgGrammar(pmcfg) = parse_or_exit(&grammar_string, grammar_name);
+            to_lcfrs(pmcfg, grammar_name)
+        }
+        "discodop" => {
+            let dgmr: DiscoDopGrammar<String, (), LogDomain<f64>> =
+                grammar_string.parse().unwrap_or_else(|e| {
+                    exit_with_error(
+                        grammar_name,
+                        format!("malformed disco-dop grammar ({:?})", e),
+                    )
+                });
+            if let Some(path) = params.value_of("lexer") {
+                let lexer_string = read_or_exit(Some(path), grammar_is_gzipped);
+                let lexer = lexer_string.parse().unwrap_or_else(|e| {
+                    exit_with_error(path, format!("malformed disco-dop lexer ({:?})", e))
+                });
+                dgmr.with_lexer(lexer).into()
+            } else {
+                eprintln!(
+                    "warning: no lexer file given, each part-of-speech tag derives itself as \
+                     terminal symbol"
+                );
+                dgmr.with_default_lexer().into()
+            }
+        }
+        "rparse" => {
+            let clauses: RparseClauses<String, LogDomain<f64>> = grammar_string
+                .parse()
+                .unwrap_or_else(|()| exit_with_error(grammar_name, "malformed rparse clauses"));
+            eprintln!(
+                "warning: rparse grammars have no lexicon, each part-of-speech tag derives \
+                 itself as terminal symbol"
+            );
+            clauses.with_default_lexer()
+        }
+        _ => unreachable!(),
+    };
+
+    match params.value_of("to").unwrap() {
+        "rustomata" => print!("{}", grammar),
+        "srcg" => {
+            let (rules, init) = grammar.destruct();
+            print_srcg(PMCFG {
+                initial: vec![init],
+                rules,
+            })
+        }
+        "discodop" => write_discodop(grammar, grammar_name, params.value_of("output").unwrap()),
+        "bincode" => bincode::serialize_into(
+            &mut write::GzEncoder::new(stdout(), Compression::best()),
+            &grammar,
+            bincode::Infinite,
+        )
+        .unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+/// Converts a PMCFG read from the given file into an LCFRS; all but the first initial
+/// nonterminal are dropped.  Exits with an error if the grammar is not an LCFRS.
+pub fn to_lcfrs(pmcfg: PMCFG<String, String, LogDomain<f64>>, file_name: &str) -> Grammar {
+    let PMCFG { mut initial, rules } = pmcfg;
+
+    if initial.is_empty() {
+        exit_with_error(file_name, "the grammar has no initial nonterminal");
+    }
+    if initial.len() > 1 {
+        eprintln!(
+            "warning: LCFRS have a single initial nonterminal, dropping {}",
+            initial[1..].join(", ")
+        );
+    }
+
+    Lcfrs::new(rules, initial.remove(0)).unwrap_or_else(|| {
+        exit_with_error(
+            file_name,
+            "the grammar is not an LCFRS, it has inconsistent fanouts, non-linear or deleting \
+             compositions, or an initial nonterminal with fanout greater than 1",
+        )
+    })
+}
+
+/// Writes a grammar read from the file `grammar_name` to the files `⟨prefix⟩.rules.gz` and
+/// `⟨prefix⟩.lex.gz`; rules with more than two successors are binarized beforehand.
+fn write_discodop(grammar: Grammar, grammar_name: &str, prefix: &str) {
+    let (_, init) = grammar.clone().destruct();
+    if init != "ROOT" {
+        eprintln!(
+            "warning: disco-dop expects ROOT as initial nonterminal, but it is {}",
+            init
+        );
+    }
+
+    let dgmr = match DiscoDopGrammar::from_lcfrs(&grammar) {
+        Err(ToDiscoDopError::Rank(_)) => {
+            eprintln!("warning: binarizing rules with more than two successors (left-to-right)");
+            let (binarized, _) = grammar.binarize(BinarizationStrategy::LeftToRight);
+            DiscoDopGrammar::from_lcfrs(&binarized)
+        }
+        result => result,
+    }
+    .unwrap_or_else(|e| {
+        exit_with_error(
+            grammar_name,
+            format!("the grammar can not be written for disco-dop ({:?})", e),
+        )
+    });
+
+    for (extension, content) in &[
+        ("rules", dgmr.constituents.to_string()),
+        ("lex", dgmr.lexer.unwrap().to_string()),
+    ] {
+        let file_name = format!("{}.{}.gz", prefix, extension);
+        File::create(&file_name)
+            .and_then(|file| {
+                let mut encoder = write::GzEncoder::new(file, Compression::best());
+                encoder.write_all(content.as_bytes())?;
+                encoder.finish()
+            })
+            .unwrap_or_else(|e| {
+                exit_with_message(format!("could not write {} ({})", file_name, e))
+            });
+    }
+}
@@ -3,13 +3,25 @@ use clap::{App, Arg, ArgMatches, SubCommand};
 use flate2::{read, write, Compression};
 use log_domain::LogDomain;
 use rustomata::grammars::lcfrs::from_discodop::DiscoDopGrammar;
+use rustomata::grammars::lcfrs::binarization::{
+    markovization::Markovization, BinarizationStrategy,
+};
+use rustomata::grammars::lcfrs::from_negra::{extract_lcfrs, extract_markovized_lcfrs};
 use rustomata::grammars::{
     lcfrs::{
         csparsing::{CSRepresentation, DebugResult},
         Lcfrs,
     },
-    pmcfg::negra::{noparse, to_negra, DumpMode},
+    pmcfg::{
+        negra::{read_negra, DumpMode},
+        srcg::SrcgGrammar,
+    },
 };
+use crate::convert::to_lcfrs;
+use crate::file_reader::read_or_exit;
+use crate::output::{output_format_arg, print_noparse, print_tree};
+use crate::parse_error::{exit_with_error, parse_or_exit};
+use crate::pmcfg::srcg_arg;
 use std::{
     fs::File,
     io::{stdin, stdout, Read},
@@ -37,6 +49,33 @@ pub fn get_sub_command(name: &str) -> App {
                         .long("disco")
                         .takes_value(false)
                         .help("Use a grammar extracted by disco-dop.")
+                ).arg(
+                    Arg::with_name("negra-treebank")
+                        .short("n")
+                        .long("negra")
+                        .takes_value(false)
+                        .help("Extract the grammar from a treebank in NEGRA export format.")
+                        .conflicts_with("disco-grammar")
+                ).arg(
+                    Arg::with_name("binarize")
+                        .short("b")
+                        .long("binarize")
+                        .takes_value(true)
+                        .possible_values(&["left-to-right", "right-to-left", "head-outward", "optimal"])
+                        .help("Binarize the grammar with the given strategy before the extraction.")
+                ).arg(
+                    Arg::with_name("hmarkov")
+                        .long("hmarkov")
+                        .takes_value(true)
+                        .requires("binarize")
+                        .help("Number of siblings that are remembered by auxiliary nonterminals \
+                               (default: all of them).")
+                ).arg(
+                    Arg::with_name("vmarkov")
+                        .long("vmarkov")
+                        .takes_value(true)
+                        .requires("binarize")
+                        .help("Number of ancestors in each nonterminal, including itself (default: 1).")
                 ).arg(
                     Arg::with_name("disco-lexer")
                         .short("l")
@@ -56,6 +95,8 @@ pub fn get_sub_command(name: &str) -> App {
                         .takes_value(false)
                         .help("if the provided grammar file is not Gzipped (default for raw grammars)")
                         .conflicts_with("gzipped")
+                ).arg(
+                    srcg_arg().conflicts_with_all(&["disco-grammar", "negra-treebank"])
                 ).arg(
                     Arg::with_name("grammar")
                         .index(1)
@@ -125,82 +166,68 @@ pub fn get_sub_command(name: &str) -> App {
                         .short("f")
                         .long("with-fallback")
                         .help("Will output an incorrect parse tree if the parse does not find a correct one."),
-                ),
+                )
+                .arg(output_format_arg()),
         )
 }
 
-enum FileReader<R: Read> {
-    Plain(R),
-    GZipped(R),
-}
-
-impl<R: Read> FileReader<R> {
-    fn new(file: R, zipped: bool) -> Self {
-        if zipped {
-            FileReader::GZipped(file)
-        } else {
-            FileReader::Plain(file)
-        }
-    }
-
-    fn read(self) -> Result<String, ::std::io::Error> {
-        let mut s = String::new();
-        match self {
-            FileReader::Plain(mut f) => {
-                f.read_to_string(&mut s)?;
-            }
-            FileReader::GZipped(f) => {
-                read::GzDecoder::new(f).read_to_string(&mut s)?;
-            }
-        }
-        Ok(s)
-    }
-}
-
 pub fn handle_sub_matches(submatches: &ArgMatches) {
     match submatches.subcommand() {
         ("extract", Some(params)) => {
             let grammar_is_gzipped = (params.is_present("gzipped")
                 || params.is_present("disco-grammar"))
                 && !params.is_present("ungzipped");
-            let grammar_string = if let Some(path) = params.value_of("grammar") {
-                FileReader::new(
-                    File::open(path).expect("could not open grammar file"),
-                    grammar_is_gzipped,
-                )
-                .read()
-                .expect("could not read grammar file")
-            } else {
-                FileReader::new(stdin(), grammar_is_gzipped)
-                    .read()
-                    .expect("could not read grammar file")
-            };
+            let grammar_string = read_or_exit(params.value_of("grammar"), grammar_is_gzipped);
+            let grammar_name = params
+                .value_of("grammar")
+                .unwrap_or("the grammar from stdin");
             let sxlen = params
                 .value_of("sxlen")
                 .map_or(0usize, |s| s.parse().unwrap());
 
+            let binarization: Option<BinarizationStrategy> =
+                params.value_of("binarize").map(|s| s.parse().unwrap());
+            let markovization = Markovization {
+                horizontal: params.value_of("hmarkov").map(|s| s.parse().unwrap()),
+                vertical: params.value_of("vmarkov").map_or(1, |s| s.parse().unwrap()),
+            };
+
             let gmr: Lcfrs<String, String, LogDomain<f64>> = if params.is_present("disco-grammar") {
-                let dgmr: DiscoDopGrammar<_, _, _> =
-                    grammar_string.parse().expect("Could not parse grammar.");
-                if params.is_present("disco-lexer") {
-                    let lexer = File::open(
-                        params
-                            .value_of("disco-lexer")
-                            .expect("Missing lexer file as argument"),
+                let dgmr: DiscoDopGrammar<_, _, _> = grammar_string.parse().unwrap_or_else(|e| {
+                    exit_with_error(
+                        grammar_name,
+                        format!("malformed disco-dop grammar ({:?})", e),
                     )
-                    .expect("could not open lexer file");
-                    let lexer_string = FileReader::new(lexer, grammar_is_gzipped)
-                        .read()
-                        .expect("could not read lexer file");
-                    dgmr.with_lexer(lexer_string.parse().expect("Could not parse lexer file."))
-                        .into()
+                });
+                if let Some(lexer_name) = params.value_of("disco-lexer") {
+                    let lexer_string = read_or_exit(Some(lexer_name), grammar_is_gzipped);
+                    let lexer = lexer_string.parse().unwrap_or_else(|e| {
+                        exit_with_error(lexer_name, format!("malformed disco-dop lexer ({:?})", e))
+                    });
+                    dgmr.with_lexer(lexer).into()
                 } else {
                     dgmr.with_default_lexer().into()
                 }
+            } else if params.is_present("negra-treebank") {
+                let treebank = read_negra(&grammar_string)
+                    .unwrap_or_else(|e| exit_with_error(grammar_name, e));
+                if let Some(strategy) = binarization {
+                    extract_markovized_lcfrs(&treebank, strategy, &markovization)
+                } else {
+                    extract_lcfrs(&treebank)
+                }
+                .unwrap_or_else(|e| exit_with_error(grammar_name, e))
+            } else if params.is_present("srcg") {
+                let SrcgGrammar(pmcfg) = parse_or_exit(&grammar_string, grammar_name);
+                to_lcfrs(pmcfg, grammar_name)
             } else {
-                grammar_string
-                    .parse()
-                    .expect("Could not decode the grammar provided via stdin.")
+                parse_or_exit(&grammar_string, grammar_name)
+            };
+            let gmr = match binarization {
+                Some(strategy) if !params.is_present("negra-treebank") => {
+                    gmr.markovize(strategy, &markovization)
+                }
+                _ => gmr,
             };
 
             bincode::serialize_into(
@@ -227,11 +254,13 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
             let candidates: Option<usize> =
                 params.value_of("candidates").map(|s| s.parse().unwrap());
 
-            let csfile = File::open(params.value_of("csfile").unwrap()).unwrap();
+            let csfile_name = params.value_of("csfile").unwrap();
+            let csfile =
+                File::open(csfile_name).unwrap_or_else(|e| exit_with_error(csfile_name, e));
 
             let csrep: CSRepresentation<String, String, LogDomain<f64>> =
                 bincode::deserialize_from(&mut read::GzDecoder::new(csfile), bincode::Infinite)
-                    .unwrap();
+                    .unwrap_or_else(|e| exit_with_error(csfile_name, e));
             let mut parser = csrep.build_generator();
             if let Some(beam) = beam_width {
                 parser.set_beam(beam)
@@ -243,6 +272,8 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                 parser.set_candidates(candidates)
             };
 
+            let output_format = params.value_of("output-format").unwrap();
+
             for (i, sentence) in word_strings.lines().enumerate() {
                 let (i, words) = split_line(sentence, params.is_present("with-lines"), i);
                 let (words, negra_mode) = split_pos(words, params.is_present("with-pos"));
@@ -253,15 +284,15 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                     match tuple.3 {
                         DebugResult::Parse(t, n) => {
                             eprintln!("parse {}", n);
-                            println!("{}", to_negra(&t, i, negra_mode));
+                            print_tree(&t, i, &words, negra_mode, output_format);
                         }
                         DebugResult::Fallback(t, n) => {
                             eprintln!("fallback {}", n);
-                            println!("{}", to_negra(&t, i, negra_mode));
+                            print_tree(&t, i, &words, negra_mode, output_format);
                         }
                         DebugResult::Noparse => {
                             eprintln!("noparse 0");
-                            println!("{}", noparse(&words, i, negra_mode));
+                            print_noparse(&words, i, negra_mode, output_format);
                         }
                     }
                 } else {
@@ -269,26 +300,20 @@ pub fn handle_sub_matches(submatches: &ArgMatches) {
                     let (iterator, fallback) = parser.with_fallback(words.as_slice());
                     for derivation in iterator.take(k) {
                         found_trees = true;
-                        println!(
-                            "{}",
-                            to_negra(
-                                &derivation
-                                    .into_iter()
-                                    .map(|(k, v)| (k, v.clone()))
-                                    .collect(),
-                                i,
-                                negra_mode.clone()
-                            )
-                        );
+                        let tree = derivation
+                            .into_iter()
+                            .map(|(k, v)| (k, v.clone()))
+                            .collect();
+                        print_tree(&tree, i, &words, negra_mode.clone(), output_format);
                     }
                     if !found_trees && params.is_present("fallback") {
                         if let Some(tree) = fallback {
-                            println!("{}", to_negra(&tree, i, negra_mode));
+                            print_tree(&tree, i, &words, negra_mode, output_format);
                         } else {
-                            println!("{}", noparse(&words, i, negra_mode));
+                            print_noparse(&words, i, negra_mode, output_format);
                         }
                     } else if !found_trees {
-                        println!("{}", noparse(&words, i, negra_mode));
+                        print_noparse(&words, i, negra_mode, output_format);
                     }
                 }
             }
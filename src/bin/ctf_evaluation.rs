@@ -11,6 +11,9 @@ use std::io::Read;
 use std::io;
 use std::rc::Rc;
 
+use crate::parse_error::{parse_or_exit, read_file};
+use crate::pmcfg::{read_grammar, srcg_arg};
+
 use log_domain::LogDomain;
 
 use rustomata::automata::tree_stack_automaton::{TreeStackAutomaton,PosState};
@@ -119,6 +122,7 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .required(true)
                         .index(1)
                 )
+                .arg(srcg_arg())
                 .arg(
                     Arg::with_name("tts")
                         .long("tts")
@@ -145,6 +149,7 @@ pub fn get_sub_command() -> App<'static, 'static> {
                     Arg::with_name("file_name")
                         .required(true)
                 )
+                .arg(srcg_arg())
         )
 }
 
@@ -183,8 +188,8 @@ macro_rules! recognise {
 // handle a given cfg grammar with a pda
 pub fn handle_cfg_matches(cfg_matches : &ArgMatches) {
     let grammar_file = cfg_matches.value_of("grammar").unwrap();
-    let grammar_string = read_file(grammar_file.to_string());
-    let g : CFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+    let grammar_string = read_file(grammar_file);
+    let g : CFG<String, String, LogDomain<f64>> = parse_or_exit(&grammar_string, grammar_file);
 
     let a = PushDownAutomaton::from(g);
 
@@ -219,9 +224,9 @@ pub fn handle_cfg_matches(cfg_matches : &ArgMatches) {
                 
                 let rlb_file = fst_additional.unwrap();
                 // create the rlb strategy
-                let classes_string = read_file(rlb_file);
+                let classes_string = read_file(&rlb_file);
 
-                let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                let e: EquivalenceRelation<String, String> = parse_or_exit(&classes_string, &rlb_file);
                 let f = |ps: &PushState<_,_>| ps.map(|nt| e.project(nt));
                 let s1 = RlbElement::new(&f);
 
@@ -277,9 +282,9 @@ pub fn handle_cfg_matches(cfg_matches : &ArgMatches) {
                             
                             let rlb_file = sec_additional.unwrap();
                             // create rlb strategy
-                            let classes_string = read_file(rlb_file);
+                            let classes_string = read_file(&rlb_file);
                             
-                            let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                            let e: EquivalenceRelation<String, String> = parse_or_exit(&classes_string, &rlb_file);
                             let f = |ps: &PushState<_,_>| ps.map(|nt| e.project(nt));
                             let s2 = RlbElement::new(&f);
 
@@ -313,21 +318,20 @@ pub fn bench_mcfg(mcfg_matches : &ArgMatches) {
     let equiv_file2 = format!("{}{}", &file_name, "_2.classes");
     let corpus_file = format!("{}{}", &file_name, ".txt");
 
-    let grammar_string = read_file(grammar_file);
-    let equiv_string1 = read_file(equiv_file1);
+    let equiv_string1 = read_file(&equiv_file1);
     
-    let corpus_string = read_file(corpus_file);
+    let corpus_string = read_file(&corpus_file);
 
-    let file_opt = File::open(equiv_file2).ok();
+    let file_opt = File::open(&equiv_file2).ok();
     let equiv_string2_opt = file_opt.map(|mut file| {
         let mut string = String::new();
         let _ = file.read_to_string(&mut string);
         string
     });
 
-    let grammar : PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
-    let equiv_rel1 : EquivalenceRelation<String, String> = equiv_string1.parse().unwrap();
-    let equiv_rel2_opt : Option<EquivalenceRelation<String, String>> = equiv_string2_opt.map(|equiv_string2| equiv_string2.parse().unwrap());
+    let grammar = read_grammar(&grammar_file, mcfg_matches.is_present("srcg"));
+    let equiv_rel1 : EquivalenceRelation<String, String> = parse_or_exit(&equiv_string1, &equiv_file1);
+    let equiv_rel2_opt : Option<EquivalenceRelation<String, String>> = equiv_string2_opt.map(|equiv_string2| parse_or_exit(&equiv_string2, &equiv_file2));
     
     
     let mut corpus : Vec<Vec<String>> = 
@@ -513,8 +517,7 @@ pub fn handle_mcfg_matches(mcfg_matches : &ArgMatches) {
 
 
     let grammar_file = mcfg_matches.value_of("grammar").unwrap();
-    let grammar_string = read_file(grammar_file.to_string());
-    let g : PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+    let g = read_grammar(grammar_file, mcfg_matches.is_present("srcg"));
 
     let a = TreeStackAutomaton::from(g);
 
@@ -563,9 +566,9 @@ pub fn handle_mcfg_matches(mcfg_matches : &ArgMatches) {
                             
                             let rlb_file = sec_additional.unwrap();
                             // create the rlb strategy
-                            let classes_string = read_file(rlb_file);
+                            let classes_string = read_file(&rlb_file);
                             //let e: EquivalenceRelation<PMCFGRule<_,_,_>, String> = classes_string.parse().unwrap();
-                            let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                            let e: EquivalenceRelation<String, String> = parse_or_exit(&classes_string, &rlb_file);
                             let f = |ps: &PosState<PMCFGRule<_,_,_>>| ps.map(|r| r.map_nonterminals(|nt| e.project(nt)));
                             let s2 = RlbElement::new(&f);
 
@@ -621,8 +624,8 @@ pub fn handle_mcfg_matches(mcfg_matches : &ArgMatches) {
                                         
                                         let rlb_file = trd_additional.unwrap();
                                         // create rlb strategy
-                                        let classes_string = read_file(rlb_file);
-                                        let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                                        let classes_string = read_file(&rlb_file);
+                                        let e: EquivalenceRelation<String, String> = parse_or_exit(&classes_string, &rlb_file);
                                         let f = |ps: &PosState<PMCFGRule<_,_,_>>| ps.map(|r| r.map_nonterminals(|nt| e.project(nt)));
                                         let s3 = RlbElement::new(&f);
 
@@ -652,8 +655,8 @@ pub fn handle_mcfg_matches(mcfg_matches : &ArgMatches) {
                 let rlb_file = fst_additional.unwrap();
 
                 // create the rlb strategy
-                let classes_string = read_file(rlb_file);
-                let e: EquivalenceRelation<String, String> = classes_string.parse().unwrap();
+                let classes_string = read_file(&rlb_file);
+                let e: EquivalenceRelation<String, String> = parse_or_exit(&classes_string, &rlb_file);
                 let f = |ps: &PosState<PMCFGRule<_,_,_>>| ps.map(|r| r.map_nonterminals(|nt| e.project(nt)));
                 let s1 = RlbElementTSA::new(&f);
 
@@ -768,11 +771,3 @@ fn get_tuple_vec(arg_matches : &ArgMatches, s : &str)
     }
     vec
 }
-
-// returns the content of the file given by the `path` string
-fn read_file(path: String) -> String {
-    let mut file = File::open(path).unwrap();
-    let mut string = String::new();
-    let _ = file.read_to_string(&mut string);
-    string
-}
@@ -0,0 +1,67 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rustomata::grammars::pmcfg::negra::{evaluate_treebank, read_negra, EvalParams, NegraSentence};
+use std::process;
+
+use crate::parse_error::{exit_with_error, parse_or_exit, read_file};
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("eval")
+        .about(
+            "compares parse trees with gold trees (both in NEGRA export format) and prints \
+             labeled bracket scores of discontinuous constituents, the exact match, the \
+             tagging accuracy and the scores of each label",
+        )
+        .arg(
+            Arg::with_name("gold")
+                .help("treebank with the gold trees")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("parses")
+                .help("treebank with the parse trees, in the same order as the gold trees")
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("param")
+                .help(
+                    "parameter file of EVALB or disco-dop, e.g. with labels (`DELETE_LABEL`) \
+                     and punctuation (`DELETE_ROOT_PRETERMINALS`) to ignore",
+                )
+                .long("param")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("output format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["table", "json"])
+                .default_value("table"),
+        )
+}
+
+pub fn handle_sub_matches(eval_matches: &ArgMatches) {
+    let gold = read_treebank(eval_matches.value_of("gold").unwrap());
+    let parses = read_treebank(eval_matches.value_of("parses").unwrap());
+    let params: EvalParams = match eval_matches.value_of("param") {
+        Some(file_name) => parse_or_exit(&read_file(file_name), file_name),
+        None => EvalParams::default(),
+    };
+
+    let evaluation = evaluate_treebank(&gold, &parses, &params).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1)
+    });
+
+    match eval_matches.value_of("format").unwrap() {
+        "table" => println!("{}", evaluation),
+        "json" => println!("{}", serde_json::to_string_pretty(&evaluation).unwrap()),
+        _ => unreachable!(),
+    }
+}
+
+fn read_treebank(file_name: &str) -> Vec<NegraSentence> {
+    read_negra(&read_file(file_name)).unwrap_or_else(|e| exit_with_error(file_name, e))
+}
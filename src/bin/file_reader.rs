@@ -0,0 +1,47 @@
+use flate2::read;
+use std::fs::File;
+use std::io::{stdin, Read};
+
+use crate::parse_error::exit_with_error;
+
+/// Reads a file that is optionally compressed with gzip.
+pub enum FileReader<R: Read> {
+    Plain(R),
+    GZipped(R),
+}
+
+impl<R: Read> FileReader<R> {
+    pub fn new(file: R, zipped: bool) -> Self {
+        if zipped {
+            FileReader::GZipped(file)
+        } else {
+            FileReader::Plain(file)
+        }
+    }
+
+    pub fn read(self) -> Result<String, ::std::io::Error> {
+        let mut s = String::new();
+        match self {
+            FileReader::Plain(mut f) => {
+                f.read_to_string(&mut s)?;
+            }
+            FileReader::GZipped(f) => {
+                read::GzDecoder::new(f).read_to_string(&mut s)?;
+            }
+        }
+        Ok(s)
+    }
+}
+
+/// Reads a file that is optionally compressed with gzip, or stdin if no file name is given;
+/// exits with an error naming the file if it can not be read.
+pub fn read_or_exit(file_name: Option<&str>, zipped: bool) -> String {
+    match file_name {
+        Some(file_name) => File::open(file_name)
+            .and_then(|file| FileReader::new(file, zipped).read())
+            .unwrap_or_else(|e| exit_with_error(file_name, e)),
+        None => FileReader::new(stdin(), zipped)
+            .read()
+            .unwrap_or_else(|e| exit_with_error("stdin", e)),
+    }
+}
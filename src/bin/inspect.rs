@@ -0,0 +1,105 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log_domain::LogDomain;
+use rustomata::grammars::cfg::CFG;
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::stats::{GrammarStats, LexicalCoverage};
+
+use crate::parse_error::{parse_or_exit, read_file};
+use crate::pmcfg::{read_grammar, srcg_arg};
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("inspect")
+        .about(
+            "prints figures of a grammar, e.g. its fanout and rank, and whether it is \
+             well-nested, monotone and non-deleting",
+        )
+        .arg(
+            Arg::with_name("type")
+                .help("type of the grammar")
+                .long("type")
+                .takes_value(true)
+                .possible_values(&["mcfg", "lcfrs", "cfg"])
+                .default_value("mcfg"),
+        )
+        .arg(srcg_arg().help("read the grammar in the notation of an sRCG (mcfg only)"))
+        .arg(
+            Arg::with_name("format")
+                .help("output format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["table", "json"])
+                .default_value("table"),
+        )
+        .arg(
+            Arg::with_name("corpus")
+                .help(
+                    "file with one sentence of space-separated terminals per line; \
+                     its lexical coverage by the grammar is printed as well",
+                )
+                .long("corpus")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("grammar")
+                .help("grammar file to use")
+                .index(1)
+                .required(true),
+        )
+}
+
+pub fn handle_sub_matches(inspect_matches: &ArgMatches) {
+    let grammar_file_name = inspect_matches.value_of("grammar").unwrap();
+    let corpus: Option<Vec<String>> = inspect_matches.value_of("corpus").map(|file_name| {
+        read_file(file_name)
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect()
+    });
+    let (stats, coverage) = match inspect_matches.value_of("type").unwrap() {
+        "mcfg" => {
+            let grammar = read_grammar(grammar_file_name, inspect_matches.is_present("srcg"));
+            (
+                grammar.stats(),
+                corpus.map(|words| grammar.lexical_coverage(&words)),
+            )
+        }
+        "lcfrs" => {
+            let grammar: Lcfrs<String, String, LogDomain<f64>> =
+                parse_or_exit(&read_file(grammar_file_name), grammar_file_name);
+            (
+                grammar.stats(),
+                corpus.map(|words| grammar.lexical_coverage(&words)),
+            )
+        }
+        "cfg" => {
+            let grammar: CFG<String, String, LogDomain<f64>> =
+                parse_or_exit(&read_file(grammar_file_name), grammar_file_name);
+            (
+                grammar.stats(),
+                corpus.map(|words| grammar.lexical_coverage(&words)),
+            )
+        }
+        _ => unreachable!(),
+    };
+
+    match inspect_matches.value_of("format").unwrap() {
+        "table" => print_table(&stats, coverage.as_ref()),
+        "json" => print_json(&stats, coverage.as_ref()),
+        _ => unreachable!(),
+    }
+}
+
+fn print_table(stats: &GrammarStats<String>, coverage: Option<&LexicalCoverage<String>>) {
+    println!("{}", stats);
+    if let Some(coverage) = coverage {
+        println!("{}", coverage);
+    }
+}
+
+fn print_json(stats: &GrammarStats<String>, coverage: Option<&LexicalCoverage<String>>) {
+    let mut json = serde_json::to_value(stats).unwrap();
+    if let Some(coverage) = coverage {
+        json["lexical_coverage"] = serde_json::to_value(coverage).unwrap();
+    }
+    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+}
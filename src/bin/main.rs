@@ -1,6 +1,8 @@
 extern crate clap;
 extern crate flate2;
 extern crate log_domain;
+extern crate rand;
+extern crate serde_json;
 
 #[macro_use]
 extern crate rustomata;
@@ -9,8 +11,16 @@ use clap::App;
 
 mod approximation;
 mod cfg;
+mod convert;
 mod csparsing;
+mod eval;
+mod file_reader;
+mod inspect;
+mod output;
+mod parse_error;
 mod pmcfg;
+mod reduce;
+mod train;
 mod tree_stack_automata;
 mod ctf_evaluation;
 
@@ -25,6 +35,11 @@ fn main() {
         .subcommand(approximation::get_sub_command())
         .subcommand(csparsing::get_sub_command("csparsing"))
         .subcommand(ctf_evaluation::get_sub_command())
+        .subcommand(convert::get_sub_command())
+        .subcommand(reduce::get_sub_command())
+        .subcommand(train::get_sub_command())
+        .subcommand(inspect::get_sub_command())
+        .subcommand(eval::get_sub_command())
         .get_matches();
 
     match matches.subcommand() {
@@ -34,6 +49,11 @@ fn main() {
         ("approximation", Some(r_matches)) => approximation::handle_sub_matches(r_matches),
         ("csparsing", Some(r_matches)) => csparsing::handle_sub_matches(r_matches),
         ("ctf-eval", Some(ctf_matches)) => ctf_evaluation::handle_sub_matches(ctf_matches),
+        ("convert", Some(convert_matches)) => convert::handle_sub_matches(convert_matches),
+        ("reduce", Some(reduce_matches)) => reduce::handle_sub_matches(reduce_matches),
+        ("train", Some(train_matches)) => train::handle_sub_matches(train_matches),
+        ("inspect", Some(inspect_matches)) => inspect::handle_sub_matches(inspect_matches),
+        ("eval", Some(eval_matches)) => eval::handle_sub_matches(eval_matches),
         _ => (),
     }
 }
@@ -0,0 +1,79 @@
+use clap::{Arg, ArgMatches};
+use rustomata::grammars::pmcfg::negra::{
+    noparse, noparse_discbracket, noparse_ptb, to_discbracket, to_negra, to_ptb, DumpMode,
+};
+use rustomata::grammars::pmcfg::PMCFGRule;
+use rustomata::util::dot::ToDot;
+use rustomata::util::tree::GornTree;
+use std::fmt::Display;
+
+/// Option for subcommands that print an automaton, see `print_automaton`.
+pub fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .help("output format of the automaton; `dot` is the input language of Graphviz")
+        .long("format")
+        .value_name("format")
+        .possible_values(&["text", "dot"])
+        .default_value("text")
+}
+
+/// Prints an automaton in the format given by `format_arg`.
+pub fn print_automaton<A: Display + ToDot>(automaton: &A, matches: &ArgMatches) {
+    match matches.value_of("format") {
+        Some("dot") => println!("{}", automaton.to_dot()),
+        _ => println!("{}", automaton),
+    }
+}
+
+/// Option for subcommands that print parse trees, see `print_tree`.
+pub fn output_format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("output-format")
+        .help(
+            "output format of the parse trees: the NEGRA export format, the discontinuous \
+             bracket notation of disco-dop, or the bracket notation of the Penn treebank \
+             (for continuous trees)",
+        )
+        .long("output-format")
+        .value_name("format")
+        .possible_values(&["negra", "discbracket", "ptb"])
+        .default_value("negra")
+}
+
+/// Prints a parse tree of the sentence `words` in a format of `output_format_arg`; a
+/// discontinuous tree is replaced by the `NOPARSE` tree in the bracket notation of the
+/// Penn treebank.
+pub fn print_tree<H, T, W>(
+    tree: &GornTree<PMCFGRule<H, T, W>>,
+    sentence_id: usize,
+    words: &[T],
+    mode: DumpMode<T>,
+    format: &str,
+) where
+    H: Clone + ToString,
+    T: Clone + ToString,
+{
+    match format {
+        "discbracket" => println!("{}", to_discbracket(tree, mode)),
+        "ptb" => match to_ptb(tree, mode.clone()) {
+            Ok(ptb) => println!("{}", ptb),
+            Err(err) => {
+                eprintln!("Sentence {}: {}", sentence_id, err);
+                println!("{}", noparse_ptb(words, mode));
+            }
+        },
+        _ => println!("{}", to_negra(tree, sentence_id, mode)),
+    }
+}
+
+/// Prints the flat `NOPARSE` tree of the sentence `words` in a format of
+/// `output_format_arg`.
+pub fn print_noparse<T>(words: &[T], sentence_id: usize, mode: DumpMode<T>, format: &str)
+where
+    T: ToString,
+{
+    match format {
+        "discbracket" => println!("{}", noparse_discbracket(words, mode)),
+        "ptb" => println!("{}", noparse_ptb(words, mode)),
+        _ => println!("{}", noparse(words, sentence_id, mode)),
+    }
+}
@@ -0,0 +1,58 @@
+use rustomata::util::parsing::ParseError;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use std::str::FromStr;
+
+/// Reads an object (e.g. a grammar) from the content of a file.
+/// If the content is malformed, the error is printed together with the offending line and
+/// the program exits with a non-zero exit code.
+pub fn parse_or_exit<A>(content: &str, file_name: &str) -> A
+where
+    A: FromStr<Err = ParseError>,
+{
+    match content.parse() {
+        Ok(result) => result,
+        Err(error) => {
+            eprintln!("error: could not read {}", file_name);
+            eprintln!("  {}", error);
+            if let Some((line, column)) = error.position() {
+                if let Some(l) = content.lines().nth(line - 1) {
+                    let indentation: String = l
+                        .chars()
+                        .take(column - 1)
+                        .map(|c| if c == '\t' { '\t' } else { ' ' })
+                        .collect();
+                    eprintln!("  {:>4} | {}", line, l);
+                    eprintln!("       | {}^", indentation);
+                }
+            }
+            process::exit(1)
+        }
+    }
+}
+
+/// Prints that the file could not be read together with the cause and exits with a non-zero
+/// exit code; for objects whose parsers do not report the position of an error.
+pub fn exit_with_error<E: Display>(file_name: &str, error: E) -> ! {
+    eprintln!("error: could not read {}", file_name);
+    eprintln!("  {}", error);
+    process::exit(1)
+}
+
+/// Prints an error that does not concern an input file, e.g. an invalid argument, and exits
+/// with a non-zero exit code.
+pub fn exit_with_message<M: Display>(message: M) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1)
+}
+
+/// Reads the content of a file; exits with an error naming the file if it can not be read.
+pub fn read_file(file_name: &str) -> String {
+    let mut content = String::new();
+    File::open(file_name)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .unwrap_or_else(|e| exit_with_error(file_name, e));
+    content
+}
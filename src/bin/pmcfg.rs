@@ -1,13 +1,22 @@
 use clap::{App, Arg, ArgMatches, SubCommand};
 use log_domain::LogDomain;
+use rand::{Rng, SeedableRng, StdRng};
 use rustomata::automata::tree_stack_automaton::to_abstract_syntax_tree;
 use rustomata::automata::tree_stack_automaton::TreeStackAutomaton;
-use rustomata::grammars::pmcfg::negra::{to_negra, DumpMode};
+use rustomata::grammars::pmcfg::negra::DumpMode;
+use rustomata::grammars::pmcfg::sample::sample;
+use rustomata::grammars::pmcfg::srcg::SrcgGrammar;
 use rustomata::grammars::pmcfg::PMCFG;
 use rustomata::recognisable::Recognisable;
 
-use std::fs::File;
 use std::io::{self, Read};
+use std::process;
+
+use crate::output::{format_arg, output_format_arg, print_automaton, print_tree};
+use crate::parse_error::{parse_or_exit, read_file};
+
+/// Maximal number of consecutive failed attempts of `mcfg sample`.
+const MAX_FAILED_SAMPLES: usize = 10_000;
 
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("mcfg")
@@ -44,7 +53,14 @@ pub fn get_sub_command() -> App<'static, 'static> {
                     Arg::with_name("negra")
                         .help("turn on output in NeGra export format")
                         .long("negra"),
-                ),
+                )
+                .arg(output_format_arg().help(
+                    "turn on output of parse trees in the given format: the NEGRA export \
+                     format (same as `--negra`), the discontinuous bracket notation of \
+                     disco-dop, or the bracket notation of the Penn treebank (for continuous \
+                     trees)",
+                ))
+                .arg(srcg_arg()),
         )
         .subcommand(
             SubCommand::with_name("automaton")
@@ -55,31 +71,121 @@ pub fn get_sub_command() -> App<'static, 'static> {
                         .help("grammar file to use")
                         .index(1)
                         .required(true),
-                ),
+                )
+                .arg(srcg_arg())
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("sample")
+                .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
+                .about(
+                    "samples sentences from the given multiple context-free grammar according \
+                     to its normalised rule weights and prints them, one per line",
+                )
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("grammar file to use")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("number-of-samples")
+                        .help("number of sentences that should be sampled")
+                        .short("n")
+                        .long("number")
+                        .value_name("number-of-samples")
+                        .default_value("1")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("max-depth")
+                        .help("maximal depth of the sampled derivations; deeper derivations are discarded")
+                        .short("d")
+                        .long("max-depth")
+                        .value_name("max-depth")
+                        .default_value("50")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("seed for the random number generator, for reproducible samples")
+                        .long("seed")
+                        .value_name("seed")
+                        .required(false),
+                )
+                .arg(
+                    Arg::with_name("derivations")
+                        .help("print the derivation of each sentence after the sentence")
+                        .long("derivations"),
+                )
+                .arg(srcg_arg()),
         )
 }
 
+/// Flag for subcommands that read an MCFG, see `read_grammar`.
+pub fn srcg_arg() -> Arg<'static, 'static> {
+    Arg::with_name("srcg")
+        .help("read the grammar in the notation of an sRCG")
+        .long("srcg")
+}
+
+/// Reads an MCFG from the given file, either in rustomata's notation or, if `srcg` is set,
+/// in the notation of an sRCG.
+pub fn read_grammar(
+    grammar_file_name: &str,
+    srcg: bool,
+) -> PMCFG<String, String, LogDomain<f64>> {
+    let grammar_string = read_file(grammar_file_name);
+    if srcg {
+        let SrcgGrammar(grammar) = parse_or_exit(&grammar_string, grammar_file_name);
+        grammar
+    } else {
+        parse_or_exit(&grammar_string, grammar_file_name)
+    }
+}
+
+/// Prints an MCFG in the notation of an sRCG; exits with an error if the grammar is deleting
+/// and can therefore not be written in this notation.
+pub fn print_srcg(grammar: PMCFG<String, String, LogDomain<f64>>) {
+    match SrcgGrammar::from_pmcfg(grammar) {
+        Ok(srcg) => print!("{}", srcg),
+        Err(e) => {
+            eprintln!("error: the grammar can not be written as an sRCG, {}", e);
+            process::exit(1)
+        }
+    }
+}
+
 pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
     match mcfg_matches.subcommand() {
         ("parse", Some(mcfg_parse_matches)) => {
             let grammar_file_name = mcfg_parse_matches.value_of("grammar").unwrap();
-            let mut grammar_file = File::open(grammar_file_name).unwrap();
             let n = mcfg_parse_matches
                 .value_of("number-of-parses")
                 .unwrap()
                 .parse()
                 .unwrap();
-            let mut grammar_string = String::new();
-            let _ = grammar_file.read_to_string(&mut grammar_string);
-            let grammar: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+            let grammar =
+                read_grammar(grammar_file_name, mcfg_parse_matches.is_present("srcg"));
 
             let automaton = TreeStackAutomaton::from(grammar);
 
             let mut corpus = String::new();
             let _ = io::stdin().read_to_string(&mut corpus);
 
+            // `--negra` selects the NEGRA export format regardless of `--output-format`
+            let output_format = if mcfg_parse_matches.is_present("negra") {
+                Some("negra")
+            } else if mcfg_parse_matches.occurrences_of("output-format") > 0 {
+                mcfg_parse_matches.value_of("output-format")
+            } else {
+                None
+            };
+
             for (i, sentence) in corpus.lines().enumerate() {
-                let word = sentence.split_whitespace().map(|x| x.to_string()).collect();
+                let words: Vec<String> =
+                    sentence.split_whitespace().map(|x| x.to_string()).collect();
+                let word = words.clone();
                 match mcfg_parse_matches.value_of("beam-width") {
                     Some(b) => {
                         for parse in automaton
@@ -87,8 +193,8 @@ pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
                             .take(n)
                         {
                             let ast = to_abstract_syntax_tree(parse.0.storage.to_tree());
-                            if mcfg_parse_matches.is_present("negra") {
-                                println!("{}", to_negra(&ast, i + 1, DumpMode::Default));
+                            if let Some(format) = output_format {
+                                print_tree(&ast, i + 1, &words, DumpMode::Default, format);
                             } else {
                                 println!("{}", parse.0);
                             }
@@ -97,8 +203,8 @@ pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
                     None => {
                         for parse in automaton.recognise(word).take(n) {
                             let ast = to_abstract_syntax_tree(parse.0.storage.to_tree());
-                            if mcfg_parse_matches.is_present("negra") {
-                                println!("{}", to_negra(&ast, i + 1, DumpMode::Default));
+                            if let Some(format) = output_format {
+                                print_tree(&ast, i + 1, &words, DumpMode::Default, format);
                             } else {
                                 println!("{}", parse.0);
                             }
@@ -110,13 +216,76 @@ pub fn handle_sub_matches(mcfg_matches: &ArgMatches) {
         }
         ("automaton", Some(mcfg_automaton_matches)) => {
             let grammar_file_name = mcfg_automaton_matches.value_of("grammar").unwrap();
-            let mut grammar_file = File::open(grammar_file_name).unwrap();
-            let mut grammar_string = String::new();
-            let _ = grammar_file.read_to_string(&mut grammar_string);
-            let grammar: PMCFG<String, String, LogDomain<f64>> = grammar_string.parse().unwrap();
+            let grammar =
+                read_grammar(grammar_file_name, mcfg_automaton_matches.is_present("srcg"));
             let automaton = TreeStackAutomaton::from(grammar);
-            println!("{}", automaton);
+            print_automaton(&automaton, mcfg_automaton_matches);
+        }
+        ("sample", Some(mcfg_sample_matches)) => {
+            let grammar_file_name = mcfg_sample_matches.value_of("grammar").unwrap();
+            let grammar =
+                read_grammar(grammar_file_name, mcfg_sample_matches.is_present("srcg"));
+            let n = mcfg_sample_matches
+                .value_of("number-of-samples")
+                .unwrap()
+                .parse()
+                .unwrap();
+            let max_depth = mcfg_sample_matches
+                .value_of("max-depth")
+                .unwrap()
+                .parse()
+                .unwrap();
+            let derivations = mcfg_sample_matches.is_present("derivations");
+
+            match mcfg_sample_matches.value_of("seed") {
+                Some(seed) => {
+                    let seed: &[usize] = &[seed.parse().unwrap()];
+                    let mut rng: StdRng = SeedableRng::from_seed(seed);
+                    print_samples(&grammar, &mut rng, n, max_depth, derivations);
+                }
+                None => {
+                    print_samples(&grammar, &mut rand::thread_rng(), n, max_depth, derivations)
+                }
+            }
         }
         _ => (),
     }
 }
+
+/// Prints `n` sentences sampled from `grammar`, retrying whenever a derivation is too deep.
+/// Gives up after `MAX_FAILED_SAMPLES` consecutive failures.
+fn print_samples<R: Rng>(
+    grammar: &PMCFG<String, String, LogDomain<f64>>,
+    rng: &mut R,
+    n: usize,
+    max_depth: usize,
+    derivations: bool,
+) {
+    let mut failures = 0;
+    let mut samples = 0;
+    while samples < n {
+        match sample(grammar, rng, max_depth) {
+            Some((derivation, word)) => {
+                println!("{}", word.join(" "));
+                if derivations {
+                    for (address, rule) in derivation {
+                        println!("  {:?}: {}", address, rule);
+                    }
+                }
+                samples += 1;
+                failures = 0;
+            }
+            None => {
+                failures += 1;
+                if failures >= MAX_FAILED_SAMPLES {
+                    eprintln!(
+                        "Gave up after {} consecutive derivations that were deeper than {} \
+                         or got stuck at a nonterminal without rules.",
+                        failures, max_depth
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log_domain::LogDomain;
+use rustomata::grammars::cfg::CFG;
+use rustomata::grammars::lcfrs::Lcfrs;
+use rustomata::grammars::reduce::Reduction;
+use std::fmt::Display;
+
+use crate::parse_error::{parse_or_exit, read_file};
+use crate::pmcfg::{print_srcg, read_grammar, srcg_arg};
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("reduce")
+        .about(
+            "removes unproductive and unreachable nonterminals and their rules from a grammar; \
+             the reduced grammar is printed to stdout, the removed nonterminals to stderr",
+        )
+        .arg(
+            Arg::with_name("type")
+                .help("type of the grammar")
+                .long("type")
+                .takes_value(true)
+                .possible_values(&["mcfg", "lcfrs", "cfg"])
+                .default_value("mcfg"),
+        )
+        .arg(srcg_arg().help("read and write the grammar in the notation of an sRCG (mcfg only)"))
+        .arg(
+            Arg::with_name("grammar")
+                .help("grammar file to use")
+                .index(1)
+                .required(true),
+        )
+}
+
+pub fn handle_sub_matches(reduce_matches: &ArgMatches) {
+    let grammar_file_name = reduce_matches.value_of("grammar").unwrap();
+    match reduce_matches.value_of("type").unwrap() {
+        "mcfg" => {
+            let srcg = reduce_matches.is_present("srcg");
+            let (grammar, reduction) = read_grammar(grammar_file_name, srcg).reduce();
+            if srcg {
+                print_srcg(grammar);
+            } else {
+                print!("{}", grammar);
+            }
+            report(&reduction);
+        }
+        "lcfrs" => {
+            let grammar: Lcfrs<String, String, LogDomain<f64>> =
+                parse_or_exit(&read_file(grammar_file_name), grammar_file_name);
+            let (grammar, reduction) = grammar.reduce();
+            print!("{}", grammar);
+            report(&reduction);
+        }
+        "cfg" => {
+            let grammar: CFG<String, String, LogDomain<f64>> =
+                parse_or_exit(&read_file(grammar_file_name), grammar_file_name);
+            let (grammar, reduction) = grammar.reduce();
+            print!("{}", grammar);
+            report(&reduction);
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn report<N: Display>(reduction: &Reduction<N>) {
+    if reduction.is_empty() {
+        eprintln!("the grammar is already reduced");
+    } else {
+        eprintln!("{}", reduction);
+    }
+}
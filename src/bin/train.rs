@@ -0,0 +1,100 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rustomata::grammars::pmcfg::PMCFG;
+use std::io::{self, Read};
+
+use crate::convert::to_lcfrs;
+use crate::parse_error::{exit_with_message, read_file};
+use crate::pmcfg::{print_srcg, read_grammar, srcg_arg};
+
+pub fn get_sub_command() -> App<'static, 'static> {
+    SubCommand::with_name("train")
+        .about(
+            "re-estimates the rule weights of an LCFRS from a corpus of sentences by \
+             expectation maximisation (inside-outside algorithm); the trained grammar is \
+             printed to stdout, the log-likelihood of each iteration to stderr",
+        )
+        .arg(srcg_arg().help("read and write the grammar in the notation of an sRCG"))
+        .arg(
+            Arg::with_name("iterations")
+                .help("maximal number of iterations")
+                .short("i")
+                .long("iterations")
+                .takes_value(true)
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("threshold")
+                .help("stop as soon as the log-likelihood changes by less than this")
+                .short("t")
+                .long("threshold")
+                .takes_value(true)
+                .default_value("0.0001"),
+        )
+        .arg(
+            Arg::with_name("grammar")
+                .help("grammar file to use")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("corpus")
+                .help("file with one sentence per line; reads from stdin if not provided")
+                .index(2),
+        )
+}
+
+pub fn handle_sub_matches(train_matches: &ArgMatches) {
+    let srcg = train_matches.is_present("srcg");
+    let grammar_file_name = train_matches.value_of("grammar").unwrap();
+    let grammar = to_lcfrs(read_grammar(grammar_file_name, srcg), grammar_file_name);
+    let iterations: usize = train_matches
+        .value_of("iterations")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| exit_with_message("the number of iterations must be a natural number"));
+    let threshold: f64 = train_matches
+        .value_of("threshold")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| exit_with_message("the threshold must be a number"));
+
+    let corpus = match train_matches.value_of("corpus") {
+        Some(corpus_file_name) => read_file(corpus_file_name),
+        None => {
+            let mut corpus = String::new();
+            let _ = io::stdin().read_to_string(&mut corpus);
+            corpus
+        }
+    };
+    let sentences: Vec<Vec<String>> = corpus
+        .lines()
+        .filter(|sentence| !sentence.trim().is_empty())
+        .map(|sentence| sentence.split_whitespace().map(|x| x.to_string()).collect())
+        .collect();
+
+    let trained = grammar.train(
+        &sentences,
+        iterations,
+        threshold,
+        |iteration, expectation| {
+            eprintln!(
+                "iteration {}: log-likelihood {} ({} of {} sentences without derivation)",
+                iteration,
+                expectation.log_likelihood,
+                expectation.unparsable,
+                sentences.len()
+            );
+        },
+    );
+
+    let (rules, init) = trained.destruct();
+    let grammar = PMCFG {
+        initial: vec![init],
+        rules,
+    };
+    if srcg {
+        print_srcg(grammar);
+    } else {
+        print!("{}", grammar);
+    }
+}
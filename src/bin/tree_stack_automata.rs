@@ -8,10 +8,11 @@ use rustomata::automata::tree_stack_automaton::{
 use rustomata::recognisable::coarse_to_fine::CoarseToFineRecogniser;
 use rustomata::recognisable::{Item, Recognisable};
 use std::fmt::Debug;
-use std::fs::File;
 use std::io::{self, Read};
 use std::rc::Rc;
 
+use crate::parse_error::{parse_or_exit, read_file};
+
 pub fn get_sub_command() -> App<'static, 'static> {
     SubCommand::with_name("tsa")
         .author("Tobias Denkinger <tobias.denkinger@tu-dresden.de>")
@@ -58,11 +59,9 @@ pub fn handle_sub_matches(tsa_matches: &ArgMatches) {
     match tsa_matches.subcommand() {
         ("recognise", Some(tsa_recognise_matches)) => {
             let automaton_file_name = tsa_recognise_matches.value_of("automaton").unwrap();
-            let mut automaton_file = File::open(automaton_file_name).unwrap();
-            let mut automaton_string = String::new();
-            let _ = automaton_file.read_to_string(&mut automaton_string);
+            let automaton_string = read_file(automaton_file_name);
             let automaton: TreeStackAutomaton<String, String, LogDomain<f64>> =
-                automaton_string.parse().unwrap();
+                parse_or_exit(&automaton_string, automaton_file_name);
 
             let mut corpus_raw = String::new();
             let _ = io::stdin().read_to_string(&mut corpus_raw);
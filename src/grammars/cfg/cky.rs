@@ -0,0 +1,178 @@
+//! A CKY parser for CFGs.
+//!
+//! The grammar is converted into Chomsky normal form and parsed with the
+//! chart and the lazy k-best enumeration of `lcfrs::csparsing`; for a
+//! grammar of fanout 1, the context-free approximation of its
+//! Chomsky-Schützenberger representation is exact, hence each parse of the
+//! automaton is a parse of the grammar.
+
+use std::hash::Hash;
+
+use super::cnf::{CnfNonterminal, CnfRestorer};
+use super::elimination::{to_cfg_rule, to_pmcfg_rule};
+use super::{CFGRule, CFG};
+use crate::grammars::lcfrs::csparsing::CSRepresentation;
+use crate::grammars::lcfrs::Lcfrs;
+use crate::util::factorizable::Factorizable;
+use crate::util::semiring::Semiring;
+use crate::util::tree::GornTree;
+
+/// Parses words with a CFG in Chomsky normal form; parse trees are
+/// enumerated best-first and restored to derivations of the original
+/// grammar.
+/// A parse tree stands for all derivations of the original grammar that
+/// only differ in their derivations of ε and their cycles of chain rules;
+/// its weight is the sum of their weights, and it is restored to one of
+/// them.
+/// The empty word is not recognized, see `CFG::to_cnf`.
+pub struct CkyParser<N, T, W>
+where
+    T: Eq + Hash,
+{
+    /// `None` if the language of the grammar is empty
+    representation: Option<CSRepresentation<CnfNonterminal<N, T>, T, W>>,
+    restorer: CnfRestorer<N, T, W>,
+}
+
+impl<N, T, W> CkyParser<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Ord + Hash,
+    W: Semiring + Factorizable,
+{
+    pub fn new(grammar: &CFG<N, T, W>) -> Self {
+        let (cnf, restorer) = grammar.to_cnf();
+        let rules = cnf
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, CnfNonterminal::clone))
+            .collect();
+        let representation =
+            Lcfrs::new(rules, CnfNonterminal::Start).map(|lcfrs| CSRepresentation::new(lcfrs, 0));
+
+        CkyParser {
+            representation,
+            restorer,
+        }
+    }
+
+    /// Enumerates the parse trees of `word` in descending order of their
+    /// weights.
+    pub fn parse<'a>(
+        &'a self,
+        word: &[T],
+    ) -> impl Iterator<Item = GornTree<&'a CFGRule<N, T, W>>> + 'a {
+        let restorer = &self.restorer;
+        let derivations = self
+            .representation
+            .as_ref()
+            .filter(|_| !word.is_empty())
+            .map(|representation| representation.build_generator().with_fallback(word).0);
+
+        derivations
+            .into_iter()
+            .flatten()
+            .filter_map(move |derivation| {
+                let derivation: GornTree<_> = derivation
+                    .into_iter()
+                    .map(|(address, rule)| (address, to_cfg_rule(rule.clone(), |nt| nt)))
+                    .collect();
+                restorer.restore(&derivation)
+            })
+    }
+
+    /// The `k` best parse trees of `word`.
+    pub fn kbest<'a>(&'a self, word: &[T], k: usize) -> Vec<GornTree<&'a CFGRule<N, T, W>>> {
+        self.parse(word).take(k).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::semiring::Boolean;
+    use log_domain::LogDomain;
+    use num_traits::One;
+
+    #[test]
+    fn kbest() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [Nt S, Nt S]      # 0.3\n\
+                                                        S → [T a]             # 0.6\n\
+                                                        S → [T a, Nt S, T b]  # 0.1"
+            .parse()
+            .unwrap();
+        let parser = CkyParser::new(&grammar);
+        let weight = |tree: &GornTree<&CFGRule<char, char, LogDomain<f64>>>| {
+            tree.values()
+                .fold(LogDomain::one(), |w, rule| w * rule.weight)
+        };
+
+        let parses = parser.kbest(&['a', 'a'], 5);
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[1]),
+            (vec![1], &grammar.rules[1]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(parses, vec![control]);
+
+        // "a b" is not in the language, hence only S(a S(a) b)
+        let parses = parser.kbest(&['a', 'a', 'b'], 5);
+        let control: GornTree<_> = vec![(vec![], &grammar.rules[2]), (vec![0], &grammar.rules[1])]
+            .into_iter()
+            .collect();
+        assert_eq!(parses, vec![control]);
+
+        let parses = parser.kbest(&['a', 'a', 'a'], 5);
+        assert_eq!(parses.len(), 2);
+        assert!(weight(&parses[0]) >= weight(&parses[1]));
+
+        assert!(parser.kbest(&['b'], 5).is_empty());
+        assert!(parser.kbest(&[], 5).is_empty());
+
+        // the same rules over the Boolean semiring
+        let grammar: CFG<char, char, Boolean> = "initial: [S]\n\
+                                                 S → [Nt S, Nt S]\n\
+                                                 S → [T a]\n\
+                                                 S → [T a, Nt S, T b]"
+            .parse()
+            .unwrap();
+        let parser = CkyParser::new(&grammar);
+        assert_eq!(parser.kbest(&['a', 'a', 'a'], 5).len(), 2);
+        assert!(parser.kbest(&['a', 'b'], 5).is_empty());
+    }
+
+    #[test]
+    fn several_lexical_rules() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [T a]          # 0.4\n\
+                                                        S → [T b]          # 0.4\n\
+                                                        S → [Nt S, Nt S]   # 0.2"
+            .parse()
+            .unwrap();
+        let parser = CkyParser::new(&grammar);
+
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[2]),
+            (vec![0], &grammar.rules[0]),
+            (vec![1], &grammar.rules[1]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(parser.kbest(&['a', 'b'], 5), vec![control]);
+
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[2]),
+            (vec![0], &grammar.rules[1]),
+            (vec![1], &grammar.rules[0]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(parser.kbest(&['b', 'a'], 5), vec![control]);
+
+        assert_eq!(parser.kbest(&['b'], 5).len(), 1);
+        assert_eq!(parser.kbest(&['a', 'b', 'a'], 5).len(), 2);
+    }
+}
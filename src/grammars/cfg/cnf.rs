@@ -0,0 +1,354 @@
+//! Conversion of CFGs into Chomsky normal form (CNF).
+//!
+//! The grammar is binarized, terminals in right-hand sides with several
+//! symbols are replaced by new nonterminals, and ε-rules and chain rules are
+//! eliminated as in `cfg::elimination`; hence the empty word is not in the
+//! language of the resulting grammar, but all other words keep their inside
+//! weights.
+//! Each derivation of the CNF is mapped to a derivation of the original
+//! grammar with the same yield by a `CnfRestorer`.
+
+use num_traits::{One, Zero};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Mul;
+
+use super::elimination::{ChainRestorer, EpsilonRestorer};
+use super::{CFGComposition, CFGRule, LetterT, CFG};
+use crate::util::tree::GornTree;
+
+/// A nonterminal of the Chomsky normal form of a CFG.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CnfNonterminal<N, T> {
+    /// The only initial nonterminal; it derives each initial nonterminal of
+    /// the original grammar and does not occur in right-hand sides.
+    Start,
+    Original(N),
+    /// Derives a terminal that occurs in a right-hand side with several
+    /// symbols.
+    Terminal(T),
+    /// `Suffix(r, i)` derives the right-hand side of the `r`th original rule
+    /// from its `i`th symbol on.
+    Suffix(usize, usize),
+}
+
+impl<N: fmt::Display, T: fmt::Display> fmt::Display for CnfNonterminal<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CnfNonterminal::Start => write!(f, "START"),
+            CnfNonterminal::Original(ref nt) => write!(f, "{}", nt),
+            CnfNonterminal::Terminal(ref t) => write!(f, "T[{}]", t),
+            CnfNonterminal::Suffix(r, i) => write!(f, "R{}[{}:]", r, i),
+        }
+    }
+}
+
+/// Restores derivations of a CFG from derivations of its Chomsky normal
+/// form.
+#[derive(Clone, Debug)]
+pub struct CnfRestorer<N, T, W> {
+    rules: Vec<CFGRule<N, T, W>>,
+    binarized: Vec<CFGRule<CnfNonterminal<N, T>, T, W>>,
+    /// For each binarized rule whose head is an original nonterminal: the
+    /// index of the original rule.
+    origins: Vec<Option<usize>>,
+    epsilon: EpsilonRestorer<CnfNonterminal<N, T>, T, W>,
+    chains: ChainRestorer<CnfNonterminal<N, T>, T, W>,
+}
+
+impl<N, T, W> CnfRestorer<N, T, W>
+where
+    N: Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone,
+{
+    /// Maps a derivation of the Chomsky normal form to a derivation of the
+    /// original grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// Chomsky normal form, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&CFGRule<N, T, W>>>
+    where
+        R: Borrow<CFGRule<CnfNonterminal<N, T>, T, W>>,
+    {
+        let tree = self.chains.restore(tree)?;
+        let tree = self.epsilon.restore_indices(&tree)?;
+        Some(
+            self.restore_indices(&tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Reassembles the binarized rules in a derivation of the binarized
+    /// grammar and removes the rules of `CnfNonterminal::Start` and
+    /// `CnfNonterminal::Terminal`; the derivation consists of indices of
+    /// binarized rules, yields the indices of the original rules.
+    fn restore_indices(&self, tree: &GornTree<usize>) -> Option<GornTree<usize>> {
+        let rule = |address: &Vec<usize>| Some(&self.binarized[*tree.get(address)?]);
+        let root = rule(&Vec::new())?;
+        let root_address = if root.head == CnfNonterminal::Start {
+            vec![0]
+        } else {
+            Vec::new()
+        };
+
+        let mut restored = GornTree::new();
+        let mut stack = vec![(root_address, Vec::new())];
+
+        while let Some((address, original_address)) = stack.pop() {
+            restored.insert(
+                original_address.clone(),
+                self.origins[*tree.get(&address)?]?,
+            );
+
+            // the successors of the original rule, spread over a chain of suffixes
+            let mut successors = 0;
+            let mut node = Some((address.clone(), rule(&address)?));
+            while let Some((node_address, node_rule)) = node.take() {
+                let labels = node_rule
+                    .composition
+                    .composition
+                    .iter()
+                    .filter_map(|letter| match *letter {
+                        LetterT::Label(ref nt) => Some(nt),
+                        LetterT::Value(_) => None,
+                    });
+                for (i, nt) in labels.enumerate() {
+                    let mut child_address = node_address.clone();
+                    child_address.push(i);
+                    match *nt {
+                        CnfNonterminal::Original(_) => {
+                            let mut original_child_address = original_address.clone();
+                            original_child_address.push(successors);
+                            successors += 1;
+                            stack.push((child_address, original_child_address));
+                        }
+                        CnfNonterminal::Suffix(_, _) => {
+                            let suffix_rule = rule(&child_address)?;
+                            node = Some((child_address, suffix_rule));
+                        }
+                        CnfNonterminal::Terminal(_) | CnfNonterminal::Start => (),
+                    }
+                }
+            }
+        }
+
+        Some(restored)
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + One + PartialOrd,
+{
+    /// Converts the grammar into Chomsky normal form, i.e. each rule is
+    /// either of the form `A → [T a]` or `A → [Nt B, Nt C]`, and the only
+    /// initial nonterminal does not occur in right-hand sides.
+    /// The new rules that derive terminals, suffixes of right-hand sides and
+    /// initial nonterminals have weight one.
+    pub fn to_cnf(&self) -> (CFG<CnfNonterminal<N, T>, T, W>, CnfRestorer<N, T, W>) {
+        use self::CnfNonterminal::*;
+
+        let mut rules: Vec<CFGRule<CnfNonterminal<N, T>, T, W>> = self
+            .initial
+            .iter()
+            .map(|nt| CFGRule {
+                head: Start,
+                composition: CFGComposition::from(vec![LetterT::Label(Original(nt.clone()))]),
+                weight: W::one(),
+            })
+            .collect();
+        let mut origins = vec![None; rules.len()];
+        let mut terminals = HashSet::new();
+
+        for (rule_id, rule) in self.rules.iter().enumerate() {
+            let lexical = rule.composition.composition.len() < 2;
+            let mut symbols = Vec::with_capacity(rule.composition.composition.len());
+            for letter in &rule.composition.composition {
+                symbols.push(match *letter {
+                    LetterT::Label(ref nt) => LetterT::Label(Original(nt.clone())),
+                    LetterT::Value(ref t) if lexical => LetterT::Value(t.clone()),
+                    LetterT::Value(ref t) => {
+                        if terminals.insert(t.clone()) {
+                            rules.push(CFGRule {
+                                head: Terminal(t.clone()),
+                                composition: CFGComposition::from(vec![LetterT::Value(t.clone())]),
+                                weight: W::one(),
+                            });
+                            origins.push(None);
+                        }
+                        LetterT::Label(Terminal(t.clone()))
+                    }
+                });
+            }
+
+            // binarization: the last two symbols are derived by the last suffix
+            let mut head = Original(rule.head.clone());
+            let mut weight = rule.weight.clone();
+            while symbols.len() > 2 {
+                let i = rule.composition.composition.len() - symbols.len() + 1;
+                let rest = symbols.split_off(1);
+                let top = CFGRule {
+                    head,
+                    composition: CFGComposition::from(vec![
+                        symbols.pop().unwrap(),
+                        LetterT::Label(Suffix(rule_id, i)),
+                    ]),
+                    weight,
+                };
+                rules.push(top);
+                origins.push(if i == 1 { Some(rule_id) } else { None });
+                head = Suffix(rule_id, i);
+                weight = W::one();
+                symbols = rest;
+            }
+            let last = CFGRule {
+                head,
+                composition: CFGComposition::from(symbols),
+                weight,
+            };
+            origins.push(match last.head {
+                Original(_) => Some(rule_id),
+                _ => None,
+            });
+            rules.push(last);
+        }
+
+        let binarized = CFG {
+            initial: vec![Start],
+            rules,
+        };
+        let (epsilon_free, epsilon) = binarized.eliminate_epsilon();
+        let (cnf, chains) = epsilon_free.eliminate_chains();
+
+        (
+            cnf,
+            CnfRestorer {
+                rules: self.rules.clone(),
+                binarized: binarized.rules,
+                origins,
+                epsilon,
+                chains,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cnf() {
+        let grammar: CFG<char, char, f64> = "initial: [S]\n\
+                                             S → [T a, Nt S, Nt B, T b] # 0.5\n\
+                                             S → [Nt B]                 # 0.5\n\
+                                             B → [T c]                  # 0.5\n\
+                                             B → []                     # 0.5"
+            .parse()
+            .unwrap();
+        let (cnf, restorer) = grammar.to_cnf();
+
+        assert_eq!(cnf.initial, vec![CnfNonterminal::Start]);
+        for rule in &cnf.rules {
+            let symbols = &rule.composition.composition;
+            match symbols.len() {
+                1 => assert!(match symbols[0] {
+                    LetterT::Value(_) => true,
+                    LetterT::Label(_) => false,
+                }),
+                2 => assert!(symbols.iter().all(|letter| match *letter {
+                    LetterT::Label(ref nt) => *nt != CnfNonterminal::Start,
+                    LetterT::Value(_) => false,
+                })),
+                _ => panic!("{:?} is not in Chomsky normal form", rule),
+            }
+        }
+
+        use self::CnfNonterminal::*;
+        let find = |head: CnfNonterminal<char, char>, symbols: &[LetterT<_, char>]| {
+            cnf.rules
+                .iter()
+                .find(|rule| rule.head == head && rule.composition.composition == symbols)
+                .unwrap()
+        };
+        // a S B b with S → B → c and B → ε
+        let derivation: GornTree<_> = vec![
+            (
+                vec![],
+                find(
+                    Start,
+                    &[LetterT::Label(Terminal('a')), LetterT::Label(Suffix(0, 1))],
+                ),
+            ),
+            (vec![0], find(Terminal('a'), &[LetterT::Value('a')])),
+            (
+                vec![1],
+                find(
+                    Suffix(0, 1),
+                    &[LetterT::Label(Original('S')), LetterT::Label(Suffix(0, 2))],
+                ),
+            ),
+            (vec![1, 0], find(Original('S'), &[LetterT::Value('c')])),
+            (vec![1, 1], find(Suffix(0, 2), &[LetterT::Value('b')])),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            derivation.values().map(|rule| rule.weight).product::<f64>(),
+            0.5 * 0.5 * 0.5 * 0.5
+        );
+
+        let restored = restorer.restore(&derivation).unwrap();
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[1]),
+            (vec![0, 0], &grammar.rules[2]),
+            (vec![1], &grammar.rules[3]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restored, control);
+    }
+
+    #[test]
+    fn coinciding_rules() {
+        let grammar: CFG<char, char, f64> = "initial: [S]\n\
+                                             S → [Nt A, Nt A] # 0.25\n\
+                                             S → [Nt A, Nt A] # 0.75\n\
+                                             A → [T a]"
+            .parse()
+            .unwrap();
+        let (cnf, restorer) = grammar.to_cnf();
+
+        let start: Vec<_> = cnf
+            .rules
+            .iter()
+            .filter(|rule| rule.head == CnfNonterminal::Start)
+            .collect();
+        assert_eq!(start.len(), 1);
+        assert_eq!(start[0].weight, 1.0);
+
+        let a = cnf
+            .rules
+            .iter()
+            .find(|rule| rule.head == CnfNonterminal::Original('A'))
+            .unwrap();
+        let derivation: GornTree<_> = vec![(vec![], start[0]), (vec![0], a), (vec![1], a)]
+            .into_iter()
+            .collect();
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[1]),
+            (vec![0], &grammar.rules[2]),
+            (vec![1], &grammar.rules[2]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(control));
+    }
+}
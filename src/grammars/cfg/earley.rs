@@ -0,0 +1,261 @@
+//! An Earley parser for CFGs.
+//!
+//! The rules need not be in a normal form, i.e. ε-rules and chain rules are allowed.
+//! Items are explored best-first with the weighted agenda of the `search` crate, which may be
+//! pruned like the agenda of `recognisable::automaton::recognise_beam`.
+//! Each item is combined with the items in the chart at most `k` times, hence the `k` best
+//! parse trees of a word are found in time cubic in the length of the word.
+
+use search::agenda::weighted::Weighted;
+use search::{Agenda, Search};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+use std::rc::Rc;
+
+use super::{CFGRule, LetterT, CFG};
+use crate::util::agenda::PruningAgenda;
+use crate::util::tree::{GornTree, SharedTree};
+
+/// An item `[A → α • β, start, end]`, i.e. `α` derives the subword from `start` to `end`,
+/// together with the derivations of the nonterminals in `α`.
+/// Its weight is the product of the weights of the rule and the derivations.
+#[derive(Clone, Debug)]
+struct Item<W> {
+    rule: usize,
+    dot: usize,
+    start: usize,
+    end: usize,
+    weight: W,
+    children: Vec<Rc<SharedTree>>,
+}
+
+impl<W: Clone> Weighted for Item<W> {
+    type Weight = W;
+
+    fn get_weight(&self) -> W {
+        self.weight.clone()
+    }
+}
+
+impl<W: Copy + Mul<Output = W>> Item<W> {
+    /// Moves the dot over a nonterminal that derives the subword from `self.end` to `end`.
+    fn complete(&self, end: usize, weight: W, derivation: &Rc<SharedTree>) -> Self {
+        let mut children = self.children.clone();
+        children.push(Rc::clone(derivation));
+        Item {
+            rule: self.rule,
+            dot: self.dot + 1,
+            start: self.start,
+            end,
+            weight: self.weight * weight,
+            children,
+        }
+    }
+}
+
+/// Parses words with a CFG by Earley's algorithm.
+pub struct EarleyParser<N, T, W> {
+    grammar: CFG<N, T, W>,
+    rules_by_head: HashMap<N, Vec<usize>>,
+    beam: Option<usize>,
+    threshold: Option<W>,
+}
+
+impl<N, T, W> EarleyParser<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + PartialEq,
+    W: Copy + Ord + Mul<Output = W>,
+{
+    pub fn new(grammar: &CFG<N, T, W>) -> Self {
+        let mut rules_by_head = HashMap::new();
+        for (rule_id, rule) in grammar.rules.iter().enumerate() {
+            rules_by_head
+                .entry(rule.head.clone())
+                .or_insert_with(Vec::new)
+                .push(rule_id);
+        }
+        EarleyParser {
+            grammar: grammar.clone(),
+            rules_by_head,
+            beam: None,
+            threshold: None,
+        }
+    }
+
+    /// Limits the number of items in the agenda.
+    pub fn set_beam(&mut self, beam: usize) {
+        self.beam = Some(beam);
+    }
+
+    /// Discards items whose weight is less than the greatest weight in the agenda times
+    /// `threshold`.
+    pub fn set_threshold(&mut self, threshold: W) {
+        self.threshold = Some(threshold);
+    }
+
+    /// The best parse tree of `word`, if there is any.
+    pub fn parse(&self, word: &[T]) -> Option<GornTree<&CFGRule<N, T, W>>> {
+        self.kbest(word, 1).pop()
+    }
+
+    /// The `k` best parse trees of `word` in descending order of their weights.
+    pub fn kbest(&self, word: &[T], k: usize) -> Vec<GornTree<&CFGRule<N, T, W>>> {
+        let rules = &self.grammar.rules;
+        let initial: HashSet<&N> = self.grammar.initial.iter().collect();
+
+        let mut predicted: HashSet<(&N, usize)> = HashSet::new();
+        let mut expansions: HashMap<(usize, usize, usize, usize), usize> = HashMap::new();
+        // active items indexed by the nonterminal after the dot and their end
+        let mut active: HashMap<(&N, usize), Vec<Item<W>>> = HashMap::new();
+        // derivations of nonterminals indexed by the nonterminal and the start of the subword
+        let mut passive: HashMap<(&N, usize), Vec<(usize, W, Rc<SharedTree>)>> = HashMap::new();
+
+        let mut agenda = PruningAgenda::new(self.beam, self.threshold);
+        for nt in &self.grammar.initial {
+            if predicted.insert((nt, 0)) {
+                agenda.extend(self.predict(nt, 0));
+            }
+        }
+
+        let deduction = Search::with_agenda(agenda, move |item: &Item<W>| {
+            let mut successors = Vec::new();
+            let count = expansions
+                .entry((item.rule, item.dot, item.start, item.end))
+                .or_insert(0);
+            if *count >= k {
+                return successors;
+            }
+            *count += 1;
+
+            let rule = &rules[item.rule];
+            match rule.composition.composition.get(item.dot) {
+                Some(LetterT::Value(t)) => {
+                    if word.get(item.end) == Some(t) {
+                        let mut scanned = item.clone();
+                        scanned.dot += 1;
+                        scanned.end += 1;
+                        successors.push(scanned);
+                    }
+                }
+                Some(LetterT::Label(nt)) => {
+                    if predicted.insert((nt, item.end)) {
+                        successors.extend(self.predict(nt, item.end));
+                    }
+                    if let Some(derivations) = passive.get(&(nt, item.end)) {
+                        successors.extend(derivations.iter().map(
+                            |&(end, weight, ref derivation)| item.complete(end, weight, derivation),
+                        ));
+                    }
+                    active
+                        .entry((nt, item.end))
+                        .or_insert_with(Vec::new)
+                        .push(item.clone());
+                }
+                None => {
+                    let derivation = Rc::new(SharedTree {
+                        rule: item.rule,
+                        children: item.children.clone(),
+                    });
+                    if let Some(waiting) = active.get(&(&rule.head, item.start)) {
+                        successors.extend(
+                            waiting
+                                .iter()
+                                .map(|other| other.complete(item.end, item.weight, &derivation)),
+                        );
+                    }
+                    passive
+                        .entry((&rule.head, item.start))
+                        .or_insert_with(Vec::new)
+                        .push((item.end, item.weight, derivation));
+                }
+            }
+            successors
+        });
+
+        deduction
+            .filter(|item| {
+                let rule = &rules[item.rule];
+                item.dot == rule.composition.composition.len()
+                    && item.start == 0
+                    && item.end == word.len()
+                    && initial.contains(&rule.head)
+            })
+            .take(k)
+            .map(|item| {
+                SharedTree {
+                    rule: item.rule,
+                    children: item.children,
+                }
+                .to_gorn_tree(rules)
+            })
+            .collect()
+    }
+
+    /// The items `[A → • α, position, position]` for the rules of `nt`.
+    fn predict<'a>(&'a self, nt: &N, position: usize) -> impl Iterator<Item = Item<W>> + 'a {
+        let rules = &self.grammar.rules;
+        self.rules_by_head
+            .get(nt)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(move |&rule| Item {
+                rule,
+                dot: 0,
+                start: position,
+                end: position,
+                weight: rules[rule].weight,
+                children: Vec::new(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use num_traits::One;
+
+    #[test]
+    fn kbest() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [Nt S, Nt S]      # 0.3\n\
+                                                        S → [Nt A, T a]       # 0.6\n\
+                                                        S → [T a, Nt S, T b]  # 0.1\n\
+                                                        A → []                # 0.5\n\
+                                                        A → [Nt A]            # 0.5"
+            .parse()
+            .unwrap();
+        let parser = EarleyParser::new(&grammar);
+        let weight = |tree: &GornTree<&CFGRule<char, char, LogDomain<f64>>>| {
+            tree.values()
+                .fold(LogDomain::one(), |w, rule| w * rule.weight)
+        };
+
+        // S(A(ε) a), then S(A(A(ε)) a), …
+        let parses = parser.kbest(&['a'], 3);
+        assert_eq!(parses.len(), 3);
+        let control: GornTree<_> = vec![(vec![], &grammar.rules[1]), (vec![0], &grammar.rules[3])]
+            .into_iter()
+            .collect();
+        assert_eq!(parses[0], control);
+        assert_eq!(parses[2].len(), 4);
+        assert!(parses.windows(2).all(|p| weight(&p[0]) >= weight(&p[1])));
+
+        // every parse of "a a b" starts with S → [T a, Nt S, T b]
+        let parses = parser.kbest(&['a', 'a', 'b'], 2);
+        assert_eq!(parses.len(), 2);
+        assert!(parses
+            .iter()
+            .all(|tree| tree.get(&Vec::<usize>::new()) == Some(&&grammar.rules[2])));
+        assert_eq!(parser.parse(&['a', 'a', 'b']), Some(parses[0].clone()));
+
+        assert_eq!(parser.parse(&['b']), None);
+        assert_eq!(parser.parse(&[]), None);
+
+        let mut parser = EarleyParser::new(&grammar);
+        parser.set_beam(1);
+        assert_eq!(parser.parse(&['a', 'a', 'b']), None);
+    }
+}
@@ -0,0 +1,332 @@
+//! Weight-preserving elimination of ε-rules and chain rules for CFGs; see
+//! `lcfrs::elimination` for the underlying transformations.
+
+use std::borrow::Borrow;
+use std::collections::BTreeSet;
+use std::hash::Hash;
+use std::ops::Mul;
+
+use num_traits::Zero;
+
+use super::{CFGComposition, CFGRule, LetterT, CFG};
+use crate::grammars::lcfrs::elimination::{self, eliminate_chain_rules, eliminate_epsilon_rules};
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use crate::util::tree::GornTree;
+
+/// Restores derivations of a CFG from derivations of its ε-free version.
+#[derive(Clone, Debug)]
+pub struct EpsilonRestorer<N, T, W> {
+    rules: Vec<CFGRule<N, T, W>>,
+    restorer: elimination::EpsilonRestorer<N, T, W>,
+}
+
+impl<N, T, W> EpsilonRestorer<N, T, W>
+where
+    N: Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone,
+{
+    /// Inserts the removed derivations of ε into a derivation of the ε-free
+    /// grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// ε-free grammar, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&CFGRule<N, T, W>>>
+    where
+        R: Borrow<CFGRule<N, T, W>>,
+    {
+        Some(
+            self.restore_indices(tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Like `restore`, but yields the indices of the original rules.
+    pub(crate) fn restore_indices<R>(&self, tree: &GornTree<R>) -> Option<GornTree<usize>>
+    where
+        R: Borrow<CFGRule<N, T, W>>,
+    {
+        let tree: GornTree<_> = tree
+            .into_iter()
+            .map(|(address, rule)| {
+                let rule: &CFGRule<N, T, W> = rule.borrow();
+                let rule = to_pmcfg_rule(rule, |nt| (nt.clone(), BTreeSet::new()));
+                (address.clone(), rule)
+            })
+            .collect();
+        self.restorer.restore_indices(&tree)
+    }
+}
+
+/// Restores derivations of a CFG from derivations of its version without
+/// chain rules.
+#[derive(Clone, Debug)]
+pub struct ChainRestorer<N, T, W> {
+    rules: Vec<CFGRule<N, T, W>>,
+    restorer: elimination::ChainRestorer<N, T, W>,
+}
+
+impl<N, T, W> ChainRestorer<N, T, W>
+where
+    N: Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone,
+{
+    /// Expands the collapsed chain rules in a derivation of the transformed
+    /// grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// transformed grammar, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&CFGRule<N, T, W>>>
+    where
+        R: Borrow<CFGRule<N, T, W>>,
+    {
+        let tree: GornTree<_> = tree
+            .into_iter()
+            .map(|(address, rule)| {
+                let rule: &CFGRule<N, T, W> = rule.borrow();
+                (address.clone(), to_pmcfg_rule(rule, N::clone))
+            })
+            .collect();
+        Some(
+            self.restorer
+                .restore_indices(&tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    /// Removes all ε-rules.
+    /// Occurrences of nullable nonterminals are removed in copies of the
+    /// rules they occur in, and the sum of the weights of all derivations of
+    /// ε from them is multiplied into the copies.
+    /// The empty word is not in the language of the resulting grammar.
+    pub fn eliminate_epsilon(&self) -> (Self, EpsilonRestorer<N, T, W>) {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect();
+        let (new_rules, restorer) = eliminate_epsilon_rules(&rules, &self.initial);
+        let new_rules = new_rules
+            .into_iter()
+            .map(|rule| to_cfg_rule(rule, |(nt, _)| nt))
+            .collect();
+        (
+            CFG {
+                initial: self.initial.clone(),
+                rules: new_rules,
+            },
+            EpsilonRestorer {
+                rules: self.rules.clone(),
+                restorer,
+            },
+        )
+    }
+
+    /// Collapses chains of rules of the form `A → [Nt B]`.
+    /// Each chain, followed by any other rule, is replaced by a single rule;
+    /// its weight is the weight of the other rule multiplied by the sum of
+    /// the weights of all chains, including cyclic ones, from `A` to the
+    /// head of the other rule.
+    pub fn eliminate_chains(&self) -> (Self, ChainRestorer<N, T, W>) {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect();
+        let (new_rules, restorer) = eliminate_chain_rules(&rules, &self.initial);
+        let new_rules = new_rules
+            .into_iter()
+            .map(|rule| to_cfg_rule(rule, |nt| nt))
+            .collect();
+        (
+            CFG {
+                initial: self.initial.clone(),
+                rules: new_rules,
+            },
+            ChainRestorer {
+                rules: self.rules.clone(),
+                restorer,
+            },
+        )
+    }
+}
+
+/// Reads a CFG rule as a PMCFG rule with a single component, renaming its
+/// nonterminals with `nonterminal`.
+pub(crate) fn to_pmcfg_rule<N, M, T, W, F>(rule: &CFGRule<N, T, W>, nonterminal: F) -> PMCFGRule<M, T, W>
+where
+    T: Clone,
+    W: Clone,
+    F: Fn(&N) -> M,
+{
+    let mut tail = Vec::new();
+    let component = rule
+        .composition
+        .composition
+        .iter()
+        .map(|letter| match *letter {
+            LetterT::Value(ref t) => VarT::T(t.clone()),
+            LetterT::Label(ref nt) => {
+                tail.push(nonterminal(nt));
+                VarT::Var(tail.len() - 1, 0)
+            }
+        })
+        .collect();
+    PMCFGRule {
+        head: nonterminal(&rule.head),
+        tail,
+        composition: Composition {
+            composition: vec![component],
+        },
+        weight: rule.weight.clone(),
+    }
+}
+
+/// Reads a PMCFG rule with at most one component, whose successors each
+/// occur once, as a CFG rule, renaming its nonterminals with `nonterminal`.
+pub(crate) fn to_cfg_rule<M, N, T, W, F>(rule: PMCFGRule<M, T, W>, nonterminal: F) -> CFGRule<N, T, W>
+where
+    F: Fn(M) -> N,
+{
+    let PMCFGRule {
+        head,
+        tail,
+        composition,
+        weight,
+    } = rule;
+    let mut tail: Vec<Option<N>> = tail.into_iter().map(|nt| Some(nonterminal(nt))).collect();
+    let composition = composition
+        .composition
+        .into_iter()
+        .next()
+        .unwrap_or_else(Vec::new)
+        .into_iter()
+        .map(|symbol| match symbol {
+            VarT::T(t) => LetterT::Value(t),
+            VarT::Var(i, _) => LetterT::Label(tail[i].take().unwrap()),
+        })
+        .collect();
+    CFGRule {
+        head: nonterminal(head),
+        composition: CFGComposition { composition },
+        weight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::lcfrs::elimination::tests::{approx, words};
+    use log_domain::LogDomain;
+    use num_traits::Zero;
+
+    #[test]
+    fn epsilon() {
+        let grammar: CFG<char, char, f64> = "initial: [S]\n\
+                                             S → [T a, Nt S, T b] # 0.5\n\
+                                             S → [Nt A, Nt A]     # 0.5\n\
+                                             A → [T c]            # 0.5\n\
+                                             A → []               # 0.5"
+            .parse()
+            .unwrap();
+        let (eliminated, restorer) = grammar.eliminate_epsilon();
+
+        assert!(eliminated
+            .rules
+            .iter()
+            .all(|rule| !rule.composition.composition.is_empty()));
+        let ab: CFGRule<char, char, f64> = "S → [T a, T b]".parse().unwrap();
+        let ab = eliminated.rules.iter().find(|rule| **rule == ab).unwrap();
+        assert_eq!(ab.weight, 0.5 * 0.5 * 0.5 * 0.5);
+
+        let derivation: GornTree<_> = vec![(vec![], ab)].into_iter().collect();
+        let restored: GornTree<_> = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[1]),
+            (vec![0, 0], &grammar.rules[3]),
+            (vec![0, 1], &grammar.rules[3]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+
+    #[test]
+    fn chains() {
+        let grammar: CFG<char, char, f64> = "initial: [S]\n\
+                                             S → [Nt A]       # 0.5\n\
+                                             S → [T a, Nt S]  # 0.5\n\
+                                             A → [Nt S]       # 0.5\n\
+                                             A → [T b]        # 0.5"
+            .parse()
+            .unwrap();
+        let (eliminated, restorer) = grammar.eliminate_chains();
+
+        let control: Vec<CFGRule<char, char, f64>> = vec![
+            "S → [T a, Nt S]".parse().unwrap(),
+            "S → [T b]".parse().unwrap(),
+        ];
+        assert_eq!(eliminated.rules, control);
+        // the cycle S → A → S is summed up
+        assert!((eliminated.rules[0].weight - 2.0 / 3.0).abs() < 1e-9);
+        assert!((eliminated.rules[1].weight - 1.0 / 3.0).abs() < 1e-9);
+
+        let derivation: GornTree<_> = vec![(vec![], &eliminated.rules[1])].into_iter().collect();
+        let restored: GornTree<_> = vec![(vec![], &grammar.rules[0]), (vec![0], &grammar.rules[3])]
+            .into_iter()
+            .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+
+    #[test]
+    fn repeated_heads() {
+        let grammar: CFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+                                                            S → [Nt A, Nt B]      # 0.5\n\
+                                                            S → [Nt S, T c, Nt S] # 0.5\n\
+                                                            A → [T a]             # 0.25\n\
+                                                            A → [T b]             # 0.25\n\
+                                                            A → [Nt B]            # 0.25\n\
+                                                            A → [Nt A]            # 0.125\n\
+                                                            A → []                # 0.125\n\
+                                                            B → [T c]             # 0.5\n\
+                                                            B → [T d]             # 0.5"
+            .parse()
+            .unwrap();
+        let (without_epsilon, _) = grammar.eliminate_epsilon();
+        let (without_chains, _) = grammar.eliminate_chains();
+
+        // A keeps several rules in both grammars
+        let epsilon_rules = without_epsilon
+            .rules
+            .iter()
+            .filter(|r| r.head == "A")
+            .count();
+        let chain_rules = without_chains
+            .rules
+            .iter()
+            .filter(|r| r.head == "A")
+            .count();
+        assert!(epsilon_rules > 1 && chain_rules > 1);
+
+        let mut accepted = 0;
+        for word in words(&["a", "b", "c", "d"], 4) {
+            let expected = grammar.inside_score(&word);
+            assert!(approx(without_epsilon.inside_score(&word), expected));
+            assert!(approx(without_chains.inside_score(&word), expected));
+            if !expected.is_zero() {
+                accepted += 1;
+            }
+        }
+        assert!(accepted > 5);
+    }
+}
@@ -6,6 +6,9 @@ use std::str::{from_utf8, FromStr};
 use crate::grammars::cfg::{CFGComposition, CFGRule, LetterT, CFG};
 use crate::util::parsing::*;
 
+/// Describes the syntax of a rule for error messages.
+const CFG_RULE: &str = "a rule of the form `A → [T a, Nt B] # 0.5`";
+
 impl<N, T, W> FromStr for CFG<N, T, W>
 where
     N: FromStr,
@@ -15,7 +18,7 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (initial, rules) = initial_rule_grammar_from_str(s)?;
@@ -33,13 +36,10 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_cfg_rule(s.as_bytes()) {
-            IResult::Done(_, result) => Ok(result),
-            _ => Err(format!("Could not parse \'{}\'", s)),
-        }
+        parse_line(s, parse_cfg_rule, CFG_RULE)
     }
 }
 
@@ -272,7 +272,7 @@ pub mod tests {
 
         for input in incomplete_or_illegal_inputs {
             assert_eq!(
-                Err(format!("Could not parse \'{}\'", &input)),
+                Err(ParseError::syntax(1, "S", CFG_RULE)),
                 CFGRule::<u8, u8, f32>::from_str(input)
             );
         }
@@ -342,18 +342,20 @@ pub mod tests {
     #[test]
     fn test_cfg_from_str_illegal_input() {
         let malformed_initial = "initial: [a]";
-        assert_eq!(
-            Err(format!(
-                "Malformed declaration of initial nonterminals: \'{}\'",
-                &malformed_initial
-            )),
-            CFG::<u8, u8, u8>::from_str(malformed_initial)
-        );
+        match CFG::<u8, u8, u8>::from_str(malformed_initial) {
+            Err(ParseError::Syntax { line: 1, .. }) => (),
+            result => panic!("{:?}", result),
+        }
 
         let malformed_rule = "initial: [0]\n\n\
                               S → [T a]";
         assert_eq!(
-            Err(String::from("Could not parse \'S → [T a]\'")),
+            Err(ParseError::Syntax {
+                line: 3,
+                column: 1,
+                found: String::from("S"),
+                expected: String::from(CFG_RULE),
+            }),
             CFG::<u8, u8, u8>::from_str(malformed_rule)
         );
     }
@@ -1,6 +1,10 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+pub mod cky;
+pub mod cnf;
+pub mod earley;
+pub mod elimination;
 mod from_pmcfg;
 mod from_str;
 
@@ -128,3 +132,26 @@ impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for CFGRule
         )
     }
 }
+
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for CFG<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buffer = "".to_string();
+
+        let mut iter = self.initial.iter().peekable();
+
+        buffer.push_str("initial: [");
+        while let Some(nt) = iter.next() {
+            buffer.push_str(format!("\"{}\"", nt).as_str());
+            if iter.peek().is_some() {
+                buffer.push_str(", ");
+            }
+        }
+        buffer.push_str("]\n\n");
+
+        for r in &self.rules {
+            buffer.push_str(format!("{}\n", r).as_str());
+        }
+
+        write!(f, "{}", buffer)
+    }
+}
@@ -0,0 +1,124 @@
+//! Inside scores, i.e. the sums of the weights of all derivations of a word, and prefix
+//! probabilities.
+//!
+//! Cyclic derivations, e.g. via chain rules, and the arbitrary continuations of prefixes are
+//! summed up by a fixed-point iteration with a bounded number of passes.
+//! See `Lcfrs::inside_score` for LCFRS.
+
+use log_domain::LogDomain;
+use num_traits::Zero;
+use std::hash::Hash;
+
+use crate::grammars::cfg::elimination::to_pmcfg_rule;
+use crate::grammars::cfg::CFG;
+use crate::grammars::lcfrs::inside_outside::Hypergraph;
+use crate::grammars::pmcfg::{PMCFGRule, PMCFG};
+
+impl<N, T> PMCFG<N, T, LogDomain<f64>>
+where
+    N: Eq + Hash,
+    T: PartialEq,
+{
+    /// The sum of the weights of all derivations of `word` from any initial nonterminal.
+    /// Copied components must derive the same strings, deleted components may derive any
+    /// string.
+    pub fn inside_score(&self, word: &[T]) -> LogDomain<f64> {
+        inside_score(&self.rules, &self.initial, word, false)
+    }
+}
+
+impl<N, T> CFG<N, T, LogDomain<f64>>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + PartialEq,
+{
+    /// The sum of the weights of all derivations of `word` from any initial nonterminal.
+    pub fn inside_score(&self, word: &[T]) -> LogDomain<f64> {
+        inside_score(&self.pmcfg_rules(), &self.initial, word, false)
+    }
+
+    /// The sum of the weights of all derivations whose yield starts with `word`.
+    /// For a proper and consistent grammar, this is the probability that a sentence starts
+    /// with `word`.
+    pub fn prefix_probability(&self, word: &[T]) -> LogDomain<f64> {
+        inside_score(&self.pmcfg_rules(), &self.initial, word, true)
+    }
+
+    fn pmcfg_rules(&self) -> Vec<PMCFGRule<N, T, LogDomain<f64>>> {
+        self.rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect()
+    }
+}
+
+fn inside_score<N, T>(
+    rules: &[PMCFGRule<N, T, LogDomain<f64>>],
+    initials: &[N],
+    word: &[T],
+    prefix: bool,
+) -> LogDomain<f64>
+where
+    N: Eq + Hash,
+    T: PartialEq,
+{
+    let weights: Vec<_> = rules.iter().map(|rule| rule.weight).collect();
+    initials
+        .iter()
+        .map(|initial| {
+            let hypergraph = if prefix {
+                Hypergraph::prefix(rules, initial, word)
+            } else {
+                Hypergraph::new(rules, initial, word)
+            };
+            hypergraph.total(&weights)
+        })
+        .fold(LogDomain::zero(), |sum, w| sum + w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: LogDomain<f64>, b: f64) -> bool {
+        (a.value() - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn pmcfg() {
+        let grammar: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                          S → [[Var 0 0, Var 0 0]] (A) # 0.5\n\
+                                                          S → [[Var 0 0]] (B) # 0.5\n\
+                                                          A → [[T a, Var 0 0]] (A) # 0.5\n\
+                                                          A → [[T a]] () # 0.5\n\
+                                                          B → [[T a, Var 0 0], [T b, Var 0 1]] (B) # 0.5\n\
+                                                          B → [[], []] () # 0.5"
+            .parse()
+            .unwrap();
+
+        // copying: A derives "a"; deletion: B derives "aa" and an arbitrary second component
+        assert!(approx(grammar.inside_score(&['a', 'a']), 0.25 + 0.0625));
+        assert!(approx(
+            grammar.inside_score(&['a', 'a', 'a', 'a']),
+            0.125 + 0.015625
+        ));
+        assert!(grammar.inside_score(&['a', 'b']).is_zero());
+    }
+
+    #[test]
+    fn cfg() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [T a, Nt S] # 0.5\n\
+                                                        S → [T b]       # 0.5"
+            .parse()
+            .unwrap();
+
+        assert!(approx(grammar.inside_score(&['a', 'b']), 0.25));
+        assert!(grammar.inside_score(&['a', 'a']).is_zero());
+        assert!(approx(grammar.prefix_probability(&['a']), 0.5));
+        assert!(approx(grammar.prefix_probability(&['a', 'a']), 0.25));
+        assert!(approx(grammar.prefix_probability(&['a', 'b']), 0.25));
+        assert!(grammar.prefix_probability(&['b', 'a']).is_zero());
+        assert!(approx(grammar.prefix_probability(&[]), 1.0));
+    }
+}
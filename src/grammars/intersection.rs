@@ -0,0 +1,478 @@
+//! Intersection of grammars with weighted finite-state automata (Bar-Hillel construction).
+//!
+//! Each nonterminal of the resulting grammar is annotated with a pair of states for each of its
+//! components, such that the component derives exactly the words that lead from the first to
+//! the second state in the automaton.
+//! The weight of a rule is multiplied with the weights of the transitions that read its
+//! terminals.
+//! The rules are constructed bottom-up, i.e. only for productive nonterminals, and the result
+//! is reduced afterwards.
+
+use num_traits::One;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Mul;
+use std::slice;
+
+use crate::automata::finite_state_automaton::FiniteStateAutomaton;
+use crate::grammars::cfg::elimination::{to_cfg_rule, to_pmcfg_rule};
+use crate::grammars::cfg::CFG;
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT, PMCFG};
+use crate::grammars::reduce::{integerise, reduce_pmcfg_rules};
+
+/// A nonterminal annotated with a pair of states for each of its components.
+pub type AnnotatedNonterminal<N, Q> = (N, Vec<(Q, Q)>);
+
+impl<N, T, W> PMCFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash + Ord,
+    W: Clone + Mul<Output = W> + One + Ord,
+{
+    /// Constructs a grammar whose derivations are those derivations of `self` whose yield is
+    /// accepted by `automaton`, e.g. a lattice of possible inputs.
+    /// Its initial nonterminals are the initial nonterminals of `self` annotated with the
+    /// initial state and a final state of the automaton.
+    ///
+    /// Transitions may read several terminals, but only terminals that are adjacent in a
+    /// component of a rule; transitions that read the empty word are ignored.
+    /// Copies of a component must be read between the same pair of states and the states of
+    /// deleted components are not constrained by the automaton, so the construction is only
+    /// exact for grammars without copying and deletion, e.g. LCFRS and CFG.
+    pub fn intersect<Q>(
+        &self,
+        automaton: &FiniteStateAutomaton<Q, T, W>,
+    ) -> PMCFG<AnnotatedNonterminal<N, Q>, T, W>
+    where
+        Q: Clone + Eq + Hash + Ord,
+    {
+        let (rules, initial) = intersect_rules(&self.rules, &self.initial, automaton);
+        PMCFG { initial, rules }
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash + Ord,
+    W: Clone + Mul<Output = W> + One + Ord,
+{
+    /// Constructs a grammar whose derivations are those derivations of `self` whose yield is
+    /// accepted by `automaton`, see `PMCFG::intersect`.
+    pub fn intersect<Q>(&self, automaton: &FiniteStateAutomaton<Q, T, W>) -> CFG<(N, (Q, Q)), T, W>
+    where
+        Q: Clone + Eq + Hash + Ord,
+    {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect();
+        let (rules, initial) = intersect_rules(&rules, &self.initial, automaton);
+        CFG {
+            initial: initial.into_iter().map(single_span).collect(),
+            rules: rules
+                .into_iter()
+                .map(|rule| to_cfg_rule(rule, single_span))
+                .collect(),
+        }
+    }
+}
+
+fn single_span<N, Q>((nonterminal, mut spans): AnnotatedNonterminal<N, Q>) -> (N, (Q, Q)) {
+    (nonterminal, spans.remove(0))
+}
+
+/// Transitions that read a non-empty word, indexed by their source state.
+type Arcs<T, W> = Vec<Vec<(Vec<T>, usize, W)>>;
+
+/// An integerised nonterminal with a pair of integerised states for each component.
+type Item = (usize, Vec<(usize, usize)>);
+
+/// Intersects a list of PMCFG rules with `automaton`; returns the reduced rules and the
+/// annotated initial nonterminals.
+fn intersect_rules<N, Q, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    initials: &[N],
+    automaton: &FiniteStateAutomaton<Q, T, W>,
+) -> (
+    Vec<PMCFGRule<AnnotatedNonterminal<N, Q>, T, W>>,
+    Vec<AnnotatedNonterminal<N, Q>>,
+)
+where
+    N: Clone + Eq + Hash,
+    Q: Clone + Eq + Hash + Ord,
+    T: Clone + Eq + Hash + Ord,
+    W: Clone + Mul<Output = W> + One + Ord,
+{
+    let mut state_map: HashMap<Q, usize> = HashMap::new();
+    let mut states: Vec<Q> = Vec::new();
+    let (initial_state, final_states, transitions) = {
+        let mut integerise_state = |q: Q| {
+            *state_map.entry(q.clone()).or_insert_with(|| {
+                states.push(q);
+                states.len() - 1
+            })
+        };
+        let initial_state = integerise_state(automaton.initial_state());
+        let final_states: Vec<usize> = automaton
+            .final_states()
+            .into_iter()
+            .map(&mut integerise_state)
+            .collect();
+        let transitions: Vec<_> = automaton
+            .list_transitions()
+            .filter(|transition| !transition.word.is_empty())
+            .map(|transition| {
+                let source = integerise_state(transition.instruction.source_state().clone());
+                let target = integerise_state(transition.instruction.target_state().clone());
+                (source, transition.word, target, transition.weight)
+            })
+            .collect();
+        (initial_state, final_states, transitions)
+    };
+    let mut arcs: Arcs<T, W> = vec![Vec::new(); states.len()];
+    for (source, word, target, weight) in transitions {
+        arcs[source].push((word, target, weight));
+    }
+
+    let mut nonterminal_map = HashMap::new();
+    let mut names = Vec::new();
+    let heads: Vec<usize> = rules
+        .iter()
+        .map(|rule| integerise(&rule.head, &mut nonterminal_map, &mut names))
+        .collect();
+    let tails: Vec<Vec<usize>> = rules
+        .iter()
+        .map(|rule| {
+            rule.tail
+                .iter()
+                .map(|nt| integerise(nt, &mut nonterminal_map, &mut names))
+                .collect()
+        })
+        .collect();
+    let initials: Vec<usize> = initials
+        .iter()
+        .map(|nt| integerise(nt, &mut nonterminal_map, &mut names))
+        .collect();
+    let mut occurrences: Vec<Vec<(usize, usize)>> = vec![Vec::new(); names.len()];
+    for (r, tail) in tails.iter().enumerate() {
+        for (position, &nonterminal) in tail.iter().enumerate() {
+            occurrences[nonterminal].push((r, position));
+        }
+    }
+
+    // Items are processed in some order; a rule is instantiated with each combination of
+    // processed items that contains the current item, where occurrences of the current item
+    // at earlier positions are excluded to avoid duplicate instances.
+    let mut chart = Chart {
+        items: Vec::new(),
+        item_map: HashMap::new(),
+        agenda: Vec::new(),
+        instances: Vec::new(),
+    };
+    for (r, rule) in rules.iter().enumerate() {
+        if rule.tail.is_empty() {
+            chart.instantiate(r, rule, heads[r], Vec::new(), &arcs);
+        }
+    }
+    let mut processed: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+    while let Some(item) = chart.agenda.pop() {
+        let nonterminal = chart.items[item].0;
+        processed[nonterminal].push(item);
+        for &(r, k) in &occurrences[nonterminal] {
+            let successors = {
+                let choices: Vec<&[usize]> = tails[r]
+                    .iter()
+                    .enumerate()
+                    .map(|(position, &nt)| {
+                        if position == k {
+                            slice::from_ref(&item)
+                        } else if position < k && nt == nonterminal {
+                            &processed[nt][..processed[nt].len() - 1]
+                        } else {
+                            &processed[nt][..]
+                        }
+                    })
+                    .collect();
+                combinations(&choices)
+            };
+            for successors in successors {
+                chart.instantiate(r, &rules[r], heads[r], successors, &arcs);
+            }
+        }
+    }
+
+    let initial: Vec<Item> = initials
+        .into_iter()
+        .flat_map(|nt| {
+            final_states
+                .iter()
+                .map(move |&q| (nt, vec![(initial_state, q)]))
+        })
+        .filter(|item| chart.item_map.contains_key(item))
+        .collect();
+    let Chart {
+        items, instances, ..
+    } = chart;
+    let annotate = |&(nonterminal, ref spans): &Item| {
+        (
+            names[nonterminal].clone(),
+            spans
+                .iter()
+                .map(|&(p, q)| (states[p].clone(), states[q].clone()))
+                .collect(),
+        )
+    };
+    let annotated_rules = instances
+        .into_iter()
+        .map(|(r, successors, head, weight)| PMCFGRule {
+            head: annotate(&items[head]),
+            tail: successors
+                .iter()
+                .map(|&item| annotate(&items[item]))
+                .collect(),
+            composition: rules[r].composition.clone(),
+            weight,
+        })
+        .collect();
+    let initial: Vec<_> = initial.iter().map(annotate).collect();
+    let (annotated_rules, _) = reduce_pmcfg_rules(annotated_rules, &initial);
+    (annotated_rules, initial)
+}
+
+/// The items discovered so far and the rules instantiated with them.
+struct Chart<W> {
+    items: Vec<Item>,
+    item_map: HashMap<Item, usize>,
+    /// Items that have not been processed yet.
+    agenda: Vec<usize>,
+    /// Instantiated rules: the index of the rule, its successor items, its head item and its
+    /// weight.
+    instances: Vec<(usize, Vec<usize>, usize, W)>,
+}
+
+impl<W> Chart<W>
+where
+    W: Clone + Mul<Output = W> + One,
+{
+    /// Instantiates the rule with index `r` with the given successor items, i.e. adds a rule
+    /// for each consistent assignment of states to the components of the head.
+    fn instantiate<N, T: PartialEq>(
+        &mut self,
+        r: usize,
+        rule: &PMCFGRule<N, T, W>,
+        head: usize,
+        successors: Vec<usize>,
+        arcs: &Arcs<T, W>,
+    ) {
+        let assignments = {
+            let spans: Vec<&[(usize, usize)]> = successors
+                .iter()
+                .map(|&item| &self.items[item].1[..])
+                .collect();
+            head_spans(&rule.composition, &spans, arcs)
+        };
+        for (spans, weight) in assignments {
+            let item = (head, spans);
+            let index = match self.item_map.get(&item) {
+                Some(&index) => index,
+                None => {
+                    let index = self.items.len();
+                    self.items.push(item.clone());
+                    self.item_map.insert(item, index);
+                    self.agenda.push(index);
+                    index
+                }
+            };
+            self.instances
+                .push((r, successors.clone(), index, rule.weight.clone() * weight));
+        }
+    }
+}
+
+/// All assignments of pairs of states to the components of `composition` given the pairs of
+/// the successors, with the weights of the transitions that read the terminals.
+fn head_spans<T, W>(
+    composition: &Composition<T>,
+    spans: &[&[(usize, usize)]],
+    arcs: &Arcs<T, W>,
+) -> Vec<(Vec<(usize, usize)>, W)>
+where
+    T: PartialEq,
+    W: Clone + Mul<Output = W> + One,
+{
+    let mut assignments = vec![(Vec::new(), W::one())];
+    for component in &composition.composition {
+        if assignments.is_empty() {
+            break;
+        }
+        let paths = walk(component, spans, arcs);
+        assignments = assignments
+            .iter()
+            .flat_map(|&(ref head, ref weight)| {
+                paths.iter().map(move |&(span, ref path_weight)| {
+                    let mut head = head.clone();
+                    head.push(span);
+                    (head, weight.clone() * path_weight.clone())
+                })
+            })
+            .collect();
+    }
+    assignments
+}
+
+/// All paths through the automaton that read `component`, where the variables must be read
+/// between the states given by `spans`.
+/// A component that starts with a terminal or is empty may start at any state.
+fn walk<T, W>(
+    component: &[VarT<T>],
+    spans: &[&[(usize, usize)]],
+    arcs: &Arcs<T, W>,
+) -> Vec<((usize, usize), W)>
+where
+    T: PartialEq,
+    W: Clone + Mul<Output = W> + One,
+{
+    let mut stack: Vec<(usize, usize, usize, W)> = match component.first() {
+        Some(&VarT::Var(i, j)) => {
+            let (p, q) = spans[i][j];
+            vec![(p, q, 1, W::one())]
+        }
+        _ => (0..arcs.len()).map(|p| (p, p, 0, W::one())).collect(),
+    };
+
+    let mut paths = Vec::new();
+    while let Some((start, current, position, weight)) = stack.pop() {
+        match component.get(position) {
+            None => paths.push(((start, current), weight)),
+            Some(&VarT::Var(i, j)) => {
+                let (p, q) = spans[i][j];
+                if p == current {
+                    stack.push((start, q, position + 1, weight));
+                }
+            }
+            Some(&VarT::T(_)) => {
+                for &(ref word, target, ref arc_weight) in &arcs[current] {
+                    let end = position + word.len();
+                    let matches = end <= component.len()
+                        && word
+                            .iter()
+                            .zip(&component[position..end])
+                            .all(|(t, symbol)| match *symbol {
+                                VarT::T(ref s) => s == t,
+                                VarT::Var(..) => false,
+                            });
+                    if matches {
+                        stack.push((start, target, end, weight.clone() * arc_weight.clone()));
+                    }
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// The cartesian product of the `choices`.
+fn combinations(choices: &[&[usize]]) -> Vec<Vec<usize>> {
+    let mut result = vec![Vec::new()];
+    for choice in choices {
+        result = result
+            .iter()
+            .flat_map(|prefix| {
+                choice.iter().map(move |&item| {
+                    let mut combination = prefix.clone();
+                    combination.push(item);
+                    combination
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn pmcfg() {
+        let grammar: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                          S → [[Var 0 0, Var 0 1]] (A) # 1\n\
+                                                          A → [[T a, Var 0 0], [T b, Var 0 1]] (A) # 0.5\n\
+                                                          A → [[], []] () # 0.5"
+            .parse()
+            .unwrap();
+        // a lattice for the words "a b" and "c b", and the word "a a b b" with transitions that
+        // read two terminals each
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                            final: [2, 4]\n\
+                                                                            Transition [a] (0 → 1) # 0.5\n\
+                                                                            Transition [c] (0 → 1) # 0.5\n\
+                                                                            Transition [b] (1 → 2) # 1\n\
+                                                                            Transition [a, a] (0 → 3) # 1\n\
+                                                                            Transition [b, b] (3 → 4) # 1"
+            .parse()
+            .unwrap();
+        let intersection = grammar.intersect(&automaton);
+
+        // the terminals of "a a" are read by different rules, so "a a b b" is not derivable
+        assert_eq!(intersection.initial, vec![('S', vec![(0, 2)])]);
+        assert!(intersection
+            .rules
+            .iter()
+            .all(|rule| rule.head.1.iter().all(|&(_, q)| q <= 2)));
+        assert!((intersection.inside_score(&['a', 'b']).value() - 0.125).abs() < 1e-9);
+        assert!(intersection.inside_score(&['c', 'b']).is_zero());
+        assert!(intersection.inside_score(&['a', 'a', 'b', 'b']).is_zero());
+    }
+
+    #[test]
+    fn repeated_heads() {
+        let grammar: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                          S → [[Var 0 0, Var 1 0]] (A, B) # 1\n\
+                                                          A → [[T a]] () # 0.5\n\
+                                                          A → [[T b]] () # 0.5\n\
+                                                          B → [[T c]] () # 1"
+            .parse()
+            .unwrap();
+        // the transitions for "a" and "b" share their states, so the annotated nonterminal
+        // A(0, 1) has two rules
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                            final: [2]\n\
+                                                                            Transition [a] (0 → 1) # 1\n\
+                                                                            Transition [b] (0 → 1) # 1\n\
+                                                                            Transition [c] (1 → 2) # 1"
+            .parse()
+            .unwrap();
+        let intersection = grammar.intersect(&automaton);
+
+        assert_eq!(intersection.rules.len(), 4);
+        assert!((intersection.inside_score(&['a', 'c']).value() - 0.5).abs() < 1e-9);
+        assert!((intersection.inside_score(&['b', 'c']).value() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cfg() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [T a, Nt S, T b] # 0.5\n\
+                                                        S → []               # 0.5"
+            .parse()
+            .unwrap();
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                            final: [0]\n\
+                                                                            Transition [a] (0 → 1) # 1\n\
+                                                                            Transition [b] (1 → 0) # 1"
+            .parse()
+            .unwrap();
+        let intersection = grammar.intersect(&automaton);
+
+        // (ab)* ∩ aⁿbⁿ = {ε, ab}
+        assert_eq!(intersection.initial, vec![('S', (0, 0))]);
+        assert_eq!(intersection.rules.len(), 3);
+        assert!((intersection.inside_score(&[]).value() - 0.5).abs() < 1e-9);
+        assert!((intersection.inside_score(&['a', 'b']).value() - 0.25).abs() < 1e-9);
+        assert!(intersection.inside_score(&['a', 'a', 'b', 'b']).is_zero());
+    }
+}
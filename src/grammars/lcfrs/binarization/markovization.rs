@@ -0,0 +1,552 @@
+use super::*;
+use std::ops::{Add, Div, Mul};
+
+/// Separates the label of a nonterminal from its parent annotation.
+const PARENTS: &str = "^<";
+/// Separates the label of an auxiliary nonterminal from its history.
+const HISTORY: &str = "|<";
+
+/// Checks if a label denotes an auxiliary nonterminal, i.e. if it is of the
+/// form `A|<…>`, `A|<…>_2` or `A|<…>#7`.
+pub fn is_auxiliary_label(label: &str) -> bool {
+    label.contains(HISTORY)
+}
+
+/// Removes all annotations that are introduced by the extraction,
+/// binarization and Markovization of a grammar from a label.
+/// Labels are of the form `A_f^<P1,…,Pv>|<B1,…,Bh>_g#id` where
+/// * `_f` is the fanout of discontinuous constituents,
+/// * `^<P1,…,Pv>` are the parent labels of vertical Markovization,
+/// * `|<B1,…,Bh>` is the history of an auxiliary nonterminal after
+///   horizontal Markovization (or all successors that it derives),
+/// * `_g` is the fanout of an auxiliary nonterminal, and
+/// * `#id` makes an auxiliary nonterminal of an exact binarization unique;
+///
+/// all parts except for `A` are optional.
+/// E.g. `VP_2^<S>|<NN>_2` is reduced to `VP`.
+pub fn base_label(label: &str) -> &str {
+    let end = [PARENTS, HISTORY]
+        .iter()
+        .filter_map(|separator| label.find(separator))
+        .min()
+        .unwrap_or_else(|| label.len());
+    let label = &label[0..end];
+
+    match label.rfind('_') {
+        Some(i)
+            if i > 0 && i + 1 < label.len() && label[i + 1..].chars().all(|c| c.is_digit(10)) =>
+        {
+            &label[0..i]
+        }
+        _ => label,
+    }
+}
+
+/// Parameters for the Markovization of a grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Markovization {
+    /// The number of successors that are remembered by an auxiliary
+    /// nonterminal; `None` remembers all of them.
+    pub horizontal: Option<usize>,
+    /// The number of ancestors in each nonterminal, including the
+    /// nonterminal itself; `1` does not annotate any parents.
+    pub vertical: usize,
+}
+
+impl Default for Markovization {
+    fn default() -> Self {
+        Markovization {
+            horizontal: None,
+            vertical: 1,
+        }
+    }
+}
+
+impl Markovization {
+    /// Constructs the auxiliary nonterminal for an inner node of a plan.
+    fn auxiliary<N: Auxiliary>(&self, head: &N, tail: &[N], plan: &Plan, fanout: usize) -> N {
+        let mut history = plan.recency();
+        if let Some(h) = self.horizontal {
+            history.truncate(h);
+        }
+        history.sort();
+        let history: Vec<&N> = history.into_iter().map(|i| &tail[i]).collect();
+        N::markovized(head, &history, fanout)
+    }
+
+    /// Extends the ancestors of a nonterminal (starting with its parent) to
+    /// the ancestors of its successors.
+    fn successor_ancestors<N: Auxiliary>(&self, nonterminal: &N, ancestors: &[N]) -> Vec<N> {
+        let mut successor_ancestors = vec![nonterminal.base()];
+        successor_ancestors.extend(ancestors.iter().map(N::base));
+        successor_ancestors.truncate(self.vertical.saturating_sub(1));
+        successor_ancestors
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Auxiliary + Clone + Hash + Eq,
+    T: Clone,
+    W: Clone,
+{
+    /// Annotates each nonterminal with the labels of its `vertical - 1`
+    /// nearest ancestors.
+    /// Rules are copied for each context in which their left-hand side
+    /// occurs, the weights remain unchanged.
+    pub fn annotate_parents(&self, vertical: usize) -> Self {
+        let markovization = Markovization {
+            horizontal: None,
+            vertical,
+        };
+        let mut rules_by_head: HashMap<&N, Vec<&PMCFGRule<N, T, W>>> = HashMap::new();
+        for rule in &self.rules {
+            rules_by_head.entry(&rule.head).or_default().push(rule);
+        }
+
+        let mut rules = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![(self.init.clone(), Vec::new())];
+        seen.insert(stack[0].clone());
+
+        while let Some((nonterminal, ancestors)) = stack.pop() {
+            let head = nonterminal.annotated(&ancestors.iter().collect::<Vec<_>>());
+            let successor_ancestors = markovization.successor_ancestors(&nonterminal, &ancestors);
+
+            for rule in rules_by_head.get(&nonterminal).into_iter().flatten() {
+                let mut tail = Vec::with_capacity(rule.tail.len());
+                for successor in &rule.tail {
+                    tail.push(successor.annotated(&successor_ancestors.iter().collect::<Vec<_>>()));
+                    let item = (successor.clone(), successor_ancestors.clone());
+                    if seen.insert(item.clone()) {
+                        stack.push(item);
+                    }
+                }
+                rules.push(PMCFGRule {
+                    head: head.clone(),
+                    tail,
+                    composition: rule.composition.clone(),
+                    weight: rule.weight.clone(),
+                });
+            }
+        }
+
+        Lcfrs {
+            rules,
+            init: self.init.clone(),
+        }
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Auxiliary + Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone + One + Add<Output = W> + Div<Output = W>,
+{
+    /// Binarizes the LCFRS with the given strategy after annotating its
+    /// nonterminals with parents (see `annotate_parents`).
+    /// Auxiliary nonterminals only remember the last `horizontal` successors
+    /// that were attached, hence they are shared among rules.
+    /// The weights of the original rules are treated as counts: binarized
+    /// rules that are constructed multiple times are merged, and the rules
+    /// of each auxiliary nonterminal are weighted by their relative
+    /// frequency.
+    /// Derivations of the result can be mapped back with `debinarize`.
+    pub fn markovize(
+        &self,
+        strategy: BinarizationStrategy,
+        markovization: &Markovization,
+    ) -> Self {
+        self.markovize_with_heads(strategy, markovization, |rule| rule.tail.len() - 1)
+    }
+
+    /// Markovizes the LCFRS like `markovize`, but the head-outward strategy
+    /// uses the successor with the index `head(rule)` as head of each rule.
+    pub fn markovize_with_heads<F>(
+        &self,
+        strategy: BinarizationStrategy,
+        markovization: &Markovization,
+        head: F,
+    ) -> Self
+    where
+        F: Fn(&PMCFGRule<N, T, W>) -> usize,
+    {
+        let annotated = if markovization.vertical > 1 {
+            self.annotate_parents(markovization.vertical)
+        } else {
+            self.clone()
+        };
+
+        let mut rule_ids: HashMap<PMCFGRule<N, T, W>, usize> = HashMap::new();
+        let mut rules_with_counts: Vec<(PMCFGRule<N, T, W>, Option<W>)> = Vec::new();
+        let mut auxiliary_counts: HashMap<N, W> = HashMap::new();
+
+        for rule in &annotated.rules {
+            let binarized = if rule.tail.len() <= 2 {
+                vec![rule.clone()]
+            } else {
+                let plan = Plan::for_rule(
+                    strategy,
+                    &rule.composition.composition,
+                    rule.tail.len(),
+                    head(rule),
+                );
+                let name = |aux: &Plan, fanout| {
+                    markovization.auxiliary(&rule.head, &rule.tail, aux, fanout)
+                };
+                let (binarized, _) = RuleBinarizer::new(rule, W::one(), name).binarize(&plan);
+                binarized.into_iter().map(|(_, r)| r).collect()
+            };
+
+            for binarized_rule in binarized {
+                if binarized_rule.head.is_auxiliary() {
+                    add_count(&mut auxiliary_counts, binarized_rule.head.clone(), &rule.weight);
+                }
+                let id = *rule_ids.entry(binarized_rule.clone()).or_insert_with(|| {
+                    rules_with_counts.push((binarized_rule, None));
+                    rules_with_counts.len() - 1
+                });
+                let count = &mut rules_with_counts[id].1;
+                *count = Some(match count.take() {
+                    Some(c) => c + rule.weight.clone(),
+                    None => rule.weight.clone(),
+                });
+            }
+        }
+
+        let rules = rules_with_counts
+            .into_iter()
+            .map(|(mut rule, count)| {
+                let count = count.unwrap();
+                rule.weight = match auxiliary_counts.get(&rule.head) {
+                    Some(total) => count / total.clone(),
+                    None => count,
+                };
+                rule
+            })
+            .collect();
+
+        Lcfrs {
+            rules,
+            init: annotated.init,
+        }
+    }
+}
+
+/// Adds `weight` to the count of `key`.
+fn add_count<K: Hash + Eq, W: Clone + Add<Output = W>>(counts: &mut HashMap<K, W>, key: K, weight: &W) {
+    let count = match counts.remove(&key) {
+        Some(count) => count + weight.clone(),
+        None => weight.clone(),
+    };
+    counts.insert(key, count);
+}
+
+/// Collapses all rules with auxiliary left-hand sides in a derivation of a
+/// binarized or Markovized grammar and removes all annotations from the
+/// nonterminals (see `base_label`).
+/// The weights of collapsed rules are multiplied.
+/// Returns `None` if the derivation is incomplete.
+pub fn debinarize<N, T, W, R>(tree: &GornTree<R>) -> Option<GornTree<PMCFGRule<N, T, W>>>
+where
+    N: Auxiliary,
+    T: Clone,
+    W: Clone + Mul<Output = W>,
+    R: Borrow<PMCFGRule<N, T, W>>,
+{
+    let mut debinarized = GornTree::new();
+    let mut stack = vec![(Vec::new(), Vec::new())];
+
+    while let Some((address, original_address)) = stack.pop() {
+        let rule: &PMCFGRule<N, T, W> = tree.get(&address)?.borrow();
+        let mut leaves = Vec::new();
+        let mut weight = None;
+        let composition = collapse(tree, &address, &mut leaves, &mut weight)?;
+
+        let mut tail = Vec::with_capacity(leaves.len());
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            let successor: &PMCFGRule<N, T, W> = tree.get(&leaf)?.borrow();
+            tail.push(successor.head.base());
+            let mut child_address = original_address.clone();
+            child_address.push(i);
+            stack.push((leaf, child_address));
+        }
+
+        debinarized.insert(
+            original_address,
+            PMCFGRule {
+                head: rule.head.base(),
+                tail,
+                composition: Composition::from(composition),
+                weight: weight.unwrap(),
+            },
+        );
+    }
+
+    Some(debinarized)
+}
+
+/// Computes the composition of the rule at `address` after substituting
+/// the compositions of all auxiliary successors.
+/// The addresses of the remaining successors are appended to `leaves`.
+fn collapse<N, T, W, R>(
+    tree: &GornTree<R>,
+    address: &[usize],
+    leaves: &mut Vec<Vec<usize>>,
+    weight: &mut Option<W>,
+) -> Option<Vec<Vec<VarT<T>>>>
+where
+    N: Auxiliary,
+    T: Clone,
+    W: Clone + Mul<Output = W>,
+    R: Borrow<PMCFGRule<N, T, W>>,
+{
+    let rule: &PMCFGRule<N, T, W> = tree.get(&address.to_vec())?.borrow();
+    *weight = Some(match weight.take() {
+        Some(w) => w * rule.weight.clone(),
+        None => rule.weight.clone(),
+    });
+
+    let mut successors = Vec::with_capacity(rule.tail.len());
+    for (i, successor) in rule.tail.iter().enumerate() {
+        let mut child_address = address.to_vec();
+        child_address.push(i);
+        if successor.is_auxiliary() {
+            successors.push(collapse(tree, &child_address, leaves, weight)?);
+        } else {
+            let fanout = tree.get(&child_address)?.borrow().composition.composition.len();
+            let variables = (0..fanout)
+                .map(|j| vec![VarT::Var(leaves.len(), j)])
+                .collect();
+            leaves.push(child_address);
+            successors.push(variables);
+        }
+    }
+
+    let mut composition = Vec::with_capacity(rule.composition.composition.len());
+    for component in &rule.composition.composition {
+        let mut new_component = Vec::new();
+        for symbol in component {
+            match *symbol {
+                VarT::Var(i, j) => new_component.extend(successors[i].get(j)?.iter().cloned()),
+                VarT::T(ref t) => new_component.push(VarT::T(t.clone())),
+            }
+        }
+        composition.push(new_component);
+    }
+    Some(composition)
+}
+
+/// Binarizes and Markovizes each rule of a derivation, e.g. one that was
+/// extracted from a treebank.
+/// The successors of rules without terminals are annotated with the labels
+/// of their ancestors, and the rules are binarized with the given strategy,
+/// where `head(address, rule)` is the index of the head successor of the
+/// rule at `address`.
+pub fn markovize_derivation<N, T, F>(
+    tree: &GornTree<PMCFGRule<N, T, ()>>,
+    strategy: BinarizationStrategy,
+    markovization: &Markovization,
+    head: F,
+) -> Option<GornTree<PMCFGRule<N, T, ()>>>
+where
+    N: Auxiliary + Clone,
+    T: Clone,
+    F: Fn(&[usize], &PMCFGRule<N, T, ()>) -> usize,
+{
+    let mut markovized = GornTree::new();
+    // address in the original tree, address in the markovized tree, ancestors
+    let mut stack = vec![(Vec::new(), Vec::new(), Vec::new())];
+
+    while let Some((address, new_address, ancestors)) = stack.pop() {
+        let original = tree.get(&address)?;
+        let successor_ancestors = markovization.successor_ancestors(&original.head, &ancestors);
+
+        let mut rule = original.clone();
+        if !ancestors.is_empty() {
+            rule.head = rule.head.annotated(&ancestors.iter().collect::<Vec<_>>());
+        }
+        for (i, successor) in rule.tail.iter_mut().enumerate() {
+            let mut child_address = address.clone();
+            child_address.push(i);
+            if !tree.get(&child_address)?.tail.is_empty() {
+                *successor = successor.annotated(&successor_ancestors.iter().collect::<Vec<_>>());
+            }
+        }
+
+        let rank = rule.tail.len();
+        let (binarized, leaves) = if rank <= 2 {
+            let binarized: GornTree<_> = vec![(Vec::new(), rule)].into_iter().collect();
+            (binarized, (0..rank).map(|i| (vec![i], i)).collect())
+        } else {
+            let plan = Plan::for_rule(
+                strategy,
+                &rule.composition.composition,
+                rank,
+                head(&address, original),
+            );
+            let name = |aux: &Plan, fanout| {
+                markovization.auxiliary(&rule.head, &rule.tail, aux, fanout)
+            };
+            RuleBinarizer::new(&rule, (), name).binarize(&plan)
+        };
+
+        for (relative_address, binarized_rule) in binarized {
+            let mut binarized_address = new_address.clone();
+            binarized_address.extend(relative_address);
+            markovized.insert(binarized_address, binarized_rule);
+        }
+        for (relative_address, i) in leaves {
+            let mut child_address = address.clone();
+            child_address.push(i);
+            let mut new_child_address = new_address.clone();
+            new_child_address.extend(relative_address);
+            let child_ancestors = if tree.get(&child_address)?.tail.is_empty() {
+                Vec::new()
+            } else {
+                successor_ancestors.clone()
+            };
+            stack.push((child_address, new_child_address, child_ancestors));
+        }
+    }
+
+    Some(markovized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        PMCFGRule::from_str(s).unwrap()
+    }
+
+    fn lcfrs() -> Lcfrs<String, String, f64> {
+        Lcfrs::new(
+            vec![
+                rule("S → [[Var 0 0, Var 1 0, Var 2 0, Var 3 0]] (A, B, C, D) # 0.25"),
+                rule("S → [[Var 0 0, Var 1 0, Var 2 0, Var 3 0]] (A, A, C, D) # 0.75"),
+                rule("A → [[T a]] ()"),
+                rule("B → [[T b]] ()"),
+                rule("C → [[T c]] ()"),
+                rule("D → [[T d]] ()"),
+            ],
+            "S".to_owned(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(base_label("VP_2^<S>|<NN>_2"), "VP");
+        assert_eq!(base_label("S|<B,C>#0"), "S");
+        assert_eq!(base_label("NP^<VP,S>"), "NP");
+        assert_eq!(base_label("$_"), "$_");
+        assert_eq!(base_label("_1"), "_1");
+        assert!(is_auxiliary_label("VP_2^<S>|<NN>_2"));
+        assert!(!is_auxiliary_label("VP_2^<S>"));
+
+        let vp = String::from("VP_2");
+        let nn = String::from("NN");
+        assert_eq!(String::markovized(&vp, &[&nn], 2), "VP_2|<NN>_2");
+        assert_eq!(String::markovized(&vp, &[], 1), "VP_2|<>");
+        assert_eq!(vp.annotated(&[&String::from("S")]), "VP_2^<S>");
+        assert_eq!(vp.annotated(&[]), "VP_2");
+    }
+
+    #[test]
+    fn horizontal() {
+        let markovization = Markovization {
+            horizontal: Some(1),
+            vertical: 1,
+        };
+        let markovized = lcfrs().markovize(BinarizationStrategy::RightToLeft, &markovization);
+
+        // both rules share the auxiliary nonterminal S|<C>
+        let heads: HashSet<&str> = markovized.rules.iter().map(|r| r.head.as_str()).collect();
+        assert!(heads.contains("S|<C>"));
+        assert!(heads.contains("S|<B>"));
+        assert!(heads.contains("S|<A>"));
+        assert!(Lcfrs::new(markovized.rules.clone(), markovized.init.clone()).is_some());
+
+        let weight = |s: &str| markovized.rules.iter().find(|r| **r == rule(s)).unwrap().weight;
+        assert_eq!(weight("S → [[Var 0 0, Var 1 0]] (\"S|<C>\", D)"), 1.0);
+        assert_eq!(weight("\"S|<C>\" → [[Var 0 0, Var 1 0]] (\"S|<B>\", C)"), 0.25);
+        assert_eq!(weight("\"S|<C>\" → [[Var 0 0, Var 1 0]] (\"S|<A>\", C)"), 0.75);
+    }
+
+    #[test]
+    fn vertical() {
+        let annotated = lcfrs().annotate_parents(2);
+        assert_eq!(annotated.rules.len(), 6);
+        assert!(annotated
+            .rules
+            .contains(&rule("\"A^<S>\" → [[T a]] ()")));
+        assert!(annotated.rules.contains(&rule(
+            "S → [[Var 0 0, Var 1 0, Var 2 0, Var 3 0]] (\"A^<S>\", \"B^<S>\", \"C^<S>\", \"D^<S>\")"
+        )));
+    }
+
+    #[test]
+    fn debinarize_derivation() {
+        let original: GornTree<_> = vec![
+            (vec![], rule("S → [[Var 0 0, Var 1 0, Var 2 0, Var 3 0]] (A, B, C, D) # 0.5")),
+            (vec![0], rule("A → [[T a]] ()")),
+            (vec![1], rule("B → [[T b]] ()")),
+            (vec![2], rule("C → [[T c]] ()")),
+            (vec![3], rule("D → [[T d]] ()")),
+        ]
+        .into_iter()
+        .collect();
+        let unweighted: GornTree<PMCFGRule<String, String, ()>> = original
+            .iter()
+            .map(|(a, r)| {
+                (
+                    a.clone(),
+                    PMCFGRule {
+                        head: r.head.clone(),
+                        tail: r.tail.clone(),
+                        composition: r.composition.clone(),
+                        weight: (),
+                    },
+                )
+            })
+            .collect();
+        let markovization = Markovization {
+            horizontal: Some(1),
+            vertical: 2,
+        };
+
+        for &strategy in &[
+            BinarizationStrategy::LeftToRight,
+            BinarizationStrategy::RightToLeft,
+            BinarizationStrategy::HeadOutward,
+            BinarizationStrategy::Optimal,
+        ] {
+            let markovized =
+                markovize_derivation(&unweighted, strategy, &markovization, |_, _| 1).unwrap();
+            assert_eq!(markovized.len(), 7);
+            assert!(markovized.values().all(|r| r.tail.len() <= 2));
+
+            // reweight to check the multiplication of weights
+            let weighted: GornTree<PMCFGRule<String, String, f64>> = markovized
+                .into_iter()
+                .map(|(a, r)| {
+                    let weight = if a.is_empty() { 0.5 } else { 1.0 };
+                    (
+                        a,
+                        PMCFGRule {
+                            head: r.head,
+                            tail: r.tail,
+                            composition: r.composition,
+                            weight,
+                        },
+                    )
+                })
+                .collect();
+            assert_eq!(debinarize(&weighted), Some(original.clone()));
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use super::*;
+use crate::grammars::pmcfg::Composition;
+use crate::util::tree::GornTree;
+use num_traits::One;
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+pub mod markovization;
+
+use self::markovization::{base_label, is_auxiliary_label};
+
+/// Nonterminals that can be introduced or annotated during the binarization
+/// and Markovization of a grammar.
+pub trait Auxiliary: Sized {
+    /// Constructs an auxiliary nonterminal that derives the successors
+    /// `successors` of a rule with left-hand side `head`.
+    /// `id` is unique among all auxiliary nonterminals of a binarized grammar.
+    fn auxiliary(head: &Self, successors: &[&Self], id: usize) -> Self;
+
+    /// Constructs an auxiliary nonterminal of a horizontally Markovized
+    /// grammar that only remembers the `history` of the last successors
+    /// that were attached in a rule with left-hand side `head`.
+    fn markovized(head: &Self, history: &[&Self], fanout: usize) -> Self;
+
+    /// Annotates a nonterminal with its nearest ancestors (vertical
+    /// Markovization).
+    fn annotated(&self, ancestors: &[&Self]) -> Self;
+
+    /// Checks if the nonterminal was constructed by `auxiliary` or
+    /// `markovized`.
+    fn is_auxiliary(&self) -> bool;
+
+    /// Removes all annotations from the nonterminal.
+    fn base(&self) -> Self;
+}
+
+/// Labels follow the scheme in `markovization`: auxiliary nonterminals are
+/// of the form `A|<B,C>#id` or `A|<B,C>_2`, annotated nonterminals of the
+/// form `A^<P,G>`.
+impl Auxiliary for String {
+    fn auxiliary(head: &Self, successors: &[&Self], id: usize) -> Self {
+        let successors: Vec<&str> = successors.iter().map(|s| s.as_str()).collect();
+        format!("{}|<{}>#{}", head, successors.join(","), id)
+    }
+
+    fn markovized(head: &Self, history: &[&Self], fanout: usize) -> Self {
+        let history: Vec<&str> = history.iter().map(|s| s.as_str()).collect();
+        if fanout == 1 {
+            format!("{}|<{}>", head, history.join(","))
+        } else {
+            format!("{}|<{}>_{}", head, history.join(","), fanout)
+        }
+    }
+
+    fn annotated(&self, ancestors: &[&Self]) -> Self {
+        if ancestors.is_empty() {
+            self.clone()
+        } else {
+            let ancestors: Vec<&str> = ancestors.iter().map(|s| s.as_str()).collect();
+            format!("{}^<{}>", self, ancestors.join(","))
+        }
+    }
+
+    fn is_auxiliary(&self) -> bool {
+        is_auxiliary_label(self)
+    }
+
+    fn base(&self) -> Self {
+        base_label(self).to_owned()
+    }
+}
+
+/// The order in which the successors of a rule are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinarizationStrategy {
+    /// Keeps the first successor in the topmost rule and proceeds to the
+    /// right, i.e. `A → B A|<C,D>` and `A|<C,D> → C D`.
+    LeftToRight,
+    /// Keeps the last successor in the topmost rule and proceeds to the
+    /// left, i.e. `A → A|<B,C> D` and `A|<B,C> → B C`.
+    RightToLeft,
+    /// Starts with the head successor, adds its right siblings and then its
+    /// left siblings.
+    HeadOutward,
+    /// Chooses a binarization that minimizes the maximal fanout of the
+    /// auxiliary nonterminals (and then the sum of their fanouts).
+    /// Its running time is exponential in the rank of the rules.
+    Optimal,
+}
+
+impl FromStr for BinarizationStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left-to-right" => Ok(BinarizationStrategy::LeftToRight),
+            "right-to-left" => Ok(BinarizationStrategy::RightToLeft),
+            "head-outward" => Ok(BinarizationStrategy::HeadOutward),
+            "optimal" => Ok(BinarizationStrategy::Optimal),
+            _ => Err(format!("unknown binarization strategy '{}'", s)),
+        }
+    }
+}
+
+/// A binary tree whose leaves are the successors of a rule; each inner node
+/// corresponds to a rule in the binarized grammar.
+/// Inner nodes store the subtree that was combined first on the left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Plan {
+    Leaf(usize),
+    Node(Box<Plan>, Box<Plan>),
+}
+
+impl Plan {
+    /// Chooses the plan for a rule according to a binarization strategy.
+    fn for_rule<T>(
+        strategy: BinarizationStrategy,
+        composition: &[Vec<VarT<T>>],
+        rank: usize,
+        head: usize,
+    ) -> Plan {
+        match strategy {
+            BinarizationStrategy::LeftToRight => {
+                Plan::sequence(&(0..rank).rev().collect::<Vec<_>>())
+            }
+            BinarizationStrategy::RightToLeft => Plan::sequence(&(0..rank).collect::<Vec<_>>()),
+            BinarizationStrategy::HeadOutward => {
+                assert!(head < rank, "head successor out of range");
+                let order: Vec<usize> = (head..rank).chain((0..head).rev()).collect();
+                Plan::sequence(&order)
+            }
+            BinarizationStrategy::Optimal => optimal_plan(composition, rank),
+        }
+    }
+
+    /// Combines the successors in the given order such that the first two
+    /// are combined in the deepest auxiliary nonterminal.
+    fn sequence(order: &[usize]) -> Plan {
+        let mut plan = Plan::Leaf(order[0]);
+        for &i in &order[1..] {
+            plan = Plan::Node(Box::new(plan), Box::new(Plan::Leaf(i)));
+        }
+        plan
+    }
+
+    /// The children of an inner node; the subtree that contains the
+    /// leftmost successor comes first.
+    fn ordered(&self) -> [&Plan; 2] {
+        match *self {
+            Plan::Node(ref a, ref b) if a.min_leaf() < b.min_leaf() => [&**a, &**b],
+            Plan::Node(ref a, ref b) => [&**b, &**a],
+            Plan::Leaf(_) => panic!("leaves do not correspond to rules"),
+        }
+    }
+
+    fn min_leaf(&self) -> usize {
+        match *self {
+            Plan::Leaf(i) => i,
+            Plan::Node(ref a, ref b) => a.min_leaf().min(b.min_leaf()),
+        }
+    }
+
+    /// The successors in the order of the successors in the binarized rules.
+    fn leaves(&self) -> Vec<usize> {
+        match *self {
+            Plan::Leaf(i) => vec![i],
+            Plan::Node(_, _) => {
+                let [a, b] = self.ordered();
+                let mut leaves = a.leaves();
+                leaves.extend(b.leaves());
+                leaves
+            }
+        }
+    }
+
+    /// The successors, starting with the one that was combined last.
+    fn recency(&self) -> Vec<usize> {
+        match *self {
+            Plan::Leaf(i) => vec![i],
+            Plan::Node(ref first, ref last) => {
+                let mut successors = last.recency();
+                successors.extend(first.recency());
+                successors
+            }
+        }
+    }
+
+    fn contains(&self, successor: usize) -> bool {
+        match *self {
+            Plan::Leaf(i) => i == successor,
+            Plan::Node(ref a, ref b) => a.contains(successor) || b.contains(successor),
+        }
+    }
+}
+
+/// Counts the maximal blocks of variables in `composition` that belong to
+/// the successors in `group` (given as a bit mask).
+fn group_fanout<T>(composition: &[Vec<VarT<T>>], group: u64) -> usize {
+    let mut fanout = 0;
+    for component in composition {
+        let mut in_block = false;
+        for symbol in component {
+            let in_group = match *symbol {
+                VarT::Var(i, _) => group & (1 << i) != 0,
+                VarT::T(_) => false,
+            };
+            if in_group && !in_block {
+                fanout += 1;
+            }
+            in_block = in_group;
+        }
+    }
+    fanout
+}
+
+/// Finds a binarization of a rule with `rank` successors that minimizes the
+/// maximal fanout among auxiliary nonterminals by dynamic programming over
+/// all subsets of successors.
+fn optimal_plan<T>(composition: &[Vec<VarT<T>>], rank: usize) -> Plan {
+    assert!(rank < 64, "optimal binarization is only supported for rank < 64");
+    let full: u64 = (1 << rank) - 1;
+    // for each subset: (maximal fanout, sum of fanouts, first part of the split)
+    let mut best: HashMap<u64, (usize, usize, u64)> = HashMap::new();
+
+    for group in 1..=full {
+        if group.count_ones() == 1 {
+            best.insert(group, (0, 0, 0));
+            continue;
+        }
+        let own_fanout = if group == full {
+            0
+        } else {
+            group_fanout(composition, group)
+        };
+        let lowest = group & group.wrapping_neg();
+        let mut candidate: Option<(usize, usize, u64)> = None;
+
+        // enumerate proper subsets that contain the lowest successor
+        let mut part = (group - 1) & group;
+        while part != 0 {
+            if part & lowest != 0 {
+                let (max1, sum1, _) = best[&part];
+                let (max2, sum2, _) = best[&(group & !part)];
+                let cost = (
+                    own_fanout.max(max1).max(max2),
+                    own_fanout + sum1 + sum2,
+                    part,
+                );
+                if candidate.map_or(true, |c| (cost.0, cost.1) < (c.0, c.1)) {
+                    candidate = Some(cost);
+                }
+            }
+            part = (part - 1) & group;
+        }
+        best.insert(group, candidate.unwrap());
+    }
+
+    fn build(group: u64, best: &HashMap<u64, (usize, usize, u64)>) -> Plan {
+        if group.count_ones() == 1 {
+            Plan::Leaf(group.trailing_zeros() as usize)
+        } else {
+            let part = best[&group].2;
+            Plan::Node(
+                Box::new(build(part, best)),
+                Box::new(build(group & !part, best)),
+            )
+        }
+    }
+
+    build(full, &best)
+}
+
+/// Replaces a rule by binary rules according to a `Plan`.
+/// The binary rules are arranged in a (partial) derivation whose leaves are
+/// the successors of the original rule.
+struct RuleBinarizer<'a, N: 'a, T: 'a, W: 'a, F> {
+    rule: &'a PMCFGRule<N, T, W>,
+    /// weight of the rules with auxiliary left-hand sides
+    aux_weight: W,
+    /// constructs the auxiliary nonterminal for an inner node of the plan
+    /// with the given fanout
+    name: F,
+    binarized: GornTree<PMCFGRule<N, T, W>>,
+    /// address and original index of each successor of the original rule
+    leaves: Vec<(Vec<usize>, usize)>,
+}
+
+impl<'a, N, T, W, F> RuleBinarizer<'a, N, T, W, F>
+where
+    N: Clone,
+    T: Clone,
+    W: Clone,
+    F: FnMut(&Plan, usize) -> N,
+{
+    fn new(rule: &'a PMCFGRule<N, T, W>, aux_weight: W, name: F) -> Self {
+        RuleBinarizer {
+            rule,
+            aux_weight,
+            name,
+            binarized: GornTree::new(),
+            leaves: Vec::new(),
+        }
+    }
+
+    fn binarize(
+        mut self,
+        plan: &Plan,
+    ) -> (GornTree<PMCFGRule<N, T, W>>, Vec<(Vec<usize>, usize)>) {
+        let rule = self.rule;
+        self.build(
+            rule.head.clone(),
+            &rule.composition.composition,
+            plan,
+            rule.weight.clone(),
+            Vec::new(),
+        );
+        (self.binarized, self.leaves)
+    }
+
+    /// Constructs the binary rule with left-hand side `head` for an inner
+    /// node of a `Plan`, where `composition` refers to the successors of
+    /// the original rule.
+    fn build(
+        &mut self,
+        head: N,
+        composition: &[Vec<VarT<T>>],
+        plan: &Plan,
+        weight: W,
+        address: Vec<usize>,
+    ) {
+        let children = plan.ordered();
+
+        // the components of each auxiliary successor, in terms of the
+        // original variables
+        let mut blocks: [Vec<Vec<VarT<T>>>; 2] = [Vec::new(), Vec::new()];
+        let mut new_composition = Vec::with_capacity(composition.len());
+        for component in composition {
+            let mut new_component = Vec::new();
+            let mut last_child = None;
+            for symbol in component {
+                match *symbol {
+                    VarT::Var(i, j) => {
+                        let c = if children[0].contains(i) { 0 } else { 1 };
+                        match *children[c] {
+                            Plan::Leaf(_) => new_component.push(VarT::Var(c, j)),
+                            Plan::Node(_, _) if last_child == Some(c) => {
+                                blocks[c].last_mut().unwrap().push(symbol.clone());
+                            }
+                            Plan::Node(_, _) => {
+                                new_component.push(VarT::Var(c, blocks[c].len()));
+                                blocks[c].push(vec![symbol.clone()]);
+                            }
+                        }
+                        last_child = Some(c);
+                    }
+                    VarT::T(_) => {
+                        new_component.push(symbol.clone());
+                        last_child = None;
+                    }
+                }
+            }
+            new_composition.push(new_component);
+        }
+
+        let mut tail = Vec::with_capacity(2);
+        for (c, child) in children.iter().enumerate() {
+            let mut child_address = address.clone();
+            child_address.push(c);
+            match **child {
+                Plan::Leaf(i) => {
+                    tail.push(self.rule.tail[i].clone());
+                    self.leaves.push((child_address, i));
+                }
+                Plan::Node(_, _) => {
+                    let auxiliary = (self.name)(child, blocks[c].len());
+                    tail.push(auxiliary.clone());
+                    let aux_weight = self.aux_weight.clone();
+                    self.build(auxiliary, &blocks[c], child, aux_weight, child_address);
+                }
+            }
+        }
+
+        self.binarized.insert(
+            address,
+            PMCFGRule {
+                head,
+                tail,
+                composition: Composition::from(new_composition),
+                weight,
+            },
+        );
+    }
+}
+
+/// Reconstructs derivations over the rules of an LCFRS from derivations
+/// over its binarization.
+#[derive(Debug, Clone)]
+pub struct Debinarizer<N, T, W> {
+    rules: Vec<PMCFGRule<N, T, W>>,
+    /// For each rule in the binarized grammar whose left-hand side is not an
+    /// auxiliary nonterminal: the index of the original rule, and the
+    /// original index for each successor that is reached via auxiliary
+    /// nonterminals from left to right.
+    origins: HashMap<PMCFGRule<N, T, W>, (usize, Vec<usize>)>,
+    auxiliaries: HashSet<N>,
+}
+
+impl<N, T, W> Debinarizer<N, T, W>
+where
+    N: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Checks if a nonterminal was introduced by the binarization.
+    pub fn is_auxiliary(&self, nonterminal: &N) -> bool {
+        self.auxiliaries.contains(nonterminal)
+    }
+
+    /// Collapses all rules with auxiliary nonterminals in a derivation of
+    /// the binarized grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// binarized grammar, or if it is incomplete.
+    pub fn debinarize<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&PMCFGRule<N, T, W>>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let mut debinarized = GornTree::new();
+        let mut stack = vec![(Vec::new(), Vec::new())];
+
+        while let Some((address, original_address)) = stack.pop() {
+            let rule: &PMCFGRule<N, T, W> = tree.get(&address)?.borrow();
+            let &(rule_id, ref successors) = self.origins.get(rule)?;
+            let leaves = self.leaves(tree, address)?;
+            if leaves.len() != successors.len() {
+                return None;
+            }
+            for (leaf, &i) in leaves.into_iter().zip(successors) {
+                let mut child_address = original_address.clone();
+                child_address.push(i);
+                stack.push((leaf, child_address));
+            }
+            debinarized.insert(original_address, &self.rules[rule_id]);
+        }
+
+        Some(debinarized)
+    }
+
+    /// Collects the addresses of all successors of the rule at `address`
+    /// that are not auxiliary, looking through auxiliary nonterminals.
+    fn leaves<R>(&self, tree: &GornTree<R>, address: Vec<usize>) -> Option<Vec<Vec<usize>>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let rule: &PMCFGRule<N, T, W> = tree.get(&address)?.borrow();
+        let mut leaves = Vec::new();
+        for (i, successor) in rule.tail.iter().enumerate() {
+            let mut child_address = address.clone();
+            child_address.push(i);
+            if self.is_auxiliary(successor) {
+                leaves.extend(self.leaves(tree, child_address)?);
+            } else {
+                leaves.push(child_address);
+            }
+        }
+        Some(leaves)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Auxiliary + Clone + Hash + Eq,
+    T: Clone + Hash + Eq,
+    W: Clone + One,
+{
+    /// Binarizes each rule with more than two successors using the given
+    /// strategy; the head-outward strategy assumes the last successor to be
+    /// the head of each rule (see `binarize_with_heads`).
+    /// Each original rule is replaced by a chain of binary rules with fresh
+    /// auxiliary nonterminals; the topmost rule carries the original weight,
+    /// the others are weighted with one.
+    pub fn binarize(&self, strategy: BinarizationStrategy) -> (Self, Debinarizer<N, T, W>) {
+        self.binarize_with_heads(strategy, |rule| rule.tail.len() - 1)
+    }
+
+    /// Binarizes the LCFRS like `binarize`, but the head-outward strategy
+    /// uses the successor with the index `head(rule)` as head of each rule.
+    pub fn binarize_with_heads<F>(
+        &self,
+        strategy: BinarizationStrategy,
+        head: F,
+    ) -> (Self, Debinarizer<N, T, W>)
+    where
+        F: Fn(&PMCFGRule<N, T, W>) -> usize,
+    {
+        let mut rules = Vec::new();
+        let mut origins = HashMap::new();
+        let mut auxiliaries = HashSet::new();
+
+        for (rule_id, rule) in self.rules.iter().enumerate() {
+            let rank = rule.tail.len();
+            if rank <= 2 {
+                rules.push(rule.clone());
+                origins
+                    .entry(rule.clone())
+                    .or_insert_with(|| (rule_id, (0..rank).collect()));
+                continue;
+            }
+
+            let plan = Plan::for_rule(strategy, &rule.composition.composition, rank, head(rule));
+            let name = |aux: &Plan, _| {
+                let successors: Vec<&N> = aux.leaves().into_iter().map(|i| &rule.tail[i]).collect();
+                let auxiliary = N::auxiliary(&rule.head, &successors, auxiliaries.len());
+                auxiliaries.insert(auxiliary.clone());
+                auxiliary
+            };
+            let (binarized, leaves) = RuleBinarizer::new(rule, W::one(), name).binarize(&plan);
+
+            // the root address precedes all other addresses
+            let mut binarized = binarized.into_iter().map(|(_, r)| r);
+            let top = binarized.next().unwrap();
+            origins.insert(
+                top.clone(),
+                (rule_id, leaves.into_iter().map(|(_, i)| i).collect()),
+            );
+            rules.push(top);
+            rules.extend(binarized);
+        }
+
+        (
+            Lcfrs {
+                rules,
+                init: self.init.clone(),
+            },
+            Debinarizer {
+                rules: self.rules.clone(),
+                origins,
+                auxiliaries,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        PMCFGRule::from_str(s).unwrap()
+    }
+
+    fn lcfrs() -> Lcfrs<String, String, f64> {
+        Lcfrs::new(
+            vec![
+                rule("S → [[Var 0 0, Var 1 0, Var 2 0]] (A, B, C) # 0.5"),
+                rule("A → [[T a]] ()"),
+                rule("B → [[T b]] ()"),
+                rule("C → [[T c]] ()"),
+            ],
+            "S".to_owned(),
+        )
+        .unwrap()
+    }
+
+    fn find<'a>(
+        lcfrs: &'a Lcfrs<String, String, f64>,
+        head: &str,
+    ) -> &'a PMCFGRule<String, String, f64> {
+        lcfrs.rules.iter().find(|r| r.head == head).unwrap()
+    }
+
+    #[test]
+    fn left_to_right() {
+        let (binarized, _) = lcfrs().binarize(BinarizationStrategy::LeftToRight);
+        assert_eq!(binarized.rules.len(), 5);
+        assert_eq!(
+            find(&binarized, "S"),
+            &rule("S → [[Var 0 0, Var 1 0]] (A, \"S|<B,C>#0\")")
+        );
+        assert_eq!(find(&binarized, "S").weight, 0.5);
+        assert_eq!(
+            find(&binarized, "S|<B,C>#0"),
+            &rule("\"S|<B,C>#0\" → [[Var 0 0, Var 1 0]] (B, C)")
+        );
+        assert_eq!(find(&binarized, "S|<B,C>#0").weight, 1.0);
+    }
+
+    #[test]
+    fn right_to_left() {
+        let (binarized, _) = lcfrs().binarize(BinarizationStrategy::RightToLeft);
+        assert_eq!(
+            find(&binarized, "S"),
+            &rule("S → [[Var 0 0, Var 1 0]] (\"S|<A,B>#0\", C)")
+        );
+        assert_eq!(
+            find(&binarized, "S|<A,B>#0"),
+            &rule("\"S|<A,B>#0\" → [[Var 0 0, Var 1 0]] (A, B)")
+        );
+    }
+
+    #[test]
+    fn head_outward() {
+        let (binarized, _) =
+            lcfrs().binarize_with_heads(BinarizationStrategy::HeadOutward, |_| 1);
+        assert_eq!(
+            find(&binarized, "S"),
+            &rule("S → [[Var 0 0, Var 1 0]] (A, \"S|<B,C>#0\")")
+        );
+    }
+
+    #[test]
+    fn optimal() {
+        let discontinuous = Lcfrs::new(
+            vec![
+                rule(
+                    "S → [[Var 0 0, Var 2 0, Var 1 0, Var 3 0, Var 0 1, Var 2 1, Var 1 1, Var 3 1]] (A, B, C, D)",
+                ),
+                rule("A → [[T a], [T a]] ()"),
+                rule("B → [[T b], [T b]] ()"),
+                rule("C → [[T c], [T c]] ()"),
+                rule("D → [[T d], [T d]] ()"),
+            ],
+            "S".to_owned(),
+        )
+        .unwrap();
+        let max_fanout = |strategy| {
+            let (binarized, _) = discontinuous.binarize(strategy);
+            let fanouts = read_fanouts(&binarized.rules).unwrap();
+            assert!(binarized.rules.iter().all(|r| r.tail.len() <= 2));
+            fanouts.values().cloned().max().unwrap()
+        };
+
+        assert_eq!(max_fanout(BinarizationStrategy::LeftToRight), 4);
+        assert_eq!(max_fanout(BinarizationStrategy::Optimal), 2);
+    }
+
+    #[test]
+    fn debinarize() {
+        let original = lcfrs();
+        for &strategy in &[
+            BinarizationStrategy::LeftToRight,
+            BinarizationStrategy::RightToLeft,
+            BinarizationStrategy::HeadOutward,
+            BinarizationStrategy::Optimal,
+        ] {
+            let (binarized, debinarizer) = original.binarize(strategy);
+
+            // constructs the only derivation of the binarized grammar
+            let mut derivation = GornTree::new();
+            let mut stack = vec![(Vec::new(), "S".to_owned())];
+            while let Some((address, head)) = stack.pop() {
+                let r = find(&binarized, &head);
+                for (i, successor) in r.tail.iter().enumerate() {
+                    let mut child_address = address.clone();
+                    child_address.push(i);
+                    stack.push((child_address, successor.clone()));
+                }
+                derivation.insert(address, r);
+            }
+
+            let control: GornTree<_> = vec![
+                (vec![], &original.rules[0]),
+                (vec![0], &original.rules[1]),
+                (vec![1], &original.rules[2]),
+                (vec![2], &original.rules[3]),
+            ]
+            .into_iter()
+            .collect();
+            assert_eq!(debinarizer.debinarize(&derivation), Some(control));
+        }
+    }
+}
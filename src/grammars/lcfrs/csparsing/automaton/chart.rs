@@ -1,8 +1,13 @@
-use super::{RangeT, StateT};
+use super::{RangeT, RuleIdT, StateT};
+use fnv::FnvHashMap;
 use num_traits::Zero;
 use std::mem::zeroed;
 use std::ops::AddAssign;
 
+/// The arc of the input lattice with the greatest weight that is read by a terminal rule for
+/// a span.
+type TerminalArcs<W> = FnvHashMap<(RangeT, RangeT, RuleIdT), (u32, W)>;
+
 /// A chart for cfg parsing.
 #[derive(Debug, Clone)]
 pub struct DenseChart<W>(
@@ -12,6 +17,7 @@ pub struct DenseChart<W>(
     usize,            // n
     usize,            // states
     u16,              // max no. of constituents per span
+    TerminalArcs<W>,  // best lattice arc per span and terminal rule
 );
 
 pub fn chart_size(n: usize) -> usize {
@@ -45,9 +51,28 @@ impl<W: Copy> DenseChart<W> {
             n,
             states,
             bt_per_cell as u16,
+            FnvHashMap::default(),
         )
     }
 
+    /// Stores an arc of the input lattice that is read by a terminal rule for a span;
+    /// keeps the arc with the greatest weight.
+    pub fn add_terminal(&mut self, i: RangeT, j: RangeT, rule: RuleIdT, arc: u32, weight: W)
+    where
+        W: PartialOrd,
+    {
+        let entry = self.6.entry((i, j, rule)).or_insert((arc, weight));
+        if weight > entry.1 {
+            *entry = (arc, weight);
+        }
+    }
+
+    /// Gets the arc of the input lattice and its weight that is read by a terminal rule for
+    /// a span.
+    pub fn get_terminal(&self, i: RangeT, j: RangeT, rule: RuleIdT) -> Option<(u32, W)> {
+        self.6.get(&(i, j, rule)).cloned()
+    }
+
     /// Adds a constituent with viterbi weight to a span.
     pub fn add_entry(&mut self, i: RangeT, j: RangeT, state: StateT, weight: W) {
         let tri_index = index(i, j, self.3);
@@ -2,9 +2,9 @@ use super::{
     Automaton, Bracket, BracketContent, DenseChart, RangeT, RuleIdT, StateT, TdBinary, TdBrackets,
     TdNullary, TdUnary,
 };
+use crate::util::semiring::Semiring;
 use fnv::FnvHashMap;
-use num_traits::Zero;
-use std::{cmp::min, collections::hash_map::Entry, hash::Hash, ops::Mul};
+use std::{cmp::min, collections::hash_map::Entry, hash::Hash};
 use unique_heap::FnvUniqueHeap;
 
 mod backtrace;
@@ -66,7 +66,7 @@ impl<'a, W: Ord> ChartIterator<'a, W> {
 
 impl<'a, W> ChartIterator<'a, W>
 where
-    W: Mul<Output = W> + Ord + Copy + Zero,
+    W: Semiring,
 {
     /// Computes the weight of an item recursively and checks the existence of all predecessors.
     fn weight(&mut self, i: RangeT, j: RangeT, ce: &IndexedBacktrace<W>) -> Option<W> {
@@ -123,12 +123,14 @@ where
                 heap.push(IndexedBacktrace::Unary(r, q1, w, 0u32), w1 * w);
             }
         }
-        if j - i == 1 {
-            for &(r, w) in nullaries[q as usize]
-                .iter()
-                .filter(|&(r, _)| filter[*r as usize])
-            {
-                // is is not correct in general, but ok for terminal-seperated rules
+        for &(r, w) in nullaries[q as usize]
+            .iter()
+            .filter(|&(r, _)| filter[*r as usize])
+        {
+            // the terminal rule is only applicable if its terminal is read by an arc of the
+            // input lattice for this span
+            if let Some((_, arc_weight)) = chart.get_terminal(i, j, r) {
+                let w = w * arc_weight;
                 heap.push(IndexedBacktrace::Nullary(r, w), w);
             }
         }
@@ -218,12 +220,14 @@ where
         }
     }
 
-    // Reads the bracket word for a hyperpath.
+    // Reads the bracket word for a hyperpath and appends the arcs of the input lattice that
+    // are read by its terminal rules to `path`.
     fn read(
         &mut self,
         i: RangeT,
         j: RangeT,
         ce: &IndexedBacktrace<W>,
+        path: &mut Vec<usize>,
     ) -> Vec<Bracket<BracketContent>> {
         use self::IndexedBacktrace::*;
 
@@ -236,8 +240,8 @@ where
                 let additional_elements =
                     2 + if ob.is_ignore() { 0 } else { 2 } + if lb.is_ignore() { 0 } else { 2 };
 
-                let mut w1 = self.read(i, m, &ce1);
-                let w2 = self.read(m, j, &ce2);
+                let mut w1 = self.read(i, m, &ce1, path);
+                let w2 = self.read(m, j, &ce2, path);
                 w1.reserve(w2.len() + additional_elements);
                 if !lb.is_ignore() {
                     w1.insert(0, Bracket::Open(lb));
@@ -255,7 +259,7 @@ where
             }
             Unary(rid, q, _, k) => {
                 let ice = self.kth(i, j, q, k as usize).unwrap().0;
-                let mut derivation = self.read(i, j, &ice);
+                let mut derivation = self.read(i, j, &ice, path);
                 derivation.reserve(4);
                 let (ob, ib, _) = self.rules_to_brackets[rid as usize];
 
@@ -266,6 +270,9 @@ where
                 derivation
             }
             Nullary(rid, _) => {
+                if let Some((arc, _)) = self.chart.get_terminal(i, j, rid) {
+                    path.push(arc as usize);
+                }
                 let (ob, ib, _) = self.rules_to_brackets[rid as usize];
                 vec![
                     Bracket::Open(ob),
@@ -278,14 +285,38 @@ where
     }
 }
 
-impl<'a, W: Ord + Copy + Mul<Output = W> + Zero> Iterator for ChartIterator<'a, W> {
-    type Item = Vec<Bracket<BracketContent>>;
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a, W: Semiring> ChartIterator<'a, W> {
+    /// Turns this iterator into one that also yields the indices of the arcs of the input
+    /// lattice that are read by each bracket word.
+    pub fn with_paths(self) -> PathIterator<'a, W> {
+        PathIterator(self)
+    }
+
+    fn next_with_path(&mut self) -> Option<(Vec<Bracket<BracketContent>>, Vec<usize>)> {
         let &mut ChartIterator { initial, n, k, .. } = self;
         self.k += 1;
 
-        self.kth(0u8, n as u8, initial, k)
-            .map(|(backtrace, _)| self.read(0u8, n as u8, &backtrace))
+        let (backtrace, _) = self.kth(0u8, n as u8, initial, k)?;
+        let mut path = Vec::new();
+        let word = self.read(0u8, n as u8, &backtrace, &mut path);
+        Some((word, path))
+    }
+}
+
+impl<'a, W: Semiring> Iterator for ChartIterator<'a, W> {
+    type Item = Vec<Bracket<BracketContent>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_path().map(|(word, _)| word)
+    }
+}
+
+/// A `ChartIterator` that also yields the path through the input lattice of each bracket word.
+pub struct PathIterator<'a, W: Ord>(ChartIterator<'a, W>);
+
+impl<'a, W: Semiring> Iterator for PathIterator<'a, W> {
+    type Item = (Vec<Bracket<BracketContent>>, Vec<usize>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_path()
     }
 }
 
@@ -294,6 +325,7 @@ mod test {
     use super::super::*;
     use super::*;
     use log_domain::LogDomain;
+    use num_traits::Zero;
     extern crate bincode;
     extern crate flate2;
 
@@ -2,11 +2,13 @@ use crate::grammars::{
     lcfrs::csparsing::{Bracket, BracketContent},
     pmcfg::{PMCFGRule, VarT},
 };
+use crate::recognisable::lattice::Lattice;
 use crate::util::factorizable::Factorizable;
+use crate::util::forest::Forest;
+use crate::util::semiring::Semiring;
 use fnv::FnvHashMap;
 use integeriser::{HashIntegeriser, Integeriser};
 use num_traits::One;
-use num_traits::Zero;
 use std::{collections::BinaryHeap, default::Default, hash::Hash, mem::replace, ops::Mul};
 use vecmultimap::VecMultiMap;
 
@@ -253,9 +255,33 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
         rulefilter: Vec<bool>,
     ) -> ChartIterator<'a, W>
     where
-        W: Ord + Copy + Mul<Output = W> + Zero + One,
+        T: Clone,
+        W: Semiring,
     {
-        let chart = self.fill_chart(word, beam, delta, estimates, &rulefilter);
+        self.generate_lattice(
+            &Lattice::from_word(word),
+            beam,
+            delta,
+            estimates,
+            rulefilter,
+        )
+    }
+
+    /// Create an Iterator for well bracketed words in the language of the
+    /// context-free approximation whose terminals are read along a path
+    /// through the lattice
+    pub fn generate_lattice<'a>(
+        &'a self,
+        lattice: &Lattice<T, W>,
+        beam: usize,
+        delta: W,
+        estimates: &SxOutside<W>,
+        rulefilter: Vec<bool>,
+    ) -> ChartIterator<'a, W>
+    where
+        W: Semiring,
+    {
+        let chart = self.fill_lattice_chart(lattice, beam, delta, estimates, &rulefilter);
         ChartIterator::new(chart, self, rulefilter)
     }
 
@@ -264,8 +290,10 @@ impl<T: Eq + Hash, W> Automaton<T, W> {
     }
 }
 
-impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W> {
-    /// implements the CKY algorithm with chain rules
+impl<T: Eq + Hash, W: Semiring> Automaton<T, W> {
+    /// implements the CKY algorithm with chain rules; each constituent is
+    /// stored with the best weight of its derivations w.r.t. the natural
+    /// order of the semiring
     pub fn fill_chart(
         &self,
         word: &[T],
@@ -273,9 +301,38 @@ impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W>
         delta: W,
         outsides: &SxOutside<W>,
         rule_filter: &[bool],
+    ) -> DenseChart<W>
+    where
+        T: Clone,
+    {
+        self.fill_lattice_chart(
+            &Lattice::from_word(word),
+            beam,
+            delta,
+            outsides,
+            rule_filter,
+        )
+    }
+
+    /// implements the CKY algorithm with chain rules for the paths through a
+    /// lattice, whose states are the positions of the chart; the weights of
+    /// the arcs are multiplied with the weights of the terminal rules.
+    /// The outside estimates are only used for lattices whose paths all have
+    /// the same length.
+    pub fn fill_lattice_chart(
+        &self,
+        lattice: &Lattice<T, W>,
+        beam: usize,
+        delta: W,
+        outsides: &SxOutside<W>,
+        rule_filter: &[bool],
     ) -> DenseChart<W> {
-        let n = word.len();
+        let n = lattice.final_state();
         let nonterminals = self.0.len();
+        let use_estimates = lattice.is_position_lattice();
+        let has_outside = |q: StateT, l: usize, r: usize| -> bool {
+            !use_estimates || outsides.get(q, l, r, n).is_some()
+        };
 
         // contains the constituents ordered by weight
         let mut heap_of_nonterminals: BinaryHeap<(W, StateT)> = BinaryHeap::with_capacity(beam);
@@ -287,16 +344,20 @@ impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W>
 
                 heap_of_nonterminals.clear();
 
-                // initial predictions for each position in word
-                if range == 1 {
-                    if let Some(initials) = self.2.get(&word[l]) {
-                        heap_of_nonterminals.extend(initials.iter().filter_map(|&(rid, (w, q))| {
-                            if !rule_filter[rid as usize] {
-                                return None;
+                // initial predictions for each arc of the lattice
+                for &arc_id in lattice.outgoing(l) {
+                    let arc = &lattice.arcs()[arc_id];
+                    if arc.target != r {
+                        continue;
+                    }
+                    if let Some(initials) = self.2.get(&arc.terminal) {
+                        for &(rid, (w, q)) in initials {
+                            if !rule_filter[rid as usize] || !has_outside(q, l, r) {
+                                continue;
                             }
-                            let _ = outsides.get(q, l, r, n)?;
-                            Some((w, q))
-                        }))
+                            chart.add_terminal(l as u8, r as u8, rid, arc_id as u32, arc.weight);
+                            heap_of_nonterminals.push((w * arc.weight, q));
+                        }
                     }
                 }
 
@@ -315,7 +376,9 @@ impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W>
                                 } else {
                                     chart.get_weight(mid as u8, r as u8, rnt)
                                 }?;
-                                let _ = outsides.get(lhs, l, r, n)?;
+                                if !has_outside(lhs, l, r) {
+                                    return None;
+                                }
                                 Some((lew * ruw * riw, lhs))
                             },
                         ));
@@ -337,7 +400,9 @@ impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W>
                             if !rule_filter[rid as usize] {
                                 return None;
                             }
-                            let _ = outsides.get(q, l, r, n)?;
+                            if !has_outside(q, l, r) {
+                                return None;
+                            }
                             Some((rw * w, q))
                         },
                     ));
@@ -351,4 +416,83 @@ impl<T: Eq + Hash, W: Ord + Mul<Output = W> + Copy + Zero + One> Automaton<T, W>
 
         chart
     }
+
+    /// Collects the constituents of the chart for a lattice that occur in a
+    /// derivation of the initial state in a packed forest; each hyperedge is
+    /// labelled with the brackets of its (binarized) rule, and the weights of
+    /// the arcs are multiplied with the weights of the terminal rules.
+    pub fn generate_forest(
+        &self,
+        lattice: &Lattice<T, W>,
+        beam: usize,
+        delta: W,
+        estimates: &SxOutside<W>,
+        rule_filter: &[bool],
+    ) -> Forest<StateT, TdBrackets, W> {
+        type Constituent = (RangeT, RangeT, StateT);
+
+        // looks up the node of a constituent, or adds it and schedules its
+        // exploration
+        fn node<W>(
+            forest: &mut Forest<StateT, TdBrackets, W>,
+            nodes: &mut FnvHashMap<Constituent, usize>,
+            stack: &mut Vec<Constituent>,
+            (i, j, q): Constituent,
+        ) -> usize {
+            *nodes.entry((i, j, q)).or_insert_with(|| {
+                stack.push((i, j, q));
+                forest.add_node(q, vec![(i as usize, j as usize)])
+            })
+        }
+
+        let chart = self.fill_lattice_chart(lattice, beam, delta, estimates, rule_filter);
+        let n = lattice.final_state() as RangeT;
+        let mut forest = Forest::new();
+        if chart.get_weight(0, n, self.7).is_none() {
+            return forest;
+        }
+
+        let mut nodes = FnvHashMap::default();
+        let mut stack = Vec::new();
+        let root = node(&mut forest, &mut nodes, &mut stack, (0, n, self.7));
+        forest.set_root(root);
+
+        while let Some((i, j, q)) = stack.pop() {
+            let head = nodes[&(i, j, q)];
+            for &(r, q1, q2, w) in self.3[q as usize]
+                .iter()
+                .filter(|&(r, _, _, _)| rule_filter[*r as usize])
+            {
+                for mid in (i + 1)..j {
+                    if chart.get_weight(i, mid, q1).is_none()
+                        || chart.get_weight(mid, j, q2).is_none()
+                    {
+                        continue;
+                    }
+                    let left = node(&mut forest, &mut nodes, &mut stack, (i, mid, q1));
+                    let right = node(&mut forest, &mut nodes, &mut stack, (mid, j, q2));
+                    forest.add_edge(self.8[r as usize], w, head, vec![left, right]);
+                }
+            }
+            for &(r, q1, w) in self.4[q as usize]
+                .iter()
+                .filter(|&(r, _, _)| rule_filter[*r as usize])
+            {
+                if chart.get_weight(i, j, q1).is_some() {
+                    let child = node(&mut forest, &mut nodes, &mut stack, (i, j, q1));
+                    forest.add_edge(self.8[r as usize], w, head, vec![child]);
+                }
+            }
+            for &(r, w) in self.5[q as usize]
+                .iter()
+                .filter(|&(r, _)| rule_filter[*r as usize])
+            {
+                if let Some((_, arc_weight)) = chart.get_terminal(i, j, r) {
+                    forest.add_edge(self.8[r as usize], w * arc_weight, head, vec![]);
+                }
+            }
+        }
+
+        forest
+    }
 }
@@ -5,7 +5,13 @@ use super::Lcfrs;
 
 use crate::dyck::Bracket;
 use crate::grammars::pmcfg::PMCFGRule;
-use crate::util::{factorizable::Factorizable, tree::GornTree};
+use crate::recognisable::lattice::Lattice;
+use crate::util::{
+    factorizable::Factorizable,
+    forest::{Forest, Range},
+    semiring::Semiring,
+    tree::GornTree,
+};
 use num_traits::{One, Zero};
 use std::time::{Duration, Instant};
 use std::{
@@ -15,7 +21,7 @@ use std::{
     ops::Mul,
 };
 
-use self::automaton::{Automaton, RuleMaskBuilder, SxOutside};
+use self::automaton::{Automaton, RuleMaskBuilder, StateT, SxOutside, TdBrackets};
 
 /// The indices of a bracket in a CS representation for an lcfrs.
 /// Assumes integerized an itergerized set of (at most 2^32) rules and fanouts
@@ -77,7 +83,7 @@ pub struct GeneratorBuilder<'a, N, T: Eq + Hash, W> {
 impl<'a, N, T, W> GeneratorBuilder<'a, N, T, W>
 where
     T: Eq + Hash + Clone,
-    W: Zero + Ord + Copy + One + Mul<Output = W>,
+    W: Semiring,
     N: Clone,
 {
     pub fn set_candidates(&mut self, c: usize) {
@@ -99,6 +105,20 @@ where
     ) -> (
         impl Iterator<Item = GornTree<&'a PMCFGRule<N, T, W>>> + 'a,
         Option<GornTree<PMCFGRule<N, T, W>>>,
+    ) {
+        let (derivations, fallback) = self.with_fallback_lattice(&Lattice::from_word(word));
+        (derivations.map(|(derivation, _)| derivation), fallback)
+    }
+
+    /// Like `with_fallback`, but parses the paths through a lattice; each
+    /// derivation is returned with the indices of the arcs of its path (see
+    /// `Lattice::arcs`), whose weights are multiplied with the rule weights.
+    pub fn with_fallback_lattice(
+        &self,
+        lattice: &Lattice<T, W>,
+    ) -> (
+        impl Iterator<Item = (GornTree<&'a PMCFGRule<N, T, W>>, Vec<usize>)> + 'a,
+        Option<GornTree<PMCFGRule<N, T, W>>>,
     ) {
         let &Self {
             grammar,
@@ -108,16 +128,22 @@ where
             ..
         } = self;
         let realbeam = beam.unwrap_or_else(|| grammar.generator.states());
-        let rulemask = grammar.rulemaskbuilder.build(word);
+        let terminals: Vec<T> = lattice
+            .arcs()
+            .iter()
+            .map(|arc| arc.terminal.clone())
+            .collect();
+        let rulemask = grammar.rulemaskbuilder.build(&terminals);
         let mut word_iterator = grammar
             .generator
-            .generate(word, realbeam, delta, &grammar.estimates, rulemask)
+            .generate_lattice(lattice, realbeam, delta, &grammar.estimates, rulemask)
+            .with_paths()
             .peekable();
         let first = word_iterator
             .peek()
-            .map(|w| cowderiv::CowDerivation::new(w).fallback(&grammar.rules));
+            .map(|&(ref w, _)| cowderiv::CowDerivation::new(w).fallback(&grammar.rules));
 
-        let count_candidates = move |_: &Vec<Delta>| -> bool {
+        let count_candidates = move |_: &(Vec<Delta>, Vec<usize>)| -> bool {
             candidates.as_mut().map_or(true, |c| {
                 if *c == 0 {
                     false
@@ -131,11 +157,44 @@ where
         (
             word_iterator
                 .take_while(count_candidates)
-                .filter_map(move |bs| grammar.toderiv(&bs)),
+                .filter_map(move |(bs, path)| Some((grammar.toderiv(&bs)?, path))),
             first,
         )
     }
 
+    /// The packed forest of the derivations for `word` that are found in the
+    /// chart of the context-free approximation, restricted by the beam and
+    /// delta of this builder; its nodes are nonterminals together with the
+    /// ranges of their components and its hyperedges are labelled with rules.
+    pub fn forest(&self, word: &[T]) -> Forest<N, PMCFGRule<N, T, W>, W> {
+        self.forest_lattice(&Lattice::from_word(word))
+    }
+
+    /// Like `forest`, but for the paths through a lattice.
+    pub fn forest_lattice(&self, lattice: &Lattice<T, W>) -> Forest<N, PMCFGRule<N, T, W>, W> {
+        let &Self {
+            grammar,
+            beam,
+            delta,
+            ..
+        } = self;
+        let realbeam = beam.unwrap_or_else(|| grammar.generator.states());
+        let terminals: Vec<T> = lattice
+            .arcs()
+            .iter()
+            .map(|arc| arc.terminal.clone())
+            .collect();
+        let rulemask = grammar.rulemaskbuilder.build(&terminals);
+        let brackets = grammar.generator.generate_forest(
+            lattice,
+            realbeam,
+            delta,
+            &grammar.estimates,
+            &rulemask,
+        );
+        grammar.toforest(&brackets)
+    }
+
     pub fn debug(&self, word: &[T]) -> (usize, usize, Duration, DebugResult<N, T, W>) {
         let starting_time = Instant::now();
         let &Self {
@@ -274,11 +333,169 @@ where
                 .collect(),
         )
     }
+
+    /// Reads off the forest of derivations from a forest of the context-free
+    /// approximation. A node is a nonterminal with the ranges of its
+    /// components, where the nonterminal is identified by the state of its
+    /// first component; a rule derives it if the binarized rules of each of
+    /// its components occur in `brackets`, and the variables of these
+    /// binarized rules determine the ranges of its successors.
+    fn toforest(&self, brackets: &Forest<StateT, TdBrackets, W>) -> Forest<N, PMCFGRule<N, T, W>, W>
+    where
+        N: Clone,
+        T: Clone,
+        W: Semiring,
+    {
+        type Item = (StateT, Vec<Range>);
+
+        // the assignments of the variables of a component to nodes of
+        // `brackets` below a hyperedge, each with the weight of its
+        // binarized rules
+        fn assignments<W: Semiring>(
+            brackets: &Forest<StateT, TdBrackets, W>,
+            edge: usize,
+        ) -> Vec<(Vec<((u8, u8), usize)>, W)> {
+            let edge = &brackets.edges()[edge];
+            let (_, left, right) = edge.label;
+            let mut result = match left {
+                BracketContent::Ignore if edge.tail.len() == 2 => brackets
+                    .incoming(edge.tail[0])
+                    .iter()
+                    .flat_map(|&e| assignments(brackets, e))
+                    .collect(),
+                BracketContent::Variable(_, i, j) => vec![(vec![((i, j), edge.tail[0])], W::one())],
+                _ => vec![(Vec::new(), W::one())],
+            };
+            for (variables, weight) in &mut result {
+                if let BracketContent::Variable(_, i, j) = right {
+                    variables.push(((i, j), edge.tail[1]));
+                }
+                *weight *= edge.weight;
+            }
+            result
+        }
+
+        // looks up the node of an item, or adds it and schedules its
+        // exploration
+        fn node<N: Clone, L, W>(
+            forest: &mut Forest<N, L, W>,
+            nodes: &mut BTreeMap<Item, usize>,
+            stack: &mut Vec<Item>,
+            label: &N,
+            item: Item,
+        ) -> usize {
+            if let Some(&node) = nodes.get(&item) {
+                return node;
+            }
+            let node = forest.add_node(label.clone(), item.1.clone());
+            nodes.insert(item.clone(), node);
+            stack.push(item);
+            node
+        }
+
+        let mut forest = Forest::new();
+        let root = match brackets.root() {
+            Some(root) => root,
+            None => return forest,
+        };
+        let constituents: BTreeMap<(StateT, Range), usize> = brackets
+            .nodes()
+            .iter()
+            .enumerate()
+            .map(|(id, node)| ((node.label, node.ranges[0]), id))
+            .collect();
+        let mut states = BTreeMap::new();
+        for edge in brackets.edges() {
+            if let BracketContent::Component(rule_id, component) = edge.label.0 {
+                states.insert((rule_id, component), brackets.nodes()[edge.head].label);
+            }
+        }
+        // the rules whose first component is derived by a hyperedge
+        let rule_ids = |node: usize| {
+            let mut rule_ids: Vec<u32> = brackets
+                .incoming(node)
+                .iter()
+                .filter_map(|&e| match brackets.edges()[e].label.0 {
+                    BracketContent::Component(rule_id, 0) => Some(rule_id),
+                    _ => None,
+                })
+                .collect();
+            rule_ids.sort_unstable();
+            rule_ids.dedup();
+            rule_ids
+        };
+
+        let mut nodes = BTreeMap::new();
+        let mut stack = Vec::new();
+        if let Some(&rule_id) = rule_ids(root).first() {
+            let label = &self.rules[rule_id as usize].head;
+            let item = (
+                brackets.nodes()[root].label,
+                brackets.nodes()[root].ranges.clone(),
+            );
+            let root = node(&mut forest, &mut nodes, &mut stack, label, item);
+            forest.set_root(root);
+        }
+
+        while let Some((state, ranges)) = stack.pop() {
+            let head = nodes[&(state, ranges.clone())];
+            for rule_id in rule_ids(constituents[&(state, ranges[0])]) {
+                let rule = &self.rules[rule_id as usize];
+                let mut combinations = vec![(Vec::new(), W::one())];
+                for (component, &range) in ranges.iter().enumerate() {
+                    let component_t = BracketContent::Component(rule_id, component as u8);
+                    let alternatives: Vec<_> = states
+                        .get(&(rule_id, component as u8))
+                        .and_then(|&q| constituents.get(&(q, range)))
+                        .into_iter()
+                        .flat_map(|&constituent| brackets.incoming(constituent))
+                        .filter(|&&e| brackets.edges()[e].label.0 == component_t)
+                        .flat_map(|&e| assignments(brackets, e))
+                        .collect();
+                    combinations = combinations
+                        .iter()
+                        .flat_map(|(variables, weight)| {
+                            alternatives.iter().map(move |(others, other_weight)| {
+                                (
+                                    [&variables[..], &others[..]].concat(),
+                                    *weight * *other_weight,
+                                )
+                            })
+                        })
+                        .collect();
+                }
+
+                for (variables, weight) in combinations {
+                    let mut successors = vec![Vec::new(); rule.tail.len()];
+                    for ((i, j), constituent) in variables {
+                        successors[i as usize].push((j, constituent));
+                    }
+                    let tail = successors
+                        .into_iter()
+                        .zip(&rule.tail)
+                        .map(|(mut components, label)| {
+                            components.sort();
+                            let state = brackets.nodes()[components[0].1].label;
+                            let ranges = components
+                                .iter()
+                                .map(|&(_, constituent)| brackets.nodes()[constituent].ranges[0])
+                                .collect();
+                            node(&mut forest, &mut nodes, &mut stack, label, (state, ranges))
+                        })
+                        .collect();
+                    forest.add_edge(rule.clone(), weight, head, tail);
+                }
+            }
+        }
+
+        forest.prune(W::zero())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{CSRepresentation, Lcfrs};
+    use super::{CSRepresentation, Lattice, Lcfrs};
+    use crate::automata::finite_state_automaton::FiniteStateAutomaton;
     use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT};
     use log_domain::LogDomain;
 
@@ -305,6 +522,109 @@ mod test {
         );
     }
 
+    #[test]
+    fn lattice() {
+        let grammar = lcfrs();
+        let d1 = vec![(vec![], &grammar.rules[1])].into_iter().collect();
+        let d2 = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[1]),
+            (vec![1], &grammar.rules[1]),
+        ]
+        .into_iter()
+        .collect();
+
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                            final: [2]\n\
+                                                                            Transition [A] (0 → 1) # 0.5\n\
+                                                                            Transition [B] (0 → 1) # 1\n\
+                                                                            Transition [A] (1 → 2) # 0.5\n\
+                                                                            Transition [A] (0 → 2) # 0.1"
+            .parse()
+            .unwrap();
+        let lattice = Lattice::from_automaton(&automaton).unwrap();
+
+        let cs = CSRepresentation::new(grammar.clone(), 0);
+        let generator = cs.build_generator();
+        let mut parses = generator.with_fallback_lattice(&lattice).0;
+
+        // 0.7 · 0.1 > 0.3 · 0.7 · 0.5 · 0.7 · 0.5
+        let (derivation, path) = parses.next().unwrap();
+        assert_eq!(derivation, d1);
+        assert_eq!(lattice.word(&path), vec![&'A']);
+        assert_eq!(lattice.weight(&path), LogDomain::new(0.1).unwrap());
+
+        let (derivation, path) = parses.next().unwrap();
+        assert_eq!(derivation, d2);
+        assert_eq!(lattice.word(&path), vec![&'A', &'A']);
+        assert_eq!(lattice.weight(&path), LogDomain::new(0.25).unwrap());
+    }
+
+    #[test]
+    fn forest() {
+        let grammar = lcfrs();
+        let cs = CSRepresentation::new(grammar.clone(), 0);
+        let forest = cs.build_generator().forest(&['A', 'A', 'A']);
+
+        // S(S(A) S(S(A) S(A))) and S(S(S(A) S(A)) S(A))
+        let root = forest.root().unwrap();
+        assert_eq!(forest.nodes()[root].label, "S");
+        assert_eq!(forest.nodes()[root].ranges, vec![(0, 3)]);
+        assert_eq!(forest.nodes().len(), 6);
+        assert_eq!(forest.edges().len(), 7);
+        let total = 2.0 * 0.3 * 0.3 * 0.7 * 0.7 * 0.7;
+        assert!((forest.total().value() - total).abs() < 1e-9);
+
+        let derivations: Vec<_> = forest.kbest().collect();
+        assert_eq!(derivations.len(), 2);
+        for (derivation, weight) in derivations {
+            assert_eq!(derivation.len(), 5);
+            assert_eq!(derivation.get(&vec![]), Some(&&grammar.rules[0]));
+            assert!((weight.value() - total / 2.0).abs() < 1e-9);
+        }
+
+        assert!(cs.build_generator().forest(&['B']).root().is_none());
+    }
+
+    #[test]
+    fn forest_of_copies() {
+        let grammar = copies();
+        let cs = CSRepresentation::new(grammar.clone(), 0);
+
+        let forest = cs.build_generator().forest(&['a', 'b', 'a', 'b']);
+        let root = forest.root().unwrap();
+        assert_eq!(forest.nodes()[root].ranges, vec![(0, 4)]);
+        let d = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[2]),
+            (vec![0, 0], &grammar.rules[3]),
+            (vec![0, 1], &grammar.rules[6]),
+            (vec![0, 2], &grammar.rules[6]),
+        ]
+        .into_iter()
+        .collect();
+        let derivations: Vec<_> = forest.kbest().map(|(d, _)| d).collect();
+        assert_eq!(derivations, vec![d]);
+        assert!(forest
+            .nodes()
+            .iter()
+            .any(|node| node.label == "A" && node.ranges == vec![(0, 2), (2, 4)]));
+        assert!((forest.total().value() - 0.0625).abs() < 1e-9);
+
+        // the context-free approximation derives the components of A
+        // independently of each other
+        assert!(cs
+            .build_generator()
+            .with_fallback(&['a', 'b', 'b', 'a'])
+            .1
+            .is_some());
+        assert!(cs
+            .build_generator()
+            .forest(&['a', 'b', 'b', 'a'])
+            .root()
+            .is_none());
+    }
+
     fn lcfrs() -> Lcfrs<&'static str, char, LogDomain<f64>> {
         Lcfrs {
             init: "S",
@@ -328,4 +648,44 @@ mod test {
             ],
         }
     }
+
+    fn copies() -> Lcfrs<&'static str, char, LogDomain<f64>> {
+        let rule = |head, tail: Vec<&'static str>, composition, weight: f64| PMCFGRule {
+            head,
+            tail,
+            composition: Composition { composition },
+            weight: LogDomain::new(weight).unwrap(),
+        };
+        let append = vec![
+            vec![VarT::Var(0, 0), VarT::Var(1, 0)],
+            vec![VarT::Var(0, 1), VarT::Var(2, 0)],
+        ];
+        Lcfrs {
+            init: "S",
+            rules: vec![
+                rule(
+                    "S",
+                    vec!["A"],
+                    vec![vec![VarT::Var(0, 0), VarT::Var(0, 1)]],
+                    1.0,
+                ),
+                rule("A", vec!["A", "X", "X"], append.clone(), 0.25),
+                rule("A", vec!["A", "Y", "Y"], append, 0.25),
+                rule(
+                    "A",
+                    vec![],
+                    vec![vec![VarT::T('a')], vec![VarT::T('a')]],
+                    0.25,
+                ),
+                rule(
+                    "A",
+                    vec![],
+                    vec![vec![VarT::T('b')], vec![VarT::T('b')]],
+                    0.25,
+                ),
+                rule("X", vec![], vec![vec![VarT::T('a')]], 1.0),
+                rule("Y", vec![], vec![vec![VarT::T('b')]], 1.0),
+            ],
+        }
+    }
 }
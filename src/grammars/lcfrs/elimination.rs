@@ -0,0 +1,858 @@
+//! Weight-preserving elimination of ε-components and chain rules.
+//!
+//! The ε-elimination replaces each successor that derives an ε-tuple by the
+//! sum of the weights of all derivations of this tuple, and the chain
+//! elimination replaces chains of chain rules, including cyclic ones, by the
+//! sum of their weights; new rules that coincide up to their weight are
+//! merged by summing their weights.
+//! Hence each non-empty word has the same inside weight in both grammars.
+//! Sums over cyclic derivations are computed by a fixed-point iteration with
+//! a bounded number of passes.
+//!
+//! Each derivation of the transformed grammar stands for several derivations
+//! of the original grammar with the same yield; it is mapped to one of them
+//! by an `EpsilonRestorer` and a `ChainRestorer`, respectively, which insert
+//! the best derivation of each ε-tuple and a shortest chain, and restore
+//! merged rules using the heaviest of them.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::iter;
+use std::ops::Mul;
+
+use num_traits::Zero;
+
+use super::{merge_rules, Lcfrs};
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use crate::grammars::reduce::reduce_pmcfg_rules;
+use crate::util::tree::GornTree;
+
+/// Maximal number of passes of the fixed-point iterations once no new
+/// nonterminals are found; more passes are only needed for cyclic
+/// derivations.
+const MAX_PASSES: usize = 100;
+
+/// A nonterminal together with the set of its components that derive the
+/// empty string; these components are removed in the transformed grammar.
+pub type EpsilonNonterminal<N> = (N, BTreeSet<usize>);
+
+/// The successor of an original rule in a rule without ε-components: it is
+/// either kept at the given index, or it derives an ε-tuple via the
+/// derivation of original rule indices.
+#[derive(Clone, Debug)]
+enum Successor {
+    Kept(usize),
+    Empty(GornTree<usize>),
+}
+
+/// Restores derivations of an LCFRS from derivations of its ε-free version.
+#[derive(Clone, Debug)]
+pub struct EpsilonRestorer<N, T, W> {
+    rules: Vec<PMCFGRule<N, T, W>>,
+    /// For each new rule: the index of each original rule it was constructed
+    /// from, together with its successors, the heaviest one first.
+    origins: HashMap<PMCFGRule<EpsilonNonterminal<N>, T, W>, Vec<(usize, Vec<Successor>)>>,
+}
+
+impl<N, T, W> EpsilonRestorer<N, T, W>
+where
+    N: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Inserts the removed derivations of ε-tuples into a derivation of the
+    /// ε-free grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// ε-free grammar, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&PMCFGRule<N, T, W>>>
+    where
+        R: Borrow<PMCFGRule<EpsilonNonterminal<N>, T, W>>,
+    {
+        Some(
+            self.restore_indices(tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Like `restore`, but yields the indices of the original rules.
+    pub(crate) fn restore_indices<R>(&self, tree: &GornTree<R>) -> Option<GornTree<usize>>
+    where
+        R: Borrow<PMCFGRule<EpsilonNonterminal<N>, T, W>>,
+    {
+        let mut restored = GornTree::new();
+        let mut stack = vec![(Vec::new(), Vec::new())];
+
+        while let Some((address, original_address)) = stack.pop() {
+            let rule: &PMCFGRule<EpsilonNonterminal<N>, T, W> = tree.get(&address)?.borrow();
+            let (rule_id, ref successors) = self.origins.get(rule)?[0];
+            for (i, successor) in successors.iter().enumerate() {
+                let mut child_address = original_address.clone();
+                child_address.push(i);
+                match *successor {
+                    Successor::Kept(k) => {
+                        let mut new_address = address.clone();
+                        new_address.push(k);
+                        stack.push((new_address, child_address));
+                    }
+                    Successor::Empty(ref derivation) => {
+                        for (suffix, &empty_rule_id) in derivation {
+                            let mut empty_address = child_address.clone();
+                            empty_address.extend(suffix);
+                            restored.insert(empty_address, empty_rule_id);
+                        }
+                    }
+                }
+            }
+            restored.insert(original_address, rule_id);
+        }
+
+        Some(restored)
+    }
+}
+
+/// Restores derivations of an LCFRS from derivations of its version
+/// without chain rules.
+#[derive(Clone, Debug)]
+pub struct ChainRestorer<N, T, W> {
+    rules: Vec<PMCFGRule<N, T, W>>,
+    /// For each new rule and each chain it was constructed from, the heaviest
+    /// one first: the indices of the collapsed chain rules from top to
+    /// bottom, followed by the index of the rule at the end of the chain.
+    origins: HashMap<PMCFGRule<N, T, W>, Vec<Vec<usize>>>,
+}
+
+impl<N, T, W> ChainRestorer<N, T, W>
+where
+    N: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Expands the collapsed chain rules in a derivation of the transformed
+    /// grammar.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// transformed grammar, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&PMCFGRule<N, T, W>>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        Some(
+            self.restore_indices(tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Like `restore`, but yields the indices of the original rules.
+    pub(crate) fn restore_indices<R>(&self, tree: &GornTree<R>) -> Option<GornTree<usize>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let mut restored = GornTree::new();
+        let mut stack = vec![(Vec::new(), Vec::new())];
+
+        while let Some((address, mut original_address)) = stack.pop() {
+            let rule: &PMCFGRule<N, T, W> = tree.get(&address)?.borrow();
+            let chain = &self.origins.get(rule)?[0];
+            for (depth, &rule_id) in chain.iter().enumerate() {
+                if depth > 0 {
+                    original_address.push(0);
+                }
+                restored.insert(original_address.clone(), rule_id);
+            }
+            for i in 0..rule.tail.len() {
+                let mut child_address = address.clone();
+                child_address.push(i);
+                let mut original_child_address = original_address.clone();
+                original_child_address.push(i);
+                stack.push((child_address, original_child_address));
+            }
+        }
+
+        Some(restored)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    /// Removes all components that derive the empty string.
+    /// Each nonterminal is annotated with the set of its components that are
+    /// empty; successors whose components are all empty are removed, and the
+    /// sum of the weights of all their derivations is multiplied into the
+    /// rule.
+    /// The empty word is not in the language of the resulting grammar.
+    pub fn eliminate_epsilon(
+        &self,
+    ) -> (Lcfrs<EpsilonNonterminal<N>, T, W>, EpsilonRestorer<N, T, W>) {
+        let (rules, restorer) = eliminate_epsilon_rules(&self.rules, &[self.init.clone()]);
+        (
+            Lcfrs {
+                rules,
+                init: (self.init.clone(), BTreeSet::new()),
+            },
+            restorer,
+        )
+    }
+
+    /// Collapses chains of rules of the form `A → [[Var 0 i₁], …, [Var 0 iₖ]] (B)`,
+    /// i.e. rules that only permute the components of a single successor.
+    /// Each chain, followed by any other rule, is replaced by a single rule;
+    /// its weight is the weight of the other rule multiplied by the sum of
+    /// the weights of all chains, including cyclic ones, that lead to the
+    /// same nonterminal and permute the components in the same way.
+    pub fn eliminate_chains(&self) -> (Self, ChainRestorer<N, T, W>) {
+        let (rules, restorer) = eliminate_chain_rules(&self.rules, &[self.init.clone()]);
+        (
+            Lcfrs {
+                rules,
+                init: self.init.clone(),
+            },
+            restorer,
+        )
+    }
+}
+
+/// Removes ε-components from a list of rules of an LCFRS with the given
+/// initial nonterminals, see `Lcfrs::eliminate_epsilon`.
+pub(crate) fn eliminate_epsilon_rules<N, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    initials: &[N],
+) -> (
+    Vec<PMCFGRule<EpsilonNonterminal<N>, T, W>>,
+    EpsilonRestorer<N, T, W>,
+)
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    let fanouts: HashMap<&N, usize> = rules
+        .iter()
+        .map(|rule| (&rule.head, rule.composition.len()))
+        .collect();
+    let signatures = empty_signatures(rules);
+    let weights = empty_weights(rules);
+    let derivations = empty_derivations(rules, fanouts.len());
+
+    let mut new_rules = Vec::new();
+
+    for (rule_id, rule) in rules.iter().enumerate() {
+        let options: Option<Vec<Vec<&BTreeSet<usize>>>> = rule
+            .tail
+            .iter()
+            .map(|nt| signatures.get(nt).map(|s| s.iter().collect()))
+            .collect();
+        let options = match options {
+            Some(options) => options,
+            None => continue,
+        };
+
+        'choices: for choice in combinations(&options) {
+            let head_signature = empty_components(&rule.composition, &choice);
+            if head_signature.len() == rule.composition.len() {
+                continue;
+            }
+
+            let mut weight = rule.weight.clone();
+            let mut tail = Vec::new();
+            let mut successors = Vec::with_capacity(rule.tail.len());
+            for (nt, &signature) in rule.tail.iter().zip(&choice) {
+                if signature.len() == fanouts[nt] {
+                    match (weights.get(nt), derivations.get(nt)) {
+                        (Some(empty_weight), Some(&(_, ref derivation))) => {
+                            weight = weight * empty_weight.clone();
+                            successors.push(Successor::Empty(derivation.clone()));
+                        }
+                        _ => continue 'choices,
+                    }
+                } else {
+                    successors.push(Successor::Kept(tail.len()));
+                    tail.push((nt.clone(), signature.clone()));
+                }
+            }
+
+            let composition = rule
+                .composition
+                .composition
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| !head_signature.contains(&j))
+                .map(|(_, component)| {
+                    component
+                        .iter()
+                        .filter_map(|symbol| match *symbol {
+                            VarT::T(ref t) => Some(VarT::T(t.clone())),
+                            VarT::Var(i, l) => match successors[i] {
+                                Successor::Kept(k) if !choice[i].contains(&l) => {
+                                    Some(VarT::Var(k, l - choice[i].range(..l).count()))
+                                }
+                                _ => None,
+                            },
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let new_rule = PMCFGRule {
+                head: (rule.head.clone(), head_signature),
+                tail,
+                composition: Composition { composition },
+                weight,
+            };
+            new_rules.push((new_rule, (rule_id, successors)));
+        }
+    }
+
+    let initials: Vec<_> = initials
+        .iter()
+        .map(|nt| (nt.clone(), BTreeSet::new()))
+        .collect();
+    let (new_rules, origins) = merge_rules(new_rules);
+    let (new_rules, _) = reduce_pmcfg_rules(new_rules, &initials);
+
+    (
+        new_rules,
+        EpsilonRestorer {
+            rules: rules.to_vec(),
+            origins,
+        },
+    )
+}
+
+/// Collapses chain rules in a list of rules of an LCFRS with the given
+/// initial nonterminals, see `Lcfrs::eliminate_chains`.
+pub(crate) fn eliminate_chain_rules<N, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    initials: &[N],
+) -> (Vec<PMCFGRule<N, T, W>>, ChainRestorer<N, T, W>)
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    let permutations: Vec<Option<Vec<usize>>> = rules.iter().map(chain_permutation).collect();
+    let mut heads = Vec::new();
+    let mut by_head: HashMap<&N, Vec<usize>> = HashMap::new();
+    for (rule_id, rule) in rules.iter().enumerate() {
+        by_head
+            .entry(&rule.head)
+            .or_insert_with(|| {
+                heads.push(&rule.head);
+                Vec::new()
+            })
+            .push(rule_id);
+    }
+
+    let mut new_rules = Vec::new();
+
+    for &head in &heads {
+        let fanout = rules[by_head[head][0]].composition.len();
+        // the empty chain from `head`, followed by the ends of all other chains
+        let ends = iter::once((head, (0..fanout).collect(), None, Vec::new())).chain(
+            chain_ends(rules, &permutations, &by_head, head, fanout)
+                .into_iter()
+                .map(|(nt, positions, weight, chain)| (nt, positions, Some(weight), chain)),
+        );
+
+        for (nt, positions, chain_weight, chain) in ends {
+            for &rule_id in by_head.get(nt).map_or(&[][..], |ids| &ids[..]) {
+                if permutations[rule_id].is_some() {
+                    continue;
+                }
+                let rule = &rules[rule_id];
+                let weight = match chain_weight {
+                    Some(ref chain_weight) => chain_weight.clone() * rule.weight.clone(),
+                    None => rule.weight.clone(),
+                };
+                let composition = positions
+                    .iter()
+                    .map(|&j: &usize| rule.composition.composition[j].clone())
+                    .collect();
+                let new_rule = PMCFGRule {
+                    head: head.clone(),
+                    tail: rule.tail.clone(),
+                    composition: Composition { composition },
+                    weight,
+                };
+                let mut origin = chain.clone();
+                origin.push(rule_id);
+                new_rules.push((new_rule, origin));
+            }
+        }
+    }
+
+    let (new_rules, origins) = merge_rules(new_rules);
+    let (new_rules, _) = reduce_pmcfg_rules(new_rules, initials);
+
+    (
+        new_rules,
+        ChainRestorer {
+            rules: rules.to_vec(),
+            origins,
+        },
+    )
+}
+
+/// The ends of all non-empty chains of chain rules from `head`: each
+/// nonterminal at the end of a chain, together with the position of each
+/// component of `head` in it, the sum of the weights of all such chains
+/// (including cyclic ones) and the rules of a shortest one.
+fn chain_ends<'a, N, T, W>(
+    rules: &'a [PMCFGRule<N, T, W>],
+    permutations: &[Option<Vec<usize>>],
+    by_head: &HashMap<&'a N, Vec<usize>>,
+    head: &'a N,
+    fanout: usize,
+) -> Vec<(&'a N, Vec<usize>, W, Vec<usize>)>
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialEq,
+{
+    let identity: Vec<usize> = (0..fanout).collect();
+    let mut ends: Vec<(&N, Vec<usize>, Vec<usize>)> = Vec::new();
+    let mut weights: Vec<W> = Vec::new();
+    let mut index: HashMap<(&N, Vec<usize>), usize> = HashMap::new();
+
+    for pass in 1.. {
+        let mut next = vec![W::zero(); ends.len()];
+        let mut found = Vec::new();
+        // chains of length one start at `head`, longer ones extend the known chains
+        let known = ends
+            .iter()
+            .zip(&weights)
+            .map(|(&(nt, ref positions, ref chain), weight)| {
+                (nt, positions, Some(weight), &chain[..])
+            });
+        let starts = iter::once((head, &identity, None, &[][..])).chain(known);
+        for (nt, positions, weight, chain) in starts {
+            for &rule_id in by_head.get(nt).map_or(&[][..], |ids| &ids[..]) {
+                let permutation = match permutations[rule_id] {
+                    Some(ref permutation) => permutation,
+                    None => continue,
+                };
+                let rule = &rules[rule_id];
+                let weight = match weight {
+                    Some(weight) => weight.clone() * rule.weight.clone(),
+                    None => rule.weight.clone(),
+                };
+                let positions: Vec<usize> = positions.iter().map(|&j| permutation[j]).collect();
+                let key = (&rule.tail[0], positions);
+                match index.get(&key).cloned() {
+                    Some(e) => next[e] = next[e].clone() + weight,
+                    None => {
+                        index.insert(key.clone(), next.len());
+                        next.push(weight);
+                        let mut chain = chain.to_vec();
+                        chain.push(rule_id);
+                        found.push((key.0, key.1, chain));
+                    }
+                }
+            }
+        }
+
+        let grown = !found.is_empty();
+        let changed = grown || next != weights;
+        ends.extend(found);
+        weights = next;
+        if !changed || (!grown && pass >= MAX_PASSES) {
+            break;
+        }
+    }
+
+    ends.into_iter()
+        .zip(weights)
+        .map(|((nt, positions, chain), weight)| (nt, positions, weight, chain))
+        .collect()
+}
+
+/// If the rule is a chain rule, i.e. its only successor's components are
+/// permuted, yields the component of the successor for each component.
+fn chain_permutation<N, T, W>(rule: &PMCFGRule<N, T, W>) -> Option<Vec<usize>> {
+    if rule.tail.len() != 1 {
+        return None;
+    }
+    rule.composition
+        .composition
+        .iter()
+        .map(|component| match component.as_slice() {
+            [VarT::Var(0, l)] => Some(*l),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The components of a composition that are empty if the given components
+/// of the successors are empty.
+fn empty_components<T>(
+    composition: &Composition<T>,
+    signatures: &[&BTreeSet<usize>],
+) -> BTreeSet<usize> {
+    composition
+        .composition
+        .iter()
+        .enumerate()
+        .filter(|&(_, component)| {
+            component.iter().all(|symbol| match *symbol {
+                VarT::Var(i, l) => signatures[i].contains(&l),
+                VarT::T(_) => false,
+            })
+        })
+        .map(|(j, _)| j)
+        .collect()
+}
+
+/// Computes all combinations of empty components that occur in derivations
+/// of each nonterminal.
+fn empty_signatures<N, T, W>(rules: &[PMCFGRule<N, T, W>]) -> HashMap<&N, BTreeSet<BTreeSet<usize>>>
+where
+    N: Eq + Hash,
+{
+    let mut signatures: HashMap<&N, BTreeSet<BTreeSet<usize>>> = HashMap::new();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+        for rule in rules {
+            let found: Vec<BTreeSet<usize>> = {
+                let options: Option<Vec<Vec<&BTreeSet<usize>>>> = rule
+                    .tail
+                    .iter()
+                    .map(|nt| signatures.get(nt).map(|s| s.iter().collect()))
+                    .collect();
+                options.map_or(Vec::new(), |options| {
+                    combinations(&options)
+                        .into_iter()
+                        .map(|choice| empty_components(&rule.composition, &choice))
+                        .collect()
+                })
+            };
+            let known = signatures.entry(&rule.head).or_insert_with(BTreeSet::new);
+            for signature in found {
+                changed |= known.insert(signature);
+            }
+        }
+    }
+
+    signatures
+}
+
+/// Computes the sum of the weights of all derivations of an ε-tuple for each
+/// nonterminal that has one.
+fn empty_weights<N, T, W>(rules: &[PMCFGRule<N, T, W>]) -> HashMap<&N, W>
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialEq,
+{
+    let mut weights: HashMap<&N, W> = HashMap::new();
+
+    for pass in 1.. {
+        let mut next: HashMap<&N, W> = HashMap::new();
+        for rule in rules {
+            if rule
+                .composition
+                .composition
+                .iter()
+                .any(|component| component.iter().any(VarT::is_t))
+            {
+                continue;
+            }
+            let weight = rule
+                .tail
+                .iter()
+                .try_fold(rule.weight.clone(), |weight, nt| {
+                    Some(weight * weights.get(nt)?.clone())
+                });
+            if let Some(weight) = weight {
+                let sum = next.entry(&rule.head).or_insert_with(W::zero);
+                *sum = sum.clone() + weight;
+            }
+        }
+
+        let grown = next.len() > weights.len();
+        let changed = next != weights;
+        weights = next;
+        if !changed || (!grown && pass >= MAX_PASSES) {
+            break;
+        }
+    }
+
+    weights
+}
+
+/// Computes a best derivation (with its weight) of an ε-tuple for each
+/// nonterminal that has one; the derivations consist of rule indices and
+/// are inserted by the `EpsilonRestorer`.
+/// Weights of cyclic derivations are improved for at most `rounds` rounds.
+fn empty_derivations<N, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    rounds: usize,
+) -> HashMap<&N, (W, GornTree<usize>)>
+where
+    N: Eq + Hash,
+    W: Clone + Mul<Output = W> + PartialOrd,
+{
+    let mut best: HashMap<&N, (W, GornTree<usize>)> = HashMap::new();
+
+    for _ in 0..=rounds {
+        let mut changed = false;
+        for (rule_id, rule) in rules.iter().enumerate() {
+            if rule
+                .composition
+                .composition
+                .iter()
+                .any(|component| component.iter().any(VarT::is_t))
+            {
+                continue;
+            }
+
+            let candidate = {
+                let mut weight = rule.weight.clone();
+                let mut derivation = GornTree::new();
+                derivation.insert(Vec::new(), rule_id);
+                let mut complete = true;
+                for (i, nt) in rule.tail.iter().enumerate() {
+                    match best.get(nt) {
+                        Some(&(ref successor_weight, ref successor_derivation)) => {
+                            weight = weight * successor_weight.clone();
+                            for (address, &successor_rule_id) in successor_derivation {
+                                let mut child_address = vec![i];
+                                child_address.extend(address);
+                                derivation.insert(child_address, successor_rule_id);
+                            }
+                        }
+                        None => {
+                            complete = false;
+                            break;
+                        }
+                    }
+                }
+                if complete {
+                    Some((weight, derivation))
+                } else {
+                    None
+                }
+            };
+
+            if let Some((weight, derivation)) = candidate {
+                let better = match best.get(&rule.head) {
+                    Some(&(ref known_weight, _)) => weight > *known_weight,
+                    None => true,
+                };
+                if better {
+                    best.insert(&rule.head, (weight, derivation));
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    best
+}
+
+/// All combinations that choose one element of each option.
+fn combinations<A: Clone>(options: &[Vec<A>]) -> Vec<Vec<A>> {
+    let mut combinations = vec![Vec::new()];
+    for option in options {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|prefix| {
+                option.iter().map(move |a| {
+                    let mut combination = prefix.clone();
+                    combination.push(a.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use num_traits::Zero;
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        s.parse().unwrap()
+    }
+
+    /// Compares weights up to a relative error.
+    pub fn approx(a: LogDomain<f64>, b: LogDomain<f64>) -> bool {
+        (a.value() - b.value()).abs() <= 1e-9 * b.value()
+    }
+
+    /// All non-empty words over `alphabet` up to length `max_length`.
+    pub fn words(alphabet: &[&str], max_length: usize) -> Vec<Vec<String>> {
+        let mut words = vec![Vec::new()];
+        let mut all = Vec::new();
+        for _ in 0..max_length {
+            words = words
+                .iter()
+                .flat_map(|word: &Vec<String>| {
+                    alphabet.iter().map(move |a| {
+                        let mut word = word.clone();
+                        word.push(a.to_string());
+                        word
+                    })
+                })
+                .collect();
+            all.extend(words.iter().cloned());
+        }
+        all
+    }
+
+    #[test]
+    fn epsilon() {
+        let rules = vec![
+            rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)"),
+            rule("A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5"),
+            rule("A → [[], []] () # 0.5"),
+            rule("B → [[T b, Var 0 0], [T d, Var 0 1]] (B) # 0.5"),
+            rule("B → [[], []] () # 0.5"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        let (eliminated, restorer) = lcfrs.eliminate_epsilon();
+        let (new_rules, init) = eliminated.destruct();
+
+        assert_eq!(init, ("S".to_owned(), BTreeSet::new()));
+        assert_eq!(new_rules.len(), 7);
+        assert!(new_rules.iter().all(|r| r
+            .composition
+            .composition
+            .iter()
+            .all(|component| !component.is_empty())));
+
+        let empty = |nt: &str| (nt.to_owned(), BTreeSet::new());
+        let top = PMCFGRule {
+            head: empty("S"),
+            tail: vec![empty("A")],
+            composition: Composition::from(vec![vec![VarT::Var(0, 0), VarT::Var(0, 1)]]),
+            weight: 0.5,
+        };
+        let bottom = PMCFGRule {
+            head: empty("A"),
+            tail: vec![],
+            composition: Composition::from(vec![
+                vec![VarT::T("a".to_owned())],
+                vec![VarT::T("c".to_owned())],
+            ]),
+            weight: 0.25,
+        };
+        let found = new_rules.iter().find(|r| **r == top).unwrap();
+        assert_eq!(found.weight, 0.5);
+        let found = new_rules.iter().find(|r| **r == bottom).unwrap();
+        assert_eq!(found.weight, 0.25);
+
+        let derivation: GornTree<_> = vec![(vec![], top), (vec![0], bottom)].into_iter().collect();
+        let control: GornTree<_> = vec![
+            (vec![], &rules[0]),
+            (vec![0], &rules[1]),
+            (vec![0, 0], &rules[2]),
+            (vec![1], &rules[4]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(control));
+    }
+
+    #[test]
+    fn chains() {
+        let rules = vec![
+            rule("S → [[Var 0 0, Var 0 1]] (A)"),
+            rule("S → [[Var 0 0]] (C) # 0.5"),
+            rule("A → [[Var 0 1], [Var 0 0]] (B) # 0.5"),
+            rule("B → [[T a], [T b]] ()"),
+            rule("C → [[T c]] () # 0.5"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        let (eliminated, restorer) = lcfrs.eliminate_chains();
+        let (new_rules, _) = eliminated.destruct();
+
+        let control = vec![
+            rule("S → [[Var 0 0, Var 0 1]] (A)"),
+            rule("S → [[T c]] () # 0.25"),
+            rule("A → [[T b], [T a]] () # 0.5"),
+        ];
+        assert_eq!(new_rules, control);
+        assert_eq!(
+            new_rules.iter().map(|r| r.weight).collect::<Vec<_>>(),
+            vec![1.0, 0.25, 0.5]
+        );
+
+        let derivation: GornTree<_> = vec![(vec![], control[1].clone())].into_iter().collect();
+        let restored: GornTree<_> = vec![(vec![], &rules[1]), (vec![0], &rules[4])]
+            .into_iter()
+            .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+
+        let derivation: GornTree<_> =
+            vec![(vec![], control[0].clone()), (vec![0], control[2].clone())]
+                .into_iter()
+                .collect();
+        let restored: GornTree<_> = vec![
+            (vec![], &rules[0]),
+            (vec![0], &rules[2]),
+            (vec![0, 0], &rules[3]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+
+    #[test]
+    fn repeated_heads() {
+        let rules: Vec<PMCFGRule<String, String, LogDomain<f64>>> = vec![
+            "S → [[Var 0 0, Var 0 1]] (A)",
+            "A → [[T a, Var 0 0], [T b, Var 0 1]] (A) # 0.25",
+            "A → [[T a], [T b]] () # 0.125",
+            "A → [[], [T c]] () # 0.125",
+            "A → [[], []] () # 0.125",
+            "A → [[Var 0 1], [Var 0 0]] (B) # 0.25",
+            "A → [[Var 0 0], [Var 0 1]] (A) # 0.125",
+            "B → [[T c], [T d]] () # 0.5",
+            "B → [[T d], []] () # 0.25",
+            "B → [[], []] () # 0.125",
+            "B → [[Var 0 1], [Var 0 0]] (A) # 0.125",
+        ]
+        .into_iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let lcfrs = Lcfrs::new(rules, "S".to_owned()).unwrap();
+        let (without_epsilon, _) = lcfrs.eliminate_epsilon();
+        let (without_chains, _) = lcfrs.eliminate_chains();
+
+        // A keeps several rules in both grammars
+        let epsilon_rules = without_epsilon
+            .rules
+            .iter()
+            .filter(|r| r.head.0 == "A")
+            .count();
+        let chain_rules = without_chains
+            .rules
+            .iter()
+            .filter(|r| r.head == "A")
+            .count();
+        assert!(epsilon_rules > 1 && chain_rules > 1);
+
+        // several derivations of ε-tuples and cyclic chains are summed up
+        let mut accepted = 0;
+        for word in words(&["a", "b", "c", "d"], 4) {
+            let expected = lcfrs.inside_score(&word);
+            assert!(approx(without_epsilon.inside_score(&word), expected));
+            assert!(approx(without_chains.inside_score(&word), expected));
+            if !expected.is_zero() {
+                accepted += 1;
+            }
+        }
+        assert!(accepted > 5);
+    }
+}
@@ -3,7 +3,7 @@ use fnv::FnvHashMap;
 use nom::*;
 use num_traits::One;
 use std::collections::hash_map::Entry;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 use std::ops::{AddAssign, Div, DivAssign};
 use std::str::FromStr;
 
@@ -72,15 +72,18 @@ named!(parse_yield<&str,DiscoYield>,
 );
 
 /// Parse the weight given in the disco-dop format.
-/// Weights are given as pseudocounts, which are either fractions (numerator/denominator), or
-/// integer values.
+/// Weights are given as pseudocounts, which are either fractions (numerator/denominator),
+/// integer values, or decimal numbers.
 fn parse_pseudocount<W>(s: &str) -> IResult<&str, W>
 where
     W: Div<Output = W> + FromStr,
 {
     map_res!(
         s,
-        tuple!(digit, opt!(complete!(preceded!(tag!("/"), digit)))),
+        tuple!(
+            recognize!(tuple!(digit, opt!(complete!(preceded!(tag!("."), digit))))),
+            opt!(complete!(preceded!(tag!("/"), digit)))
+        ),
         |(num, denom): (&str, Option<&str>)| -> Result<W, W::Err> {
             if let Some(denominator) = denom {
                 let numerator: W = num.parse()?;
@@ -353,13 +356,196 @@ where
     }
 }
 
+/// Writes a yield function in binary format, i.e. the inverse of `parse_yield`.
+fn write_yield(y: &[(u8, u64)]) -> String {
+    let components: Vec<String> = y
+        .iter()
+        .map(|&(clen, c)| {
+            (0..clen)
+                .map(|i| if 2u64.pow(u32::from(i)) & c == 0 { '0' } else { '1' })
+                .collect()
+        })
+        .collect();
+    components.join(",")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToDiscoDopError {
+    /// The rule with the given index has more than two successors.
+    Rank(usize),
+    /// The rule with the given index has no successors, but its composition is not a single
+    /// terminal symbol.
+    Lexical(usize),
+    /// The composition of the rule with the given index contains terminals and variables.
+    Mixed(usize),
+    /// The variables of a successor do not occur in the order of their components in the
+    /// composition of the rule with the given index.
+    NonMonotone(usize),
+    /// A component of the rule with the given index contains more than 64 variables.
+    ComponentLength(usize),
+}
+
+/// Reads off the yield function of a rule with one or two successors in binary format;
+/// the successors are swapped if the composition starts with a variable of the second one.
+fn discodop_yield<T>(
+    rule_id: usize,
+    composition: &[Vec<VarT<T>>],
+) -> Result<(bool, DiscoYield), ToDiscoDopError> {
+    let swapped = match composition.get(0).and_then(|c| c.get(0)) {
+        Some(&VarT::Var(i, _)) => i == 1,
+        _ => false,
+    };
+    let mut next_component = [0, 0];
+    let mut y = Vec::with_capacity(composition.len());
+
+    for component in composition {
+        if component.len() > 64 {
+            return Err(ToDiscoDopError::ComponentLength(rule_id));
+        }
+        let mut c = 0u64;
+        for (position, symbol) in component.iter().enumerate() {
+            match *symbol {
+                VarT::Var(i, j) => {
+                    let successor = if swapped { 1 - i } else { i };
+                    if j != next_component[i] {
+                        return Err(ToDiscoDopError::NonMonotone(rule_id));
+                    }
+                    next_component[i] += 1;
+                    if successor == 1 {
+                        c |= 2u64.pow(position as u32);
+                    }
+                }
+                VarT::T(_) => return Err(ToDiscoDopError::Mixed(rule_id)),
+            }
+        }
+        y.push((component.len() as u8, c));
+    }
+
+    Ok((swapped, y))
+}
+
+impl<N, T, W> DiscoDopGrammar<N, T, W>
+where
+    N: Clone,
+    T: Clone,
+    W: Clone,
+{
+    /// Splits a binarized LCFRS into constituent rules and a lexer for disco-dop.
+    /// Each rule must either have one or two successors and a composition without
+    /// terminals, or no successor and a single terminal.
+    /// The weights are written as they are; disco-dop and `Lcfrs::from` normalize them
+    /// for each left-hand side.
+    /// Note that disco-dop (and the conversion back to `Lcfrs`) assumes `ROOT` as initial
+    /// nonterminal.
+    pub fn from_lcfrs(lcfrs: &Lcfrs<N, T, W>) -> Result<Self, ToDiscoDopError> {
+        let mut constituents = Vec::new();
+        let mut lexer = Vec::new();
+
+        for (rule_id, rule) in lcfrs.rules.iter().enumerate() {
+            let composition = &rule.composition.composition;
+            match rule.tail.len() {
+                0 => match composition.as_slice() {
+                    [component] => match component.as_slice() {
+                        [VarT::T(word)] => {
+                            lexer.push((word.clone(), rule.head.clone(), rule.weight.clone()))
+                        }
+                        _ => return Err(ToDiscoDopError::Lexical(rule_id)),
+                    },
+                    _ => return Err(ToDiscoDopError::Lexical(rule_id)),
+                },
+                1 => {
+                    let (_, y) = discodop_yield(rule_id, composition)?;
+                    let deriv = DiscoDeriv::Chain {
+                        lhs: rule.head.clone(),
+                        rhs: rule.tail[0].clone(),
+                    };
+                    constituents.push((deriv, y, rule.weight.clone()));
+                }
+                2 => {
+                    let (swapped, y) = discodop_yield(rule_id, composition)?;
+                    let (rhs1, rhs2) = if swapped {
+                        (rule.tail[1].clone(), rule.tail[0].clone())
+                    } else {
+                        (rule.tail[0].clone(), rule.tail[1].clone())
+                    };
+                    let deriv = DiscoDeriv::Binary {
+                        lhs: rule.head.clone(),
+                        rhs1,
+                        rhs2,
+                    };
+                    constituents.push((deriv, y, rule.weight.clone()));
+                }
+                _ => return Err(ToDiscoDopError::Rank(rule_id)),
+            }
+        }
+
+        Ok(DiscoDopGrammar {
+            constituents: DiscoConstituents(constituents),
+            lexer: Some(DiscoLexer(lexer)),
+        })
+    }
+}
+
+/// Writes the constituent rules in the format of disco-dop's `.rules` files.
+impl<N: Display, W: Display> Display for DiscoConstituents<N, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (deriv, y, weight) in &self.0 {
+            match *deriv {
+                DiscoDeriv::Chain { ref lhs, ref rhs } => write!(f, "{}\t{}", lhs, rhs)?,
+                DiscoDeriv::Binary {
+                    ref lhs,
+                    ref rhs1,
+                    ref rhs2,
+                } => write!(f, "{}\t{}\t{}", lhs, rhs1, rhs2)?,
+            }
+            writeln!(f, "\t{}\t{}", write_yield(y), weight)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the lexer in the format of disco-dop's `.lex` files, i.e. one line per word
+/// with all of its tags.
+impl<N, T, W> Display for DiscoLexer<N, T, W>
+where
+    N: Display,
+    T: Display + Hash + Eq,
+    W: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words: Vec<(&T, Vec<(&N, &W)>)> = Vec::new();
+        let mut word_ids: FnvHashMap<&T, usize> = HashMap::default();
+        for (word, pos, weight) in &self.0 {
+            let id = *word_ids.entry(word).or_insert_with(|| {
+                words.push((word, Vec::new()));
+                words.len() - 1
+            });
+            words[id].1.push((pos, weight));
+        }
+
+        for (word, tags) in words {
+            write!(f, "{}", word)?;
+            for (pos, weight) in tags {
+                write!(f, "\t{}\t{}", pos, weight)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn weight() {
-        let ws = vec![("1", 1f64), ("1000", 1000f64), ("23/64", 23f64 / 64f64)];
+        let ws = vec![
+            ("1", 1f64),
+            ("1000", 1000f64),
+            ("23/64", 23f64 / 64f64),
+            ("0.25", 0.25f64),
+        ];
         for (s, w) in ws {
             assert_eq!(parse_pseudocount::<f64>(s).unwrap().1, w);
         }
@@ -575,4 +761,76 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn yield_write() {
+        for y in &["000,11111", "0101010,10111010", "0", "1,0,1,0,10100,01,0,1"] {
+            assert_eq!(&write_yield(&parse_yield(y).unwrap().1), y);
+        }
+    }
+
+    #[test]
+    fn export() {
+        let rule = |s: &str| -> PMCFGRule<String, String, f64> { s.parse().unwrap() };
+        let lcfrs = Lcfrs {
+            rules: vec![
+                rule("ROOT → [[Var 0 0]] (S) # 1"),
+                rule("S → [[Var 1 0, Var 0 0, Var 1 1]] (NP, VP_2) # 0.75"),
+                rule("S → [[Var 0 0, Var 1 0]] (NP, VP) # 0.25"),
+                rule("VP_2 → [[Var 0 0], [Var 0 1]] (VP_2) # 0.5"),
+                rule("VP_2 → [[T is], [T rich]] () # 0.5"),
+            ],
+            init: "ROOT".to_string(),
+        };
+        assert_eq!(
+            DiscoDopGrammar::from_lcfrs(&lcfrs).unwrap_err(),
+            ToDiscoDopError::Lexical(4)
+        );
+
+        let mut rules = lcfrs.rules.clone();
+        rules[4] = rule("VP_2 → [[Var 0 0], [Var 1 0]] (VB, JJ) # 0.5");
+        rules.push(rule("NP → [[T John]] () # 1"));
+        rules.push(rule("VB → [[T is]] () # 1"));
+        rules.push(rule("JJ → [[T rich]] () # 0.5"));
+        rules.push(rule("JJ → [[T John]] () # 0.5"));
+        rules.push(rule("VP → [[T is]] () # 1"));
+        let lcfrs = Lcfrs {
+            rules,
+            init: "ROOT".to_string(),
+        };
+
+        let disco = DiscoDopGrammar::from_lcfrs(&lcfrs).unwrap();
+        let constituents = disco.constituents.to_string();
+        let lexer = disco.lexer.unwrap().to_string();
+        assert_eq!(
+            constituents,
+            "ROOT\tS\t0\t1\n\
+             S\tVP_2\tNP\t010\t0.75\n\
+             S\tNP\tVP\t01\t0.25\n\
+             VP_2\tVP_2\t0,0\t0.5\n\
+             VP_2\tVB\tJJ\t0,1\t0.5\n"
+        );
+        assert_eq!(
+            lexer,
+            "John\tNP\t1\tJJ\t0.5\nis\tVB\t1\tVP\t1\nrich\tJJ\t0.5\n"
+        );
+
+        // round trip
+        let reimported: Lcfrs<String, String, f64> = constituents
+            .trim_end()
+            .parse::<DiscoDopGrammar<String, (), f64>>()
+            .unwrap()
+            .with_lexer(lexer.trim_end().parse().unwrap())
+            .into();
+        assert_eq!(reimported.init, lcfrs.init);
+        assert_eq!(reimported.rules.len(), lcfrs.rules.len());
+        for r in &reimported.rules {
+            let original = lcfrs.rules.iter().find(|o| *o == r);
+            let swapped = lcfrs
+                .rules
+                .iter()
+                .find(|o| o.head == r.head && o.tail.iter().rev().eq(r.tail.iter()));
+            assert_eq!(original.or(swapped).map(|o| o.weight), Some(r.weight), "{}", r);
+        }
+    }
 }
@@ -0,0 +1,373 @@
+use super::binarization::markovization::{markovize_derivation, Markovization};
+use super::binarization::BinarizationStrategy;
+use super::*;
+use crate::grammars::pmcfg::negra::NegraSentence;
+use crate::util::tree::GornTree;
+use log_domain::LogDomain;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The nonterminal that is used for the virtual root node of each sentence.
+pub const ROOT: &str = "ROOT";
+
+/// The edge label that marks the head child of a constituent.
+const HEAD: &str = "HD";
+
+/// A child of a node in a _NEGRA_ tree, either the position of a terminal or
+/// the number of a nonterminal node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Child {
+    Terminal(usize),
+    Node(usize),
+}
+
+/// Splits a set of positions into maximal ranges of consecutive positions.
+fn ranges(positions: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &position in positions {
+        if let Some(last) = ranges.last_mut() {
+            if last.1 + 1 == position {
+                last.1 = position;
+                continue;
+            }
+        }
+        ranges.push((position, position));
+    }
+    ranges
+}
+
+/// Annotates a constituent label with its fanout if it is discontinuous,
+/// e.g. `VP_2` for a `VP` that spans two ranges.
+fn nonterminal(label: &str, fanout: usize) -> String {
+    if fanout == 1 {
+        label.to_owned()
+    } else {
+        format!("{}_{}", label, fanout)
+    }
+}
+
+/// Reads off a derivation of PMCFG rules from a _NEGRA_ sentence.
+/// There is one rule for each nonterminal node, its successors are the
+/// children ordered by their leftmost terminal; each terminal is derived by
+/// a rule `POS → [[T word]] ()`.
+/// The virtual root node is represented by the nonterminal `ROOT`.
+pub fn extract_derivation(
+    sentence: &NegraSentence,
+) -> Result<GornTree<PMCFGRule<String, String, ()>>, String> {
+    derivation_with_heads(sentence).map(|(tree, _)| tree)
+}
+
+/// Reads off a derivation like `extract_derivation` together with the index
+/// of the head successor of each rule, i.e. the child with the edge label
+/// `HD`, or the last child if there is none.
+fn derivation_with_heads(
+    sentence: &NegraSentence,
+) -> Result<(GornTree<PMCFGRule<String, String, ()>>, GornTree<usize>), String> {
+    let yields = sentence.yields()?;
+    if !yields.contains_key(&0) {
+        return Err(format!("sentence {} is empty", sentence.id));
+    }
+
+    let mut children: BTreeMap<usize, Vec<Child>> = BTreeMap::new();
+    for (position, terminal) in sentence.terminals.iter().enumerate() {
+        children
+            .entry(terminal.parent)
+            .or_default()
+            .push(Child::Terminal(position));
+    }
+    for (&number, node) in &sentence.nodes {
+        if yields.contains_key(&number) {
+            children
+                .entry(node.parent)
+                .or_default()
+                .push(Child::Node(number));
+        }
+    }
+
+    let mut tree = GornTree::new();
+    let mut heads = GornTree::new();
+    let mut stack = vec![(0, Vec::new())];
+
+    while let Some((node, address)) = stack.pop() {
+        let label = if node == 0 {
+            ROOT
+        } else {
+            sentence.nodes[&node].label.as_str()
+        };
+        let mut kids = children.remove(&node).unwrap_or_default();
+        kids.sort_by_key(|kid| match *kid {
+            Child::Terminal(position) => position,
+            Child::Node(number) => *yields[&number].iter().next().unwrap(),
+        });
+
+        // maps the first position of each range to the variable that spans it
+        let mut starts: BTreeMap<usize, (usize, usize, usize)> = BTreeMap::new();
+        let head = kids
+            .iter()
+            .position(|kid| match *kid {
+                Child::Terminal(position) => sentence.terminals[position].edge == HEAD,
+                Child::Node(number) => sentence.nodes[&number].edge == HEAD,
+            })
+            .unwrap_or_else(|| kids.len().saturating_sub(1));
+        heads.insert(address.clone(), head);
+
+        let mut tail = Vec::with_capacity(kids.len());
+        for (i, kid) in kids.into_iter().enumerate() {
+            let mut kid_address = address.clone();
+            kid_address.push(i);
+            match kid {
+                Child::Terminal(position) => {
+                    let terminal = &sentence.terminals[position];
+                    starts.insert(position, (i, 0, position));
+                    tail.push(terminal.pos.clone());
+                    tree.insert(
+                        kid_address,
+                        PMCFGRule {
+                            head: terminal.pos.clone(),
+                            tail: Vec::new(),
+                            composition: vec![vec![VarT::T(terminal.word.clone())]].into(),
+                            weight: (),
+                        },
+                    );
+                }
+                Child::Node(number) => {
+                    let kid_ranges = ranges(&yields[&number]);
+                    for (j, &(start, end)) in kid_ranges.iter().enumerate() {
+                        starts.insert(start, (i, j, end));
+                    }
+                    tail.push(nonterminal(
+                        &sentence.nodes[&number].label,
+                        kid_ranges.len(),
+                    ));
+                    stack.push((number, kid_address));
+                }
+            }
+        }
+
+        let node_ranges = ranges(&yields[&node]);
+        let mut composition = Vec::with_capacity(node_ranges.len());
+        for (start, end) in node_ranges.iter().cloned() {
+            let mut component = Vec::new();
+            let mut position = start;
+            while position <= end {
+                let (i, j, range_end) = starts[&position];
+                component.push(VarT::Var(i, j));
+                position = range_end + 1;
+            }
+            composition.push(component);
+        }
+
+        tree.insert(
+            address,
+            PMCFGRule {
+                head: nonterminal(label, node_ranges.len()),
+                tail,
+                composition: composition.into(),
+                weight: (),
+            },
+        );
+    }
+
+    Ok((tree, heads))
+}
+
+/// Extracts an LCFRS from a treebank in _NEGRA_ export format.
+/// Each rule is weighted by its relative frequency among all rules with the
+/// same left-hand side nonterminal.
+/// Nonterminals of discontinuous constituents are annotated with their
+/// fanout (see `extract_derivation`).
+pub fn extract_lcfrs<'a, I>(treebank: I) -> Result<Lcfrs<String, String, LogDomain<f64>>, String>
+where
+    I: IntoIterator<Item = &'a NegraSentence>,
+{
+    let mut derivations = Vec::new();
+    for sentence in treebank {
+        derivations.push(extract_derivation(sentence)?);
+    }
+    Ok(relative_frequencies(derivations))
+}
+
+/// Extracts a binary LCFRS from a treebank in _NEGRA_ export format.
+/// The derivation of each sentence is binarized with the given strategy
+/// and Markovized before the rules are counted (see `extract_lcfrs`);
+/// the head-outward strategy uses the children with the edge label `HD`
+/// as heads.
+/// The labels of the resulting nonterminals follow the scheme described in
+/// `base_label`, so that parse trees can be written with `to_negra`.
+pub fn extract_markovized_lcfrs<'a, I>(
+    treebank: I,
+    strategy: BinarizationStrategy,
+    markovization: &Markovization,
+) -> Result<Lcfrs<String, String, LogDomain<f64>>, String>
+where
+    I: IntoIterator<Item = &'a NegraSentence>,
+{
+    let mut derivations = Vec::new();
+    for sentence in treebank {
+        let (derivation, heads) = derivation_with_heads(sentence)?;
+        let markovized =
+            markovize_derivation(&derivation, strategy, markovization, |address, _| {
+                *heads.get(&address.to_vec()).unwrap()
+            })
+            .ok_or_else(|| format!("sentence {}: incomplete derivation", sentence.id))?;
+        derivations.push(markovized);
+    }
+    Ok(relative_frequencies(derivations))
+}
+
+/// Counts the rules in a collection of derivations and weights each rule by
+/// its relative frequency among all rules with the same left-hand side.
+fn relative_frequencies<I>(derivations: I) -> Lcfrs<String, String, LogDomain<f64>>
+where
+    I: IntoIterator<Item = GornTree<PMCFGRule<String, String, ()>>>,
+{
+    let mut rule_ids: HashMap<PMCFGRule<String, String, ()>, usize> = HashMap::new();
+    let mut rules_with_counts: Vec<(PMCFGRule<String, String, ()>, usize)> = Vec::new();
+    let mut head_counts: HashMap<String, usize> = HashMap::new();
+
+    for derivation in derivations {
+        for (_, rule) in derivation {
+            *head_counts.entry(rule.head.clone()).or_insert(0) += 1;
+            let id = *rule_ids.entry(rule.clone()).or_insert_with(|| {
+                rules_with_counts.push((rule, 0));
+                rules_with_counts.len() - 1
+            });
+            rules_with_counts[id].1 += 1;
+        }
+    }
+
+    let rules = rules_with_counts
+        .into_iter()
+        .map(|(rule, count)| {
+            let PMCFGRule {
+                head,
+                tail,
+                composition,
+                ..
+            } = rule;
+            let weight = LogDomain::new(count as f64 / head_counts[&head] as f64).unwrap();
+            PMCFGRule {
+                head,
+                tail,
+                composition,
+                weight,
+            }
+        })
+        .collect();
+
+    Lcfrs {
+        rules,
+        init: ROOT.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grammars::pmcfg::negra::{read_negra, to_negra, DumpMode};
+    use std::str::FromStr;
+
+    const TREEBANK: &str = "#BOS 1\n\
+                            Darüber\tPROAV\t--\tMO\t501\n\
+                            muss\tVMFIN\t--\tHD\t500\n\
+                            nachgedacht\tVVPP\t--\tHD\t501\n\
+                            werden\tVAINF\t--\tHD\t500\n\
+                            #500\tS\t--\t--\t0\n\
+                            #501\tVP\t--\tOC\t500\n\
+                            #EOS 1\n\
+                            #BOS 2\n\
+                            muss\tVMFIN\t--\tHD\t500\n\
+                            werden\tVAINF\t--\tHD\t500\n\
+                            .\t$.\t--\t--\t0\n\
+                            #500\tS\t--\t--\t0\n\
+                            #EOS 2\n";
+
+    fn rule(s: &str) -> PMCFGRule<String, String, ()> {
+        let r: PMCFGRule<String, String, usize> = PMCFGRule::from_str(s).unwrap();
+        PMCFGRule {
+            head: r.head,
+            tail: r.tail,
+            composition: r.composition,
+            weight: (),
+        }
+    }
+
+    #[test]
+    fn ranges_of_positions() {
+        let positions = vec![0, 1, 3, 5, 6, 7].into_iter().collect();
+        assert_eq!(ranges(&positions), vec![(0, 1), (3, 3), (5, 7)]);
+        assert_eq!(ranges(&BTreeSet::new()), vec![]);
+    }
+
+    #[test]
+    fn derivation() {
+        let treebank = read_negra(TREEBANK).unwrap();
+        let derivation = extract_derivation(&treebank[0]).unwrap();
+
+        let control: GornTree<_> = vec![
+            (vec![], rule("ROOT → [[Var 0 0]] (S)")),
+            (
+                vec![0],
+                rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 2 0]] (VP_2, VMFIN, VAINF)"),
+            ),
+            (vec![0, 0], rule("VP_2 → [[Var 0 0], [Var 1 0]] (PROAV, VVPP)")),
+            (vec![0, 0, 0], rule("PROAV → [[T Darüber]] ()")),
+            (vec![0, 0, 1], rule("VVPP → [[T nachgedacht]] ()")),
+            (vec![0, 1], rule("VMFIN → [[T muss]] ()")),
+            (vec![0, 2], rule("VAINF → [[T werden]] ()")),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(derivation, control);
+        assert_eq!(
+            to_negra(&derivation, 1, DumpMode::Default),
+            "#BOS 1\n\
+             Darüber\tPROAV\t--\t--\t500\n\
+             muss\tVMFIN\t--\t--\t501\n\
+             nachgedacht\tVVPP\t--\t--\t500\n\
+             werden\tVAINF\t--\t--\t501\n\
+             #500\tVP\t--\t--\t501\n\
+             #501\tS\t--\t--\t0\n\
+             #EOS 1"
+        );
+    }
+
+    #[test]
+    fn relative_frequencies() {
+        let treebank = read_negra(TREEBANK).unwrap();
+        let lcfrs = extract_lcfrs(&treebank).unwrap();
+
+        assert_eq!(lcfrs.init, ROOT);
+        assert_eq!(lcfrs.rules.len(), 10);
+        for r in &lcfrs.rules {
+            let expected = match r.head.as_str() {
+                "ROOT" | "S" => 0.5,
+                _ => 1.0,
+            };
+            assert_eq!(r.weight, LogDomain::new(expected).unwrap(), "{:?}", r);
+        }
+        assert!(Lcfrs::new(lcfrs.rules, lcfrs.init).is_some());
+    }
+
+    #[test]
+    fn markovized() {
+        let treebank = read_negra(TREEBANK).unwrap();
+        let markovization = Markovization {
+            horizontal: Some(1),
+            vertical: 2,
+        };
+        let lcfrs =
+            extract_markovized_lcfrs(&treebank, BinarizationStrategy::HeadOutward, &markovization)
+                .unwrap();
+
+        assert!(lcfrs.rules.iter().all(|r| r.tail.len() <= 2));
+        assert!(lcfrs
+            .rules
+            .iter()
+            .any(|r| r.head == "VP_2^<S>" && r.tail == vec!["PROAV", "VVPP"]));
+        // the successors of S are attached to its head VMFIN, starting with VAINF
+        assert!(lcfrs.rules.iter().any(|r| r.head == "S^<ROOT>"
+            && r.tail == vec!["VP_2^<S>", "S^<ROOT>|<VAINF>_2"]));
+        assert!(Lcfrs::new(lcfrs.rules, lcfrs.init).is_some());
+    }
+}
@@ -1,5 +1,7 @@
 use super::*;
 use nom::*;
+use num_traits::One;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::str::FromStr;
 
@@ -10,8 +12,9 @@ enum Rhs<N> {
 }
 type Yield = Vec<Vec<bool>>;
 
+/// A grammar in rparse's format, i.e. binary clauses without a lexicon.
 #[derive(Debug)]
-struct RparseClauses<N, W>(Vec<(W, N, Rhs<N>, Yield)>);
+pub struct RparseClauses<N, W>(Vec<(W, N, Rhs<N>, Yield)>);
 
 fn is_token(c: char) -> bool {
     !c.is_whitespace()
@@ -136,6 +139,64 @@ where
     }
 }
 
+impl<N, W> RparseClauses<N, W>
+where
+    N: FromStr + Clone + Hash + Eq,
+    <N as FromStr>::Err: Debug,
+    W: One,
+{
+    /// Converts the clauses into an LCFRS where each nonterminal that does not occur on the
+    /// left-hand side of a clause (i.e. each part-of-speech tag) derives itself as terminal.
+    pub fn with_default_lexer(self) -> Lcfrs<N, N, W> {
+        let lcfrs: Lcfrs<N, (), W> = self.into();
+        let lhss: HashSet<N> = lcfrs.rules.iter().map(|r| r.head.clone()).collect();
+
+        let mut rules = Vec::with_capacity(lcfrs.rules.len());
+        let mut preterminals = Vec::new();
+        for rule in lcfrs.rules {
+            for successor in &rule.tail {
+                if !lhss.contains(successor) && !preterminals.contains(successor) {
+                    preterminals.push(successor.clone());
+                }
+            }
+            let composition: Vec<Vec<VarT<N>>> = rule
+                .composition
+                .composition
+                .into_iter()
+                .map(|component| {
+                    component
+                        .into_iter()
+                        .filter_map(|symbol| match symbol {
+                            VarT::Var(i, j) => Some(VarT::Var(i, j)),
+                            VarT::T(()) => None,
+                        })
+                        .collect()
+                })
+                .collect();
+            rules.push(PMCFGRule {
+                head: rule.head,
+                tail: rule.tail,
+                composition: composition.into(),
+                weight: rule.weight,
+            });
+        }
+
+        for pos in preterminals {
+            rules.push(PMCFGRule {
+                head: pos.clone(),
+                tail: vec![],
+                composition: vec![vec![VarT::T(pos)]].into(),
+                weight: W::one(),
+            });
+        }
+
+        Lcfrs {
+            rules,
+            init: lcfrs.init,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -242,4 +303,33 @@ mod test {
         assert_eq!(conv.rules, rules);
         assert_eq!(conv.init, "VROOT1".to_owned());
     }
+
+    #[test]
+    fn default_lexer() {
+        let clauses: RparseClauses<String, f64> =
+            "1 1.0:VROOT1 --> S1 $.1 [[[false, true]]]\n1 1.0:S1 --> NN1 [[[false]]]"
+                .parse()
+                .unwrap();
+        let lcfrs = clauses.with_default_lexer();
+
+        assert_eq!(lcfrs.init, "VROOT1");
+        assert_eq!(lcfrs.rules.len(), 4);
+        assert_eq!(
+            lcfrs.rules[2..].to_vec(),
+            vec![
+                PMCFGRule {
+                    head: "$.1".to_owned(),
+                    tail: vec![],
+                    weight: 1.0,
+                    composition: vec![vec![VarT::T("$.1".to_owned())]].into(),
+                },
+                PMCFGRule {
+                    head: "NN1".to_owned(),
+                    tail: vec![],
+                    weight: 1.0,
+                    composition: vec![vec![VarT::T("NN1".to_owned())]].into(),
+                },
+            ]
+        );
+    }
 }
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::str::FromStr;
 
 use super::Lcfrs;
-use crate::util::parsing::initial_rule_grammar_from_str;
+use crate::util::parsing::{initial_rule_grammar_from_str, ParseError};
 
 impl<N, T, W> FromStr for Lcfrs<N, T, W>
 where
@@ -14,12 +14,14 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (mut initials, rules) = initial_rule_grammar_from_str(s)?;
         if initials.len() != 1 {
-            Err("multiple initial nonterminals".to_owned())
+            Err(ParseError::Inconsistent {
+                message: "an LCFRS has exactly one initial nonterminal".to_owned(),
+            })
         } else {
             Ok(Lcfrs {
                 init: initials.remove(0),
@@ -0,0 +1,648 @@
+//! Inside and outside weights of the derivations of a word in an LCFRS, and expectation
+//! maximisation of the rule weights with respect to a corpus of words (the inside-outside
+//! algorithm).
+
+use log_domain::LogDomain;
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::Lcfrs;
+use crate::grammars::pmcfg::{PMCFGRule, VarT};
+use crate::grammars::reduce::integerise;
+
+/// Maximal number of passes over the items; more than one pass is only needed for cyclic
+/// derivations, e.g. via chain rules or ε-components.
+const MAX_PASSES: usize = 100;
+
+/// The part of a word that is derived by a component of an item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Constraint {
+    /// The component derives the symbols at positions `i` to `j - 1`.
+    Exact(usize, usize),
+    /// The component derives the symbols from position `i` to the end of the word, followed
+    /// by an arbitrary string.
+    Prefix(usize),
+    /// The component derives an arbitrary string, e.g. because it is deleted.
+    Any,
+}
+
+/// A nonterminal together with a constraint for each of its components.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Item {
+    nonterminal: usize,
+    spans: Vec<Constraint>,
+}
+
+/// The application of a rule that derives its head item from its tail items.
+#[derive(Clone, Debug)]
+struct Hyperedge {
+    rule: usize,
+    head: usize,
+    tail: Vec<usize>,
+}
+
+/// All derivations of a word as a hypergraph whose nodes are items.
+#[derive(Clone, Debug)]
+pub(crate) struct Hypergraph {
+    goal: usize,
+    items: usize,
+    edges: Vec<Hyperedge>,
+    /// The edges with each item as their head.
+    incoming: Vec<Vec<usize>>,
+    /// Each item occurs after the items in its derivations, unless they are cyclic.
+    order: Vec<usize>,
+}
+
+impl Hypergraph {
+    /// Constructs the hypergraph of all derivations of `word` from `initial` top-down.
+    pub(crate) fn new<N, T, W>(rules: &[PMCFGRule<N, T, W>], initial: &N, word: &[T]) -> Self
+    where
+        N: Eq + Hash,
+        T: PartialEq,
+    {
+        Hypergraph::with_goal(rules, initial, word, Constraint::Exact(0, word.len()))
+    }
+
+    /// Constructs the hypergraph of all derivations from `initial` whose yield starts with
+    /// `word`.
+    pub(crate) fn prefix<N, T, W>(rules: &[PMCFGRule<N, T, W>], initial: &N, word: &[T]) -> Self
+    where
+        N: Eq + Hash,
+        T: PartialEq,
+    {
+        Hypergraph::with_goal(rules, initial, word, Constraint::Prefix(0))
+    }
+
+    /// Constructs the hypergraph of all derivations of the goal item top-down; copied
+    /// components must derive the same symbols, deleted components are unconstrained.
+    fn with_goal<N, T, W>(
+        rules: &[PMCFGRule<N, T, W>],
+        initial: &N,
+        word: &[T],
+        goal: Constraint,
+    ) -> Self
+    where
+        N: Eq + Hash,
+        T: PartialEq,
+    {
+        let mut map = HashMap::new();
+        let mut names = Vec::new();
+        let initial = integerise(initial, &mut map, &mut names);
+        let mut by_head: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut head_fanouts: HashMap<usize, usize> = HashMap::new();
+        let mut tails = Vec::with_capacity(rules.len());
+        for (rule_id, rule) in rules.iter().enumerate() {
+            let head = integerise(&rule.head, &mut map, &mut names);
+            by_head.entry(head).or_insert_with(Vec::new).push(rule_id);
+            head_fanouts.insert(head, rule.composition.len());
+            let tail: Vec<usize> = rule
+                .tail
+                .iter()
+                .map(|nt| integerise(nt, &mut map, &mut names))
+                .collect();
+            tails.push(tail);
+        }
+        // the fanout of each successor; if it has no rules, its components that do not occur
+        // in the composition are irrelevant
+        let fanouts: Vec<Vec<usize>> = rules
+            .iter()
+            .zip(&tails)
+            .map(|(rule, tail)| {
+                let mut fanout: Vec<usize> = tail
+                    .iter()
+                    .map(|nt| head_fanouts.get(nt).cloned().unwrap_or(0))
+                    .collect();
+                for symbol in rule.composition.composition.iter().flat_map(|c| c.iter()) {
+                    if let VarT::Var(i, l) = *symbol {
+                        fanout[i] = fanout[i].max(l + 1);
+                    }
+                }
+                fanout
+            })
+            .collect();
+
+        let goal = Item {
+            nonterminal: initial,
+            spans: vec![goal],
+        };
+        let mut item_ids: HashMap<Item, usize> = HashMap::new();
+        item_ids.insert(goal.clone(), 0);
+        let mut items = vec![goal];
+        let mut incoming = vec![Vec::new()];
+        let mut edges = Vec::new();
+        let mut agenda = vec![0];
+
+        while let Some(item_id) = agenda.pop() {
+            let item = items[item_id].clone();
+            for &rule_id in by_head
+                .get(&item.nonterminal)
+                .map_or(&[][..], |ids| &ids[..])
+            {
+                let composition = &rules[rule_id].composition.composition;
+                if composition.len() != item.spans.len() {
+                    continue;
+                }
+                let matcher = Matcher {
+                    composition,
+                    spans: &item.spans,
+                    word,
+                };
+                for assignment in matcher.assignments(&fanouts[rule_id]) {
+                    let tail = assignment
+                        .into_iter()
+                        .zip(&tails[rule_id])
+                        .map(|(spans, &nonterminal)| {
+                            let child = Item { nonterminal, spans };
+                            *item_ids.entry(child.clone()).or_insert_with(|| {
+                                items.push(child);
+                                incoming.push(Vec::new());
+                                agenda.push(items.len() - 1);
+                                items.len() - 1
+                            })
+                        })
+                        .collect();
+                    incoming[item_id].push(edges.len());
+                    edges.push(Hyperedge {
+                        rule: rule_id,
+                        head: item_id,
+                        tail,
+                    });
+                }
+            }
+        }
+
+        // post-order of a depth-first traversal from the goal item
+        let mut order = Vec::with_capacity(items.len());
+        let mut visited = vec![false; items.len()];
+        let mut stack = vec![(0, false)];
+        while let Some((item, expanded)) = stack.pop() {
+            if expanded {
+                order.push(item);
+            } else if !visited[item] {
+                visited[item] = true;
+                stack.push((item, true));
+                for &edge in &incoming[item] {
+                    for &child in &edges[edge].tail {
+                        if !visited[child] {
+                            stack.push((child, false));
+                        }
+                    }
+                }
+            }
+        }
+
+        Hypergraph {
+            goal: 0,
+            items: items.len(),
+            edges,
+            incoming,
+            order,
+        }
+    }
+
+    /// The sum of the weights of all derivations of each item; `weights` contains the weight
+    /// of each rule.
+    pub(crate) fn inside(&self, weights: &[LogDomain<f64>]) -> Vec<LogDomain<f64>> {
+        let mut inside = vec![LogDomain::zero(); self.items];
+
+        for _ in 0..MAX_PASSES {
+            let mut changed = false;
+            for &item in &self.order {
+                let value = self.incoming[item]
+                    .iter()
+                    .map(|&e| {
+                        let edge = &self.edges[e];
+                        edge.tail
+                            .iter()
+                            .fold(weights[edge.rule], |w, &child| w * inside[child])
+                    })
+                    .fold(LogDomain::zero(), |sum, w| sum + w);
+                if value != inside[item] {
+                    inside[item] = value;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        inside
+    }
+
+    /// The sum of the weights of all derivations of the goal item.
+    pub(crate) fn total(&self, weights: &[LogDomain<f64>]) -> LogDomain<f64> {
+        self.inside(weights)[self.goal]
+    }
+
+    /// The sum of the weights of all contexts of each item in derivations of the goal item.
+    pub(crate) fn outside(
+        &self,
+        weights: &[LogDomain<f64>],
+        inside: &[LogDomain<f64>],
+    ) -> Vec<LogDomain<f64>> {
+        let mut parents: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.items];
+        for (e, edge) in self.edges.iter().enumerate() {
+            for (k, &child) in edge.tail.iter().enumerate() {
+                parents[child].push((e, k));
+            }
+        }
+        let mut outside = vec![LogDomain::zero(); self.items];
+
+        for _ in 0..MAX_PASSES {
+            let mut changed = false;
+            for &item in self.order.iter().rev() {
+                let mut value = if item == self.goal {
+                    LogDomain::one()
+                } else {
+                    LogDomain::zero()
+                };
+                for &(e, k) in &parents[item] {
+                    let edge = &self.edges[e];
+                    value = value
+                        + edge
+                            .tail
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != k)
+                            .fold(outside[edge.head] * weights[edge.rule], |w, (_, &child)| {
+                                w * inside[child]
+                            });
+                }
+                if value != outside[item] {
+                    outside[item] = value;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        outside
+    }
+
+    /// Adds the expected number of applications of each rule in a derivation of the word to
+    /// `counts`, and returns the sum of the weights of all derivations of the word.
+    fn add_expected_counts(
+        &self,
+        weights: &[LogDomain<f64>],
+        counts: &mut [LogDomain<f64>],
+    ) -> LogDomain<f64> {
+        let inside = self.inside(weights);
+        let total = inside[self.goal];
+        if total.is_zero() {
+            return total;
+        }
+
+        let outside = self.outside(weights, &inside);
+        for edge in &self.edges {
+            let weight = edge
+                .tail
+                .iter()
+                .fold(outside[edge.head] * weights[edge.rule], |w, &child| {
+                    w * inside[child]
+                });
+            counts[edge.rule] = counts[edge.rule] + weight / total;
+        }
+
+        total
+    }
+}
+
+/// Matches the components of a composition against the parts of a word they derive.
+struct Matcher<'a, T> {
+    composition: &'a [Vec<VarT<T>>],
+    spans: &'a [Constraint],
+    word: &'a [T],
+}
+
+impl<'a, T: PartialEq> Matcher<'a, T> {
+    /// All assignments of constraints to the variables such that each component satisfies its
+    /// constraint; they are indexed by successor and component of the successor.
+    /// Variables that occur in no constrained component are assigned `Any`.
+    fn assignments(&self, fanouts: &[usize]) -> Vec<Vec<Vec<Constraint>>> {
+        let mut assignment: Vec<Vec<Option<Constraint>>> =
+            fanouts.iter().map(|&f| vec![None; f]).collect();
+        let mut results = Vec::new();
+        self.assign(0, 0, self.start(0), &mut assignment, &mut results);
+        results
+    }
+
+    /// The first position of the word that is derived by a component.
+    fn start(&self, component: usize) -> usize {
+        match self.spans.get(component) {
+            Some(&Constraint::Exact(i, _)) | Some(&Constraint::Prefix(i)) => i,
+            _ => 0,
+        }
+    }
+
+    fn assign(
+        &self,
+        component: usize,
+        symbol: usize,
+        position: usize,
+        assignment: &mut Vec<Vec<Option<Constraint>>>,
+        results: &mut Vec<Vec<Vec<Constraint>>>,
+    ) {
+        if component == self.composition.len() {
+            results.push(
+                assignment
+                    .iter()
+                    .map(|cs| cs.iter().map(|c| c.unwrap_or(Constraint::Any)).collect())
+                    .collect(),
+            );
+            return;
+        }
+        let next = self.start(component + 1);
+
+        match (
+            self.spans[component],
+            self.composition[component].get(symbol),
+        ) {
+            (Constraint::Any, _) => self.assign(component + 1, 0, next, assignment, results),
+
+            (Constraint::Exact(_, end), None) => {
+                if position == end {
+                    self.assign(component + 1, 0, next, assignment, results);
+                }
+            }
+            (Constraint::Exact(_, end), Some(&VarT::T(ref t))) => {
+                if position < end && self.word[position] == *t {
+                    self.assign(component, symbol + 1, position + 1, assignment, results);
+                }
+            }
+            (Constraint::Exact(_, end), Some(&VarT::Var(i, l))) => match assignment[i][l] {
+                // a copy of a variable must derive the same symbols
+                Some(Constraint::Exact(from, to)) => {
+                    let copy_end = position + to - from;
+                    if copy_end <= end && self.word[position..copy_end] == self.word[from..to] {
+                        self.assign(component, symbol + 1, copy_end, assignment, results);
+                    }
+                }
+                Some(_) => (),
+                None => {
+                    for to in position..=end {
+                        assignment[i][l] = Some(Constraint::Exact(position, to));
+                        self.assign(component, symbol + 1, to, assignment, results);
+                    }
+                    assignment[i][l] = None;
+                }
+            },
+
+            // the remaining symbols of a prefix component are unconstrained once the end of
+            // the word is reached; before that, each symbol derives at least one symbol of
+            // the word, except for the symbol that reaches the end
+            (Constraint::Prefix(_), _) if position == self.word.len() => {
+                self.assign(component + 1, 0, next, assignment, results)
+            }
+            (Constraint::Prefix(_), None) => (),
+            (Constraint::Prefix(_), Some(&VarT::T(ref t))) => {
+                if self.word[position] == *t {
+                    if position + 1 == self.word.len() {
+                        self.assign(component + 1, 0, next, assignment, results);
+                    } else {
+                        self.assign(component, symbol + 1, position + 1, assignment, results);
+                    }
+                }
+            }
+            (Constraint::Prefix(_), Some(&VarT::Var(i, l))) => {
+                if assignment[i][l].is_none() {
+                    assignment[i][l] = Some(Constraint::Prefix(position));
+                    self.assign(component + 1, 0, next, assignment, results);
+                    for to in position..self.word.len() {
+                        assignment[i][l] = Some(Constraint::Exact(position, to));
+                        self.assign(component, symbol + 1, to, assignment, results);
+                    }
+                    assignment[i][l] = None;
+                }
+            }
+        }
+    }
+}
+
+/// The expected rule counts for a corpus with respect to the current rule weights.
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    /// The expected number of applications of each rule, in the order of the rules.
+    pub counts: Vec<LogDomain<f64>>,
+    /// The natural logarithm of the probability of the corpus, without the words that have
+    /// no derivation.
+    pub log_likelihood: f64,
+    /// The number of words without a derivation, they are ignored.
+    pub unparsable: usize,
+}
+
+impl<N, T> Lcfrs<N, T, LogDomain<f64>>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + PartialEq,
+{
+    /// The sum of the weights of all derivations of `word`.
+    pub fn inside_score(&self, word: &[T]) -> LogDomain<f64> {
+        let weights: Vec<_> = self.rules.iter().map(|rule| rule.weight).collect();
+        Hypergraph::new(&self.rules, &self.init, word).total(&weights)
+    }
+
+    /// Computes the expected number of applications of each rule in derivations of the words
+    /// in the corpus.
+    pub fn expected_counts<'a, I>(&self, corpus: I) -> Expectation
+    where
+        I: IntoIterator<Item = &'a [T]>,
+        T: 'a,
+    {
+        let weights: Vec<_> = self.rules.iter().map(|rule| rule.weight).collect();
+        let mut expectation = Expectation {
+            counts: vec![LogDomain::zero(); self.rules.len()],
+            log_likelihood: 0.0,
+            unparsable: 0,
+        };
+
+        for word in corpus {
+            let total = Hypergraph::new(&self.rules, &self.init, word)
+                .add_expected_counts(&weights, &mut expectation.counts);
+            if total.is_zero() {
+                expectation.unparsable += 1;
+            } else {
+                expectation.log_likelihood += total.ln();
+            }
+        }
+
+        expectation
+    }
+
+    /// Performs a single step of expectation maximisation: each rule weight is replaced by
+    /// the rule's expected count divided by the expected count of its head.
+    /// Rules whose head does not occur in any derivation keep their weight.
+    pub fn em_step<'a, I>(&self, corpus: I) -> (Self, Expectation)
+    where
+        I: IntoIterator<Item = &'a [T]>,
+        T: 'a,
+    {
+        let expectation = self.expected_counts(corpus);
+        let mut sums: HashMap<&N, LogDomain<f64>> = HashMap::new();
+        for (rule, &count) in self.rules.iter().zip(&expectation.counts) {
+            let sum = sums.entry(&rule.head).or_insert_with(LogDomain::zero);
+            *sum = *sum + count;
+        }
+
+        let rules = self
+            .rules
+            .iter()
+            .zip(&expectation.counts)
+            .map(|(rule, &count)| {
+                let sum = sums[&rule.head];
+                let mut rule = rule.clone();
+                if !sum.is_zero() {
+                    rule.weight = count / sum;
+                }
+                rule
+            })
+            .collect();
+
+        (
+            Lcfrs {
+                rules,
+                init: self.init.clone(),
+            },
+            expectation,
+        )
+    }
+
+    /// Re-estimates the rule weights by expectation maximisation for at most `iterations`
+    /// steps; training stops early if the log-likelihood of the corpus changes by less than
+    /// `threshold`. `report` is called with the number and the expectation of each step.
+    pub fn train<F>(
+        self,
+        corpus: &[Vec<T>],
+        iterations: usize,
+        threshold: f64,
+        mut report: F,
+    ) -> Self
+    where
+        F: FnMut(usize, &Expectation),
+    {
+        let mut grammar = self;
+        let mut previous: Option<f64> = None;
+
+        for iteration in 1..=iterations {
+            let (next, expectation) = grammar.em_step(corpus.iter().map(|word| &word[..]));
+            report(iteration, &expectation);
+            grammar = next;
+            if let Some(log_likelihood) = previous {
+                if (expectation.log_likelihood - log_likelihood).abs() < threshold {
+                    break;
+                }
+            }
+            previous = Some(expectation.log_likelihood);
+        }
+
+        grammar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight(value: f64) -> LogDomain<f64> {
+        LogDomain::new(value).unwrap()
+    }
+
+    fn approx(a: LogDomain<f64>, b: f64) -> bool {
+        (a.value() - b).abs() < 1e-9
+    }
+
+    fn grammar() -> Lcfrs<String, String, LogDomain<f64>> {
+        "initial: [S]\n\
+         S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)\n\
+         A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5\n\
+         A → [[], []] () # 0.5\n\
+         B → [[T b, Var 0 0], [T d, Var 0 1]] (B) # 0.5\n\
+         B → [[], []] () # 0.5"
+            .parse()
+            .unwrap()
+    }
+
+    fn word(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn inside_outside() {
+        let grammar = grammar();
+        let weights: Vec<_> = grammar.rules.iter().map(|rule| rule.weight).collect();
+
+        let hypergraph = Hypergraph::new(&grammar.rules, &grammar.init, &word("a b c d"));
+        assert!(approx(hypergraph.total(&weights), 0.0625));
+        let hypergraph = Hypergraph::new(&grammar.rules, &grammar.init, &word("a b d c"));
+        assert!(hypergraph.total(&weights).is_zero());
+
+        let corpus = vec![word("a c"), word("a a c c"), word("b d c")];
+        let expectation = grammar.expected_counts(corpus.iter().map(|w| &w[..]));
+        assert_eq!(expectation.unparsable, 1);
+        assert!((expectation.log_likelihood - (0.125f64.ln() + 0.0625f64.ln())).abs() < 1e-9);
+        for (count, control) in expectation.counts.iter().zip(vec![2.0, 3.0, 2.0, 0.0, 2.0]) {
+            assert!(approx(*count, control));
+        }
+    }
+
+    #[test]
+    fn em() {
+        let corpus = vec![word("a c"), word("a a c c"), word("b d")];
+        let mut steps = 0;
+        let trained = grammar().train(&corpus, 10, 1e-12, |_, _| steps += 1);
+        let (rules, _) = trained.destruct();
+
+        assert!(steps > 1);
+        assert!(approx(rules[0].weight, 1.0));
+        assert!(approx(rules[1].weight, 0.5));
+        assert!(approx(rules[2].weight, 0.5));
+        assert!(approx(rules[3].weight, 0.25));
+        assert!(approx(rules[4].weight, 0.75));
+    }
+
+    #[test]
+    fn assignments() {
+        use super::Constraint::*;
+
+        let composition = vec![vec![VarT::Var(0, 0), VarT::T('a'), VarT::Var(1, 0)]];
+        let matcher = Matcher {
+            composition: &composition,
+            spans: &[Exact(0, 3)],
+            word: &['a', 'a', 'b'],
+        };
+        assert_eq!(
+            matcher.assignments(&[1, 1]),
+            vec![
+                vec![vec![Exact(0, 0)], vec![Exact(1, 3)]],
+                vec![vec![Exact(0, 1)], vec![Exact(2, 3)]]
+            ]
+        );
+
+        // a copied variable derives the same symbols twice
+        let composition = vec![vec![VarT::Var(0, 0), VarT::Var(0, 0)]];
+        let matcher = Matcher {
+            composition: &composition,
+            spans: &[Exact(0, 4)],
+            word: &['a', 'b', 'a', 'b'],
+        };
+        assert_eq!(matcher.assignments(&[1]), vec![vec![vec![Exact(0, 2)]]]);
+
+        // only the first variable that reaches the end of a prefix is a prefix
+        let composition = vec![vec![VarT::Var(0, 0), VarT::Var(1, 0)]];
+        let matcher = Matcher {
+            composition: &composition,
+            spans: &[Prefix(0)],
+            word: &['a'],
+        };
+        assert_eq!(
+            matcher.assignments(&[1, 1]),
+            vec![
+                vec![vec![Prefix(0)], vec![Any]],
+                vec![vec![Exact(0, 0)], vec![Prefix(0)]],
+            ]
+        );
+    }
+}
@@ -1,17 +1,32 @@
 use crate::grammars::pmcfg::PMCFGRule;
 use crate::grammars::pmcfg::VarT;
+use crate::grammars::normalization::{normalize_pmcfg_rules, pmcfg_consistency, Consistency};
+use crate::grammars::reduce::{reduce_pmcfg_rules, Reduction};
+use crate::grammars::stats::{
+    lexical_coverage, pmcfg_stats, pmcfg_terminals, GrammarStats, LexicalCoverage,
+};
+use log_domain::LogDomain;
+use num_traits::Zero;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::hash::Hash;
+use std::ops::Div;
 
+pub mod binarization;
 mod conversion;
 pub mod csparsing;
+pub mod elimination;
 pub mod from_discodop;
+pub mod from_negra;
 pub mod from_rparse;
 mod from_str;
+pub mod inside_outside;
+pub mod monotone;
+pub mod well_nested;
 
 /// A linear context-free rewriting system.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lcfrs<N, T, W> {
     rules: Vec<PMCFGRule<N, T, W>>,
     init: N,
@@ -31,8 +46,15 @@ where
             // scope for lieftime of `fanouts` that borrows from rules
             let fanouts = read_fanouts(&rules)?;
             let check_fanouts = |rule: &PMCFGRule<N, T, W>| -> bool {
-                let fanouts: Vec<usize> = rule.tail.iter().map(|nt| fanouts[nt]).collect();
-                check_composition(&rule.composition.composition, &fanouts)
+                // successors without rules have no fanout
+                let fanouts: Option<Vec<usize>> = rule
+                    .tail
+                    .iter()
+                    .map(|nt| fanouts.get(nt).cloned())
+                    .collect();
+                fanouts.map_or(false, |fanouts| {
+                    check_composition(&rule.composition.composition, &fanouts)
+                })
             };
 
             // check initial fanout
@@ -57,6 +79,61 @@ impl<N, T, W> Lcfrs<N, T, W> {
     }
 }
 
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Removes all useless nonterminals and the rules they occur in.
+    /// The initial nonterminal is kept even if it is unproductive.
+    pub fn reduce(self) -> (Self, Reduction<N>) {
+        let Lcfrs { rules, init } = self;
+        let (rules, reduction) = reduce_pmcfg_rules(rules, std::slice::from_ref(&init));
+        (Lcfrs { rules, init }, reduction)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Div<Output = W>,
+{
+    /// Rescales the weights of the rules for each nonterminal such that they sum to one.
+    pub fn normalize(mut self) -> Self {
+        normalize_pmcfg_rules(&mut self.rules);
+        self
+    }
+}
+
+impl<N, T> Lcfrs<N, T, LogDomain<f64>>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Computes the spectral radius of the expected-children matrix and the partition function
+    /// of each nonterminal.
+    pub fn consistency(&self) -> Consistency<N> {
+        pmcfg_consistency(&self.rules)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Eq + Hash,
+{
+    pub fn stats(&self) -> GrammarStats<N> {
+        pmcfg_stats(&self.rules, std::slice::from_ref(&self.init))
+    }
+
+    /// Counts the tokens of a corpus that are terminals of the grammar.
+    pub fn lexical_coverage<'a, I>(&self, corpus: I) -> LexicalCoverage<T>
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a + Clone,
+    {
+        lexical_coverage(pmcfg_terminals(&self.rules), corpus)
+    }
+}
+
 impl<N: Display, T: Display, W: Display> Display for Lcfrs<N, T, W> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let mut buffer = "".to_string();
@@ -73,7 +150,7 @@ impl<N: Display, T: Display, W: Display> Display for Lcfrs<N, T, W> {
 
 /// Reads the fanout off `PMCFGRules`.
 /// Will return `None` if rule fanouts are inconsistent for same nonterminals.
-fn read_fanouts<'a, R, N, T, W>(rules: R) -> Option<HashMap<&'a N, usize>>
+pub(crate) fn read_fanouts<'a, R, N, T, W>(rules: R) -> Option<HashMap<&'a N, usize>>
 where
     R: IntoIterator<Item = &'a PMCFGRule<N, T, W>>,
     N: 'a + Hash + Eq,
@@ -92,6 +169,48 @@ where
     Some(fanouts)
 }
 
+/// Merges the rules of a transformed grammar that coincide up to their weight by summing their
+/// weights.
+/// Each merged rule keeps the origins of all its parts, the origin of the heaviest part first.
+pub(crate) fn merge_rules<N, T, W, O>(
+    rules: Vec<(PMCFGRule<N, T, W>, O)>,
+) -> (Vec<PMCFGRule<N, T, W>>, HashMap<PMCFGRule<N, T, W>, Vec<O>>)
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + PartialOrd,
+{
+    let mut merged: Vec<PMCFGRule<N, T, W>> = Vec::new();
+    let mut parts: Vec<Vec<(W, O)>> = Vec::new();
+    let mut index: HashMap<PMCFGRule<N, T, W>, usize> = HashMap::new();
+
+    for (rule, origin) in rules {
+        match index.get(&rule).cloned() {
+            Some(i) => {
+                merged[i].weight = merged[i].weight.clone() + rule.weight.clone();
+                parts[i].push((rule.weight, origin));
+            }
+            None => {
+                index.insert(rule.clone(), merged.len());
+                parts.push(vec![(rule.weight.clone(), origin)]);
+                merged.push(rule);
+            }
+        }
+    }
+
+    let origins = merged
+        .iter()
+        .cloned()
+        .zip(parts)
+        .map(|(rule, mut parts)| {
+            parts.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            (rule, parts.into_iter().map(|(_, origin)| origin).collect())
+        })
+        .collect();
+
+    (merged, origins)
+}
+
 /// Checks a composition for linearity.
 /// Will return true if each variable occurs exactly once according to the given fanouts.
 fn check_composition<T>(composition: &[Vec<VarT<T>>], fanouts: &[usize]) -> bool {
@@ -142,6 +261,11 @@ mod tests {
         assert!(Lcfrs::new(lcfrs_rules(), 1).is_some());
         assert!(Lcfrs::new(inconsistent_rules(), 1).is_none());
         assert!(Lcfrs::new(mcfg_rules(), 1).is_none());
+
+        // successor without rules
+        let mut rules = lcfrs_rules();
+        rules[2].tail = vec![3];
+        assert!(Lcfrs::new(rules, 1).is_none());
     }
 
     #[test]
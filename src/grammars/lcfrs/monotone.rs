@@ -0,0 +1,300 @@
+//! Transformation of an LCFRS into an equivalent monotone (ordered) LCFRS,
+//! i.e. an LCFRS in which the components of each successor occur from left
+//! to right in the order of their indices.
+//!
+//! Each nonterminal is annotated with a permutation of its components; the
+//! rules of the annotated nonterminal produce the components of the original
+//! nonterminal in this order.
+//! Derivations of both grammars coincide up to the annotation, they are
+//! mapped back by a `MonotoneRestorer`.
+//! Copies of an original rule that coincide up to their weight yield the same
+//! monotone rule; these are merged by summing their weights and restored to
+//! the heaviest copy.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use num_traits::Zero;
+
+use super::{merge_rules, Lcfrs};
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use crate::util::tree::GornTree;
+
+/// A nonterminal together with a permutation of its components: the `j`th
+/// component of the annotated nonterminal is the `π[j]`th component of the
+/// original one.
+pub type PermutedNonterminal<N> = (N, Vec<usize>);
+
+/// Restores derivations of an LCFRS from derivations of its monotone
+/// version.
+#[derive(Clone, Debug)]
+pub struct MonotoneRestorer<N, T, W> {
+    rules: Vec<PMCFGRule<N, T, W>>,
+    /// For each monotone rule: the indices of the original rules it was
+    /// constructed from, the heaviest one first.
+    origins: HashMap<PMCFGRule<PermutedNonterminal<N>, T, W>, Vec<usize>>,
+}
+
+impl<N, T, W> MonotoneRestorer<N, T, W>
+where
+    N: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Replaces each rule in a derivation of the monotone grammar by the rule
+    /// it was constructed from.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// monotone grammar.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&PMCFGRule<N, T, W>>>
+    where
+        R: Borrow<PMCFGRule<PermutedNonterminal<N>, T, W>>,
+    {
+        Some(
+            self.restore_indices(tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Like `restore`, but yields the indices of the original rules.
+    pub(crate) fn restore_indices<R>(&self, tree: &GornTree<R>) -> Option<GornTree<usize>>
+    where
+        R: Borrow<PMCFGRule<PermutedNonterminal<N>, T, W>>,
+    {
+        tree.into_iter()
+            .map(|(address, rule)| {
+                let rule: &PMCFGRule<PermutedNonterminal<N>, T, W> = rule.borrow();
+                Some((address.clone(), self.origins.get(rule)?[0]))
+            })
+            .collect()
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W> {
+    /// Is `true` iff the composition of each rule is monotone, see
+    /// `Composition::is_monotone`.
+    pub fn is_monotone(&self) -> bool {
+        self.rules.iter().all(|rule| rule.composition.is_monotone())
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + PartialOrd,
+{
+    /// Constructs an equivalent monotone LCFRS.
+    /// Starting with the initial nonterminal and the identity, the components
+    /// of each rule are permuted according to the annotation of its head, and
+    /// each successor is annotated with the order in which its components
+    /// occur in the permuted composition.
+    /// Only annotated nonterminals that are reachable this way are
+    /// constructed; the weights of the rules are kept, but copies of a rule
+    /// are merged.
+    pub fn make_monotone(
+        &self,
+    ) -> (
+        Lcfrs<PermutedNonterminal<N>, T, W>,
+        MonotoneRestorer<N, T, W>,
+    ) {
+        let (rules, restorer) = monotone_rules(&self.rules, &self.init);
+        (
+            Lcfrs {
+                rules,
+                init: (self.init.clone(), vec![0]),
+            },
+            restorer,
+        )
+    }
+}
+
+/// Makes a list of rules of a (linear and non-deleting) LCFRS with the given
+/// initial nonterminal of fanout 1 monotone, see `Lcfrs::make_monotone`.
+pub(crate) fn monotone_rules<N, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    initial: &N,
+) -> (
+    Vec<PMCFGRule<PermutedNonterminal<N>, T, W>>,
+    MonotoneRestorer<N, T, W>,
+)
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + PartialOrd,
+{
+    let mut by_head: HashMap<&N, Vec<usize>> = HashMap::new();
+    for (rule_id, rule) in rules.iter().enumerate() {
+        by_head
+            .entry(&rule.head)
+            .or_insert_with(Vec::new)
+            .push(rule_id);
+    }
+
+    let mut new_rules = Vec::new();
+    let start = (initial.clone(), vec![0]);
+    let mut visited = HashSet::new();
+    visited.insert(start.clone());
+    let mut agenda = vec![start];
+
+    while let Some((nt, permutation)) = agenda.pop() {
+        for &rule_id in by_head.get(&nt).map_or(&[][..], Vec::as_slice) {
+            let rule = &rules[rule_id];
+            let (new_rule, successors) = permute(rule, &permutation);
+            for successor in successors {
+                if visited.insert(successor.clone()) {
+                    agenda.push(successor);
+                }
+            }
+            new_rules.push((new_rule, rule_id));
+        }
+    }
+
+    let (new_rules, origins) = merge_rules(new_rules);
+
+    (
+        new_rules,
+        MonotoneRestorer {
+            rules: rules.to_vec(),
+            origins,
+        },
+    )
+}
+
+/// Permutes the components of `rule` according to `permutation` and
+/// renumbers the components of each successor in the order of their
+/// occurrence; returns the monotone rule and its successors.
+fn permute<N, T, W>(
+    rule: &PMCFGRule<N, T, W>,
+    permutation: &[usize],
+) -> (
+    PMCFGRule<PermutedNonterminal<N>, T, W>,
+    Vec<PermutedNonterminal<N>>,
+)
+where
+    N: Clone,
+    T: Clone,
+    W: Clone,
+{
+    let components: Vec<&Vec<VarT<T>>> =
+        permutation.iter().map(|&j| &rule.composition[j]).collect();
+
+    // orders[i][p] is the component of the i-th successor that occurs p-th
+    let mut orders: Vec<Vec<usize>> = vec![Vec::new(); rule.tail.len()];
+    for symbol in components.iter().flat_map(|component| component.iter()) {
+        if let VarT::Var(i, j) = *symbol {
+            orders[i].push(j);
+        }
+    }
+    let positions: Vec<HashMap<usize, usize>> = orders
+        .iter()
+        .map(|order| order.iter().enumerate().map(|(p, &j)| (j, p)).collect())
+        .collect();
+
+    let composition = components
+        .iter()
+        .map(|component| {
+            component
+                .iter()
+                .map(|symbol| match *symbol {
+                    VarT::Var(i, j) => VarT::Var(i, positions[i][&j]),
+                    VarT::T(ref t) => VarT::T(t.clone()),
+                })
+                .collect()
+        })
+        .collect();
+    let tail: Vec<PermutedNonterminal<N>> = rule.tail.iter().cloned().zip(orders).collect();
+
+    (
+        PMCFGRule {
+            head: (rule.head.clone(), permutation.to_vec()),
+            tail: tail.clone(),
+            composition: Composition { composition },
+            weight: rule.weight.clone(),
+        },
+        tail,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn monotone() {
+        let rules = vec![
+            rule("S → [[Var 0 1, T x, Var 0 0]] (A)"),
+            rule("A → [[T a], [T b, Var 0 1, Var 0 0]] (B) # 0.5"),
+            rule("B → [[T c], [T d]] () # 0.25"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        assert!(!lcfrs.is_monotone());
+
+        let (monotone, restorer) = lcfrs.make_monotone();
+        assert!(monotone.is_monotone());
+        let (new_rules, init) = monotone.destruct();
+        assert_eq!(init, ("S".to_owned(), vec![0]));
+
+        let annotate = |nt: &String| {
+            let permutation = if nt == "S" { vec![0] } else { vec![1, 0] };
+            (nt.clone(), permutation)
+        };
+        let control: Vec<_> = vec![
+            rule("S → [[Var 0 0, T x, Var 0 1]] (A)"),
+            rule("A → [[T b, Var 0 0, Var 0 1], [T a]] (B) # 0.5"),
+            rule("B → [[T d], [T c]] () # 0.25"),
+        ]
+        .iter()
+        .map(|r| r.map_nonterminals(annotate))
+        .collect();
+        assert_eq!(new_rules, control);
+        assert_eq!(
+            new_rules.iter().map(|r| r.weight).collect::<Vec<_>>(),
+            vec![1.0, 0.5, 0.25]
+        );
+
+        let derivation: GornTree<_> = vec![
+            (vec![], &control[0]),
+            (vec![0], &control[1]),
+            (vec![0, 0], &control[2]),
+        ]
+        .into_iter()
+        .collect();
+        let restored: GornTree<_> = vec![
+            (vec![], &rules[0]),
+            (vec![0], &rules[1]),
+            (vec![0, 0], &rules[2]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+
+    #[test]
+    fn copies() {
+        let rules = vec![
+            rule("S → [[Var 0 1, Var 0 0]] (A)"),
+            rule("A → [[T a], [T b]] () # 0.25"),
+            rule("A → [[T a], [T b]] () # 0.5"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        let (monotone, restorer) = lcfrs.make_monotone();
+        let (new_rules, _) = monotone.destruct();
+
+        assert_eq!(new_rules.len(), 2);
+        assert_eq!(new_rules[1].weight, 0.75);
+
+        let derivation: GornTree<_> = vec![(vec![], &new_rules[0]), (vec![0], &new_rules[1])]
+            .into_iter()
+            .collect();
+        let restored: GornTree<_> = vec![(vec![], &rules[0]), (vec![0], &rules[2])]
+            .into_iter()
+            .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+}
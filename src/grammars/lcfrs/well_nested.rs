@@ -0,0 +1,384 @@
+//! Well-nestedness of LCFRS and the approximation of an LCFRS by a well-nested one.
+//!
+//! Well-nested LCFRS are strictly less powerful than LCFRS, so in general
+//! there is no equivalent well-nested grammar.
+//! The approximation substitutes rules into each ill-nested rule until the
+//! interleaving successors disappear, up to a given depth, and removes the
+//! ill-nested rules that remain; hence it derives a subset of the language
+//! of the original grammar.
+//! Rules of the approximation that coincide up to their weight are merged by
+//! summing their weights, hence the weight of a derivation of the
+//! approximation is the sum of the weights of the original derivations it
+//! stands for.
+//! Each derivation of the approximation is mapped to the best of these
+//! derivations by a `WellNestedRestorer`.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use num_traits::Zero;
+
+use super::{merge_rules, Lcfrs};
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT};
+use crate::grammars::reduce::reduce_pmcfg_rules;
+use crate::util::tree::GornTree;
+
+/// A fragment of a derivation of original rule indices that was collapsed
+/// into a single rule, together with the address in the fragment at which
+/// each successor of the rule is attached.
+#[derive(Clone, Debug)]
+struct Fragment {
+    rules: GornTree<usize>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl Fragment {
+    fn new(rule_id: usize, rank: usize) -> Self {
+        let mut rules = GornTree::new();
+        rules.insert(Vec::new(), rule_id);
+        Fragment {
+            rules,
+            successors: (0..rank).map(|i| vec![i]).collect(),
+        }
+    }
+
+    /// Attaches a rule with the given rank at the `i`th successor.
+    fn substitute(&self, i: usize, rule_id: usize, rank: usize) -> Self {
+        let mut rules = self.rules.clone();
+        rules.insert(self.successors[i].clone(), rule_id);
+        let mut successors = self.successors[..i].to_vec();
+        successors.extend((0..rank).map(|l| {
+            let mut address = self.successors[i].clone();
+            address.push(l);
+            address
+        }));
+        successors.extend_from_slice(&self.successors[i + 1..]);
+        Fragment { rules, successors }
+    }
+}
+
+/// Restores derivations of an LCFRS from derivations of its well-nested
+/// approximation.
+#[derive(Clone, Debug)]
+pub struct WellNestedRestorer<N, T, W> {
+    rules: Vec<PMCFGRule<N, T, W>>,
+    /// For each rule of the approximation: the fragments of the rules that
+    /// were merged into it, the heaviest one first.
+    origins: HashMap<PMCFGRule<N, T, W>, Vec<Fragment>>,
+}
+
+impl<N, T, W> WellNestedRestorer<N, T, W>
+where
+    N: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Expands the substituted rules in a derivation of the approximation;
+    /// merged rules are expanded to their heaviest fragment.
+    /// Returns `None` if the derivation contains a rule that is not in the
+    /// approximation, or if it is incomplete.
+    pub fn restore<R>(&self, tree: &GornTree<R>) -> Option<GornTree<&PMCFGRule<N, T, W>>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        Some(
+            self.restore_indices(tree)?
+                .into_iter()
+                .map(|(address, rule_id)| (address, &self.rules[rule_id]))
+                .collect(),
+        )
+    }
+
+    /// Like `restore`, but yields the indices of the original rules.
+    pub(crate) fn restore_indices<R>(&self, tree: &GornTree<R>) -> Option<GornTree<usize>>
+    where
+        R: Borrow<PMCFGRule<N, T, W>>,
+    {
+        let mut restored = GornTree::new();
+        let mut stack = vec![(Vec::new(), Vec::new())];
+
+        while let Some((address, original_address)) = stack.pop() {
+            let rule: &PMCFGRule<N, T, W> = tree.get(&address)?.borrow();
+            let fragment = &self.origins.get(rule)?[0];
+            for (suffix, &rule_id) in &fragment.rules {
+                let mut fragment_address = original_address.clone();
+                fragment_address.extend(suffix);
+                restored.insert(fragment_address, rule_id);
+            }
+            for (k, suffix) in fragment.successors.iter().enumerate() {
+                let mut child_address = address.clone();
+                child_address.push(k);
+                let mut original_child_address = original_address.clone();
+                original_child_address.extend(suffix);
+                stack.push((child_address, original_child_address));
+            }
+        }
+
+        Some(restored)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W> {
+    /// The indices of the rules whose compositions are not well-nested, see
+    /// `Composition::is_well_nested`.
+    pub fn ill_nested_rules(&self) -> Vec<usize> {
+        (0..self.rules.len())
+            .filter(|&r| !self.rules[r].is_well_nested())
+            .collect()
+    }
+
+    pub fn is_well_nested(&self) -> bool {
+        self.rules.iter().all(PMCFGRule::is_well_nested)
+    }
+}
+
+impl<N, T, W> Lcfrs<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    /// Approximates the grammar by a well-nested LCFRS.
+    /// For the first pair of interleaving successors of an ill-nested rule
+    /// (see `Composition::interleaving_successors`), each rule of the
+    /// successor with fewer rules is substituted into the rule; the weight
+    /// of the new rule is the product of both weights.
+    /// This is repeated at most `depth` times, then the rules that are still
+    /// ill-nested are removed; e.g. `depth = 0` removes all ill-nested rules.
+    /// New rules that coincide up to their weight are merged.
+    pub fn well_nested_approximation(&self, depth: usize) -> (Self, WellNestedRestorer<N, T, W>) {
+        let (rules, restorer) = well_nested_rules(&self.rules, &[self.init.clone()], depth);
+        (
+            Lcfrs {
+                rules,
+                init: self.init.clone(),
+            },
+            restorer,
+        )
+    }
+}
+
+/// Approximates a list of rules of an LCFRS with the given initial
+/// nonterminals by well-nested rules, see `Lcfrs::well_nested_approximation`.
+pub(crate) fn well_nested_rules<N, T, W>(
+    rules: &[PMCFGRule<N, T, W>],
+    initials: &[N],
+    depth: usize,
+) -> (Vec<PMCFGRule<N, T, W>>, WellNestedRestorer<N, T, W>)
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone + Zero + Mul<Output = W> + PartialOrd,
+{
+    let mut by_head: HashMap<&N, Vec<usize>> = HashMap::new();
+    for (rule_id, rule) in rules.iter().enumerate() {
+        by_head
+            .entry(&rule.head)
+            .or_insert_with(Vec::new)
+            .push(rule_id);
+    }
+    let rules_of = |nt: &N| by_head.get(nt).map_or(&[][..], Vec::as_slice);
+
+    let mut new_rules = Vec::new();
+    let mut agenda: VecDeque<_> = rules
+        .iter()
+        .enumerate()
+        .map(|(rule_id, rule)| (rule.clone(), Fragment::new(rule_id, rule.tail.len()), 0))
+        .collect();
+
+    while let Some((rule, fragment, substitutions)) = agenda.pop_front() {
+        match rule.composition.interleaving_successors() {
+            None => new_rules.push((rule, fragment)),
+            Some((i, k)) if substitutions < depth => {
+                let s = if rules_of(&rule.tail[k]).len() < rules_of(&rule.tail[i]).len() {
+                    k
+                } else {
+                    i
+                };
+                for &child_id in rules_of(&rule.tail[s]) {
+                    let child = &rules[child_id];
+                    agenda.push_back((
+                        substitute(&rule, s, child),
+                        fragment.substitute(s, child_id, child.tail.len()),
+                        substitutions + 1,
+                    ));
+                }
+            }
+            Some(_) => (),
+        }
+    }
+
+    let (new_rules, origins) = merge_rules(new_rules);
+    let (new_rules, _) = reduce_pmcfg_rules(new_rules, initials);
+
+    (
+        new_rules,
+        WellNestedRestorer {
+            rules: rules.to_vec(),
+            origins,
+        },
+    )
+}
+
+/// Substitutes `child` for the `i`th successor of `rule`; the successors of
+/// `child` take its place.
+fn substitute<N, T, W>(
+    rule: &PMCFGRule<N, T, W>,
+    i: usize,
+    child: &PMCFGRule<N, T, W>,
+) -> PMCFGRule<N, T, W>
+where
+    N: Clone,
+    T: Clone,
+    W: Clone + Mul<Output = W>,
+{
+    let rank = child.tail.len();
+    let mut tail = rule.tail[..i].to_vec();
+    tail.extend_from_slice(&child.tail);
+    tail.extend_from_slice(&rule.tail[i + 1..]);
+
+    let composition = rule
+        .composition
+        .composition
+        .iter()
+        .map(|component| {
+            let mut new_component = Vec::new();
+            for symbol in component {
+                match *symbol {
+                    VarT::Var(k, l) if k == i => {
+                        new_component.extend(child.composition[l].iter().map(|child_symbol| {
+                            match *child_symbol {
+                                VarT::Var(c, m) => VarT::Var(i + c, m),
+                                VarT::T(ref t) => VarT::T(t.clone()),
+                            }
+                        }))
+                    }
+                    VarT::Var(k, l) if k > i => new_component.push(VarT::Var(k + rank - 1, l)),
+                    _ => new_component.push(symbol.clone()),
+                }
+            }
+            new_component
+        })
+        .collect();
+
+    PMCFGRule {
+        head: rule.head.clone(),
+        tail,
+        composition: Composition { composition },
+        weight: rule.weight.clone() * child.weight.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn approximation() {
+        let rules = vec![
+            rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)"),
+            rule("A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5"),
+            rule("A → [[T a], [T c]] () # 0.5"),
+            rule("B → [[T b, Var 0 0], [T d, Var 0 1]] (B) # 0.5"),
+            rule("B → [[T b], [T d]] () # 0.5"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        assert!(!lcfrs.is_well_nested());
+        assert_eq!(lcfrs.ill_nested_rules(), vec![0]);
+
+        let (approximation, _) = lcfrs.well_nested_approximation(0);
+        assert!(approximation.destruct().0.is_empty());
+
+        let (approximation, restorer) = lcfrs.well_nested_approximation(2);
+        assert!(approximation.is_well_nested());
+        let (new_rules, _) = approximation.destruct();
+        let control = vec![
+            rules[3].clone(),
+            rules[4].clone(),
+            rule("S → [[T a, Var 0 0, T c, Var 0 1]] (B) # 0.5"),
+            rule("S → [[T a, T a, Var 0 0, T c, T c, Var 0 1]] (B) # 0.25"),
+        ];
+        assert_eq!(new_rules, control);
+        assert_eq!(
+            new_rules.iter().map(|r| r.weight).collect::<Vec<_>>(),
+            vec![0.5, 0.5, 0.5, 0.25]
+        );
+
+        let derivation: GornTree<_> =
+            vec![(vec![], control[3].clone()), (vec![0], control[1].clone())]
+                .into_iter()
+                .collect();
+        let restored: GornTree<_> = vec![
+            (vec![], &rules[0]),
+            (vec![0], &rules[1]),
+            (vec![0, 0], &rules[2]),
+            (vec![1], &rules[4]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+
+        let unknown: GornTree<_> = vec![(vec![], rules[0].clone())].into_iter().collect();
+        assert_eq!(restorer.restore(&unknown), None);
+    }
+
+    #[test]
+    fn repeated_heads() {
+        let rules = vec![
+            rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)"),
+            rule("A → [[T a], [T c]] () # 0.5"),
+            rule("A → [[T e], [T g]] () # 0.5"),
+            rule("B → [[T b], [T d]] () # 0.5"),
+            rule("B → [[T f], [T h]] () # 0.5"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        let (approximation, _) = lcfrs.well_nested_approximation(2);
+        let (new_rules, _) = approximation.destruct();
+
+        let control = vec![
+            rules[3].clone(),
+            rules[4].clone(),
+            rule("S → [[T a, Var 0 0, T c, Var 0 1]] (B) # 0.5"),
+            rule("S → [[T e, Var 0 0, T g, Var 0 1]] (B) # 0.5"),
+        ];
+        assert_eq!(new_rules.len(), control.len());
+        assert!(control.iter().all(|rule| new_rules.contains(rule)));
+    }
+
+    #[test]
+    fn coinciding_rules() {
+        let rules = vec![
+            rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)"),
+            rule("A → [[T a], [T c]] () # 0.25"),
+            rule("A → [[T a], [T c]] () # 0.5"),
+            rule("B → [[T b], [T d]] () # 0.5"),
+            rule("B → [[T f], [T h]] () # 0.25"),
+            rule("B → [[T b, Var 0 0], [T d, Var 0 1]] (B) # 0.25"),
+        ];
+        let lcfrs = Lcfrs::new(rules.clone(), "S".to_owned()).unwrap();
+        let (approximation, restorer) = lcfrs.well_nested_approximation(1);
+        let (new_rules, _) = approximation.destruct();
+
+        let merged = rule("S → [[T a, Var 0 0, T c, Var 0 1]] (B)");
+        let found: Vec<_> = new_rules.iter().filter(|r| **r == merged).collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].weight, 0.75);
+
+        let derivation: GornTree<_> = vec![(vec![], merged), (vec![0], rules[3].clone())]
+            .into_iter()
+            .collect();
+        let restored: GornTree<_> = vec![
+            (vec![], &rules[0]),
+            (vec![0], &rules[2]),
+            (vec![1], &rules[3]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(restorer.restore(&derivation), Some(restored));
+    }
+}
@@ -1,4 +1,9 @@
 pub mod cfg;
+pub mod inside;
+pub mod intersection;
 pub mod lcfrs;
 pub mod mcfg;
+pub mod normalization;
 pub mod pmcfg;
+pub mod reduce;
+pub mod stats;
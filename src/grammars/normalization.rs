@@ -0,0 +1,298 @@
+//! Normalisation of rule weights and a consistency check for probabilistic grammars.
+//!
+//! A grammar is *proper* if the weights of the rules for each nonterminal sum to one.
+//! It is *consistent* if, moreover, the weights of all derivations of each nonterminal sum to
+//! one, i.e. no probability mass is lost to infinite derivations.
+
+use log_domain::LogDomain;
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+use std::ops::Div;
+
+use crate::grammars::cfg::{CFGRule, CFG};
+use crate::grammars::pmcfg::{PMCFGRule, PMCFG};
+use crate::grammars::reduce::{cfg_symbols, integerise, pmcfg_symbols};
+
+/// Maximal number of iterations for the spectral radius and the partition function.
+const MAX_ITERATIONS: usize = 10_000;
+/// The iterations stop as soon as no value changes by more than this.
+const TOLERANCE: f64 = 1e-12;
+/// Maximal deviation of the partition function from one for consistent grammars.
+const CONSISTENCY_TOLERANCE: f64 = 1e-6;
+
+/// The result of a consistency check.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Consistency<N> {
+    /// Spectral radius of the expected-children matrix, i.e. the matrix that contains the
+    /// expected number of occurrences of each nonterminal in the right-hand side of a rule for
+    /// each nonterminal.
+    /// A proper grammar is consistent if it is smaller than one, and inconsistent if it is
+    /// greater than one.
+    pub spectral_radius: f64,
+    /// The sum of the weights of all derivations of each nonterminal, in the order of their
+    /// first occurrence in the grammar.
+    pub partition: Vec<(N, f64)>,
+}
+
+impl<N> Consistency<N> {
+    /// Is `true` iff the partition function is one for each nonterminal.
+    /// For critical grammars, i.e. with a spectral radius of one, the fixed-point iteration
+    /// converges very slowly, so they may be reported as inconsistent.
+    pub fn is_consistent(&self) -> bool {
+        self.partition
+            .iter()
+            .all(|&(_, z)| (z - 1.0).abs() <= CONSISTENCY_TOLERANCE)
+    }
+}
+
+impl<N: Display> Display for Consistency<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "spectral radius: {}", self.spectral_radius)?;
+        write!(f, "partition function:")?;
+        for &(ref nt, z) in &self.partition {
+            write!(f, "\n  {}: {}", nt, z)?;
+        }
+        Ok(())
+    }
+}
+
+impl<N, T, W> PMCFG<N, T, W>
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Div<Output = W>,
+{
+    /// Rescales the weights of the rules for each nonterminal such that they sum to one.
+    /// Rules for nonterminals whose weights sum to zero are left unchanged.
+    pub fn normalize(mut self) -> Self {
+        normalize_pmcfg_rules(&mut self.rules);
+        self
+    }
+}
+
+impl<N, T> PMCFG<N, T, LogDomain<f64>>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Computes the spectral radius of the expected-children matrix and the partition function
+    /// of each nonterminal.
+    pub fn consistency(&self) -> Consistency<N> {
+        pmcfg_consistency(&self.rules)
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Div<Output = W>,
+{
+    /// Rescales the weights of the rules for each nonterminal such that they sum to one.
+    /// Rules for nonterminals whose weights sum to zero are left unchanged.
+    pub fn normalize(mut self) -> Self {
+        normalize_rules(&mut self.rules, cfg_symbols, cfg_weight);
+        self
+    }
+}
+
+impl<N, T> CFG<N, T, LogDomain<f64>>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Computes the spectral radius of the expected-children matrix and the partition function
+    /// of each nonterminal.
+    pub fn consistency(&self) -> Consistency<N> {
+        consistency(&self.rules, cfg_symbols, |rule| rule.weight.value())
+    }
+}
+
+/// Normalises a list of PMCFG rules, see `PMCFG::normalize`.
+pub(crate) fn normalize_pmcfg_rules<N, T, W>(rules: &mut [PMCFGRule<N, T, W>])
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Div<Output = W>,
+{
+    normalize_rules(rules, pmcfg_symbols, pmcfg_weight);
+}
+
+/// Checks the consistency of a list of PMCFG rules, see `PMCFG::consistency`.
+pub(crate) fn pmcfg_consistency<N, T>(rules: &[PMCFGRule<N, T, LogDomain<f64>>]) -> Consistency<N>
+where
+    N: Clone + Eq + Hash,
+{
+    consistency(rules, pmcfg_symbols, |rule| rule.weight.value())
+}
+
+fn pmcfg_weight<N, T, W>(rule: &mut PMCFGRule<N, T, W>) -> &mut W {
+    &mut rule.weight
+}
+
+fn cfg_weight<N, T, W>(rule: &mut CFGRule<N, T, W>) -> &mut W {
+    &mut rule.weight
+}
+
+/// Divides the weight of each rule by the sum of the weights of the rules with the same
+/// left-hand side; `symbols` yields the nonterminals of a rule as in `reduce`.
+fn normalize_rules<N, R, W, F, G>(rules: &mut [R], symbols: F, weight: G)
+where
+    N: Eq + Hash,
+    W: Clone + Zero + Div<Output = W>,
+    F: Fn(&R) -> (&N, Vec<&N>),
+    G: Fn(&mut R) -> &mut W,
+{
+    let (heads, nonterminals) = {
+        let mut map = HashMap::new();
+        let mut names = Vec::new();
+        let heads: Vec<usize> = rules
+            .iter()
+            .map(|rule| integerise(symbols(rule).0, &mut map, &mut names))
+            .collect();
+        (heads, names.len())
+    };
+
+    let mut sums = vec![W::zero(); nonterminals];
+    for (rule, &head) in rules.iter_mut().zip(&heads) {
+        sums[head] = sums[head].clone() + weight(rule).clone();
+    }
+    for (rule, &head) in rules.iter_mut().zip(&heads) {
+        if !sums[head].is_zero() {
+            let w = weight(rule);
+            *w = w.clone() / sums[head].clone();
+        }
+    }
+}
+
+/// Computes the spectral radius of the expected-children matrix by power iteration and the
+/// partition function by fixed-point iteration starting from zero, which converges to the
+/// least solution of `Z(A) = Σ_{A → B₁…Bₖ} weight · Z(B₁) · … · Z(Bₖ)`.
+fn consistency<N, R, F, G>(rules: &[R], symbols: F, weight: G) -> Consistency<N>
+where
+    N: Clone + Eq + Hash,
+    F: Fn(&R) -> (&N, Vec<&N>),
+    G: Fn(&R) -> f64,
+{
+    let mut map = HashMap::new();
+    let mut names = Vec::new();
+    let mut indexed: Vec<(usize, Vec<usize>, f64)> = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let (head, tail) = symbols(rule);
+        let head = integerise(head, &mut map, &mut names);
+        let tail = tail
+            .into_iter()
+            .map(|nt| integerise(nt, &mut map, &mut names))
+            .collect();
+        indexed.push((head, tail, weight(rule)));
+    }
+
+    let spectral_radius = spectral_radius(&indexed, names.len());
+    let partition = partition(&indexed, names.len());
+    Consistency {
+        spectral_radius,
+        partition: names.into_iter().cloned().zip(partition).collect(),
+    }
+}
+
+/// Power iteration with the matrix `I + M`, whose dominant eigenvalue is the spectral radius
+/// of the non-negative matrix `M` plus one; the shift avoids oscillation for periodic `M`.
+fn spectral_radius(rules: &[(usize, Vec<usize>, f64)], nonterminals: usize) -> f64 {
+    let mut vector = vec![1.0; nonterminals];
+    let mut radius = 0.0;
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut next = vector.clone();
+        for &(head, ref tail, weight) in rules {
+            for &nt in tail {
+                next[head] += weight * vector[nt];
+            }
+        }
+        let norm = next.iter().cloned().fold(0.0, f64::max);
+        if norm == 0.0 || !norm.is_finite() {
+            return norm;
+        }
+        for value in &mut next {
+            *value /= norm;
+        }
+
+        let estimate = norm - 1.0;
+        let converged = (estimate - radius).abs() <= TOLERANCE;
+        radius = estimate;
+        vector = next;
+        if converged {
+            break;
+        }
+    }
+
+    radius.max(0.0)
+}
+
+fn partition(rules: &[(usize, Vec<usize>, f64)], nonterminals: usize) -> Vec<f64> {
+    let mut values = vec![0.0; nonterminals];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut next = vec![0.0; nonterminals];
+        for &(head, ref tail, weight) in rules {
+            next[head] += tail
+                .iter()
+                .fold(weight, |product, &nt| product * values[nt]);
+        }
+        let change = next
+            .iter()
+            .zip(&values)
+            .map(|(new, old)| (new - old).abs())
+            .fold(0.0, f64::max);
+        values = next;
+        if change <= TOLERANCE || !change.is_finite() {
+            break;
+        }
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let grammar: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+                                                              S → [[Var 0 0]] (A) # 0.5\n\
+                                                              A → [[T a, Var 0 0]] (A) # 0.2\n\
+                                                              A → [[]] () # 0.6"
+            .parse()
+            .unwrap();
+        let weights: Vec<f64> = grammar
+            .normalize()
+            .rules
+            .into_iter()
+            .map(|rule| rule.weight.value())
+            .collect();
+
+        for (weight, control) in weights.into_iter().zip(vec![1.0, 0.25, 0.75]) {
+            assert!((weight - control).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn consistency() {
+        let subcritical: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                            S → [Nt S, Nt S] # 0.25\n\
+                                                            S → [T a]        # 0.75"
+            .parse()
+            .unwrap();
+        let result = subcritical.consistency();
+        assert!((result.spectral_radius - 0.5).abs() < 1e-6);
+        assert!(result.is_consistent());
+
+        let supercritical: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                              S → [Nt S, Nt S] # 0.75\n\
+                                                              S → [T a]        # 0.25"
+            .parse()
+            .unwrap();
+        let result = supercritical.consistency();
+        assert!((result.spectral_radius - 1.5).abs() < 1e-6);
+        assert_eq!(result.partition.len(), 1);
+        assert!((result.partition[0].1 - 1.0 / 3.0).abs() < 1e-6);
+        assert!(!result.is_consistent());
+    }
+}
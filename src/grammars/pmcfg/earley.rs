@@ -0,0 +1,432 @@
+//! An incremental Earley parser for PMCFGs, cf. Kallmeyer and Maier (2009): An incremental
+//! Earley parser for simple range concatenation grammar.
+//!
+//! An item is a rule with a dot in one of its components, the position in the word, and a
+//! range vector: the ranges of the components of the head that were finished and of the
+//! components of the successors that were read so far.
+//! The components are read from left to right; whenever a component of the head is finished,
+//! the item is suspended and the next component is resumed at the position where it is needed
+//! in the parent item.
+//! Hence, the components of each successor have to occur in the order of their indices (their
+//! first occurrences, that is, since copies are allowed) and must not be deleted, see
+//! `Lcfrs::make_monotone`.
+//!
+//! Like in `cfg::earley`, items are explored best-first with the weighted agenda of the
+//! `search` crate, and each item is combined with the items in the chart at most `k` times.
+
+use search::agenda::weighted::Weighted;
+use search::{Agenda, Search};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+use std::rc::Rc;
+
+use super::{PMCFGRule, VarT, PMCFG};
+use crate::grammars::lcfrs::read_fanouts;
+use crate::util::agenda::PruningAgenda;
+use crate::util::tree::{GornTree, SharedTree};
+
+/// A subword given by its start and end.
+type Range = (usize, usize);
+
+/// A nonterminal, the ranges of its first `c` components and the start of its `c`th component.
+type Key<'a, N> = (&'a N, Vec<Range>, usize);
+
+/// The rule, component, dot, position, start and range vector of an item.
+type Signature = (
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    Vec<Range>,
+    Vec<Vec<Range>>,
+);
+
+#[derive(Clone, Debug)]
+struct Item<W> {
+    rule: usize,
+    component: usize,
+    dot: usize,
+    position: usize,
+    /// the start of the current component of the head
+    start: usize,
+    /// the ranges of the finished components of the head
+    ranges: Vec<Range>,
+    /// for each successor, the ranges of its components that were read so far
+    successors: Vec<Vec<Range>>,
+    weight: W,
+    /// the derivations of the successors that were read completely
+    children: Vec<Option<Rc<SharedTree>>>,
+}
+
+impl<W: Clone> Weighted for Item<W> {
+    type Weight = W;
+
+    fn get_weight(&self) -> W {
+        self.weight.clone()
+    }
+}
+
+impl<W: Copy + Mul<Output = W>> Item<W> {
+    fn signature(&self) -> Signature {
+        (
+            self.rule,
+            self.component,
+            self.dot,
+            self.position,
+            self.start,
+            self.ranges.clone(),
+            self.successors.clone(),
+        )
+    }
+
+    /// Moves the dot over a terminal or a copy of a component that was read before.
+    fn advance(&self, position: usize) -> Self {
+        let mut item = self.clone();
+        item.dot += 1;
+        item.position = position;
+        item
+    }
+
+    /// Moves the dot over the next component of the `m`th successor, which spans `range`.
+    /// If it is the last component, `derivation` is the derivation of the successor and its
+    /// weight.
+    fn read(&self, m: usize, range: Range, derivation: Option<(W, &Rc<SharedTree>)>) -> Self {
+        let mut item = self.advance(range.1);
+        item.successors[m].push(range);
+        if let Some((weight, derivation)) = derivation {
+            item.weight = item.weight * weight;
+            item.children[m] = Some(Rc::clone(derivation));
+        }
+        item
+    }
+
+    /// Continues with the next component of the head at `position`.
+    fn resume(&self, position: usize) -> Self {
+        let mut item = self.clone();
+        item.ranges.push((self.start, self.position));
+        item.component += 1;
+        item.dot = 0;
+        item.start = position;
+        item.position = position;
+        item
+    }
+}
+
+/// Parses words with a PMCFG by an incremental Earley algorithm.
+pub struct EarleyParser<N, T, W> {
+    grammar: PMCFG<N, T, W>,
+    rules_by_head: HashMap<N, Vec<usize>>,
+    beam: Option<usize>,
+    threshold: Option<W>,
+}
+
+impl<N, T, W> EarleyParser<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + PartialEq,
+    W: Copy + Ord + Mul<Output = W>,
+{
+    /// Returns `None` if the fanouts of the nonterminals are not consistent, or if a rule
+    /// deletes a component or reads the components of a successor out of order.
+    pub fn new(grammar: &PMCFG<N, T, W>) -> Option<Self> {
+        let fanouts = read_fanouts(&grammar.rules)?;
+        if !grammar
+            .rules
+            .iter()
+            .all(|rule| reads_in_order(rule, &fanouts))
+        {
+            return None;
+        }
+
+        let mut rules_by_head = HashMap::new();
+        for (rule_id, rule) in grammar.rules.iter().enumerate() {
+            rules_by_head
+                .entry(rule.head.clone())
+                .or_insert_with(Vec::new)
+                .push(rule_id);
+        }
+        Some(EarleyParser {
+            grammar: grammar.clone(),
+            rules_by_head,
+            beam: None,
+            threshold: None,
+        })
+    }
+
+    /// Limits the number of items in the agenda.
+    pub fn set_beam(&mut self, beam: usize) {
+        self.beam = Some(beam);
+    }
+
+    /// Discards items whose weight is less than the greatest weight in the agenda times
+    /// `threshold`.
+    pub fn set_threshold(&mut self, threshold: W) {
+        self.threshold = Some(threshold);
+    }
+
+    /// The best parse tree of `word`, if there is any.
+    pub fn parse(&self, word: &[T]) -> Option<GornTree<&PMCFGRule<N, T, W>>> {
+        self.kbest(word, 1).pop()
+    }
+
+    /// The `k` best parse trees of `word` in descending order of their weights.
+    pub fn kbest(&self, word: &[T], k: usize) -> Vec<GornTree<&PMCFGRule<N, T, W>>> {
+        let rules = &self.grammar.rules;
+        let initial: HashSet<&N> = self.grammar.initial.iter().collect();
+
+        let mut predicted: HashSet<(&N, usize)> = HashSet::new();
+        let mut expansions: HashMap<Signature, usize> = HashMap::new();
+        // The following maps are indexed by `Key`s; they contain
+        // items waiting for the `c`th component, together with the index of the successor,
+        let mut waiting: HashMap<Key<N>, Vec<(usize, Item<W>)>> = HashMap::new();
+        // the ends of the `c`th component if it is not the last one, and
+        let mut suspended_ends: HashMap<Key<N>, HashSet<usize>> = HashMap::new();
+        // the ends, weights and derivations of the last component.
+        let mut completed: HashMap<Key<N>, Vec<(usize, W, Rc<SharedTree>)>> = HashMap::new();
+        // suspended items and the positions at which they are resumed, indexed by a nonterminal
+        // and the ranges of its finished components
+        let mut suspended: HashMap<(&N, Vec<Range>), Vec<Item<W>>> = HashMap::new();
+        let mut resumptions: HashMap<(&N, Vec<Range>), HashSet<usize>> = HashMap::new();
+
+        let mut agenda = PruningAgenda::new(self.beam, self.threshold);
+        for nt in &self.grammar.initial {
+            if predicted.insert((nt, 0)) {
+                agenda.extend(self.predict(nt, 0));
+            }
+        }
+
+        let deduction = Search::with_agenda(agenda, move |item: &Item<W>| {
+            let mut successors = Vec::new();
+            let count = expansions.entry(item.signature()).or_insert(0);
+            if *count >= k {
+                return successors;
+            }
+            *count += 1;
+
+            let rule = &rules[item.rule];
+            match rule.composition[item.component].get(item.dot) {
+                Some(VarT::T(t)) => {
+                    if word.get(item.position) == Some(t) {
+                        successors.push(item.advance(item.position + 1));
+                    }
+                }
+                Some(&VarT::Var(m, c)) if c < item.successors[m].len() => {
+                    let (start, end) = item.successors[m][c];
+                    let position = item.position + end - start;
+                    if position <= word.len() && word[start..end] == word[item.position..position] {
+                        successors.push(item.advance(position));
+                    }
+                }
+                Some(&VarT::Var(m, _)) => {
+                    let nt = &rule.tail[m];
+                    let read = &item.successors[m];
+                    if read.is_empty() {
+                        if predicted.insert((nt, item.position)) {
+                            successors.extend(self.predict(nt, item.position));
+                        }
+                    } else {
+                        let key = (nt, read.clone());
+                        let positions = resumptions.entry(key.clone()).or_insert_with(HashSet::new);
+                        if positions.insert(item.position) {
+                            if let Some(items) = suspended.get(&key) {
+                                successors.extend(items.iter().map(|s| s.resume(item.position)));
+                            }
+                        }
+                    }
+
+                    let key = (nt, read.clone(), item.position);
+                    if let Some(ends) = suspended_ends.get(&key) {
+                        successors.extend(
+                            ends.iter()
+                                .map(|&end| item.read(m, (item.position, end), None)),
+                        );
+                    }
+                    if let Some(derivations) = completed.get(&key) {
+                        successors.extend(derivations.iter().map(
+                            |&(end, weight, ref derivation)| {
+                                item.read(m, (item.position, end), Some((weight, derivation)))
+                            },
+                        ));
+                    }
+                    waiting
+                        .entry(key)
+                        .or_insert_with(Vec::new)
+                        .push((m, item.clone()));
+                }
+                None => {
+                    let range = (item.start, item.position);
+                    let key = (&rule.head, item.ranges.clone(), item.start);
+                    if item.component + 1 == rule.composition.len() {
+                        let derivation = Rc::new(SharedTree {
+                            rule: item.rule,
+                            children: item.children.iter().flatten().cloned().collect(),
+                        });
+                        if let Some(parents) = waiting.get(&key) {
+                            successors.extend(parents.iter().map(|&(m, ref parent)| {
+                                parent.read(m, range, Some((item.weight, &derivation)))
+                            }));
+                        }
+                        completed.entry(key).or_insert_with(Vec::new).push((
+                            item.position,
+                            item.weight,
+                            derivation,
+                        ));
+                    } else {
+                        let ends = suspended_ends
+                            .entry(key.clone())
+                            .or_insert_with(HashSet::new);
+                        if ends.insert(item.position) {
+                            if let Some(parents) = waiting.get(&key) {
+                                successors.extend(
+                                    parents
+                                        .iter()
+                                        .map(|&(m, ref parent)| parent.read(m, range, None)),
+                                );
+                            }
+                        }
+
+                        let mut ranges = item.ranges.clone();
+                        ranges.push(range);
+                        let key = (&rule.head, ranges);
+                        if let Some(positions) = resumptions.get(&key) {
+                            successors.extend(positions.iter().map(|&p| item.resume(p)));
+                        }
+                        suspended
+                            .entry(key)
+                            .or_insert_with(Vec::new)
+                            .push(item.clone());
+                    }
+                }
+            }
+            successors
+        });
+
+        deduction
+            .filter(|item| {
+                let rule = &rules[item.rule];
+                rule.composition.len() == 1
+                    && item.dot == rule.composition[0].len()
+                    && item.start == 0
+                    && item.position == word.len()
+                    && initial.contains(&rule.head)
+            })
+            .take(k)
+            .map(|item| {
+                SharedTree {
+                    rule: item.rule,
+                    children: item.children.into_iter().flatten().collect(),
+                }
+                .to_gorn_tree(rules)
+            })
+            .collect()
+    }
+
+    /// The items with the dot in front of the first component of the rules of `nt`.
+    fn predict<'a>(&'a self, nt: &N, position: usize) -> impl Iterator<Item = Item<W>> + 'a {
+        let rules = &self.grammar.rules;
+        self.rules_by_head
+            .get(nt)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(move |&rule| Item {
+                rule,
+                component: 0,
+                dot: 0,
+                position,
+                start: position,
+                ranges: Vec::new(),
+                successors: vec![Vec::new(); rules[rule].tail.len()],
+                weight: rules[rule].weight,
+                children: vec![None; rules[rule].tail.len()],
+            })
+    }
+}
+
+/// Is `true` iff the first occurrence of each `Var(m, c + 1)` in the composition of `rule` is
+/// preceded by an occurrence of `Var(m, c)`, and every component of every successor with a
+/// known fanout occurs.
+fn reads_in_order<N, T, W>(rule: &PMCFGRule<N, T, W>, fanouts: &HashMap<&N, usize>) -> bool
+where
+    N: Eq + Hash,
+{
+    let mut read = vec![0; rule.tail.len()];
+    for (m, c) in rule.composition.variables() {
+        if c > read[m] {
+            return false;
+        } else if c == read[m] {
+            read[m] += 1;
+        }
+    }
+    rule.tail
+        .iter()
+        .zip(read)
+        .all(|(nt, components)| fanouts.get(nt).map_or(true, |&fanout| fanout == components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn kbest() {
+        let grammar: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                          S → [[Var 0 0, Var 0 1]] (A)\n\
+                                                          A → [[T a, Var 0 0, T b], [T c, Var 0 1]] (A) # 0.4\n\
+                                                          A → [[], []] () # 0.6\n\
+                                                          A → [[T a], [T b, T c]] () # 0.1"
+            .parse()
+            .unwrap();
+        let parser = EarleyParser::new(&grammar).unwrap();
+
+        let parses = parser.kbest(&['a', 'b', 'c'], 5);
+        let control = vec![
+            vec![
+                (vec![], &grammar.rules[0]),
+                (vec![0], &grammar.rules[1]),
+                (vec![0, 0], &grammar.rules[2]),
+            ]
+            .into_iter()
+            .collect(),
+            vec![(vec![], &grammar.rules[0]), (vec![0], &grammar.rules[3])]
+                .into_iter()
+                .collect(),
+        ];
+        assert_eq!(parses, control);
+
+        let parse = parser.parse(&['a', 'a', 'b', 'b', 'c', 'c']).unwrap();
+        assert_eq!(parse.len(), 4);
+        assert_eq!(parser.parse(&[]).map(|tree| tree.len()), Some(2));
+        assert_eq!(parser.parse(&['a', 'b', 'b', 'c']), None);
+    }
+
+    #[test]
+    fn copying() {
+        let grammar: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                          S → [[Var 0 0, Var 0 0]] (B)\n\
+                                                          B → [[T a]] () # 0.5\n\
+                                                          B → [[T a, Var 0 0]] (B) # 0.5"
+            .parse()
+            .unwrap();
+        let parser = EarleyParser::new(&grammar).unwrap();
+
+        let control: GornTree<_> = vec![
+            (vec![], &grammar.rules[0]),
+            (vec![0], &grammar.rules[2]),
+            (vec![0, 0], &grammar.rules[1]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(parser.parse(&['a', 'a', 'a', 'a']), Some(control));
+        assert_eq!(parser.parse(&['a', 'a', 'a']), None);
+
+        let unordered: PMCFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                            S → [[Var 0 1, Var 0 0]] (A)\n\
+                                                            A → [[T a], [T b]] ()"
+            .parse()
+            .unwrap();
+        assert!(EarleyParser::new(&unordered).is_none());
+    }
+}
@@ -6,6 +6,9 @@ use std::str::{from_utf8, FromStr};
 use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT, PMCFG};
 use crate::util::parsing::*;
 
+/// Describes the syntax of a rule for error messages.
+const PMCFG_RULE: &str = "a rule of the form `A → [[T a, Var 0 0]] (B) # 0.5`";
+
 impl<N, T, W> FromStr for PMCFG<N, T, W>
 where
     N: FromStr,
@@ -15,7 +18,7 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (initial, rules) = initial_rule_grammar_from_str(s)?;
@@ -33,13 +36,10 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_pmcfg_rule(s.as_bytes()) {
-            IResult::Done(_, result) => Ok(result),
-            _ => Err(format!("Could not parse {}", s)),
-        }
+        parse_line(s, parse_pmcfg_rule, PMCFG_RULE)
     }
 }
 
@@ -7,10 +7,14 @@ use std::slice;
 use std::vec;
 
 use crate::grammars::mcfg::Mcfg;
+use crate::util::dot::{Digraph, ToDot};
 use crate::util::tree::GornTree;
 
+pub mod earley;
 mod from_str;
 pub mod negra;
+pub mod sample;
+pub mod srcg;
 
 /// Variable or terminal symbol in a PMCFG.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
@@ -67,6 +71,66 @@ impl<T> Composition<T> {
     pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
         self.into_iter()
     }
+
+    /// The variables of all components from left to right.
+    pub fn variables<'a>(&'a self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.composition
+            .iter()
+            .flat_map(|component| component.iter())
+            .filter_map(|symbol| match *symbol {
+                VarT::Var(i, j) => Some((i, j)),
+                VarT::T(_) => None,
+            })
+    }
+
+    /// Is `true` iff the components of each successor occur from left to right in the order
+    /// of their indices.
+    pub fn is_monotone(&self) -> bool {
+        let mut last: HashMap<usize, usize> = HashMap::new();
+        for (i, j) in self.variables() {
+            if last.get(&i).map_or(false, |&k| j < k) {
+                return false;
+            }
+            last.insert(i, j);
+        }
+        true
+    }
+
+    /// Is `true` iff the components of no two successors interleave, see
+    /// `interleaving_successors`.
+    pub fn is_well_nested(&self) -> bool {
+        self.interleaving_successors().is_none()
+    }
+
+    /// Finds successors `i ≠ k` whose variables occur in the order `i, k, i, k` from left to
+    /// right, with the least `i` and then the least `k`.
+    pub fn interleaving_successors(&self) -> Option<(usize, usize)> {
+        let successors: Vec<usize> = self.variables().map(|(i, _)| i).collect();
+        let mut indices = successors.clone();
+        indices.sort();
+        indices.dedup();
+
+        let interleave = |i: usize, k: usize| {
+            let pattern = [i, k, i, k];
+            let mut matched = 0;
+            for &s in &successors {
+                if s == pattern[matched] {
+                    matched += 1;
+                    if matched == pattern.len() {
+                        return true;
+                    }
+                }
+            }
+            false
+        };
+
+        indices.iter().find_map(|&i| {
+            indices
+                .iter()
+                .find(|&&k| i != k && interleave(i, k))
+                .map(|&k| (i, k))
+        })
+    }
 }
 
 impl<T> Index<usize> for Composition<T> {
@@ -156,6 +220,14 @@ where
     }
 }
 
+impl<N, T, W> PMCFGRule<N, T, W> {
+    /// Is `true` iff the composition of the rule is well-nested, see
+    /// `Composition::is_well_nested`.
+    pub fn is_well_nested(&self) -> bool {
+        self.composition.is_well_nested()
+    }
+}
+
 /// A weighted, parallel multiple context-free grammar (PMCFG) with a set of initial nonterminal
 /// symbols and a set of PMCFG rules.
 ///
@@ -295,6 +367,46 @@ impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> fmt::Display for PMCFG<N
     }
 }
 
+/// Each node of a derivation is drawn with the head, composition and weight
+/// of its rule; the successors of a node are ordered from left to right.
+impl<N: fmt::Display, T: fmt::Display, W: fmt::Display> ToDot for GornTree<PMCFGRule<N, T, W>> {
+    fn to_dot(&self) -> String {
+        derivation_to_dot(self.into_iter())
+    }
+}
+
+impl<'a, N, T, W> ToDot for GornTree<&'a PMCFGRule<N, T, W>>
+where
+    N: fmt::Display,
+    T: fmt::Display,
+    W: fmt::Display,
+{
+    fn to_dot(&self) -> String {
+        derivation_to_dot(self.into_iter().map(|(address, &rule)| (address, rule)))
+    }
+}
+
+fn derivation_to_dot<'a, N, T, W, I>(derivation: I) -> String
+where
+    I: Iterator<Item = (&'a Vec<usize>, &'a PMCFGRule<N, T, W>)>,
+    N: fmt::Display + 'a,
+    T: fmt::Display + 'a,
+    W: fmt::Display + 'a,
+{
+    let mut graph = Digraph::new();
+    graph.attribute("ordering=out");
+    graph.attribute("node [shape=box]");
+    for (address, rule) in derivation {
+        let id = format!("{:?}", address);
+        let label = format!("{}\n{}\n# {}", rule.head, rule.composition, rule.weight);
+        graph.node(&id, &[("label", label.as_str())]);
+        if let Some((_, parent)) = address.split_last() {
+            graph.edge(&format!("{:?}", parent), &id, &[]);
+        }
+    }
+    graph.to_string()
+}
+
 pub fn evaluate<T>(term_map: &GornTree<Composition<T>>) -> Composition<T>
 where
     T: Clone + fmt::Display,
@@ -615,6 +727,15 @@ mod tests {
         tree_map
     }
 
+    #[test]
+    fn test_derivation_to_dot() {
+        let dot = example_tree_map().to_dot();
+
+        assert!(dot.contains("\"[0, 1]\" [label=\"a\\n[[T \\\"a\\\"]]\\n# 1\"];"));
+        assert!(dot.contains("\"[0]\" -> \"[0, 1]\";"));
+        assert_eq!(dot.matches(" -> ").count(), 11);
+    }
+
     #[test]
     fn test_evaluate() {
         let tree_map = example_tree_map();
@@ -657,6 +778,30 @@ mod tests {
         evaluate(&term_map);
     }
 
+    #[test]
+    fn test_composition_properties() {
+        let crossing: Composition<char> =
+            Composition::from(vec![vec![Var(0, 0), Var(1, 0), Var(0, 1), Var(1, 1)]]);
+        assert!(crossing.is_monotone());
+        assert!(!crossing.is_well_nested());
+        assert_eq!(crossing.interleaving_successors(), Some((0, 1)));
+
+        let nested: Composition<char> = Composition::from(vec![
+            vec![Var(0, 0), T('a'), Var(1, 0)],
+            vec![Var(1, 1), Var(0, 1)],
+        ]);
+        assert!(nested.is_monotone());
+        assert!(nested.is_well_nested());
+        assert_eq!(
+            nested.variables().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (1, 1), (0, 1)]
+        );
+
+        let swapped: Composition<char> = Composition::from(vec![vec![Var(0, 1)], vec![Var(0, 0)]]);
+        assert!(!swapped.is_monotone());
+        assert!(swapped.is_well_nested());
+    }
+
     #[test]
     fn test_to_term() {
         let mut tree_map: GornTree<PMCFGRule<String, char, usize>> = GornTree::new();
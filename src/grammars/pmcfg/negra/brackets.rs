@@ -0,0 +1,302 @@
+//! Writers for trees in bracket notation: the discontinuous bracket notation
+//! (_discbracket_) of disco-dop, in which each terminal is annotated with its
+//! position in the sentence, and the bracket notation of the Penn treebank (_PTB_),
+//! which can only represent continuous trees.
+
+use super::{assert_negra_criteria, to_negra_nodes, DumpMode};
+use crate::grammars::lcfrs::binarization::markovization::base_label;
+use crate::grammars::pmcfg::PMCFGRule;
+use crate::util::tree::GornTree;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Takes a tree stack _(encoded in a Gorn tree)_ of PMCFG rules and transforms it into
+/// the discbracket notation, e.g.
+/// `(ROOT (S (VP (PROAV 0=Darüber) (VVPP 2=nachgedacht)) (VMFIN 1=muss)))`.
+/// Nodes are treated as in `to_negra`.
+pub fn to_discbracket<H, T, W>(tree_map: &GornTree<PMCFGRule<H, T, W>>, mode: DumpMode<T>) -> String
+where
+    H: Clone + ToString,
+    T: Clone + ToString,
+{
+    Brackets::from_tree(tree_map, mode).write(true)
+}
+
+/// Takes a tree stack _(encoded in a Gorn tree)_ of PMCFG rules and transforms it into
+/// the bracket notation of the Penn treebank, e.g. `(ROOT (S (PPER Er) (VVFIN schläft)))`.
+/// Fails with the label of a discontinuous constituent if the tree is not continuous.
+pub fn to_ptb<H, T, W>(
+    tree_map: &GornTree<PMCFGRule<H, T, W>>,
+    mode: DumpMode<T>,
+) -> Result<String, String>
+where
+    H: Clone + ToString,
+    T: Clone + ToString,
+{
+    let brackets = Brackets::from_tree(tree_map, mode);
+    match brackets.discontinuous_label() {
+        Some(label) => Err(format!("The constituent {} is discontinuous.", label)),
+        None => Ok(brackets.write(false)),
+    }
+}
+
+/// The flat tree `(NOPARSE …)` over the sentence in the discbracket notation, see `noparse`.
+pub fn noparse_discbracket<T>(sentence: &[T], mode: DumpMode<T>) -> String
+where
+    T: ToString,
+{
+    Brackets::noparse(sentence, mode).write(true)
+}
+
+/// The flat tree `(NOPARSE …)` over the sentence in the bracket notation of the Penn
+/// treebank, see `noparse`.
+pub fn noparse_ptb<T>(sentence: &[T], mode: DumpMode<T>) -> String
+where
+    T: ToString,
+{
+    Brackets::noparse(sentence, mode).write(false)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Child {
+    Terminal(usize),
+    Node(usize),
+}
+
+/// A tree whose leaves are the terminals of a sentence; the root is the node `0`.
+struct Brackets {
+    /// word and tag of each terminal
+    terminals: Vec<(String, String)>,
+    labels: BTreeMap<usize, String>,
+    children: BTreeMap<usize, Vec<Child>>,
+    /// positions of the terminals below each node
+    yields: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl Brackets {
+    fn from_tree<H, T, W>(tree_map: &GornTree<PMCFGRule<H, T, W>>, mode: DumpMode<T>) -> Self
+    where
+        H: Clone + ToString,
+        T: Clone + ToString,
+    {
+        assert_negra_criteria(tree_map);
+
+        let (terminals, nodes) = to_negra_nodes(tree_map, mode);
+        let root: Vec<usize> = Vec::new();
+        let root_label = base_label(&tree_map.get(&root).unwrap().head.to_string()).to_owned();
+
+        let mut labels = BTreeMap::new();
+        labels.insert(0, root_label);
+        let mut parents = Vec::new();
+        for (number, label, parent) in nodes {
+            labels.insert(number, label);
+            parents.push((Child::Node(number), parent));
+        }
+        let terminals = terminals
+            .into_iter()
+            .enumerate()
+            .map(|(position, (word, tag, parent))| {
+                parents.push((Child::Terminal(position), parent));
+                (word, tag)
+            })
+            .collect();
+
+        Self::new(terminals, labels, parents)
+    }
+
+    fn noparse<T>(sentence: &[T], mode: DumpMode<T>) -> Self
+    where
+        T: ToString,
+    {
+        let terminals: Vec<(String, String)> = if let DumpMode::FromPos(poss) = mode {
+            sentence
+                .iter()
+                .zip(&poss)
+                .map(|(pos, word)| (word.to_string(), pos.to_string()))
+                .collect()
+        } else {
+            sentence
+                .iter()
+                .map(|word| (word.to_string(), "--".to_string()))
+                .collect()
+        };
+
+        let mut labels = BTreeMap::new();
+        labels.insert(0, "NOPARSE".to_string());
+        let parents = (0..terminals.len())
+            .map(|position| (Child::Terminal(position), 0))
+            .collect();
+
+        Self::new(terminals, labels, parents)
+    }
+
+    fn new(
+        terminals: Vec<(String, String)>,
+        labels: BTreeMap<usize, String>,
+        parents: Vec<(Child, usize)>,
+    ) -> Self {
+        let mut children: BTreeMap<usize, Vec<Child>> = BTreeMap::new();
+        let mut node_parents = BTreeMap::new();
+        for &(child, parent) in &parents {
+            children.entry(parent).or_insert_with(Vec::new).push(child);
+            if let Child::Node(number) = child {
+                node_parents.insert(number, parent);
+            }
+        }
+
+        let mut yields: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for &(child, parent) in &parents {
+            if let Child::Terminal(position) = child {
+                let mut node = parent;
+                yields
+                    .entry(node)
+                    .or_insert_with(BTreeSet::new)
+                    .insert(position);
+                while let Some(&parent) = node_parents.get(&node) {
+                    node = parent;
+                    yields
+                        .entry(node)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(position);
+                }
+            }
+        }
+
+        Brackets {
+            terminals,
+            labels,
+            children,
+            yields,
+        }
+    }
+
+    /// The label of some node whose terminals are not consecutive.
+    fn discontinuous_label(&self) -> Option<&str> {
+        self.yields
+            .iter()
+            .find(
+                |&(_, positions)| match (positions.iter().next(), positions.iter().last()) {
+                    (Some(first), Some(last)) => last - first + 1 != positions.len(),
+                    _ => false,
+                },
+            )
+            .map(|(node, _)| self.labels[node].as_str())
+    }
+
+    fn first_position(&self, child: Child) -> usize {
+        match child {
+            Child::Terminal(position) => position,
+            Child::Node(number) => self
+                .yields
+                .get(&number)
+                .and_then(|positions| positions.iter().next().cloned())
+                .unwrap_or(usize::max_value()),
+        }
+    }
+
+    /// Writes the tree; children are ordered by their leftmost terminal, and terminals are
+    /// annotated with their position if `indexed` is set.
+    fn write(&self, indexed: bool) -> String {
+        let mut output = String::new();
+        self.write_node(0, indexed, &mut output);
+        output
+    }
+
+    fn write_node(&self, node: usize, indexed: bool, output: &mut String) {
+        output.push('(');
+        output.push_str(&escape(&self.labels[&node]));
+
+        let mut children = self.children.get(&node).cloned().unwrap_or_default();
+        children.sort_by_key(|&child| self.first_position(child));
+        for child in children {
+            output.push(' ');
+            match child {
+                Child::Terminal(position) => {
+                    let (ref word, ref tag) = self.terminals[position];
+                    if indexed {
+                        output.push_str(&format!(
+                            "({} {}={})",
+                            escape(tag),
+                            position,
+                            escape(word)
+                        ));
+                    } else {
+                        output.push_str(&format!("({} {})", escape(tag), escape(word)));
+                    }
+                }
+                Child::Node(number) => self.write_node(number, indexed, output),
+            }
+        }
+
+        output.push(')');
+    }
+}
+
+/// Replaces brackets in labels and words as in the Penn treebank.
+fn escape(s: &str) -> String {
+    s.replace('(', "-LRB-").replace(')', "-RRB-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::tests::example_tree_map;
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_discbracket() {
+        let tree_map = example_tree_map();
+
+        assert_eq!(
+            to_discbracket(&tree_map, DumpMode::Default),
+            "(S (A (a 0=a) (A (a 1=a) (c 4=c)) (c 3=c)) (B (b 2=b) (d 5=d)))"
+        );
+        assert_eq!(
+            to_ptb(&tree_map, DumpMode::Default),
+            Err("The constituent A is discontinuous.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_ptb() {
+        let mut tree_map: GornTree<PMCFGRule<String, String, usize>> = GornTree::new();
+        tree_map.insert(
+            vec![],
+            PMCFGRule::from_str("S -> [[Var 0 0, Var 1 0]] (NP, VVFIN) # 1").unwrap(),
+        );
+        tree_map.insert(
+            vec![0],
+            PMCFGRule::from_str("NP -> [[Var 0 0]] (PPER) # 1").unwrap(),
+        );
+        tree_map.insert(
+            vec![0, 0],
+            PMCFGRule::from_str("PPER -> [[T Er]] () # 1").unwrap(),
+        );
+        tree_map.insert(
+            vec![1],
+            PMCFGRule::from_str("VVFIN -> [[T schläft]] () # 1").unwrap(),
+        );
+
+        assert_eq!(
+            to_ptb(&tree_map, DumpMode::Default),
+            Ok("(S (NP (PPER Er)) (VVFIN schläft))".to_string())
+        );
+        assert_eq!(
+            to_discbracket(
+                &tree_map,
+                DumpMode::FromPos(vec!["Sie".to_string(), "(lacht)".to_string()])
+            ),
+            "(S (NP (PPER 0=Sie)) (VVFIN 1=-LRB-lacht-RRB-))"
+        );
+        assert_eq!(
+            noparse_ptb(
+                &["PPER".to_string(), "VVFIN".to_string()],
+                DumpMode::FromPos(vec!["Er".to_string(), "schläft".to_string()])
+            ),
+            "(NOPARSE (PPER Er) (VVFIN schläft))"
+        );
+        assert_eq!(
+            noparse_discbracket(&["Er", "schläft"], DumpMode::Default),
+            "(NOPARSE (-- 0=Er) (-- 1=schläft))"
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! Evaluation of parse trees against gold trees in _NEGRA_ export format, in the style of
+//! EVALB and its discontinuous variant in disco-dop: a constituent is a labelled bracket
+//! that consists of a label and the (possibly discontinuous) set of positions of its
+//! terminals.
+
+use super::NegraSentence;
+use crate::util::parsing::ParseError;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Parameters of an evaluation, as read from a parameter file of EVALB or disco-dop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    /// Sentences with more words are not evaluated (`CUTOFF_LEN`).
+    pub cutoff_len: Option<usize>,
+    /// Compare the labels of brackets (`LABELED`).
+    pub labeled: bool,
+    /// Only evaluate discontinuous brackets (`DISC_ONLY`).
+    pub disc_only: bool,
+    /// Labels of brackets that are ignored, and tags of words (e.g. punctuation) that are
+    /// removed before the evaluation (`DELETE_LABEL`).
+    pub delete_labels: BTreeSet<String>,
+    /// Tags of words that do not count for the length of a sentence
+    /// (`DELETE_LABEL_FOR_LENGTH`).
+    pub delete_labels_for_length: BTreeSet<String>,
+    /// Maps labels to a representative of their class of equivalent labels (`EQ_LABEL`).
+    pub equivalent_labels: BTreeMap<String, String>,
+    /// Removes words that are attached directly to the root, i.e. punctuation in
+    /// _NEGRA_ treebanks (`DELETE_ROOT_PRETERMINALS`).
+    pub delete_root_preterminals: bool,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            cutoff_len: None,
+            labeled: true,
+            disc_only: false,
+            delete_labels: BTreeSet::new(),
+            delete_labels_for_length: BTreeSet::new(),
+            equivalent_labels: BTreeMap::new(),
+            delete_root_preterminals: false,
+        }
+    }
+}
+
+/// Reads a parameter file with one `KEY value` pair per line.
+/// Empty lines and lines starting with `#` are ignored, as well as the keys `DEBUG`,
+/// `MAX_ERROR`, `EQ_WORD`, `TED`, `DEP` and `LA`, which concern other metrics or the
+/// output of disco-dop.
+impl FromStr for EvalParams {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        let mut params = EvalParams::default();
+
+        for (i, line) in s.lines().enumerate() {
+            let offset = line.chars().take_while(|c| c.is_whitespace()).count();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            read_parameter(&mut params, &fields).map_err(|e| e.at_line(i + 1, offset))?;
+        }
+
+        Ok(params)
+    }
+}
+
+/// Reads the whitespace-separated `fields` of a line of a parameter file.
+fn read_parameter(params: &mut EvalParams, fields: &[&str]) -> Result<(), ParseError> {
+    let (key, values) = match fields.split_first() {
+        Some((key, _)) if key.starts_with('#') => return Ok(()),
+        Some((key, values)) => (*key, values),
+        None => return Ok(()),
+    };
+    // the column of the first value, assuming a single space after the key
+    let column = key.chars().count() + 2;
+    let value = || {
+        if values.len() == 1 {
+            Ok(values[0])
+        } else {
+            Err(ParseError::invalid(
+                column,
+                format!("expected a single value for {}", key),
+            ))
+        }
+    };
+    let flag = || -> Result<bool, ParseError> {
+        match value()? {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            v => Err(ParseError::invalid(
+                column,
+                format!("expected 0 or 1, found '{}'", v),
+            )),
+        }
+    };
+
+    match key {
+        "CUTOFF_LEN" => {
+            let v = value()?;
+            let cutoff: usize = v.parse().map_err(|_| {
+                ParseError::invalid(column, format!("could not read length '{}'", v))
+            })?;
+            params.cutoff_len = Some(cutoff);
+        }
+        "LABELED" => params.labeled = flag()?,
+        "DISC_ONLY" => params.disc_only = flag()?,
+        "DELETE_ROOT_PRETERMINALS" => params.delete_root_preterminals = flag()?,
+        "DELETE_LABEL" => {
+            params.delete_labels.insert(value()?.to_string());
+        }
+        "DELETE_LABEL_FOR_LENGTH" => {
+            params.delete_labels_for_length.insert(value()?.to_string());
+        }
+        "EQ_LABEL" if values.len() > 1 => {
+            for label in &values[1..] {
+                params
+                    .equivalent_labels
+                    .insert(label.to_string(), values[0].to_string());
+            }
+        }
+        "EQ_LABEL" => {
+            return Err(ParseError::invalid(
+                column,
+                "expected at least two equivalent labels",
+            ));
+        }
+        "DEBUG" | "MAX_ERROR" | "EQ_WORD" | "TED" | "DEP" | "LA" => (),
+        _ => {
+            return Err(ParseError::syntax(
+                1,
+                key,
+                "a parameter of EVALB or disco-dop",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts of brackets in the gold trees, in the parsed trees and in both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketScores {
+    pub gold: usize,
+    pub test: usize,
+    pub matched: usize,
+}
+
+impl BracketScores {
+    pub fn recall(&self) -> f64 {
+        ratio(self.matched, self.gold)
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.matched, self.test)
+    }
+
+    /// The harmonic mean of precision and recall.
+    pub fn f1(&self) -> f64 {
+        ratio(2 * self.matched, self.gold + self.test)
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// The result of `evaluate_treebank`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// The number of evaluated sentences.
+    pub sentences: usize,
+    /// The number of sentences that are longer than the cutoff length.
+    pub skipped: usize,
+    pub labeled: bool,
+    pub brackets: BracketScores,
+    /// The scores of the brackets with each label (only for labeled evaluations).
+    pub labels: BTreeMap<String, BracketScores>,
+    /// The number of sentences whose parsed brackets are exactly the gold brackets.
+    pub exact_matches: usize,
+    /// The number of evaluated words.
+    pub words: usize,
+    /// The number of evaluated words whose parsed tag is the gold tag.
+    pub correct_tags: usize,
+}
+
+impl Evaluation {
+    pub fn exact_match(&self) -> f64 {
+        ratio(self.exact_matches, self.sentences)
+    }
+
+    pub fn tagging_accuracy(&self) -> f64 {
+        ratio(self.correct_tags, self.words)
+    }
+}
+
+impl Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = if self.labeled { "labeled" } else { "unlabeled" };
+        writeln!(
+            f,
+            "{:<24}{} ({} skipped)",
+            "sentences", self.sentences, self.skipped
+        )?;
+        writeln!(
+            f,
+            "{:<24}{:.2}%",
+            format!("{} recall", prefix),
+            100.0 * self.brackets.recall()
+        )?;
+        writeln!(
+            f,
+            "{:<24}{:.2}%",
+            format!("{} precision", prefix),
+            100.0 * self.brackets.precision()
+        )?;
+        writeln!(
+            f,
+            "{:<24}{:.2}%",
+            format!("{} F1", prefix),
+            100.0 * self.brackets.f1()
+        )?;
+        writeln!(f, "{:<24}{:.2}%", "exact match", 100.0 * self.exact_match())?;
+        write!(
+            f,
+            "{:<24}{:.2}%",
+            "tagging accuracy",
+            100.0 * self.tagging_accuracy()
+        )?;
+
+        if !self.labels.is_empty() {
+            write!(
+                f,
+                "\n\n{:<12}{:>8}{:>8}{:>8}{:>10}{:>10}{:>10}",
+                "label", "gold", "test", "match", "recall", "precision", "F1"
+            )?;
+            for (label, scores) in &self.labels {
+                write!(
+                    f,
+                    "\n{:<12}{:>8}{:>8}{:>8}{:>9.2}%{:>9.2}%{:>9.2}%",
+                    label,
+                    scores.gold,
+                    scores.test,
+                    scores.matched,
+                    100.0 * scores.recall(),
+                    100.0 * scores.precision(),
+                    100.0 * scores.f1()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates the parsed sentences `test` against the `gold` sentences, which are aligned
+/// by their order and must have the same number of words.
+/// Words are removed according to the gold tags; the bracket of the virtual root is not
+/// evaluated.
+pub fn evaluate_treebank(
+    gold: &[NegraSentence],
+    test: &[NegraSentence],
+    params: &EvalParams,
+) -> Result<Evaluation, String> {
+    if gold.len() != test.len() {
+        return Err(format!(
+            "there are {} gold sentences, but {} parsed sentences",
+            gold.len(),
+            test.len()
+        ));
+    }
+
+    let mut evaluation = Evaluation {
+        labeled: params.labeled,
+        ..Evaluation::default()
+    };
+
+    for (gold_sentence, test_sentence) in gold.iter().zip(test) {
+        if gold_sentence.terminals.len() != test_sentence.terminals.len() {
+            return Err(format!(
+                "gold sentence {} has {} words, but parsed sentence {} has {}",
+                gold_sentence.id,
+                gold_sentence.terminals.len(),
+                test_sentence.id,
+                test_sentence.terminals.len()
+            ));
+        }
+
+        let length = gold_sentence
+            .terminals
+            .iter()
+            .filter(|terminal| !params.delete_labels_for_length.contains(&terminal.pos))
+            .count();
+        if params.cutoff_len.map_or(false, |cutoff| length > cutoff) {
+            evaluation.skipped += 1;
+            continue;
+        }
+
+        let kept = kept_positions(gold_sentence, params);
+        let gold_brackets = brackets(gold_sentence, &kept, params)?;
+        let test_brackets = brackets(test_sentence, &kept, params)?;
+
+        evaluation.sentences += 1;
+        if gold_brackets == test_brackets {
+            evaluation.exact_matches += 1;
+        }
+
+        for (&(ref label, _), &count) in &gold_brackets {
+            evaluation.brackets.gold += count;
+            if params.labeled {
+                evaluation.labels.entry(label.clone()).or_default().gold += count;
+            }
+        }
+        for (bracket, &count) in &test_brackets {
+            let matched = gold_brackets
+                .get(bracket)
+                .map_or(0, |&gold| gold.min(count));
+            evaluation.brackets.test += count;
+            evaluation.brackets.matched += matched;
+            if params.labeled {
+                let scores = evaluation.labels.entry(bracket.0.clone()).or_default();
+                scores.test += count;
+                scores.matched += matched;
+            }
+        }
+
+        for &position in kept.keys() {
+            evaluation.words += 1;
+            if gold_sentence.terminals[position].pos == test_sentence.terminals[position].pos {
+                evaluation.correct_tags += 1;
+            }
+        }
+    }
+
+    Ok(evaluation)
+}
+
+/// Maps the positions of the words that are not removed to their positions in the
+/// sentence without the removed words.
+fn kept_positions(gold: &NegraSentence, params: &EvalParams) -> BTreeMap<usize, usize> {
+    gold.terminals
+        .iter()
+        .enumerate()
+        .filter(|&(_, terminal)| {
+            !params.delete_labels.contains(&terminal.pos)
+                && !(params.delete_root_preterminals && terminal.parent == 0)
+        })
+        .enumerate()
+        .map(|(new_position, (position, _))| (position, new_position))
+        .collect()
+}
+
+/// The multiset of labelled brackets of a sentence, i.e. the number of nodes with each
+/// label and set of (kept) terminal positions.
+fn brackets(
+    sentence: &NegraSentence,
+    kept: &BTreeMap<usize, usize>,
+    params: &EvalParams,
+) -> Result<BTreeMap<(String, Vec<usize>), usize>, String> {
+    let mut brackets = BTreeMap::new();
+
+    for (number, positions) in sentence.yields()? {
+        let node = match sentence.nodes.get(&number) {
+            Some(node) if !params.delete_labels.contains(&node.label) => node,
+            _ => continue,
+        };
+        let positions: Vec<usize> = positions
+            .iter()
+            .filter_map(|position| kept.get(position).cloned())
+            .collect();
+        let discontinuous = match (positions.first(), positions.last()) {
+            (Some(first), Some(last)) => last - first + 1 != positions.len(),
+            _ => continue,
+        };
+        if params.disc_only && !discontinuous {
+            continue;
+        }
+
+        let label = if params.labeled {
+            params
+                .equivalent_labels
+                .get(&node.label)
+                .unwrap_or(&node.label)
+                .clone()
+        } else {
+            String::new()
+        };
+        *brackets.entry((label, positions)).or_insert(0) += 1;
+    }
+
+    Ok(brackets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::read_negra;
+    use super::*;
+
+    #[test]
+    fn test_evaluate_treebank() {
+        let gold = read_negra(
+            "#BOS 1\n\
+             Darüber\tPROAV\t--\tMO\t501\n\
+             muss\tVMFIN\t--\tHD\t500\n\
+             nachgedacht\tVVPP\t--\tHD\t501\n\
+             .\t$.\t--\t--\t0\n\
+             #500\tS\t--\t--\t0\n\
+             #501\tVP\t--\tOC\t500\n\
+             #EOS 1",
+        )
+        .unwrap();
+        let test = read_negra(
+            "#BOS 0\n\
+             Darüber\tADV\t--\t--\t500\n\
+             muss\tVMFIN\t--\t--\t500\n\
+             nachgedacht\tVVPP\t--\t--\t501\n\
+             .\t$.\t--\t--\t500\n\
+             #500\tS\t--\t--\t0\n\
+             #501\tVP\t--\t--\t500\n\
+             #EOS 0",
+        )
+        .unwrap();
+        let params: EvalParams = "# punctuation\n\
+                                  DEBUG 0\n\
+                                  DELETE_LABEL $.\n\
+                                  EQ_LABEL VP CVP\n"
+            .parse()
+            .unwrap();
+
+        let evaluation = evaluate_treebank(&gold, &test, &params).unwrap();
+        assert_eq!(
+            evaluation.brackets,
+            BracketScores {
+                gold: 2,
+                test: 2,
+                matched: 1
+            }
+        );
+        assert_eq!(evaluation.labels["VP"].recall(), 0.0);
+        assert_eq!(evaluation.labels["S"].f1(), 1.0);
+        assert_eq!(evaluation.exact_matches, 0);
+        assert_eq!((evaluation.correct_tags, evaluation.words), (2, 3));
+
+        let params: EvalParams = "DISC_ONLY 1\nLABELED 0\nCUTOFF_LEN 3\n\
+                                  DELETE_LABEL_FOR_LENGTH $.\nDELETE_ROOT_PRETERMINALS 1"
+            .parse()
+            .unwrap();
+        let evaluation = evaluate_treebank(&gold, &test, &params).unwrap();
+        assert_eq!((evaluation.sentences, evaluation.skipped), (1, 0));
+        assert_eq!(
+            evaluation.brackets,
+            BracketScores {
+                gold: 1,
+                test: 0,
+                matched: 0
+            }
+        );
+        assert!(evaluation.labels.is_empty());
+
+        assert_eq!(
+            "LABELED 1\nFOO 2".parse::<EvalParams>(),
+            Err(ParseError::syntax(1, "FOO", "a parameter of EVALB or disco-dop").at_line(2, 0))
+        );
+        assert!(evaluate_treebank(&gold, &[], &params).is_err());
+    }
+}
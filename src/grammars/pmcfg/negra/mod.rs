@@ -1,7 +1,17 @@
 use super::*;
+use crate::grammars::lcfrs::binarization::markovization::{base_label, is_auxiliary_label};
+use crate::util::dot::{Digraph, ToDot};
 use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
 
+mod brackets;
+mod eval;
+mod reader;
+
+pub use self::brackets::{noparse_discbracket, noparse_ptb, to_discbracket, to_ptb};
+pub use self::eval::{evaluate_treebank, BracketScores, EvalParams, Evaluation};
+pub use self::reader::{read_negra, NegraNode, NegraSentence, NegraTerminal};
+
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 struct TermId {
     address: Vec<usize>,
@@ -75,6 +85,8 @@ pub enum DumpMode<T> {
 
 /// Takes a tree stack _(encoded in a Gorn tree)_ of PMCFG rules and transforms it into a
 /// corresponding _NEGRA_ string.
+/// Nodes of auxiliary nonterminals (introduced by binarization) are skipped, and the
+/// annotations of labels are removed (see `base_label`).
 pub fn to_negra<H, T, W>(
     tree_map: &GornTree<PMCFGRule<H, T, W>>,
     sentence_id: usize,
@@ -84,12 +96,7 @@ where
     H: Clone + ToString,
     T: Clone + ToString,
 {
-    if !meets_negra_criteria(&tree_map) {
-        panic!(
-            "The given tree does not meet the negra criteria! All rules must either consist \
-             only of nonterminals or of exactly one terminal symbol."
-        );
-    }
+    assert_negra_criteria(tree_map);
 
     let negra_vector = to_negra_vector(&tree_map, mode);
     let mut output = format!("#BOS {}\n", sentence_id);
@@ -124,6 +131,15 @@ where
     output
 }
 
+fn assert_negra_criteria<H, T, W>(tree_map: &GornTree<PMCFGRule<H, T, W>>) {
+    if !meets_negra_criteria(tree_map) {
+        panic!(
+            "The given tree does not meet the negra criteria! All rules must either consist \
+             only of nonterminals or of exactly one terminal symbol."
+        );
+    }
+}
+
 pub fn meets_negra_criteria<H, T, W>(tree_map: &GornTree<PMCFGRule<H, T, W>>) -> bool {
     for (_address, rule) in tree_map {
         let &PMCFGRule {
@@ -162,8 +178,28 @@ pub fn meets_negra_criteria<H, T, W>(tree_map: &GornTree<PMCFGRule<H, T, W>>) ->
 
 fn to_negra_vector<H, T, W>(
     tree_map: &GornTree<PMCFGRule<H, T, W>>,
-    mut mode: DumpMode<T>,
+    mode: DumpMode<T>,
 ) -> Vec<(String, String, usize)>
+where
+    H: Clone + ToString,
+    T: Clone + ToString,
+{
+    let (mut negra_vector, nodes) = to_negra_nodes(tree_map, mode);
+    negra_vector.extend(
+        nodes
+            .into_iter()
+            .map(|(number, label, parent)| (format!("#{}", number), label, parent)),
+    );
+    negra_vector
+}
+
+/// Splits a tree into its terminals (word, tag and the number of the parent node) in the
+/// order of the sentence and its nonterminal nodes (number, label and the number of the
+/// parent node); the root has the number `0` and is not among the nodes.
+fn to_negra_nodes<H, T, W>(
+    tree_map: &GornTree<PMCFGRule<H, T, W>>,
+    mut mode: DumpMode<T>,
+) -> (Vec<(String, String, usize)>, Vec<(usize, String, usize)>)
 where
     H: Clone + ToString,
     T: Clone + ToString,
@@ -173,6 +209,7 @@ where
     let evaluated_compos = evaluate(&identified_tree_map);
 
     let mut negra_vector = Vec::new();
+    let mut nodes = Vec::new();
     let mut rule_queue = VecDeque::new();
     let mut rule_number_map = GornTree::new();
     let mut rule_counter = 500;
@@ -198,13 +235,17 @@ where
                         panic!("Terminals must have a nonterminal-only rule as their parent!");
                     } else {
                         get_rule_number(
-                            parent_address,
+                            visible_ancestor(parent_address, &nonterminal_map),
                             &mut rule_queue,
                             &mut rule_number_map,
                             &mut rule_counter,
                         )
                     };
-                    negra_vector.push((terminal_string, rule_label.to_string(), parent_number));
+                    negra_vector.push((
+                        terminal_string,
+                        base_label(&rule_label.to_string()).to_owned(),
+                        parent_number,
+                    ));
                 }
             }
         }
@@ -215,21 +256,35 @@ where
 
         if let Some(_) = parent_address.pop() {
             let parent_number = get_rule_number(
-                parent_address,
+                visible_ancestor(parent_address, &nonterminal_map),
                 &mut rule_queue,
                 &mut rule_number_map,
                 &mut rule_counter,
             );
             let rule_label = nonterminal_map.get(&address).unwrap();
-            negra_vector.push((
-                format!("#{}", rule_number),
-                rule_label.to_string(),
+            nodes.push((
+                rule_number,
+                base_label(&rule_label.to_string()).to_owned(),
                 parent_number,
             ));
         }
     }
 
-    negra_vector
+    (negra_vector, nodes)
+}
+
+/// Finds the nearest ancestor (or self) of `address` that is not labeled with an auxiliary
+/// nonterminal.
+fn visible_ancestor<H>(mut address: Vec<usize>, nonterminal_map: &GornTree<H>) -> Vec<usize>
+where
+    H: ToString,
+{
+    while !address.is_empty()
+        && is_auxiliary_label(&nonterminal_map.get(&address).unwrap().to_string())
+    {
+        address.pop();
+    }
+    address
 }
 
 fn get_rule_number(
@@ -254,6 +309,42 @@ fn get_rule_number(
     rule_number
 }
 
+/// Draws the tree of a sentence with its terminals at the bottom in the order
+/// of the sentence, such that discontinuous constituents show up as crossing
+/// branches; edges are labelled with their grammatical functions.
+impl ToDot for NegraSentence {
+    fn to_dot(&self) -> String {
+        let function = |edge: &str| if edge == "--" { "" } else { edge }.to_string();
+
+        let mut graph = Digraph::new();
+        graph.attribute("node [shape=plaintext]");
+        graph.node("0", &[("label", "VROOT")]);
+        for (number, node) in &self.nodes {
+            let id = number.to_string();
+            graph.node(&id, &[("label", node.label.as_str())]);
+            let label = function(&node.edge);
+            graph.edge(&node.parent.to_string(), &id, &[("label", label.as_str())]);
+        }
+
+        let mut terminals = Vec::new();
+        for (position, terminal) in self.terminals.iter().enumerate() {
+            let id = format!("t{}", position + 1);
+            let label = format!("{}\n{}", terminal.word, terminal.pos);
+            graph.node(&id, &[("label", label.as_str())]);
+            let label = function(&terminal.edge);
+            graph.edge(
+                &terminal.parent.to_string(),
+                &id,
+                &[("label", label.as_str())],
+            );
+            terminals.push(id);
+        }
+        graph.rank("max", &terminals);
+
+        graph.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use self::VarT::{Var, T};
@@ -387,6 +478,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_negra_to_dot() {
+        let corpus = "#BOS 1\n\
+                      Darüber\tPROAV\t--\tMO\t501\n\
+                      muss\tVMFIN\t--\tHD\t500\n\
+                      nachgedacht\tVVPP\t--\tHD\t501\n\
+                      #500\tS\t--\t--\t0\n\
+                      #501\tVP\t--\tOC\t500\n\
+                      #EOS 1";
+        let dot = read_negra(corpus).unwrap()[0].to_dot();
+
+        assert!(dot.contains("\"0\" -> \"500\" [label=\"\"];"));
+        assert!(dot.contains("\"500\" -> \"501\" [label=\"OC\"];"));
+        assert!(dot.contains("\"t3\" [label=\"nachgedacht\\nVVPP\"];"));
+        assert!(dot.contains("\"501\" -> \"t1\" [label=\"MO\"];"));
+        assert!(dot.contains(
+            "{ rank=max; \"t1\"; \"t2\"; \"t3\"; \"t1\" -> \"t2\" -> \"t3\" [style=invis]; }"
+        ));
+    }
+
     #[test]
     fn test_meets_negra_criteria() {
         let mut tree_map: GornTree<PMCFGRule<String, char, usize>> = GornTree::new();
@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A terminal node of a sentence in _NEGRA_ export format, i.e. a word with
+/// its part-of-speech tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegraTerminal {
+    pub word: String,
+    /// Only present in files of export format version 4.
+    pub lemma: Option<String>,
+    pub pos: String,
+    pub morph: String,
+    pub edge: String,
+    pub parent: usize,
+}
+
+/// A nonterminal node of a sentence in _NEGRA_ export format.
+/// Nodes are numbered from 500 to 999, the number 0 is reserved for the
+/// virtual root node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegraNode {
+    pub label: String,
+    pub morph: String,
+    pub edge: String,
+    pub parent: usize,
+}
+
+/// A sentence in _NEGRA_ export format.
+/// Each node refers to its parent by number; nodes with parent 0 are
+/// attached to the virtual root node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegraSentence {
+    pub id: usize,
+    pub terminals: Vec<NegraTerminal>,
+    pub nodes: BTreeMap<usize, NegraNode>,
+}
+
+impl NegraSentence {
+    pub fn words(&self) -> Vec<&str> {
+        self.terminals.iter().map(|t| t.word.as_str()).collect()
+    }
+
+    pub fn pos_tags(&self) -> Vec<&str> {
+        self.terminals.iter().map(|t| t.pos.as_str()).collect()
+    }
+
+    /// Computes the set of dominated terminal positions for each node,
+    /// including the virtual root node `0`.
+    /// Nodes that do not dominate any terminal are omitted.
+    pub fn yields(&self) -> Result<BTreeMap<usize, BTreeSet<usize>>, String> {
+        let mut yields: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+
+        for (position, terminal) in self.terminals.iter().enumerate() {
+            let mut node = terminal.parent;
+            // a chain of parents can not be longer than the number of nodes
+            for _ in 0..=self.nodes.len() {
+                yields.entry(node).or_default().insert(position);
+                if node == 0 {
+                    break;
+                }
+                node = self
+                    .nodes
+                    .get(&node)
+                    .ok_or_else(|| format!("sentence {}: undefined node #{}", self.id, node))?
+                    .parent;
+            }
+            if node != 0 {
+                return Err(format!(
+                    "sentence {}: the ancestors of terminal {} contain a cycle",
+                    self.id,
+                    position + 1
+                ));
+            }
+        }
+
+        Ok(yields)
+    }
+
+    /// Checks if each parent reference points to the root or to a
+    /// nonterminal node of the sentence.
+    fn check_parents(&self) -> Result<(), String> {
+        let is_defined = |p: usize| p == 0 || self.nodes.contains_key(&p);
+        for (position, terminal) in self.terminals.iter().enumerate() {
+            if !is_defined(terminal.parent) {
+                return Err(format!(
+                    "sentence {}: terminal {} refers to undefined parent #{}",
+                    self.id,
+                    position + 1,
+                    terminal.parent
+                ));
+            }
+        }
+        for (number, node) in &self.nodes {
+            if !is_defined(node.parent) || node.parent == *number {
+                return Err(format!(
+                    "sentence {}: node #{} refers to invalid parent #{}",
+                    self.id, number, node.parent
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads all sentences of a treebank in _NEGRA_ export format (versions 3 and 4).
+/// The version is detected for each line by the position of the parent
+/// column; secondary edges, comments (`%% …`) and the header tables
+/// (`#BOT` … `#EOT`) are ignored.
+pub fn read_negra(s: &str) -> Result<Vec<NegraSentence>, String> {
+    let mut sentences = Vec::new();
+    let mut current: Option<NegraSentence> = None;
+    let mut in_table = false;
+
+    for (line_number, full_line) in s.lines().enumerate() {
+        let line_number = line_number + 1;
+        let line = full_line.split("%%").next().unwrap().trim();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.is_empty() {
+            continue;
+        } else if in_table {
+            in_table = fields[0] != "#EOT";
+        } else if fields[0] == "#BOT" {
+            in_table = true;
+        } else if fields[0] == "#BOS" {
+            if current.is_some() {
+                return Err(format!("line {}: missing #EOS", line_number));
+            }
+            let id = fields
+                .get(1)
+                .and_then(|i| i.parse().ok())
+                .ok_or_else(|| format!("line {}: malformed sentence id", line_number))?;
+            current = Some(NegraSentence {
+                id,
+                terminals: Vec::new(),
+                nodes: BTreeMap::new(),
+            });
+        } else if fields[0] == "#EOS" {
+            let sentence = current
+                .take()
+                .ok_or_else(|| format!("line {}: #EOS without #BOS", line_number))?;
+            sentence
+                .check_parents()
+                .map_err(|e| format!("line {}: {}", line_number, e))?;
+            sentences.push(sentence);
+        } else if let Some(ref mut sentence) = current {
+            let (word, lemma, pos, morph, edge, parent) = split_fields(&fields)
+                .ok_or_else(|| format!("line {}: malformed line '{}'", line_number, full_line))?;
+
+            match node_number(word) {
+                Some(number) => {
+                    sentence.nodes.insert(
+                        number,
+                        NegraNode {
+                            label: pos.to_owned(),
+                            morph: morph.to_owned(),
+                            edge: edge.to_owned(),
+                            parent,
+                        },
+                    );
+                }
+                None => sentence.terminals.push(NegraTerminal {
+                    word: word.to_owned(),
+                    lemma: lemma.map(str::to_owned),
+                    pos: pos.to_owned(),
+                    morph: morph.to_owned(),
+                    edge: edge.to_owned(),
+                    parent,
+                }),
+            }
+        }
+    }
+
+    if current.is_some() {
+        Err("unexpected end of input: missing #EOS".to_owned())
+    } else {
+        Ok(sentences)
+    }
+}
+
+/// Splits a line into word, lemma, tag, morphology, edge label and parent.
+/// Format 3 has five columns before secondary edges, format 4 has an
+/// additional lemma column after the word.
+fn split_fields<'a>(
+    fields: &[&'a str],
+) -> Option<(&'a str, Option<&'a str>, &'a str, &'a str, &'a str, usize)> {
+    if fields.len() < 5 {
+        return None;
+    }
+    if let Ok(parent) = fields[4].parse() {
+        Some((fields[0], None, fields[1], fields[2], fields[3], parent))
+    } else {
+        let parent = fields.get(5)?.parse().ok()?;
+        Some((
+            fields[0],
+            Some(fields[1]),
+            fields[2],
+            fields[3],
+            fields[4],
+            parent,
+        ))
+    }
+}
+
+/// Reads the number of a nonterminal node of the form `#5xx`.
+fn node_number(word: &str) -> Option<usize> {
+    if word.starts_with('#') {
+        word[1..].parse().ok().filter(|&n| n >= 500)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_export_v3() {
+        let corpus = "#FORMAT 3\n\
+                      #BOT ORIGIN\n\
+                      0\tsome origin\n\
+                      #EOT ORIGIN\n\
+                      #BOS 1 2 3\n\
+                      Darüber\tPROAV\t--\tMO\t501\n\
+                      muss\tVMFIN\t3.Sg\tHD\t500 %% comment\n\
+                      nachgedacht\tVVPP\t--\tHD\t501\n\
+                      werden\tVAINF\t--\tHD\t500\n\
+                      #500\tS\t--\t--\t0\n\
+                      #501\tVP\t--\tOC\t500\tRE\t500\n\
+                      #EOS 1\n";
+        let sentences = read_negra(corpus).unwrap();
+
+        assert_eq!(sentences.len(), 1);
+        assert_eq!(sentences[0].id, 1);
+        assert_eq!(
+            sentences[0].words(),
+            vec!["Darüber", "muss", "nachgedacht", "werden"]
+        );
+        assert_eq!(
+            sentences[0].terminals[1],
+            NegraTerminal {
+                word: "muss".to_owned(),
+                lemma: None,
+                pos: "VMFIN".to_owned(),
+                morph: "3.Sg".to_owned(),
+                edge: "HD".to_owned(),
+                parent: 500,
+            }
+        );
+        assert_eq!(
+            sentences[0].nodes[&501],
+            NegraNode {
+                label: "VP".to_owned(),
+                morph: "--".to_owned(),
+                edge: "OC".to_owned(),
+                parent: 500,
+            }
+        );
+
+        let yields = sentences[0].yields().unwrap();
+        assert_eq!(yields[&0], vec![0, 1, 2, 3].into_iter().collect());
+        assert_eq!(yields[&500], vec![0, 1, 2, 3].into_iter().collect());
+        assert_eq!(yields[&501], vec![0, 2].into_iter().collect());
+    }
+
+    #[test]
+    fn read_export_v4() {
+        let corpus = "#BOS 7\n\
+                      Er\ter\tPPER\t--\tSB\t500\n\
+                      schläft\tschlafen\tVVFIN\t--\tHD\t500\n\
+                      .\t--\t$.\t--\t--\t0\n\
+                      #500\t--\tS\t--\t--\t0\n\
+                      #EOS 7";
+        let sentences = read_negra(corpus).unwrap();
+
+        assert_eq!(sentences[0].id, 7);
+        assert_eq!(sentences[0].pos_tags(), vec!["PPER", "VVFIN", "$."]);
+        assert_eq!(sentences[0].terminals[1].lemma, Some("schlafen".to_owned()));
+        assert_eq!(sentences[0].nodes[&500].label, "S");
+        assert_eq!(sentences[0].terminals[2].parent, 0);
+    }
+
+    #[test]
+    fn read_malformed() {
+        assert!(read_negra("#BOS 1\na\tA\t--\t--\t0\n").is_err());
+        assert!(read_negra("#BOS 1\na\tA\t--\t--\t501\n#EOS 1").is_err());
+        assert!(read_negra("#BOS 1\na\tA\t--\n#EOS 1").is_err());
+        assert!(read_negra("#BOS x\n#EOS 1").is_err());
+    }
+}
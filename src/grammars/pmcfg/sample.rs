@@ -0,0 +1,121 @@
+//! Random sampling of derivations from a weighted PMCFG.
+
+use log_domain::LogDomain;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use super::{evaluate, to_term, PMCFGRule, PMCFG};
+use crate::util::tree::GornTree;
+
+/// Samples a derivation top-down, starting with an initial nonterminal chosen uniformly at
+/// random; the rule for each nonterminal is chosen with a probability proportional to its
+/// weight, i.e. according to the normalised rule weights.
+/// Returns the derivation together with its yield, or `None` if the derivation is deeper than
+/// `max_depth` (the root has depth 0) or reaches a nonterminal without rules of non-zero
+/// weight.
+/// Repeating the call until it succeeds samples from the distribution of derivations that
+/// are at most `max_depth` deep.
+pub fn sample<N, T, R>(
+    grammar: &PMCFG<N, T, LogDomain<f64>>,
+    rng: &mut R,
+    max_depth: usize,
+) -> Option<(GornTree<PMCFGRule<N, T, LogDomain<f64>>>, Vec<T>)>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Display,
+    R: Rng,
+{
+    if grammar.initial.is_empty() {
+        return None;
+    }
+    let mut rules: HashMap<&N, Vec<&PMCFGRule<N, T, LogDomain<f64>>>> = HashMap::new();
+    for rule in &grammar.rules {
+        rules.entry(&rule.head).or_insert_with(Vec::new).push(rule);
+    }
+
+    let initial = &grammar.initial[rng.gen_range(0, grammar.initial.len())];
+    let mut derivation = GornTree::new();
+    let mut stack = vec![(Vec::new(), initial)];
+    while let Some((address, nonterminal)) = stack.pop() {
+        if address.len() > max_depth {
+            return None;
+        }
+        let rule = choose(rules.get(nonterminal)?, rng)?;
+        for (i, successor) in rule.tail.iter().enumerate() {
+            let mut child_address = address.clone();
+            child_address.push(i);
+            stack.push((child_address, successor));
+        }
+        derivation.insert(address, rule.clone());
+    }
+
+    let (terms, _) = to_term(&derivation);
+    let word = evaluate(&terms)
+        .composition
+        .iter()
+        .flat_map(|component| component.iter().map(|symbol| symbol.unwrap_t().clone()))
+        .collect();
+    Some((derivation, word))
+}
+
+/// Chooses one of the rules with a probability proportional to its weight.
+fn choose<'a, N, T, R: Rng>(
+    rules: &[&'a PMCFGRule<N, T, LogDomain<f64>>],
+    rng: &mut R,
+) -> Option<&'a PMCFGRule<N, T, LogDomain<f64>>> {
+    let total: f64 = rules.iter().map(|rule| rule.weight.value()).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut threshold = rng.gen::<f64>() * total;
+    for &rule in rules {
+        threshold -= rule.weight.value();
+        if threshold < 0.0 {
+            return Some(rule);
+        }
+    }
+    // rounding errors
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.weight.value() > 0.0)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    #[test]
+    fn sample_yields() {
+        let grammar: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+                                                              S → [[Var 0 0, Var 0 1]] (A)\n\
+                                                              A → [[T a, Var 0 0], [T b, Var 0 1]] (A) # 0.2\n\
+                                                              A → [[], []] () # 0.8"
+            .parse()
+            .unwrap();
+        let seed: &[usize] = &[1, 2, 3, 4];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        let mut samples = 0;
+        while samples < 100 {
+            if let Some((derivation, word)) = sample(&grammar, &mut rng, 5) {
+                let n = derivation.len() - 2;
+                assert!(n <= 4);
+                assert_eq!(word.len(), 2 * n);
+                assert!(word[..n].iter().all(|t| t == "a"));
+                assert!(word[n..].iter().all(|t| t == "b"));
+                samples += 1;
+            }
+        }
+
+        let empty: PMCFG<String, String, LogDomain<f64>> = "initial: [S]\n\
+                                                            S → [[Var 0 0]] (A)"
+            .parse()
+            .unwrap();
+        assert!(sample(&empty, &mut rng, 5).is_none());
+    }
+}
@@ -0,0 +1,690 @@
+use num_traits::One;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use crate::grammars::pmcfg::{Composition, PMCFGRule, VarT, PMCFG};
+use crate::util::parsing::{initial_rule_grammar_from_str, ParseError};
+
+/// A PMCFG rule in the notation of simple range concatenation grammars (sRCG), e.g.
+///
+/// ```text
+/// S(x₁ y₁ x₂ y₂) ← A(x₁, x₂) B(y₁, y₂)  # 1
+/// A("a" x₁, "c" x₂) ← A(x₁, x₂)          # 0.5
+/// A(ε, ε) ← ε                            # 0.5
+/// ```
+///
+/// * Each argument of a predicate on the right-hand side is a single variable; a variable
+///   is any token that is not quoted.
+/// * The arguments on the left-hand side are sequences of variables and quoted terminals
+///   (separated by whitespace); `ε` denotes the empty argument, or a clause without
+///   predicates on the right-hand side.
+/// * Each variable must occur exactly once on each side of the clause (i.e. the rule must be
+///   linear and non-deleting).
+/// * `<-` may be used instead of `←`, nonterminals may be quoted, and the weight (`# 0.5`) may
+///   be omitted.  A clause may be followed by a comment (starting with `%`).
+///
+/// ```
+/// use rustomata::grammars::pmcfg::PMCFGRule;
+/// use rustomata::grammars::pmcfg::srcg::SrcgRule;
+///
+/// let SrcgRule(rule): SrcgRule<String, String, f64> =
+///     "A(\"a\" x₁, \"c\" x₂) ← A(x₁, x₂) # 0.5".parse().unwrap();
+/// assert_eq!(
+///     rule,
+///     "A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5".parse::<PMCFGRule<_, _, _>>().unwrap()
+/// );
+/// assert_eq!(
+///     SrcgRule(rule).to_string(),
+///     "A(\"a\" x₁, \"c\" x₂) ← A(x₁, x₂)  # 0.5"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrcgRule<N, T, W>(pub PMCFGRule<N, T, W>);
+
+/// A PMCFG in sRCG notation, i.e. a declaration of initial nonterminals (`initial: [S]`, as
+/// in rustomata's notation) and a clause (see `SrcgRule`) in each line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrcgGrammar<N, T, W>(pub PMCFG<N, T, W>);
+
+/// The reason why a PMCFG can not be written in sRCG notation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToSrcgError {
+    /// The rule with the given index does not use every component of its successors, i.e.
+    /// it is deleting.
+    Deleting(usize),
+}
+
+impl Display for ToSrcgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ToSrcgError::Deleting(rule_id) => write!(
+                f,
+                "rule {} does not use every component of its successors (it is deleting)",
+                rule_id
+            ),
+        }
+    }
+}
+
+impl<N, T, W> SrcgGrammar<N, T, W>
+where
+    N: Eq + Hash,
+{
+    /// Checks that each rule uses every component of its successors, where the fanout of a
+    /// nonterminal is the number of components of its rules.  Otherwise, the successors of
+    /// a rule could not be written with all their arguments, and the clause would not be
+    /// read back.
+    pub fn from_pmcfg(pmcfg: PMCFG<N, T, W>) -> Result<Self, ToSrcgError> {
+        let mut fanouts: HashMap<&N, usize> = HashMap::new();
+        for rule in &pmcfg.rules {
+            fanouts
+                .entry(&rule.head)
+                .or_insert_with(|| rule.composition.len());
+        }
+
+        for (rule_id, rule) in pmcfg.rules.iter().enumerate() {
+            let tail_fanouts: Vec<usize> = rule
+                .tail
+                .iter()
+                .map(|nonterminal| fanouts.get(nonterminal).cloned().unwrap_or(0))
+                .collect();
+            if !uses_all_components(rule, &tail_fanouts) {
+                return Err(ToSrcgError::Deleting(rule_id));
+            }
+        }
+
+        Ok(SrcgGrammar(pmcfg))
+    }
+}
+
+impl<N, T, W> FromStr for SrcgRule<N, T, W>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scanner::new(s).clause().map(SrcgRule)
+    }
+}
+
+impl<N, T, W> FromStr for SrcgGrammar<N, T, W>
+where
+    N: FromStr,
+    N::Err: Debug,
+    T: FromStr,
+    T::Err: Debug,
+    W: FromStr + One,
+    W::Err: Debug,
+{
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (initial, rules): (Vec<N>, Vec<SrcgRule<N, T, W>>) =
+            initial_rule_grammar_from_str(s)?;
+        Ok(SrcgGrammar(PMCFG {
+            initial,
+            rules: rules.into_iter().map(|SrcgRule(rule)| rule).collect(),
+        }))
+    }
+}
+
+/// A hand-written recursive descent parser for sRCG clauses.
+struct Scanner {
+    input: Vec<char>,
+    position: usize,
+}
+
+impl Scanner {
+    fn new(s: &str) -> Self {
+        Scanner {
+            input: s.chars().collect(),
+            position: 0,
+        }
+    }
+
+    /// Fails with a syntax error at the current position.
+    fn expected<A>(&self, expected: &str) -> Result<A, ParseError> {
+        let found: String = self.input[self.position..]
+            .iter()
+            .take_while(|c| !c.is_whitespace())
+            .collect();
+        Err(ParseError::syntax(self.position + 1, found.as_str(), expected))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes `expected` if the remaining input starts with it.
+    fn eat(&mut self, expected: &str) -> bool {
+        let chars: Vec<char> = expected.chars().collect();
+        if self.input[self.position..].starts_with(&chars) {
+            self.position += chars.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            self.expected(&format!("'{}'", expected))
+        }
+    }
+
+    /// Reads a quoted string; `\` escapes the following character.
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        self.position += 1;
+        let mut content = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(content);
+                }
+                Some('\\') if self.position + 1 < self.input.len() => {
+                    content.push(self.input[self.position + 1]);
+                    self.position += 2;
+                }
+                Some(c) => {
+                    content.push(c);
+                    self.position += 1;
+                }
+                None => return Err(ParseError::invalid(start + 1, "unterminated quotation")),
+            }
+        }
+    }
+
+    /// Reads an unquoted token that ends before whitespace or any of the `delimiters`.
+    fn bare(&mut self, delimiters: &[char]) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .map_or(false, |c| !c.is_whitespace() && !delimiters.contains(&c))
+        {
+            self.position += 1;
+        }
+        self.input[start..self.position].iter().collect()
+    }
+
+    fn parse<A>(&self, token: &str, column: usize) -> Result<A, ParseError>
+    where
+        A: FromStr,
+        A::Err: Debug,
+    {
+        token.parse().map_err(|e| {
+            ParseError::invalid(column, format!("could not read '{}': {:?}", token, e))
+        })
+    }
+
+    /// Reads the name of a predicate.
+    fn nonterminal<N>(&mut self) -> Result<N, ParseError>
+    where
+        N: FromStr,
+        N::Err: Debug,
+    {
+        self.skip_whitespace();
+        let column = self.position + 1;
+        let name = if self.peek() == Some('"') {
+            self.quoted()?
+        } else {
+            self.bare(&['(', '"'])
+        };
+        if name.is_empty() {
+            return self.expected("a nonterminal");
+        }
+        self.parse(&name, column)
+    }
+
+    /// Reads the arguments of a predicate on the left-hand side.
+    fn lhs_arguments<T>(&mut self) -> Result<Vec<Vec<Symbol<T>>>, ParseError>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        self.expect("(")?;
+        let mut arguments = Vec::new();
+        self.skip_whitespace();
+        if self.eat(")") {
+            return Ok(arguments);
+        }
+        loop {
+            let mut argument = Vec::new();
+            loop {
+                self.skip_whitespace();
+                let column = self.position + 1;
+                match self.peek() {
+                    Some(',') | Some(')') => break,
+                    Some('"') => {
+                        let terminal = self.quoted()?;
+                        argument.push(Symbol::Terminal(self.parse(&terminal, column)?));
+                    }
+                    Some(_) => {
+                        let name = self.bare(&[',', ')', '"']);
+                        if name != "ε" {
+                            argument.push(Symbol::Variable(name, column));
+                        }
+                    }
+                    None => return self.expected("')'"),
+                }
+            }
+            arguments.push(argument);
+            if self.eat(")") {
+                return Ok(arguments);
+            }
+            self.position += 1; // ','
+        }
+    }
+
+    /// Reads the arguments of a predicate on the right-hand side, i.e. variables.
+    fn rhs_arguments(&mut self) -> Result<Vec<(String, usize)>, ParseError> {
+        self.expect("(")?;
+        let mut variables = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let column = self.position + 1;
+            if self.peek() == Some('"') {
+                return Err(ParseError::invalid(
+                    column,
+                    "arguments on the right-hand side must be variables, not terminals",
+                ));
+            }
+            let name = self.bare(&[',', ')', '"']);
+            if name.is_empty() || name == "ε" {
+                return self.expected("a variable");
+            }
+            variables.push((name, column));
+            self.skip_whitespace();
+            if self.eat(")") {
+                return Ok(variables);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn clause<N, T, W>(&mut self) -> Result<PMCFGRule<N, T, W>, ParseError>
+    where
+        N: FromStr,
+        N::Err: Debug,
+        T: FromStr,
+        T::Err: Debug,
+        W: FromStr + One,
+        W::Err: Debug,
+    {
+        let head = self.nonterminal()?;
+        let arguments = self.lhs_arguments()?;
+
+        self.skip_whitespace();
+        if !self.eat("←") && !self.eat("<-") {
+            return self.expected("'←'");
+        }
+
+        // the successor and component of each variable, and the variables with the column of
+        // their binding in the order of the right-hand side
+        let mut tail = Vec::new();
+        let mut bound: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut bindings = Vec::new();
+        self.skip_whitespace();
+        if !self.eat("ε") {
+            loop {
+                self.skip_whitespace();
+                match self.peek() {
+                    None | Some('#') | Some('%') => break,
+                    _ => (),
+                }
+                tail.push(self.nonterminal()?);
+                for (j, (name, column)) in self.rhs_arguments()?.into_iter().enumerate() {
+                    if bound.insert(name.clone(), (tail.len() - 1, j)).is_some() {
+                        return Err(ParseError::invalid(
+                            column,
+                            format!("variable '{}' is bound more than once", name),
+                        ));
+                    }
+                    bindings.push((name, column));
+                }
+            }
+        }
+
+        self.skip_whitespace();
+        let weight = if self.eat("#") {
+            self.skip_whitespace();
+            let column = self.position + 1;
+            let token = self.bare(&['%']);
+            self.parse(&token, column)?
+        } else {
+            W::one()
+        };
+        self.skip_whitespace();
+        if self.peek().map_or(false, |c| c != '%') {
+            return self.expected("the end of the clause");
+        }
+
+        // replace variables by indices, checking for linearity
+        let mut used: HashMap<String, usize> = HashMap::new();
+        let mut composition = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            let mut component = Vec::with_capacity(argument.len());
+            for symbol in argument {
+                match symbol {
+                    Symbol::Terminal(t) => component.push(VarT::T(t)),
+                    Symbol::Variable(name, column) => {
+                        let (i, j) = match bound.get(&name) {
+                            Some(&index) => index,
+                            None => {
+                                return Err(ParseError::invalid(
+                                    column,
+                                    format!(
+                                        "variable '{}' is not bound on the right-hand side",
+                                        name
+                                    ),
+                                ))
+                            }
+                        };
+                        if used.insert(name.clone(), column).is_some() {
+                            return Err(ParseError::invalid(
+                                column,
+                                format!(
+                                    "variable '{}' occurs more than once on the left-hand side \
+                                     (the clause is not linear)",
+                                    name
+                                ),
+                            ));
+                        }
+                        component.push(VarT::Var(i, j));
+                    }
+                }
+            }
+            composition.push(component);
+        }
+        if let Some(&(ref name, column)) =
+            bindings.iter().find(|(name, _)| !used.contains_key(name))
+        {
+            return Err(ParseError::invalid(
+                column,
+                format!(
+                    "variable '{}' does not occur on the left-hand side \
+                     (the clause is deleting)",
+                    name
+                ),
+            ));
+        }
+
+        Ok(PMCFGRule {
+            head,
+            tail,
+            composition: Composition { composition },
+            weight,
+        })
+    }
+}
+
+/// A symbol in an argument on the left-hand side of a clause; variables are stored with the
+/// column of their occurrence.
+enum Symbol<T> {
+    Terminal(T),
+    Variable(String, usize),
+}
+
+/// The name of the variable for the `j`th component of the `i`th successor of a rule with the
+/// given rank, e.g. `x₁` or `y₂`.
+fn variable_name(rank: usize, i: usize, j: usize) -> String {
+    let subscript: String = (j + 1)
+        .to_string()
+        .chars()
+        .map(|d| ::std::char::from_u32(0x2080 + d.to_digit(10).unwrap()).unwrap())
+        .collect();
+    if rank <= 6 {
+        format!("{}{}", ['x', 'y', 'z', 'u', 'v', 'w'][i], subscript)
+    } else {
+        format!("x{}{}", i + 1, subscript)
+    }
+}
+
+/// Whether the rule uses every component of its successors, where `fanouts` are the least
+/// fanouts of the successors.
+fn uses_all_components<N, T, W>(rule: &PMCFGRule<N, T, W>, fanouts: &[usize]) -> bool {
+    let mut used: Vec<Vec<bool>> = fanouts.iter().map(|&fanout| vec![false; fanout]).collect();
+    for (i, j) in rule.composition.variables() {
+        if j >= used[i].len() {
+            used[i].resize(j + 1, false);
+        }
+        used[i][j] = true;
+    }
+    used.iter()
+        .all(|components| !components.is_empty() && !components.contains(&false))
+}
+
+/// Quotes a token and escapes quotation marks and backslashes.
+fn quote(token: &str) -> String {
+    format!("\"{}\"", token.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quotes a nonterminal if it could not be read back otherwise.
+fn nonterminal_token(nonterminal: &str) -> String {
+    if nonterminal.is_empty()
+        || nonterminal == "ε"
+        || nonterminal.starts_with('#')
+        || nonterminal.starts_with('%')
+        || nonterminal
+            .chars()
+            .any(|c| c.is_whitespace() || c == '(' || c == '"')
+    {
+        quote(nonterminal)
+    } else {
+        nonterminal.to_owned()
+    }
+}
+
+/// Deleting rules are written as well, but the clauses can not be read back; see
+/// `SrcgGrammar::from_pmcfg`.
+impl<N: Display, T: Display, W: Display> Display for SrcgRule<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rule = &self.0;
+        let rank = rule.tail.len();
+
+        let arguments: Vec<String> = rule
+            .composition
+            .composition
+            .iter()
+            .map(|component| {
+                if component.is_empty() {
+                    return "ε".to_owned();
+                }
+                let symbols: Vec<String> = component
+                    .iter()
+                    .map(|symbol| match *symbol {
+                        VarT::Var(i, j) => variable_name(rank, i, j),
+                        VarT::T(ref t) => quote(&t.to_string()),
+                    })
+                    .collect();
+                symbols.join(" ")
+            })
+            .collect();
+        write!(
+            f,
+            "{}({}) ← ",
+            nonterminal_token(&rule.head.to_string()),
+            arguments.join(", ")
+        )?;
+
+        if rule.tail.is_empty() {
+            write!(f, "ε")?;
+        } else {
+            // the fanout of each successor is the number of its variables in the composition
+            let mut fanouts = vec![0; rank];
+            for (i, j) in rule.composition.variables() {
+                fanouts[i] = fanouts[i].max(j + 1);
+            }
+            let predicates: Vec<String> = rule
+                .tail
+                .iter()
+                .enumerate()
+                .map(|(i, nonterminal)| {
+                    let variables: Vec<String> =
+                        (0..fanouts[i]).map(|j| variable_name(rank, i, j)).collect();
+                    format!(
+                        "{}({})",
+                        nonterminal_token(&nonterminal.to_string()),
+                        variables.join(", ")
+                    )
+                })
+                .collect();
+            write!(f, "{}", predicates.join(" "))?;
+        }
+
+        write!(f, "  # {}", rule.weight)
+    }
+}
+
+impl<N: Display + Clone, T: Display + Clone, W: Display + Clone> Display for SrcgGrammar<N, T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let initial: Vec<String> = self.0.initial.iter().map(|n| quote(&n.to_string())).collect();
+        writeln!(f, "initial: [{}]\n", initial.join(", "))?;
+        for rule in &self.0.rules {
+            writeln!(f, "{}", SrcgRule(rule.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srcg(s: &str) -> Result<PMCFGRule<String, String, f64>, ParseError> {
+        s.parse().map(|SrcgRule(rule)| rule)
+    }
+
+    fn rule(s: &str) -> PMCFGRule<String, String, f64> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_clauses() {
+        let clauses = vec![
+            (
+                "S(x₁ y₁ x₂ y₂) ← A(x₁, x₂) B(y₁, y₂)",
+                "S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)",
+            ),
+            (
+                "A(\"a\" x1, \"c\"x2) <- A(x1, x2) # 0.5 % comment",
+                "A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5",
+            ),
+            ("A(ε, ε) ← ε # 0.5", "A → [[], []] () # 0.5"),
+            ("\"A B\"(\"(\") ←", "\"A B\" → [[T \"(\"]] ()"),
+            (
+                "VP|<A,B>_2(y₁, x₁) ← A(x₁) B(y₁)",
+                "\"VP|<A,B>_2\" → [[Var 1 0], [Var 0 0]] (A, B)",
+            ),
+        ];
+        for (clause, control) in clauses {
+            let parsed = srcg(clause).unwrap();
+            assert_eq!(parsed, rule(control), "{}", clause);
+            assert_eq!(parsed.weight, rule(control).weight, "{}", clause);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors = vec![
+            ("S(x y) ← A(x)", "line 1, column 5", "'y' is not bound"),
+            ("S(x x) ← A(x)", "line 1, column 5", "not linear"),
+            ("S(x) ← A(x, y)", "line 1, column 13", "'y' does not occur"),
+            ("S(y) ← A(x) B(y, z)", "line 1, column 10", "'x' does not occur"),
+            ("S(x y) ← A(x) B(x)", "line 1, column 17", "'x' is bound more than once"),
+            ("S(x) ← A(\"a\")", "line 1, column 10", "must be variables"),
+            ("S(x) A(x)", "line 1, column 6", "expected '←'"),
+            ("S(x ← A(x)", "line 1, column 11", "expected '←'"),
+        ];
+        for (clause, column, message) in errors {
+            let error = srcg(clause).unwrap_err().to_string();
+            assert!(error.starts_with(column), "{}: {}", clause, error);
+            assert!(error.contains(message), "{}: {}", clause, error);
+        }
+    }
+
+    #[test]
+    fn pretty_print() {
+        let rules = vec![
+            "S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)",
+            "A → [[T a, Var 0 0], [T c, Var 0 1]] (A) # 0.5",
+            "A → [[], []] () # 0.5",
+            "\"A B\" → [[T \"a b\"]] ()",
+        ];
+        let printed = vec![
+            "S(x₁ y₁ x₂ y₂) ← A(x₁, x₂) B(y₁, y₂)  # 1",
+            "A(\"a\" x₁, \"c\" x₂) ← A(x₁, x₂)  # 0.5",
+            "A(ε, ε) ← ε  # 0.5",
+            "\"A B\"(\"a b\") ← ε  # 1",
+        ];
+        for (r, p) in rules.into_iter().zip(printed) {
+            let r = rule(r);
+            assert_eq!(SrcgRule(r.clone()).to_string(), p);
+            assert_eq!(srcg(p).unwrap(), r);
+        }
+    }
+
+    #[test]
+    fn deleting_rules() {
+        assert_eq!(
+            SrcgRule(rule("S → [[Var 0 1]] (A)")).to_string(),
+            "S(x₂) ← A(x₁, x₂)  # 1"
+        );
+
+        let grammar: PMCFG<String, String, f64> = "initial: [S]\n\
+                                                    S → [[Var 0 0, Var 1 0]] (A, B)\n\
+                                                    A → [[T a], [T b]] ()\n\
+                                                    B → [[Var 0 0]] (A)\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            SrcgGrammar::from_pmcfg(grammar.clone()),
+            Err(ToSrcgError::Deleting(0))
+        );
+
+        let mut rules = grammar.rules;
+        rules[0] = rule("S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, C)");
+        rules[2] = rule("C → [[Var 0 1, Var 0 0]] (A)");
+        let grammar = PMCFG {
+            initial: grammar.initial,
+            rules,
+        };
+        let srcg = SrcgGrammar::from_pmcfg(grammar.clone()).unwrap();
+        let reparsed: SrcgGrammar<String, String, f64> = srcg.to_string().parse().unwrap();
+        assert_eq!(reparsed.0, grammar);
+    }
+
+    #[test]
+    fn grammar() {
+        let grammar: SrcgGrammar<String, String, f64> = "initial: [S]\n\
+                                                         \n\
+                                                         % a comment\n\
+                                                         S(x₁ y₁) ← A(x₁) A(y₁)\n\
+                                                         A(\"a\") ← ε # 0.5\n"
+            .parse()
+            .unwrap();
+        assert_eq!(grammar.0.initial, vec!["S".to_owned()]);
+        assert_eq!(grammar.0.rules.len(), 2);
+
+        let reparsed: SrcgGrammar<String, String, f64> = grammar.to_string().parse().unwrap();
+        assert_eq!(reparsed, grammar);
+    }
+}
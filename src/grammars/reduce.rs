@@ -0,0 +1,329 @@
+//! Removes useless nonterminals, i.e. those that are not *productive* (they do not derive any
+//! terminal string) or not *reachable* (they do not occur in any derivation from an initial
+//! nonterminal), and all rules that contain them.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+use crate::grammars::cfg::{CFGRule, LetterT, CFG};
+use crate::grammars::pmcfg::{PMCFGRule, PMCFG};
+
+/// Describes what was removed from a grammar during its reduction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reduction<N> {
+    /// Nonterminals that do not derive any terminal string.
+    pub unproductive: Vec<N>,
+    /// Productive nonterminals that do not occur in any derivation from an initial nonterminal.
+    pub unreachable: Vec<N>,
+    /// Indices of the removed rules in the original grammar.
+    pub removed_rules: Vec<usize>,
+}
+
+impl<N> Reduction<N> {
+    /// Is `true` iff the grammar was already reduced.
+    pub fn is_empty(&self) -> bool {
+        self.removed_rules.is_empty() && self.unproductive.is_empty() && self.unreachable.is_empty()
+    }
+}
+
+impl<N: Display> Display for Reduction<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn list<N: Display>(nonterminals: &[N]) -> String {
+            if nonterminals.is_empty() {
+                "none".to_owned()
+            } else {
+                nonterminals
+                    .iter()
+                    .map(|nt| nt.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        }
+
+        writeln!(f, "removed rules: {}", self.removed_rules.len())?;
+        writeln!(f, "unproductive nonterminals: {}", list(&self.unproductive))?;
+        write!(f, "unreachable nonterminals: {}", list(&self.unreachable))
+    }
+}
+
+impl<N, T, W> PMCFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Removes all useless nonterminals and the rules they occur in, as well as unproductive
+    /// initial nonterminals.
+    pub fn reduce(self) -> (Self, Reduction<N>) {
+        let PMCFG { initial, rules } = self;
+        let (rules, reduction) = reduce_pmcfg_rules(rules, &initial);
+        let initial = initial
+            .into_iter()
+            .filter(|nt| !reduction.unproductive.contains(nt))
+            .collect();
+        (PMCFG { initial, rules }, reduction)
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Removes all useless nonterminals and the rules they occur in, as well as unproductive
+    /// initial nonterminals.
+    pub fn reduce(self) -> (Self, Reduction<N>) {
+        let CFG { initial, rules } = self;
+        let (rules, reduction) = reduce_rules(rules, &initial, cfg_symbols);
+        let initial = initial
+            .into_iter()
+            .filter(|nt| !reduction.unproductive.contains(nt))
+            .collect();
+        (CFG { initial, rules }, reduction)
+    }
+}
+
+/// Reduces a list of PMCFG rules with respect to the given initial nonterminals.
+pub(crate) fn reduce_pmcfg_rules<N, T, W>(
+    rules: Vec<PMCFGRule<N, T, W>>,
+    initials: &[N],
+) -> (Vec<PMCFGRule<N, T, W>>, Reduction<N>)
+where
+    N: Clone + Eq + Hash,
+{
+    reduce_rules(rules, initials, pmcfg_symbols)
+}
+
+pub(crate) fn pmcfg_symbols<N, T, W>(rule: &PMCFGRule<N, T, W>) -> (&N, Vec<&N>) {
+    (&rule.head, rule.tail.iter().collect())
+}
+
+pub(crate) fn cfg_symbols<N, T, W>(rule: &CFGRule<N, T, W>) -> (&N, Vec<&N>) {
+    let tail = rule
+        .composition
+        .composition
+        .iter()
+        .filter_map(|letter| match *letter {
+            LetterT::Label(ref nt) => Some(nt),
+            LetterT::Value(_) => None,
+        })
+        .collect();
+    (&rule.head, tail)
+}
+
+/// Integerises a nonterminal; new nonterminals are appended to `names`.
+pub(crate) fn integerise<'a, N: Eq + Hash>(
+    nonterminal: &'a N,
+    map: &mut HashMap<&'a N, usize>,
+    names: &mut Vec<&'a N>,
+) -> usize {
+    *map.entry(nonterminal).or_insert_with(|| {
+        names.push(nonterminal);
+        names.len() - 1
+    })
+}
+
+/// Keeps the rules whose nonterminals are all productive and reachable; `symbols` yields the
+/// left-hand side nonterminal and the right-hand side nonterminals of a rule.
+fn reduce_rules<N, R, F>(rules: Vec<R>, initials: &[N], symbols: F) -> (Vec<R>, Reduction<N>)
+where
+    N: Clone + Eq + Hash,
+    F: Fn(&R) -> (&N, Vec<&N>),
+{
+    let (useful, reduction) = {
+        let mut map = HashMap::new();
+        let mut names = Vec::new();
+        let initials: Vec<usize> = initials
+            .iter()
+            .map(|nt| integerise(nt, &mut map, &mut names))
+            .collect();
+        let mut indexed: Vec<(usize, Vec<usize>)> = Vec::with_capacity(rules.len());
+        for rule in &rules {
+            let (head, tail) = symbols(rule);
+            let head = integerise(head, &mut map, &mut names);
+            let tail = tail
+                .into_iter()
+                .map(|nt| integerise(nt, &mut map, &mut names))
+                .collect();
+            indexed.push((head, tail));
+        }
+
+        // A nonterminal is productive if it is the head of a rule whose successors are
+        // productive; each rule counts its successors that are not known to be productive.
+        let mut occurrences: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        let mut missing: Vec<usize> = Vec::with_capacity(indexed.len());
+        for (r, &(_, ref tail)) in indexed.iter().enumerate() {
+            for &nt in tail {
+                occurrences[nt].push(r);
+            }
+            missing.push(tail.len());
+        }
+        let mut productive = vec![false; names.len()];
+        let mut agenda = Vec::new();
+        for &(head, ref tail) in &indexed {
+            if tail.is_empty() && !productive[head] {
+                productive[head] = true;
+                agenda.push(head);
+            }
+        }
+        while let Some(nt) = agenda.pop() {
+            for &r in &occurrences[nt] {
+                missing[r] -= 1;
+                let head = indexed[r].0;
+                if missing[r] == 0 && !productive[head] {
+                    productive[head] = true;
+                    agenda.push(head);
+                }
+            }
+        }
+
+        let productive_rule: Vec<bool> = indexed
+            .iter()
+            .map(|&(head, ref tail)| productive[head] && tail.iter().all(|&nt| productive[nt]))
+            .collect();
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        for (&(head, ref tail), _) in indexed
+            .iter()
+            .zip(&productive_rule)
+            .filter(|&(_, &p)| p)
+        {
+            successors[head].extend(tail);
+        }
+        let mut reachable = vec![false; names.len()];
+        let mut agenda = Vec::new();
+        for nt in initials {
+            if productive[nt] && !reachable[nt] {
+                reachable[nt] = true;
+                agenda.push(nt);
+            }
+        }
+        while let Some(nt) = agenda.pop() {
+            for &successor in &successors[nt] {
+                if !reachable[successor] {
+                    reachable[successor] = true;
+                    agenda.push(successor);
+                }
+            }
+        }
+
+        let useful: Vec<bool> = indexed
+            .iter()
+            .zip(productive_rule)
+            .map(|(&(head, _), p)| p && reachable[head])
+            .collect();
+        let reduction = Reduction {
+            unproductive: (0..names.len())
+                .filter(|&nt| !productive[nt])
+                .map(|nt| names[nt].clone())
+                .collect(),
+            unreachable: (0..names.len())
+                .filter(|&nt| productive[nt] && !reachable[nt])
+                .map(|nt| names[nt].clone())
+                .collect(),
+            removed_rules: (0..useful.len()).filter(|&r| !useful[r]).collect(),
+        };
+        (useful, reduction)
+    };
+
+    let rules = rules
+        .into_iter()
+        .zip(useful)
+        .filter_map(|(rule, u)| if u { Some(rule) } else { None })
+        .collect();
+    (rules, reduction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pmcfg() {
+        let grammar: PMCFG<String, String, usize> = "initial: [S, X]\n\
+                                                     S → [[Var 0 0, Var 1 0]] (A, B)\n\
+                                                     S → [[Var 0 0, Var 1 0]] (A, C)\n\
+                                                     A → [[T a, Var 0 0]] (A)\n\
+                                                     A → [[]] ()\n\
+                                                     B → [[T b]] ()\n\
+                                                     C → [[T c, Var 0 0]] (C)\n\
+                                                     D → [[T d]] ()\n\
+                                                     X → [[Var 0 0]] (X)"
+            .parse()
+            .unwrap();
+        let (reduced, reduction) = grammar.clone().reduce();
+
+        assert_eq!(reduced.initial, vec!["S".to_owned()]);
+        assert_eq!(
+            reduced.rules,
+            vec![
+                grammar.rules[0].clone(),
+                grammar.rules[2].clone(),
+                grammar.rules[3].clone(),
+                grammar.rules[4].clone(),
+            ]
+        );
+        assert_eq!(
+            reduction,
+            Reduction {
+                unproductive: vec!["X".to_owned(), "C".to_owned()],
+                unreachable: vec!["D".to_owned()],
+                removed_rules: vec![1, 5, 6, 7],
+            }
+        );
+
+        let (again, reduction) = reduced.clone().reduce();
+        assert_eq!(again, reduced);
+        assert!(reduction.is_empty());
+    }
+
+    #[test]
+    fn cfg() {
+        let grammar: CFG<char, char, usize> = "initial: [S]\n\
+                                               S → [T a, Nt S, T b]\n\
+                                               S → [Nt A]\n\
+                                               S → []\n\
+                                               A → [Nt A, T a]\n\
+                                               B → [T b]"
+            .parse()
+            .unwrap();
+        let (reduced, reduction) = grammar.clone().reduce();
+
+        assert_eq!(
+            reduced.rules,
+            vec![grammar.rules[0].clone(), grammar.rules[2].clone()]
+        );
+        assert_eq!(reduction.unproductive, vec!['A']);
+        assert_eq!(reduction.unreachable, vec!['B']);
+    }
+
+    #[test]
+    fn repeated_heads() {
+        let grammar: PMCFG<String, String, usize> = "initial: [S]\n\
+                                                     S → [[Var 0 0, Var 1 0]] (A, B)\n\
+                                                     S → [[Var 0 0, Var 1 0]] (B, B)\n\
+                                                     A → [[T a]] ()\n\
+                                                     A → [[T b]] ()\n\
+                                                     B → [[T c]] ()\n\
+                                                     B → [[Var 0 0]] (A)"
+            .parse()
+            .unwrap();
+        let (reduced, reduction) = grammar.clone().reduce();
+
+        assert_eq!(reduced, grammar);
+        assert!(reduction.is_empty());
+
+        let grammar: CFG<char, char, usize> = "initial: [S]\n\
+                                               S → [Nt A, Nt B]\n\
+                                               A → [T a]\n\
+                                               A → [T b]\n\
+                                               A → [Nt A, Nt A]\n\
+                                               B → [T c]\n\
+                                               B → [Nt C]\n\
+                                               C → [Nt C]"
+            .parse()
+            .unwrap();
+        let (reduced, reduction) = grammar.clone().reduce();
+
+        assert_eq!(reduced.rules, grammar.rules[..5].to_vec());
+        assert_eq!(reduction.unproductive, vec!['C']);
+        assert!(reduction.unreachable.is_empty());
+    }
+}
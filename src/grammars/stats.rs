@@ -0,0 +1,395 @@
+//! Figures and structural properties of grammars, e.g. to get an overview of a grammar that
+//! was extracted from a treebank.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+use crate::grammars::cfg::elimination::to_pmcfg_rule;
+use crate::grammars::cfg::CFG;
+use crate::grammars::lcfrs::read_fanouts;
+use crate::grammars::pmcfg::{PMCFGRule, VarT, PMCFG};
+
+/// Figures and properties of a grammar.
+/// A CFG is treated like a PMCFG of fanout 1.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GrammarStats<N> {
+    pub rules: usize,
+    pub nonterminals: usize,
+    pub initial_nonterminals: usize,
+    pub terminals: usize,
+    /// The number of rules that contain a terminal.
+    pub lexical_rules: usize,
+    /// The greatest number of components of a nonterminal.
+    pub fanout: usize,
+    /// The greatest number of successors of a rule.
+    pub rank: usize,
+    /// The greatest number of gaps between the components of a nonterminal, i.e. `fanout - 1`.
+    pub gap_degree: usize,
+    /// The number of rules for each nonterminal that is the head of a rule, most rules first.
+    pub rules_per_head: Vec<(N, usize)>,
+    /// Is `true` iff all rules with the same head have the same number of components, and
+    /// each variable refers to a successor of its rule.
+    pub consistent_fanouts: bool,
+    /// Is `true` iff no rule uses a component of a successor more than once.
+    pub linear: bool,
+    /// Is `true` iff each rule uses each component of its successors.
+    pub non_deleting: bool,
+    /// Is `true` iff the composition of each rule is monotone, see `Composition::is_monotone`.
+    pub monotone: bool,
+    /// Is `true` iff the composition of each rule is well-nested, see
+    /// `Composition::is_well_nested`.
+    pub well_nested: bool,
+}
+
+impl<N> GrammarStats<N> {
+    /// The least, mean and greatest number of rules per head.
+    pub fn rules_per_head_summary(&self) -> (usize, f64, usize) {
+        let counts = self.rules_per_head.iter().map(|&(_, count)| count);
+        let min = counts.clone().min().unwrap_or(0);
+        let max = counts.max().unwrap_or(0);
+        let mean = if self.rules_per_head.is_empty() {
+            0.0
+        } else {
+            self.rules as f64 / self.rules_per_head.len() as f64
+        };
+        (min, mean, max)
+    }
+}
+
+impl<N> Display for GrammarStats<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, mean, max) = self.rules_per_head_summary();
+        writeln!(f, "{:<24}{}", "rules", self.rules)?;
+        writeln!(f, "{:<24}{}", "nonterminals", self.nonterminals)?;
+        writeln!(
+            f,
+            "{:<24}{}",
+            "initial nonterminals", self.initial_nonterminals
+        )?;
+        writeln!(f, "{:<24}{}", "terminals", self.terminals)?;
+        writeln!(f, "{:<24}{}", "lexical rules", self.lexical_rules)?;
+        writeln!(f, "{:<24}{}", "fanout", self.fanout)?;
+        writeln!(f, "{:<24}{}", "rank", self.rank)?;
+        writeln!(f, "{:<24}{}", "gap degree", self.gap_degree)?;
+        writeln!(
+            f,
+            "{:<24}min {}, mean {:.2}, max {}",
+            "rules per head", min, mean, max
+        )?;
+        writeln!(f, "{:<24}{}", "consistent fanouts", self.consistent_fanouts)?;
+        writeln!(f, "{:<24}{}", "linear", self.linear)?;
+        writeln!(f, "{:<24}{}", "non-deleting", self.non_deleting)?;
+        writeln!(f, "{:<24}{}", "monotone", self.monotone)?;
+        write!(f, "{:<24}{}", "well-nested", self.well_nested)
+    }
+}
+
+/// How many tokens and types of a corpus are terminals of a grammar.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LexicalCoverage<T> {
+    pub tokens: usize,
+    pub covered_tokens: usize,
+    pub types: usize,
+    pub covered_types: usize,
+    /// The types that are not terminals of the grammar, in the order of their first occurrence.
+    pub unknown: Vec<T>,
+}
+
+impl<T> LexicalCoverage<T> {
+    /// The ratio of covered tokens, or `1` for an empty corpus.
+    pub fn token_ratio(&self) -> f64 {
+        ratio(self.covered_tokens, self.tokens)
+    }
+
+    /// The ratio of covered types, or `1` for an empty corpus.
+    pub fn type_ratio(&self) -> f64 {
+        ratio(self.covered_types, self.types)
+    }
+}
+
+fn ratio(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        covered as f64 / total as f64
+    }
+}
+
+impl<T> Display for LexicalCoverage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24}{} of {} ({:.2}%)",
+            "covered tokens",
+            self.covered_tokens,
+            self.tokens,
+            100.0 * self.token_ratio()
+        )?;
+        write!(
+            f,
+            "{:<24}{} of {} ({:.2}%)",
+            "covered types",
+            self.covered_types,
+            self.types,
+            100.0 * self.type_ratio()
+        )
+    }
+}
+
+impl<N, T, W> PMCFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Eq + Hash,
+{
+    pub fn stats(&self) -> GrammarStats<N> {
+        pmcfg_stats(&self.rules, &self.initial)
+    }
+
+    /// Counts the tokens of a corpus that are terminals of the grammar.
+    pub fn lexical_coverage<'a, I>(&self, corpus: I) -> LexicalCoverage<T>
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a + Clone,
+    {
+        lexical_coverage(pmcfg_terminals(&self.rules), corpus)
+    }
+}
+
+impl<N, T, W> CFG<N, T, W>
+where
+    N: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    W: Clone,
+{
+    pub fn stats(&self) -> GrammarStats<N> {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect();
+        pmcfg_stats(&rules, &self.initial)
+    }
+
+    /// Counts the tokens of a corpus that are terminals of the grammar.
+    pub fn lexical_coverage<'a, I>(&self, corpus: I) -> LexicalCoverage<T>
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a,
+    {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| to_pmcfg_rule(rule, N::clone))
+            .collect();
+        lexical_coverage(pmcfg_terminals(&rules), corpus)
+    }
+}
+
+/// Computes the `GrammarStats` of a list of PMCFG rules with the given initial nonterminals.
+pub(crate) fn pmcfg_stats<N, T, W>(rules: &[PMCFGRule<N, T, W>], initials: &[N]) -> GrammarStats<N>
+where
+    N: Clone + Eq + Hash,
+    T: Eq + Hash,
+{
+    let mut fanouts: HashMap<&N, usize> = HashMap::new();
+    let mut rules_per_head: Vec<(N, usize)> = Vec::new();
+    let mut head_index: HashMap<&N, usize> = HashMap::new();
+    for rule in rules {
+        let fanout = fanouts.entry(&rule.head).or_insert(0);
+        *fanout = (*fanout).max(rule.composition.len());
+        let index = *head_index.entry(&rule.head).or_insert_with(|| {
+            rules_per_head.push((rule.head.clone(), 0));
+            rules_per_head.len() - 1
+        });
+        rules_per_head[index].1 += 1;
+    }
+    // stable, i.e. heads with the same number of rules keep the order of their first rules
+    rules_per_head.sort_by(|&(_, a), &(_, b)| b.cmp(&a));
+
+    let nonterminals: HashSet<&N> = rules
+        .iter()
+        .flat_map(|rule| Some(&rule.head).into_iter().chain(rule.tail.iter()))
+        .chain(initials.iter())
+        .collect();
+    let fanout = fanouts.values().cloned().max().unwrap_or(0);
+
+    let mut linear = true;
+    let mut non_deleting = true;
+    // whether each variable refers to a successor of its rule
+    let mut bound_variables = true;
+    for rule in rules {
+        let mut occurrences: Vec<Vec<usize>> = rule
+            .tail
+            .iter()
+            .map(|nt| vec![0; fanouts.get(nt).cloned().unwrap_or(0)])
+            .collect();
+        for (i, j) in rule.composition.variables() {
+            if i >= occurrences.len() {
+                bound_variables = false;
+                linear = false;
+                continue;
+            }
+            if occurrences[i].len() <= j {
+                // successor without rules
+                occurrences[i].resize(j + 1, 0);
+            }
+            occurrences[i][j] += 1;
+        }
+        let counts = occurrences.iter().flat_map(|components| components.iter());
+        linear &= counts.clone().all(|&count| count <= 1);
+        non_deleting &= counts.all(|&count| count >= 1);
+    }
+
+    GrammarStats {
+        rules: rules.len(),
+        nonterminals: nonterminals.len(),
+        initial_nonterminals: initials.len(),
+        terminals: pmcfg_terminals(rules).len(),
+        lexical_rules: rules
+            .iter()
+            .filter(|&rule| symbols(rule).any(VarT::is_t))
+            .count(),
+        fanout,
+        rank: rules.iter().map(|rule| rule.tail.len()).max().unwrap_or(0),
+        gap_degree: fanout.saturating_sub(1),
+        rules_per_head,
+        consistent_fanouts: bound_variables && read_fanouts(rules).is_some(),
+        linear,
+        non_deleting,
+        monotone: rules.iter().all(|rule| rule.composition.is_monotone()),
+        well_nested: rules.iter().all(|rule| rule.composition.is_well_nested()),
+    }
+}
+
+fn symbols<'a, N, T, W>(rule: &'a PMCFGRule<N, T, W>) -> impl Iterator<Item = &'a VarT<T>> + 'a {
+    rule.composition
+        .composition
+        .iter()
+        .flat_map(|component| component.iter())
+}
+
+pub(crate) fn pmcfg_terminals<N, T, W>(rules: &[PMCFGRule<N, T, W>]) -> HashSet<&T>
+where
+    T: Eq + Hash,
+{
+    rules
+        .iter()
+        .flat_map(symbols)
+        .filter_map(|symbol| match *symbol {
+            VarT::T(ref t) => Some(t),
+            VarT::Var(_, _) => None,
+        })
+        .collect()
+}
+
+/// Counts the tokens of a corpus that are contained in `terminals`.
+pub(crate) fn lexical_coverage<'a, I, T>(terminals: HashSet<&T>, corpus: I) -> LexicalCoverage<T>
+where
+    I: IntoIterator<Item = &'a T>,
+    T: 'a + Clone + Eq + Hash,
+{
+    let mut coverage = LexicalCoverage {
+        tokens: 0,
+        covered_tokens: 0,
+        types: 0,
+        covered_types: 0,
+        unknown: Vec::new(),
+    };
+    let mut types = HashSet::new();
+    for token in corpus {
+        let known = terminals.contains(token);
+        coverage.tokens += 1;
+        if known {
+            coverage.covered_tokens += 1;
+        }
+        if types.insert(token) {
+            coverage.types += 1;
+            if known {
+                coverage.covered_types += 1;
+            } else {
+                coverage.unknown.push(token.clone());
+            }
+        }
+    }
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn pmcfg() {
+        let grammar: PMCFG<String, char, LogDomain<f64>> = "initial: [S]\n\
+                                                            S → [[Var 0 0, Var 1 0, Var 0 1, Var 1 1]] (A, B)\n\
+                                                            A → [[T a, Var 0 0], [T c, Var 0 1]] (A)\n\
+                                                            A → [[], []] ()\n\
+                                                            B → [[T b, Var 0 0], [T d, Var 0 1]] (B)\n\
+                                                            B → [[], []] ()\n\
+                                                            C → [[Var 0 0, Var 0 0]] (A)"
+            .parse()
+            .unwrap();
+        let stats = grammar.stats();
+
+        assert_eq!(stats.rules, 6);
+        assert_eq!(stats.nonterminals, 4);
+        assert_eq!(stats.terminals, 4);
+        assert_eq!(stats.lexical_rules, 2);
+        assert_eq!(stats.fanout, 2);
+        assert_eq!(stats.rank, 2);
+        assert_eq!(stats.gap_degree, 1);
+        assert_eq!(
+            stats.rules_per_head,
+            vec![
+                (String::from("A"), 2),
+                (String::from("B"), 2),
+                (String::from("S"), 1),
+                (String::from("C"), 1),
+            ]
+        );
+        assert_eq!(stats.rules_per_head_summary(), (1, 1.5, 2));
+        assert!(stats.consistent_fanouts);
+        assert!(!stats.linear);
+        assert!(!stats.non_deleting);
+        assert!(stats.monotone);
+        assert!(!stats.well_nested);
+
+        let coverage = grammar.lexical_coverage(&['a', 'b', 'x', 'a', 'x']);
+        assert_eq!(coverage.covered_tokens, 3);
+        assert_eq!(coverage.covered_types, 2);
+        assert_eq!(coverage.types, 3);
+        assert_eq!(coverage.unknown, vec!['x']);
+        assert!((coverage.token_ratio() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unbound_successor() {
+        let grammar: PMCFG<String, char, LogDomain<f64>> = "initial: [A]\n\
+                                                            A → [[Var 1 0]] (B)\n\
+                                                            B → [[T b]] ()"
+            .parse()
+            .unwrap();
+        let stats = grammar.stats();
+
+        assert_eq!(stats.rules, 2);
+        assert!(!stats.consistent_fanouts);
+        assert!(!stats.linear);
+    }
+
+    #[test]
+    fn cfg() {
+        let grammar: CFG<char, char, LogDomain<f64>> = "initial: [S]\n\
+                                                        S → [T a, Nt S, Nt S]\n\
+                                                        S → [T b]"
+            .parse()
+            .unwrap();
+        let stats = grammar.stats();
+
+        assert_eq!(stats.fanout, 1);
+        assert_eq!(stats.rank, 2);
+        assert_eq!(stats.gap_degree, 0);
+        assert!(stats.linear && stats.non_deleting && stats.monotone && stats.well_nested);
+    }
+}
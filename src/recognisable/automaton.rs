@@ -1,14 +1,20 @@
 use num_traits::One;
 use std::{
-    collections::{BinaryHeap, HashMap},
+    collections::{BTreeMap, BinaryHeap, HashMap},
     hash::Hash,
     ops::MulAssign,
     rc::Rc,
 };
 
+use crate::recognisable::lattice::Lattice;
 use crate::recognisable::{Configuration, Instruction, Item, Transition};
+use crate::util::forest::Forest;
 use crate::util::push_down::Pushdown;
-use search::{agenda::limited_heap::weighted::LimitedHeap, Agenda, Search};
+use crate::util::semiring::Semiring;
+use search::{
+    agenda::{limited_heap::weighted::LimitedHeap, weighted::Weighted},
+    Agenda, Search,
+};
 
 // map from key to transition
 pub type TransitionMap<K, I, T, W> = HashMap<K, BinaryHeap<Transition<I, T, W>>>;
@@ -113,6 +119,8 @@ where
     }
 }
 
+/// Enumerates the accepting runs of `a` on `word` in descending order of their weights
+/// w.r.t. the natural order of the semiring `W`.
 pub fn recognise<'a, A, T, W>(
     a: &'a A,
     word: Vec<T>,
@@ -125,7 +133,7 @@ where
     <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
     T: Clone + Eq + Ord,
     A::TInt: Clone + Eq + Ord + 'a,
-    W: Copy + MulAssign + One + Ord + 'a,
+    W: Semiring + 'a,
 {
     let the_words: Option<_> = word.iter().map(|t| a.terminal_to_int(t)).collect();
 
@@ -171,7 +179,7 @@ where
     <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
     T: Clone + Eq + Ord,
     A::TInt: Clone + Eq + Ord + 'a,
-    W: Copy + MulAssign + One + Ord + 'a,
+    W: Semiring + 'a,
 {
     let the_words: Option<_> = word.iter().map(|t| a.terminal_to_int(t)).collect();
 
@@ -206,3 +214,242 @@ where
     .filter(move |Item(c, _)| a.is_terminal(c))
     .map(move |i| a.item_map(&i))
 }
+
+/// The packed forest of the accepting runs of `a` on `word`: its nodes are the storages of
+/// the configurations, each with the range of the rest of the word, and each hyperedge leads
+/// from a configuration to a successor by a transition.
+/// Terminal configurations have a hyperedge without transition and tail, hence the
+/// derivations of the root are the accepting runs.
+/// Only the configurations of accepting runs are kept; the construction terminates if
+/// finitely many configurations are reachable.
+pub fn forest<A, T, W>(
+    a: &A,
+    word: Vec<T>,
+) -> Forest<<A::I as Instruction>::Storage, Option<Transition<A::I, T, W>>, W>
+where
+    A: Automaton<T, W>,
+    A::I: Clone + Eq + Instruction,
+    <A::I as Instruction>::Storage: Clone + Eq,
+    A::IInt: Ord,
+    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    A::TInt: Clone + Eq + Ord,
+    W: Semiring,
+{
+    let mut forest = Forest::new();
+    let the_word: Vec<A::TInt> = match word.iter().map(|t| a.terminal_to_int(t)).collect() {
+        Some(the_word) => the_word,
+        None => return forest,
+    };
+    let n = the_word.len();
+
+    // configurations and transitions are mapped to the outer representation by `item_map`
+    let outer = |storage, run: Pushdown<Transition<A::IInt, A::TInt, W>>| {
+        a.item_map(&Item(
+            Configuration {
+                word: Vec::new(),
+                storage,
+                weight: W::one(),
+            },
+            run,
+        ))
+    };
+
+    let initial = Configuration {
+        word: the_word,
+        storage: a.initial_int(),
+        weight: W::one(),
+    };
+    let storage = outer(initial.storage.clone(), Pushdown::new()).0.storage;
+    let root = forest.add_node(storage, vec![(0, n)]);
+    forest.set_root(root);
+    let mut nodes = BTreeMap::new();
+    nodes.insert((initial.storage.clone(), n), root);
+
+    let trans_map = a.transition_map();
+    let bh = BinaryHeap::new();
+    let mut stack = vec![initial];
+    while let Some(conf) = stack.pop() {
+        let head = nodes[&(conf.storage.clone(), conf.word.len())];
+        if a.is_terminal(&conf) {
+            forest.add_edge(None, W::one(), head, Vec::new());
+        }
+        for r in trans_map.get(A::extract_key(&conf)).unwrap_or(&bh).iter() {
+            for successor in r.apply(&conf) {
+                let key = (successor.storage.clone(), successor.word.len());
+                let tail = match nodes.get(&key) {
+                    Some(&node) => node,
+                    None => {
+                        let range = (n - successor.word.len(), n);
+                        let storage = outer(successor.storage.clone(), Pushdown::new()).0.storage;
+                        let node = forest.add_node(storage, vec![range]);
+                        nodes.insert(key, node);
+                        stack.push(successor);
+                        node
+                    }
+                };
+                let transition = outer(a.initial_int(), Pushdown::new().push(r.clone()))
+                    .1
+                    .peek();
+                forest.add_edge(transition, r.weight, head, vec![tail]);
+            }
+        }
+    }
+
+    forest.prune(W::zero())
+}
+
+/// An item of the search in `recognise_lattice`: an item whose configuration has an empty
+/// word, the current state of the lattice and the arcs of the lattice that were read so far.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct LatticeItem<S, I, T, W>(Item<S, I, T, W>, usize, Pushdown<usize>);
+
+impl<S, I: Instruction<Storage = S>, T, W: Clone> Weighted for LatticeItem<S, I, T, W> {
+    type Weight = W;
+
+    fn get_weight(&self) -> W {
+        self.0.get_weight()
+    }
+}
+
+/// Like `recognise`, but the automaton reads the paths of `lattice` instead of a single word;
+/// each parse is returned with the indices of the arcs of its path.
+pub fn recognise_lattice<'a, A, T, W>(
+    a: &'a A,
+    lattice: &'a Lattice<T, W>,
+) -> impl Iterator<Item = (Item<<A::I as Instruction>::Storage, A::I, T, W>, Vec<usize>)> + 'a
+where
+    A: Automaton<T, W>,
+    A::I: Clone + Eq + Instruction,
+    <A::I as Instruction>::Storage: Clone + Eq,
+    A::IInt: Ord + 'a,
+    <A::IInt as Instruction>::Storage: Clone + Eq + Ord,
+    T: Clone + Eq + Ord,
+    A::TInt: Clone + Eq + Ord + 'a,
+    W: Semiring + 'a,
+{
+    let terminals: Vec<Option<A::TInt>> = lattice
+        .arcs()
+        .iter()
+        .map(|arc| a.terminal_to_int(&arc.terminal))
+        .collect();
+
+    let init_item = LatticeItem(
+        Item(
+            Configuration {
+                word: Vec::new(),
+                storage: a.initial_int(),
+                weight: W::one(),
+            },
+            Pushdown::new(),
+        ),
+        lattice.initial_state(),
+        Pushdown::new(),
+    );
+
+    Search::weighted(
+        vec![init_item],
+        move |LatticeItem(Item(conf, run), state, path)| {
+            let key = A::extract_key(conf);
+            let trans_map = a.transition_map();
+            let bh = BinaryHeap::new();
+            let rules = trans_map.get(key).unwrap_or(&bh);
+            let mut successors = Vec::new();
+            for r in rules.iter() {
+                for (target, arcs, arc_weight) in read_lattice(lattice, &terminals, *state, &r.word)
+                {
+                    for storage in r.instruction.apply(conf.storage.clone()) {
+                        let mut weight = conf.weight;
+                        weight *= r.weight;
+                        weight *= arc_weight;
+                        let path = arcs.iter().fold(path.clone(), |path, &arc| path.push(arc));
+                        successors.push(LatticeItem(
+                            Item(
+                                Configuration {
+                                    word: Vec::new(),
+                                    storage,
+                                    weight,
+                                },
+                                run.clone().push(r.clone()),
+                            ),
+                            target,
+                            path,
+                        ));
+                    }
+                }
+            }
+            successors
+        },
+    )
+    .filter(move |LatticeItem(Item(c, _), state, _)| {
+        *state == lattice.final_state() && a.is_terminal(c)
+    })
+    .map(move |LatticeItem(i, _, path)| (a.item_map(&i), path.into()))
+}
+
+/// All paths of `lattice` from `state` that read `word`, given the internal representation
+/// of the terminal of each arc; returns the target state, the arcs and the weight of each path.
+fn read_lattice<T, TInt, W>(
+    lattice: &Lattice<T, W>,
+    terminals: &[Option<TInt>],
+    state: usize,
+    word: &[TInt],
+) -> Vec<(usize, Vec<usize>, W)>
+where
+    TInt: PartialEq,
+    W: Copy + MulAssign + One,
+{
+    let mut paths = vec![(state, Vec::new(), W::one())];
+    for t in word {
+        let mut next_paths = Vec::new();
+        for (state, arcs, weight) in paths {
+            for &arc in lattice.outgoing(state) {
+                if terminals[arc].as_ref() == Some(t) {
+                    let mut arcs = arcs.clone();
+                    arcs.push(arc);
+                    let mut weight = weight;
+                    weight *= lattice.arcs()[arc].weight;
+                    next_paths.push((lattice.arcs()[arc].target, arcs, weight));
+                }
+            }
+        }
+        paths = next_paths;
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::finite_state_automaton::FiniteStateAutomaton;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn run_forest() {
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                            final: [2]\n\
+                                                                            Transition [a] (0 → 1) # 0.5\n\
+                                                                            Transition [a] (0 → 2) # 0.5\n\
+                                                                            Transition [a] (1 → 2) # 0.4\n\
+                                                                            Transition [a] (2 → 2) # 0.6\n\
+                                                                            Transition [b] (1 → 1) # 0.1"
+            .parse()
+            .unwrap();
+
+        // the runs 0 → 2 → 2 and 0 → 1 → 2 share the terminal configuration
+        let runs = forest(&automaton, vec!['a', 'a']);
+        assert_eq!(runs.nodes().len(), 4);
+        assert_eq!(runs.edges().len(), 5);
+        assert_eq!(runs.nodes()[runs.root().unwrap()].label, 0);
+        assert!((runs.total().value() - 0.5).abs() < 1e-9);
+
+        let runs: Vec<_> = runs.kbest().collect();
+        assert_eq!(runs.len(), 2);
+        assert!((runs[0].1.value() - 0.3).abs() < 1e-9);
+        assert_eq!(runs[0].0.len(), 3);
+        assert_eq!(runs[0].0.get(&vec![0, 0]), Some(&&None));
+
+        assert!(forest(&automaton, vec!['b']).root().is_none());
+        assert!(forest(&automaton, vec!['c']).root().is_none());
+    }
+}
@@ -1,12 +1,11 @@
-use num_traits::{One,Zero};
 use std::collections::BinaryHeap;
-use std::ops::{MulAssign,AddAssign};
 use std::rc::Rc;
 use std::hash::Hash;
 
 use crate::approximation::{ApproximationInstance, ApproximationStrategy};
 use crate::recognisable::automaton::Automaton;
 use crate::recognisable::{Instruction, Item, Recognisable};
+use crate::util::semiring::Semiring;
 use search::agenda::weighted::Weighted;
 
 
@@ -25,7 +24,7 @@ where
     Strategy::I1: Instruction,
     Strategy::I2: Instruction,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: Semiring,
 {
     pub recogniser: Rc<Rec>,
     pub sublevel: Rc<SubRec>,
@@ -38,7 +37,7 @@ where
     Strategy: 'a + ApproximationStrategy<T, W>,
     Strategy::I1: Instruction,
     T: 'a + Clone + Eq + Ord + Hash,
-    W: 'a + Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: 'a + Semiring,
 {
     sublevel_parses:
         Box<Iterator<Item = Item<<Strategy::I2 as Instruction>::Storage, Strategy::I2, T, W>> + 'a>,
@@ -54,7 +53,7 @@ where
     Strategy: 'a + ApproximationStrategy<T, W>,
     Strategy::I1: Instruction,
     T: 'a + Clone + Eq + Ord + Hash,
-    W: 'a + Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: 'a + Semiring,
 {
     fn peek_input(
         &mut self,
@@ -89,7 +88,7 @@ where
     Strategy::I1: Instruction + Ord,
     <Strategy::I1 as Instruction>::Storage: Ord,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: Semiring,
 {
     type Item = Item<<Strategy::I1 as Instruction>::Storage, Strategy::I1, T, W>;
 
@@ -127,7 +126,7 @@ where
     Strategy::I1: Instruction + Ord,
     <Strategy::I1 as Instruction>::Storage: Ord,
     T: Clone + Eq + Ord + Hash,
-    W: Clone + MulAssign + One + Ord + Zero + AddAssign,
+    W: Semiring,
 {
     type Parse = Item<<Strategy::I1 as Instruction>::Storage, Strategy::I1, T, W>;
 
@@ -5,7 +5,7 @@ use std::str::{from_utf8, FromStr};
 use std::vec::Vec;
 
 use crate::recognisable::{Instruction, Transition};
-use crate::util::parsing::{parse_comment, parse_token, parse_vec};
+use crate::util::parsing::{parse_comment, parse_line, parse_token, parse_vec, ParseError};
 
 impl<I: Instruction + FromStr, T: FromStr, W: FromStr> FromStr for Transition<I, T, W>
 where
@@ -16,13 +16,14 @@ where
     W: FromStr + One,
     W::Err: Debug,
 {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_transition(s.as_bytes()) {
-            IResult::Done(_, result) => Ok(result),
-            _ => Err(format!("Could not parse: {}", s)),
-        }
+        parse_line(
+            s,
+            parse_transition,
+            "a transition of the form `Transition [a] (instruction) # 0.5`",
+        )
     }
 }
 
@@ -43,8 +44,11 @@ where
             >> take_while!(is_space)
             >> instruction:
                 map_res!(
-                    delimited!(tag!("("), take_until!(")"), tag!(")")),
-                    from_utf8
+                    map_res!(
+                        delimited!(tag!("("), take_until!(")"), tag!(")")),
+                        from_utf8
+                    ),
+                    str::parse
                 )
             >> take_while!(is_space)
             >> weight_o:
@@ -59,7 +63,7 @@ where
             >> (Transition {
                 word: word,
                 weight: weight_o.unwrap_or(W::one()),
-                instruction: instruction.parse().unwrap(),
+                instruction: instruction,
             })
     )
 }
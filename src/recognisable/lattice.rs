@@ -0,0 +1,304 @@
+//! Word lattices, i.e. acyclic weighted automata whose paths are alternative inputs, e.g. the
+//! hypotheses of a speech or OCR system.
+
+use num_traits::One;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use std::hash::Hash;
+use std::ops::Mul;
+
+use crate::automata::finite_state_automaton::FiniteStateAutomaton;
+
+/// An arc of a `Lattice` that reads a single terminal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LatticeArc<T, W> {
+    pub source: usize,
+    pub target: usize,
+    pub terminal: T,
+    pub weight: W,
+}
+
+/// A word lattice whose states are sorted topologically, i.e. `source < target` holds for each
+/// arc; the state `0` is initial and the last state is final.
+/// Paths through the lattice are given as sequences of indices into `arcs()`.
+#[derive(Clone, Debug)]
+pub struct Lattice<T, W> {
+    arcs: Vec<LatticeArc<T, W>>,
+    outgoing: Vec<Vec<usize>>,
+}
+
+/// Reasons why a `FiniteStateAutomaton` is not a `Lattice`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LatticeError {
+    /// A cycle is reachable from the initial state.
+    Cyclic,
+    /// A transition does not read exactly one terminal; contains the length of its word.
+    ArcLength(usize),
+    /// No final state is reachable from the initial state.
+    NoFinalState,
+    /// A reachable final state has outgoing transitions.
+    FinalStateWithSuccessors,
+}
+
+impl Display for LatticeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LatticeError::Cyclic => write!(f, "the automaton is cyclic"),
+            LatticeError::ArcLength(n) => write!(
+                f,
+                "a transition reads {} terminals instead of exactly one",
+                n
+            ),
+            LatticeError::NoFinalState => write!(f, "no final state is reachable"),
+            LatticeError::FinalStateWithSuccessors => {
+                write!(f, "a final state has outgoing transitions")
+            }
+        }
+    }
+}
+
+impl<T, W> Lattice<T, W> {
+    /// The lattice with a single path that reads `word`, with weight one for each arc.
+    pub fn from_word(word: &[T]) -> Self
+    where
+        T: Clone,
+        W: One,
+    {
+        let arcs = word
+            .iter()
+            .enumerate()
+            .map(|(i, t)| LatticeArc {
+                source: i,
+                target: i + 1,
+                terminal: t.clone(),
+                weight: W::one(),
+            })
+            .collect();
+        let mut outgoing: Vec<Vec<usize>> = (0..word.len()).map(|i| vec![i]).collect();
+        outgoing.push(Vec::new());
+        Lattice { arcs, outgoing }
+    }
+
+    /// Reads the part of `automaton` that is reachable from its initial state as a lattice.
+    /// Each transition must read exactly one terminal, the reachable part must be acyclic,
+    /// and the reachable final states must not have outgoing transitions; they are merged
+    /// into the final state of the lattice.
+    pub fn from_automaton(
+        automaton: &FiniteStateAutomaton<usize, T, W>,
+    ) -> Result<Self, LatticeError>
+    where
+        T: Clone + Eq + Hash + Ord,
+        W: Clone + Ord,
+    {
+        let mut successors: HashMap<usize, Vec<(usize, T, W)>> = HashMap::new();
+        for transition in automaton.list_transitions() {
+            if transition.word.len() != 1 {
+                return Err(LatticeError::ArcLength(transition.word.len()));
+            }
+            let terminal = transition.word.into_iter().next().unwrap();
+            successors
+                .entry(*transition.instruction.source_state())
+                .or_insert_with(Vec::new)
+                .push((
+                    *transition.instruction.target_state(),
+                    terminal,
+                    transition.weight,
+                ));
+        }
+        let no_successors = Vec::new();
+        let successors_of = |q: usize| successors.get(&q).unwrap_or(&no_successors);
+
+        // reachable states and their numbers of reachable predecessors
+        let initial = automaton.initial_state();
+        let mut in_degree: HashMap<usize, usize> = HashMap::new();
+        in_degree.insert(initial, 0);
+        let mut stack = vec![initial];
+        let mut reachable = BTreeSet::new();
+        while let Some(q) = stack.pop() {
+            if !reachable.insert(q) {
+                continue;
+            }
+            for &(target, _, _) in successors_of(q) {
+                *in_degree.entry(target).or_insert(0) += 1;
+                stack.push(target);
+            }
+        }
+
+        let finals: BTreeSet<usize> = automaton
+            .final_states()
+            .into_iter()
+            .filter(|q| reachable.contains(q))
+            .collect();
+        if finals.is_empty() {
+            return Err(LatticeError::NoFinalState);
+        }
+        if finals.iter().any(|&q| !successors_of(q).is_empty()) {
+            return Err(LatticeError::FinalStateWithSuccessors);
+        }
+
+        // Kahn's algorithm; the final states are sinks and are numbered last
+        if in_degree[&initial] > 0 {
+            return Err(LatticeError::Cyclic);
+        }
+        let mut numbers: HashMap<usize, usize> = HashMap::new();
+        let mut visited = 0;
+        let mut queue = vec![initial];
+        while let Some(q) = queue.pop() {
+            visited += 1;
+            if !finals.contains(&q) {
+                let number = numbers.len();
+                numbers.insert(q, number);
+            }
+            for &(target, _, _) in successors_of(q) {
+                let degree = in_degree.get_mut(&target).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push(target);
+                }
+            }
+        }
+        if visited < reachable.len() {
+            return Err(LatticeError::Cyclic);
+        }
+        let final_state = numbers.len();
+        for &q in &finals {
+            numbers.insert(q, final_state);
+        }
+
+        let mut arcs = Vec::new();
+        let mut outgoing = vec![Vec::new(); final_state + 1];
+        for &q in &reachable {
+            for &(target, ref terminal, ref weight) in successors_of(q) {
+                outgoing[numbers[&q]].push(arcs.len());
+                arcs.push(LatticeArc {
+                    source: numbers[&q],
+                    target: numbers[&target],
+                    terminal: terminal.clone(),
+                    weight: weight.clone(),
+                });
+            }
+        }
+        Ok(Lattice { arcs, outgoing })
+    }
+
+    pub fn arcs(&self) -> &[LatticeArc<T, W>] {
+        &self.arcs
+    }
+
+    /// The indices of the arcs that leave `state`.
+    pub fn outgoing(&self, state: usize) -> &[usize] {
+        &self.outgoing[state]
+    }
+
+    pub fn initial_state(&self) -> usize {
+        0
+    }
+
+    pub fn final_state(&self) -> usize {
+        self.outgoing.len() - 1
+    }
+
+    /// Is `true` iff each arc leads from a state `i` to the state `i + 1`, i.e. if the states
+    /// are the positions in the words of the lattice, which all have the same length.
+    pub fn is_position_lattice(&self) -> bool {
+        self.arcs.iter().all(|arc| arc.target == arc.source + 1)
+    }
+
+    /// The word read along a path.
+    pub fn word(&self, path: &[usize]) -> Vec<&T> {
+        path.iter().map(|&arc| &self.arcs[arc].terminal).collect()
+    }
+
+    /// The product of the weights of the arcs of a path.
+    pub fn weight(&self, path: &[usize]) -> W
+    where
+        W: Clone + Mul<Output = W> + One,
+    {
+        path.iter().fold(W::one(), |weight, &arc| {
+            weight * self.arcs[arc].weight.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+
+    #[test]
+    fn from_automaton() {
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 7\n\
+                                                                            final: [3, 4]\n\
+                                                                            Transition [a] (7 → 5) # 0.5\n\
+                                                                            Transition [b] (7 → 5) # 0.5\n\
+                                                                            Transition [c] (5 → 3) # 1\n\
+                                                                            Transition [d] (7 → 4) # 0.25\n\
+                                                                            Transition [e] (3 → 8) # 1\n\
+                                                                            Transition [e] (9 → 7) # 1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Lattice::from_automaton(&automaton).err(),
+            Some(LatticeError::FinalStateWithSuccessors)
+        );
+
+        let automaton: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 7\n\
+                                                                            final: [3, 4]\n\
+                                                                            Transition [a] (7 → 5) # 0.5\n\
+                                                                            Transition [b] (7 → 5) # 0.5\n\
+                                                                            Transition [c] (5 → 3) # 1\n\
+                                                                            Transition [d] (7 → 4) # 0.25\n\
+                                                                            Transition [e] (9 → 7) # 1"
+            .parse()
+            .unwrap();
+        let lattice = Lattice::from_automaton(&automaton).unwrap();
+        assert_eq!(lattice.initial_state(), 0);
+        assert_eq!(lattice.final_state(), 2);
+        assert_eq!(lattice.arcs().len(), 4);
+        assert!(lattice.arcs().iter().all(|arc| arc.source < arc.target));
+        assert!(!lattice.is_position_lattice());
+
+        let d = lattice
+            .outgoing(0)
+            .iter()
+            .cloned()
+            .find(|&arc| lattice.arcs()[arc].terminal == 'd')
+            .unwrap();
+        assert_eq!(lattice.arcs()[d].target, lattice.final_state());
+        assert_eq!(lattice.word(&[d]), vec![&'d']);
+        assert_eq!(lattice.weight(&[d]), LogDomain::new(0.25).unwrap());
+
+        let cyclic: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                         final: [2]\n\
+                                                                         Transition [a] (0 → 1) # 1\n\
+                                                                         Transition [b] (1 → 0) # 1\n\
+                                                                         Transition [c] (1 → 2) # 1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Lattice::from_automaton(&cyclic).err(),
+            Some(LatticeError::Cyclic)
+        );
+
+        let long: FiniteStateAutomaton<usize, char, LogDomain<f64>> = "initial: 0\n\
+                                                                       final: [1]\n\
+                                                                       Transition [a, b] (0 → 1) # 1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Lattice::from_automaton(&long).err(),
+            Some(LatticeError::ArcLength(2))
+        );
+    }
+
+    #[test]
+    fn from_word() {
+        let lattice: Lattice<char, LogDomain<f64>> = Lattice::from_word(&['a', 'b']);
+        assert_eq!(lattice.final_state(), 2);
+        assert!(lattice.is_position_lattice());
+        assert_eq!(lattice.word(&[0, 1]), vec![&'a', &'b']);
+
+        let empty: Lattice<char, LogDomain<f64>> = Lattice::from_word(&[]);
+        assert_eq!(empty.final_state(), empty.initial_state());
+    }
+}
@@ -3,9 +3,11 @@ mod transition;
 
 pub mod automaton;
 pub mod from_str;
+pub mod lattice;
 #[macro_use]
 pub mod coarse_to_fine;
 
+use self::lattice::Lattice;
 use crate::util::push_down::Pushdown;
 use search::agenda::weighted::Weighted;
 use std::vec::Vec;
@@ -49,3 +51,14 @@ pub trait Recognisable<T, W> {
         word: Vec<T>,
     ) -> Box<Iterator<Item = Self::Parse> + 'a>;
 }
+
+/// Something that recognises word lattices, see `lattice::Lattice`.
+pub trait LatticeRecognisable<T, W>: Recognisable<T, W> {
+    /// Returns the parses of the paths through `lattice`, each with the indices of the arcs of
+    /// its path (see `Lattice::arcs`); the weights of these arcs are part of the weight of the
+    /// parse.
+    fn recognise_lattice<'a>(
+        &'a self,
+        lattice: &'a Lattice<T, W>,
+    ) -> Box<Iterator<Item = (Self::Parse, Vec<usize>)> + 'a>;
+}
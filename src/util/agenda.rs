@@ -0,0 +1,105 @@
+//! A weighted agenda for `search::Search` that prunes its items like the agendas in
+//! `search::agenda`.
+
+use search::agenda::{
+    binary_heap::weighted::BinaryHeap, limited_heap::weighted::LimitedHeap, weighted::Weighted,
+};
+use search::Agenda;
+use std::ops::Mul;
+
+/// Yields its items in descending order of their weights.
+/// If a `beam` is given, only the `beam` items with the greatest weights are kept (like
+/// `search::agenda::LimitedHeap`); if a `threshold` is given, an item is discarded if its
+/// weight is less than the greatest weight in the agenda times `threshold` (like
+/// `search::agenda::BeamHeap`).
+pub struct PruningAgenda<I: Weighted>
+where
+    I::Weight: Ord,
+{
+    heap: Heap<I>,
+    threshold: Option<I::Weight>,
+}
+
+enum Heap<I: Weighted>
+where
+    I::Weight: Ord,
+{
+    Unlimited(BinaryHeap<I>),
+    Limited(LimitedHeap<I>),
+}
+
+impl<I: Weighted> PruningAgenda<I>
+where
+    I::Weight: Ord,
+{
+    pub fn new(beam: Option<usize>, threshold: Option<I::Weight>) -> Self {
+        let heap = match beam {
+            Some(capacity) => Heap::Limited(LimitedHeap::with_capacity(capacity)),
+            None => Heap::Unlimited(BinaryHeap::new()),
+        };
+        PruningAgenda { heap, threshold }
+    }
+}
+
+impl<I: Weighted> Agenda for PruningAgenda<I>
+where
+    I::Weight: Ord + Mul<Output = I::Weight> + Clone,
+{
+    type Item = I;
+
+    fn push(&mut self, element: I) {
+        if let (Some(threshold), Some(best)) = (self.threshold.as_ref(), self.peek()) {
+            if element.get_weight() < best.get_weight() * threshold.clone() {
+                return;
+            }
+        }
+        match self.heap {
+            Heap::Unlimited(ref mut heap) => heap.push(element),
+            Heap::Limited(ref mut heap) => {
+                heap.push(element);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<I> {
+        match self.heap {
+            Heap::Unlimited(ref mut heap) => heap.pop(),
+            Heap::Limited(ref mut heap) => heap.pop(),
+        }
+    }
+
+    fn peek(&self) -> Option<&I> {
+        match self.heap {
+            Heap::Unlimited(ref heap) => heap.peek(),
+            Heap::Limited(ref heap) => heap.peek(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.heap {
+            Heap::Unlimited(ref heap) => heap.len(),
+            Heap::Limited(ref heap) => heap.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pruning() {
+        let mut agenda = PruningAgenda::new(Some(2), None);
+        agenda.extend(vec![3usize, 1, 4, 2]);
+        assert_eq!(agenda.pop(), Some(4));
+        assert_eq!(agenda.pop(), Some(3));
+        assert_eq!(agenda.pop(), None);
+
+        let mut agenda = PruningAgenda::new(None, Some(1));
+        agenda.extend(vec![3usize, 5, 9, 4, 7]);
+        assert_eq!(agenda.len(), 3);
+        assert_eq!(agenda.pop(), Some(9));
+        assert_eq!(agenda.pop(), Some(5));
+        assert_eq!(agenda.pop(), Some(3));
+    }
+}
@@ -0,0 +1,124 @@
+//! Export of automata and trees to the DOT language of Graphviz, e.g. for
+//! debugging with `dot -Tpdf`.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Something that can be drawn as a directed graph in the DOT language.
+pub trait ToDot {
+    fn to_dot(&self) -> String;
+}
+
+/// A directed graph in the DOT language; identifiers and attribute values are
+/// quoted by the methods that add them.
+#[derive(Clone, Debug, Default)]
+pub struct Digraph {
+    statements: Vec<String>,
+}
+
+impl Digraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute of the graph, e.g. `rankdir=LR`, or default
+    /// attributes for its nodes, e.g. `node [shape=circle]`.
+    pub fn attribute(&mut self, statement: &str) {
+        self.statements.push(statement.to_string());
+    }
+
+    pub fn node(&mut self, id: &str, attributes: &[(&str, &str)]) {
+        self.statements
+            .push(format!("{}{}", quote(id), attribute_list(attributes)));
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attributes: &[(&str, &str)]) {
+        self.statements.push(format!(
+            "{} -> {}{}",
+            quote(from),
+            quote(to),
+            attribute_list(attributes)
+        ));
+    }
+
+    /// Places the given nodes in the same rank (e.g. `same` or `max`) from
+    /// left to right.
+    pub fn rank(&mut self, rank: &str, ids: &[String]) {
+        let ids: Vec<String> = ids.iter().map(|id| quote(id)).collect();
+        let mut statement = format!("{{ rank={}; {};", rank, ids.join("; "));
+        if ids.len() > 1 {
+            statement.push_str(&format!(" {} [style=invis];", ids.join(" -> ")));
+        }
+        statement.push_str(" }");
+        self.statements.push(statement);
+    }
+}
+
+impl Display for Digraph {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "digraph {{")?;
+        for statement in &self.statements {
+            writeln!(f, "  {};", statement)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Quotes a string as an identifier in the DOT language; line breaks are
+/// kept as line breaks of labels.
+pub fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn attribute_list(attributes: &[(&str, &str)]) -> String {
+    if attributes.is_empty() {
+        String::new()
+    } else {
+        let attributes: Vec<String> = attributes
+            .iter()
+            .map(|&(key, value)| format!("{}={}", key, quote(value)))
+            .collect();
+        format!(" [{}]", attributes.join(", "))
+    }
+}
+
+/// The label of a transition that reads `word` with weight `weight`.
+pub fn transition_label<T: Display, W: Display>(word: &[T], weight: &W) -> String {
+    let word: Vec<String> = word.iter().map(ToString::to_string).collect();
+    if word.is_empty() {
+        format!("ε # {}", weight)
+    } else {
+        format!("{} # {}", word.join(" "), weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digraph() {
+        let mut graph = Digraph::new();
+        graph.attribute("rankdir=LR");
+        graph.node("q\"0\"", &[("shape", "doublecircle")]);
+        let label = transition_label(&["a", "b"], &0.5);
+        graph.edge("q\"0\"", "q1", &[("label", label.as_str())]);
+        graph.rank("same", &["q1".to_string(), "q2".to_string()]);
+
+        assert_eq!(
+            graph.to_string(),
+            "digraph {\n\
+             \x20 rankdir=LR;\n\
+             \x20 \"q\\\"0\\\"\" [shape=\"doublecircle\"];\n\
+             \x20 \"q\\\"0\\\"\" -> \"q1\" [label=\"a b # 0.5\"];\n\
+             \x20 { rank=same; \"q1\"; \"q2\"; \"q1\" -> \"q2\" [style=invis]; };\n\
+             }"
+        );
+        assert_eq!(quote("A\nB"), "\"A\\nB\"");
+        assert_eq!(transition_label::<&str, _>(&[], &1), "ε # 1");
+    }
+}
@@ -0,0 +1,571 @@
+//! Packed parse forests, i.e. hypergraphs whose nodes are a nonterminal (or a storage) together
+//! with the ranges of the word that it derives, and whose hyperedges are labelled with the
+//! rules (or transitions) that derive their head from their tail.
+//!
+//! A forest represents all derivations of a parser's search space at once; it can be pruned
+//! w.r.t. its inside and outside weights, and its derivations can be enumerated lazily in
+//! descending order of their weights.
+
+use std::collections::{BinaryHeap, HashSet};
+
+use super::semiring::Semiring;
+use super::tree::GornTree;
+
+/// Maximal number of passes over the nodes for inside and outside weights; more than one pass
+/// is only needed for cyclic forests.
+const MAX_PASSES: usize = 100;
+
+/// A range `(i, j)` of positions of the word, i.e. the symbols at positions `i` to `j - 1`.
+pub type Range = (usize, usize);
+
+/// A node of a forest: a label together with the range of each of its components.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForestNode<N> {
+    pub label: N,
+    pub ranges: Vec<Range>,
+}
+
+/// A hyperedge that derives the node `head` from the nodes in `tail`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hyperedge<L, W> {
+    pub label: L,
+    pub weight: W,
+    pub head: usize,
+    pub tail: Vec<usize>,
+}
+
+/// A packed parse forest; nodes and hyperedges are identified by their index.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Forest<N, L, W> {
+    nodes: Vec<ForestNode<N>>,
+    edges: Vec<Hyperedge<L, W>>,
+    /// the hyperedges with each node as their head
+    incoming: Vec<Vec<usize>>,
+    root: Option<usize>,
+}
+
+impl<N, L, W> Default for Forest<N, L, W> {
+    fn default() -> Self {
+        Forest {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            incoming: Vec::new(),
+            root: None,
+        }
+    }
+}
+
+impl<N, L, W> Forest<N, L, W> {
+    /// An empty forest, i.e. one without root.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node and returns its index; nodes are not deduplicated.
+    pub fn add_node(&mut self, label: N, ranges: Vec<Range>) -> usize {
+        self.nodes.push(ForestNode { label, ranges });
+        self.incoming.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    /// Adds a hyperedge and returns its index.
+    pub fn add_edge(&mut self, label: L, weight: W, head: usize, tail: Vec<usize>) -> usize {
+        assert!(head < self.nodes.len() && tail.iter().all(|&node| node < self.nodes.len()));
+        self.incoming[head].push(self.edges.len());
+        self.edges.push(Hyperedge {
+            label,
+            weight,
+            head,
+            tail,
+        });
+        self.edges.len() - 1
+    }
+
+    pub fn set_root(&mut self, node: usize) {
+        assert!(node < self.nodes.len());
+        self.root = Some(node);
+    }
+
+    /// The node whose derivations are represented by the forest.
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    pub fn nodes(&self) -> &[ForestNode<N>] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Hyperedge<L, W>] {
+        &self.edges
+    }
+
+    /// The indices of the hyperedges with `node` as their head.
+    pub fn incoming(&self, node: usize) -> &[usize] {
+        &self.incoming[node]
+    }
+
+    /// Each node that is reachable from the root occurs after the nodes in its derivations,
+    /// unless they are cyclic.
+    fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<(usize, bool)> = self.root.into_iter().map(|n| (n, false)).collect();
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                order.push(node);
+            } else if !visited[node] {
+                visited[node] = true;
+                stack.push((node, true));
+                for &edge in &self.incoming[node] {
+                    for &child in &self.edges[edge].tail {
+                        if !visited[child] {
+                            stack.push((child, false));
+                        }
+                    }
+                }
+            }
+        }
+        order
+    }
+}
+
+impl<N, L, W: Semiring> Forest<N, L, W> {
+    /// The product of the weight of `edge` and the given weights of its tail.
+    fn product(&self, edge: &Hyperedge<L, W>, weights: &[W]) -> W {
+        edge.tail
+            .iter()
+            .fold(edge.weight, |weight, &node| weight * weights[node])
+    }
+
+    /// The sum of the weights of all derivations of each node.
+    pub fn inside(&self) -> Vec<W> {
+        let order = self.order();
+        let mut inside = vec![W::zero(); self.nodes.len()];
+
+        for _ in 0..MAX_PASSES {
+            let mut changed = false;
+            for &node in &order {
+                let value = self.incoming[node]
+                    .iter()
+                    .map(|&edge| self.product(&self.edges[edge], &inside))
+                    .fold(W::zero(), |sum, weight| sum + weight);
+                if value != inside[node] {
+                    inside[node] = value;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        inside
+    }
+
+    /// The sum of the weights of all contexts of each node in derivations of the root.
+    pub fn outside(&self, inside: &[W]) -> Vec<W> {
+        let order = self.order();
+        let mut parents: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.nodes.len()];
+        for (e, edge) in self.edges.iter().enumerate() {
+            for (k, &child) in edge.tail.iter().enumerate() {
+                parents[child].push((e, k));
+            }
+        }
+        let mut outside = vec![W::zero(); self.nodes.len()];
+
+        for _ in 0..MAX_PASSES {
+            let mut changed = false;
+            for &node in order.iter().rev() {
+                let mut value = if self.root == Some(node) {
+                    W::one()
+                } else {
+                    W::zero()
+                };
+                for &(e, k) in &parents[node] {
+                    let edge = &self.edges[e];
+                    value += edge
+                        .tail
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != k)
+                        .fold(outside[edge.head] * edge.weight, |weight, (_, &child)| {
+                            weight * inside[child]
+                        });
+                }
+                if value != outside[node] {
+                    outside[node] = value;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        outside
+    }
+
+    /// The sum of the weights of all derivations of the root.
+    pub fn total(&self) -> W {
+        self.root.map_or(W::zero(), |root| self.inside()[root])
+    }
+
+    /// Removes each hyperedge whose derivations of the root have a total weight less than
+    /// `threshold` times the total weight of the forest, as well as the nodes and hyperedges
+    /// that do not occur in any of the remaining derivations of the root.
+    /// `prune(W::zero())` only removes the nodes and hyperedges that are useless.
+    pub fn prune(&self, threshold: W) -> Self
+    where
+        N: Clone,
+        L: Clone,
+    {
+        let bound = threshold * self.total();
+        let pruned = self.filter(|weight| weight >= bound);
+        if threshold.is_zero() {
+            pruned
+        } else {
+            // hyperedges may have lost all derivations of their tail
+            pruned.filter(|_| true)
+        }
+    }
+
+    /// Keeps the hyperedges whose derivations of the root have a nonzero total weight that
+    /// satisfies `keep`.
+    fn filter<F>(&self, keep: F) -> Self
+    where
+        N: Clone,
+        L: Clone,
+        F: Fn(W) -> bool,
+    {
+        let inside = self.inside();
+        let outside = self.outside(&inside);
+
+        let mut node_ids = vec![None; self.nodes.len()];
+        let mut forest = Forest::new();
+        for edge in &self.edges {
+            let weight = outside[edge.head] * self.product(edge, &inside);
+            if weight.is_zero() || !keep(weight) {
+                continue;
+            }
+            let mut node_id = |node: usize| {
+                *node_ids[node].get_or_insert_with(|| {
+                    let ForestNode { label, ranges } = self.nodes[node].clone();
+                    forest.add_node(label, ranges)
+                })
+            };
+            let head = node_id(edge.head);
+            let tail = edge.tail.iter().map(|&node| node_id(node)).collect();
+            forest.add_edge(edge.label.clone(), edge.weight, head, tail);
+        }
+        if let Some(root) = self.root.and_then(|root| node_ids[root]) {
+            forest.set_root(root);
+        }
+
+        forest
+    }
+
+    /// Enumerates the derivations of the root together with their weights in descending
+    /// order of their weights (Huang and Chiang 2005, Algorithm 3).
+    /// The weight of a derivation must not be greater than the weights of its
+    /// subderivations.
+    pub fn kbest(&self) -> KBest<N, L, W> {
+        KBest::new(self)
+    }
+}
+
+/// A derivation of a node: a hyperedge together with the rank of the derivation of each of
+/// its tail nodes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Derivation<W> {
+    weight: W,
+    edge: usize,
+    ranks: Vec<usize>,
+}
+
+/// Lazily enumerates the derivations of the root of a forest, see `Forest::kbest`.
+pub struct KBest<'a, N, L, W> {
+    forest: &'a Forest<N, L, W>,
+    /// the best hyperedge of each node and the weight of its best derivation
+    best: Vec<Option<(W, usize)>>,
+    /// the derivations of each node found so far, in descending order of their weights
+    derivations: Vec<Vec<Derivation<W>>>,
+    candidates: Vec<BinaryHeap<Derivation<W>>>,
+    seen: Vec<HashSet<(usize, Vec<usize>)>>,
+    initialized: Vec<bool>,
+    in_progress: Vec<bool>,
+    k: usize,
+}
+
+impl<'a, N, L, W: Semiring> KBest<'a, N, L, W> {
+    fn new(forest: &'a Forest<N, L, W>) -> Self {
+        let nodes = forest.nodes.len();
+        KBest {
+            forest,
+            best: Self::best(forest),
+            derivations: vec![Vec::new(); nodes],
+            candidates: vec![BinaryHeap::new(); nodes],
+            seen: vec![HashSet::new(); nodes],
+            initialized: vec![false; nodes],
+            in_progress: vec![false; nodes],
+            k: 0,
+        }
+    }
+
+    /// The best derivation of each node by Knuth's generalization of Dijkstra's algorithm;
+    /// the best derivations are not cyclic.
+    fn best(forest: &Forest<N, L, W>) -> Vec<Option<(W, usize)>> {
+        let mut parents: Vec<Vec<usize>> = vec![Vec::new(); forest.nodes.len()];
+        let mut pending: Vec<usize> = Vec::with_capacity(forest.edges.len());
+        let mut agenda = BinaryHeap::new();
+        for (e, edge) in forest.edges.iter().enumerate() {
+            for &child in &edge.tail {
+                parents[child].push(e);
+            }
+            pending.push(edge.tail.len());
+            if edge.tail.is_empty() {
+                agenda.push((edge.weight, e));
+            }
+        }
+
+        let mut best: Vec<Option<(W, usize)>> = vec![None; forest.nodes.len()];
+        while let Some((weight, e)) = agenda.pop() {
+            let head = forest.edges[e].head;
+            if best[head].is_some() {
+                continue;
+            }
+            best[head] = Some((weight, e));
+            for &parent in &parents[head] {
+                pending[parent] -= 1;
+                if pending[parent] == 0 {
+                    let edge = &forest.edges[parent];
+                    let weight = edge.tail.iter().fold(edge.weight, |weight, &child| {
+                        weight * best[child].map_or(W::zero(), |(w, _)| w)
+                    });
+                    agenda.push((weight, parent));
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Adds the best derivation of `node` and the best derivation with each other incoming
+    /// hyperedge as candidates.
+    fn initialize(&mut self, node: usize) {
+        self.initialized[node] = true;
+        let (weight, best_edge) = match self.best[node] {
+            Some(best) => best,
+            None => return,
+        };
+        let forest = self.forest;
+        let ranks = vec![0; forest.edges[best_edge].tail.len()];
+        self.seen[node].insert((best_edge, ranks.clone()));
+        self.derivations[node].push(Derivation {
+            weight,
+            edge: best_edge,
+            ranks,
+        });
+
+        for &e in &forest.incoming[node] {
+            let edge = &forest.edges[e];
+            let ranks = vec![0; edge.tail.len()];
+            if e == best_edge {
+                for &child in &edge.tail {
+                    self.kth(child, 0);
+                }
+            } else if let Some(weight) = self.weight(e, &ranks) {
+                self.seen[node].insert((e, ranks.clone()));
+                self.candidates[node].push(Derivation {
+                    weight,
+                    edge: e,
+                    ranks,
+                });
+            }
+        }
+    }
+
+    /// The weight of the derivation with hyperedge `e` and the given ranks of the derivations
+    /// of its tail, if they exist.
+    fn weight(&mut self, e: usize, ranks: &[usize]) -> Option<W> {
+        let forest = self.forest;
+        let edge = &forest.edges[e];
+        let mut weight = edge.weight;
+        for (&child, &rank) in edge.tail.iter().zip(ranks) {
+            weight *= self.kth(child, rank)?;
+        }
+        Some(weight)
+    }
+
+    /// The weight of the `k`-th best derivation of `node`, if it exists.
+    fn kth(&mut self, node: usize, k: usize) -> Option<W> {
+        if !self.initialized[node] {
+            self.initialize(node);
+        }
+        // a cyclic request is not answered
+        if self.in_progress[node] {
+            return self.derivations[node].get(k).map(|d| d.weight);
+        }
+        self.in_progress[node] = true;
+
+        while self.derivations[node].len() <= k {
+            if let Some(last) = self.derivations[node].last().cloned() {
+                self.push_successors(node, &last);
+            }
+            match self.candidates[node].pop() {
+                Some(derivation) => self.derivations[node].push(derivation),
+                None => break,
+            }
+        }
+
+        self.in_progress[node] = false;
+        self.derivations[node].get(k).map(|d| d.weight)
+    }
+
+    /// Adds the derivations that differ from `derivation` in the rank of the derivation of
+    /// one tail node as candidates.
+    fn push_successors(&mut self, node: usize, derivation: &Derivation<W>) {
+        for i in 0..derivation.ranks.len() {
+            let mut ranks = derivation.ranks.clone();
+            ranks[i] += 1;
+            if self.seen[node].contains(&(derivation.edge, ranks.clone())) {
+                continue;
+            }
+            if let Some(weight) = self.weight(derivation.edge, &ranks) {
+                self.seen[node].insert((derivation.edge, ranks.clone()));
+                self.candidates[node].push(Derivation {
+                    weight,
+                    edge: derivation.edge,
+                    ranks,
+                });
+            }
+        }
+    }
+
+    /// Inserts the labels of the `k`-th best derivation of `node` at `address`.
+    fn tree(&self, node: usize, k: usize, address: Vec<usize>, tree: &mut GornTree<&'a L>) {
+        let forest = self.forest;
+        let derivation = &self.derivations[node][k];
+        let edge = &forest.edges[derivation.edge];
+        for (i, (&child, &rank)) in edge.tail.iter().zip(&derivation.ranks).enumerate() {
+            let mut child_address = address.clone();
+            child_address.push(i);
+            self.tree(child, rank, child_address, tree);
+        }
+        tree.insert(address, &edge.label);
+    }
+}
+
+impl<'a, N, L, W: Semiring> Iterator for KBest<'a, N, L, W> {
+    type Item = (GornTree<&'a L>, W);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let root = self.forest.root?;
+        let k = self.k;
+        let weight = self.kth(root, k)?;
+        self.k += 1;
+
+        let mut tree = GornTree::new();
+        self.tree(root, k, Vec::new(), &mut tree);
+        Some((tree, weight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log_domain::LogDomain;
+    use num_traits::Zero;
+
+    fn weight(value: f64) -> LogDomain<f64> {
+        LogDomain::new(value).unwrap()
+    }
+
+    fn approx(a: LogDomain<f64>, b: f64) -> bool {
+        (a.value() - b).abs() < 1e-9
+    }
+
+    /// The forest of "a a a" in the grammar S → S S # 0.3, S → a # 0.6, S → a S # 0.1.
+    fn forest() -> Forest<char, &'static str, LogDomain<f64>> {
+        let mut forest = Forest::new();
+        let mut nodes = Vec::new();
+        for (i, j) in vec![(0, 1), (1, 2), (2, 3), (0, 2), (1, 3), (0, 3)] {
+            nodes.push(forest.add_node('S', vec![(i, j)]));
+        }
+        for &node in &nodes[0..3] {
+            forest.add_edge("S → a", weight(0.6), node, vec![]);
+        }
+        forest.add_edge("S → S S", weight(0.3), nodes[3], vec![nodes[0], nodes[1]]);
+        forest.add_edge("S → a S", weight(0.1), nodes[3], vec![nodes[1]]);
+        forest.add_edge("S → S S", weight(0.3), nodes[4], vec![nodes[1], nodes[2]]);
+        forest.add_edge("S → a S", weight(0.1), nodes[4], vec![nodes[2]]);
+        forest.add_edge("S → S S", weight(0.3), nodes[5], vec![nodes[0], nodes[4]]);
+        forest.add_edge("S → S S", weight(0.3), nodes[5], vec![nodes[3], nodes[2]]);
+        forest.add_edge("S → a S", weight(0.1), nodes[5], vec![nodes[4]]);
+        forest.set_root(nodes[5]);
+        forest
+    }
+
+    #[test]
+    fn inside_outside() {
+        let forest = forest();
+        let inside = forest.inside();
+        // S(0, 2) derives "a a" by 0.3 · 0.6 · 0.6 + 0.1 · 0.6
+        assert!(approx(inside[3], 0.168));
+        let total = 2.0 * 0.3 * 0.6 * 0.168 + 0.1 * 0.168;
+        assert!(approx(forest.total(), total));
+
+        // the root occurs in each derivation; S(0, 1) occurs in S(S(a) S(S(a) S(a))),
+        // S(S(a) S(a S(a))) and S(S(S(a) S(a)) S(a))
+        let outside = forest.outside(&inside);
+        assert!(approx(outside[5], 1.0));
+        assert!(approx(
+            inside[0] * outside[0],
+            0.3 * 0.6 * 0.168 + 0.3 * 0.3 * 0.6 * 0.6 * 0.6
+        ));
+    }
+
+    #[test]
+    fn prune() {
+        let forest = forest();
+        assert_eq!(forest.prune(LogDomain::zero()).edges().len(), 10);
+
+        // S → a S is pruned everywhere, hence so is S(0, 2)
+        let pruned = forest.prune(weight(0.3));
+        assert_eq!(pruned.edges().len(), 5);
+        assert_eq!(pruned.nodes().len(), 5);
+        assert!(approx(pruned.total(), 0.3 * 0.3 * 0.6 * 0.6 * 0.6));
+        assert_eq!(pruned.kbest().count(), 1);
+
+        let mut empty: Forest<char, &str, LogDomain<f64>> = Forest::new();
+        let node = empty.add_node('S', vec![(0, 1)]);
+        empty.set_root(node);
+        assert_eq!(empty.prune(LogDomain::zero()).root(), None);
+    }
+
+    #[test]
+    fn kbest() {
+        let forest = forest();
+        let derivations: Vec<_> = forest.kbest().collect();
+        assert_eq!(derivations.len(), 6);
+        assert!(derivations.windows(2).all(|d| d[0].1 >= d[1].1));
+        assert!(approx(derivations[0].1, 0.3 * 0.3 * 0.6 * 0.6 * 0.6));
+        assert!(approx(derivations[5].1, 0.1 * 0.1 * 0.6));
+        let sum = derivations
+            .iter()
+            .fold(LogDomain::zero(), |sum, &(_, weight)| sum + weight);
+        assert!(approx(sum, forest.total().value()));
+        assert_eq!(derivations[5].0.len(), 3);
+    }
+
+    #[test]
+    fn serialization() {
+        let forest = forest();
+        let json = serde_json::to_string(&forest).unwrap();
+        let deserialized: Forest<char, String, LogDomain<f64>> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.nodes(), forest.nodes());
+        assert_eq!(deserialized.edges().len(), forest.edges().len());
+        assert_eq!(deserialized.incoming(5), forest.incoming(5));
+        assert!(approx(deserialized.total(), forest.total().value()));
+    }
+}
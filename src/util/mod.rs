@@ -1,9 +1,13 @@
+pub mod agenda;
+pub mod dot;
 pub mod factorizable;
+pub mod forest;
 pub mod integerisable;
 pub mod parsing;
 pub mod partition;
 pub mod push_down;
 pub mod reverse;
+pub mod semiring;
 pub mod tree;
 
 use fnv::{FnvHashMap, FnvHashSet};
@@ -1,7 +1,157 @@
 use nom::{is_space, IResult};
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::iter::once;
 use std::str::{from_utf8, FromStr};
 
+/// An error that occurs while reading a grammar, an automaton or an equivalence relation from
+/// its string representation.
+/// Lines and columns are counted from 1; the columns count characters, not bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The text `found` at the given position does not match the syntax, something described
+    /// by `expected` was expected instead.
+    Syntax {
+        line: usize,
+        column: usize,
+        found: String,
+        expected: String,
+    },
+    /// The line is syntactically correct, but the object it describes is malformed at the
+    /// given position (e.g. a symbol that can not be read or an unbound variable).
+    Invalid {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A mandatory declaration (e.g. of the initial nonterminals) is missing.
+    Missing { expected: String },
+    /// The declarations do not fit together (e.g. intersecting equivalence classes).
+    Inconsistent { message: String },
+}
+
+impl ParseError {
+    /// A syntax error in the first line.
+    pub fn syntax<S: Into<String>>(column: usize, found: S, expected: S) -> Self {
+        ParseError::Syntax {
+            line: 1,
+            column,
+            found: found.into(),
+            expected: expected.into(),
+        }
+    }
+
+    /// A malformed object in the first line.
+    pub fn invalid<S: Into<String>>(column: usize, message: S) -> Self {
+        ParseError::Invalid {
+            line: 1,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Relocates an error that occurred while reading a single line, such that it refers to
+    /// the given `line` of a file in which the parsed text started after `offset` characters.
+    pub fn at_line(self, line: usize, offset: usize) -> Self {
+        match self {
+            ParseError::Syntax {
+                column,
+                found,
+                expected,
+                ..
+            } => ParseError::Syntax {
+                line,
+                column: column + offset,
+                found,
+                expected,
+            },
+            ParseError::Invalid {
+                column, message, ..
+            } => ParseError::Invalid {
+                line,
+                column: column + offset,
+                message,
+            },
+            e => e,
+        }
+    }
+
+    /// The line and column the error refers to, if there is one.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            ParseError::Syntax { line, column, .. } | ParseError::Invalid { line, column, .. } => {
+                Some((line, column))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Syntax {
+                line,
+                column,
+                ref found,
+                ref expected,
+            } => {
+                write!(f, "line {}, column {}: expected {}, ", line, column, expected)?;
+                if found.is_empty() {
+                    write!(f, "found end of line")
+                } else {
+                    write!(f, "found '{}'", found)
+                }
+            }
+            ParseError::Invalid {
+                line,
+                column,
+                ref message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            ParseError::Missing { ref expected } => write!(f, "missing {}", expected),
+            ParseError::Inconsistent { ref message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Applies a `parser` to a single line of `input`.
+/// If the parser fails, the error points to the first character that can not be read, i.e.
+/// the end of the shortest prefix of `input` that the parser rejects.
+/// Any remaining input after a successful parse is ignored.
+pub fn parse_line<'a, A, P>(input: &'a str, parser: P, expected: &str) -> Result<A, ParseError>
+where
+    P: Fn(&'a [u8]) -> IResult<&'a [u8], A>,
+{
+    match parser(input.as_bytes()) {
+        IResult::Done(_, result) => Ok(result),
+        IResult::Incomplete(_) => Err(ParseError::syntax(
+            input.chars().count() + 1,
+            "",
+            expected,
+        )),
+        IResult::Error(_) => {
+            let end = input
+                .char_indices()
+                .map(|(i, _)| i)
+                .skip(1)
+                .chain(once(input.len()))
+                .find(|&end| match parser(input[..end].as_bytes()) {
+                    IResult::Error(_) => true,
+                    _ => false,
+                })
+                .unwrap_or_else(|| input.len());
+            let start = input[..end].char_indices().last().map_or(0, |(i, _)| i);
+            Err(ParseError::syntax(
+                input[..start].chars().count() + 1,
+                input[start..].split_whitespace().next().unwrap_or(""),
+                expected,
+            ))
+        }
+    }
+}
+
 /// Parses a token (i.e. a terminal symbol or a non-terminal symbol).
 /// A *token* can be of one of the following two forms:
 ///
@@ -117,30 +267,27 @@ pub fn parse_comment(input: &[u8]) -> IResult<&[u8], ()> {
 /// determined by their type.
 /// If the string contains multiple definitions of initials, then the initials of the grammar are
 /// going to be the union of all defined initials.
-pub fn initial_rule_grammar_from_str<I, R>(s: &str) -> Result<((Vec<I>, Vec<R>)), String>
+pub fn initial_rule_grammar_from_str<I, R>(s: &str) -> Result<((Vec<I>, Vec<R>)), ParseError>
 where
     I: FromStr,
     I::Err: Debug,
-    R: FromStr,
-    String: From<R::Err>,
+    R: FromStr<Err = ParseError>,
 {
-    let mut it = s.lines();
     let mut initial = Vec::new();
     let mut rules: Vec<R> = Vec::new();
 
-    while let Some(l) = it.next() {
+    for (i, l) in s.lines().enumerate() {
+        let offset = l.chars().count() - l.trim_start().chars().count();
         if l.trim_start().starts_with("initial:") {
-            match parse_initials(l.as_bytes()) {
-                IResult::Done(_, mut result) => initial.append(&mut result),
-                _ => {
-                    return Err(format!(
-                        "Malformed declaration of initial nonterminals: \'{}\'",
-                        l
-                    ))
-                }
-            }
-        } else if !l.is_empty() && !l.trim_start().starts_with("%") {
-            rules.push(l.trim().parse()?);
+            let mut result = parse_line(
+                l.trim_start(),
+                parse_initials,
+                "a list of initial nonterminals",
+            )
+            .map_err(|e| e.at_line(i + 1, offset))?;
+            initial.append(&mut result);
+        } else if !l.trim().is_empty() && !l.trim_start().starts_with("%") {
+            rules.push(l.trim().parse().map_err(|e: ParseError| e.at_line(i + 1, offset))?);
         }
     }
 
@@ -349,4 +496,33 @@ pub mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_line_illegal_input() {
+        let illegal_inputs = vec![
+            ("initials: [a]", ParseError::syntax(8, "s:", "initials")),
+            ("initial: [a, b", ParseError::syntax(15, "", "initials")),
+        ];
+
+        for (illegal_input, control_error) in illegal_inputs {
+            assert_eq!(
+                Err(control_error),
+                parse_line(illegal_input, parse_initials::<String>, "initials")
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let error = ParseError::syntax(8, "s:", "initials").at_line(3, 2);
+        assert_eq!(Some((3, 10)), error.position());
+        assert_eq!(
+            "line 3, column 10: expected initials, found 's:'",
+            error.to_string()
+        );
+        assert_eq!(
+            "line 1, column 4: expected ')', found end of line",
+            ParseError::syntax(4, "", "')'").to_string()
+        );
+    }
 }
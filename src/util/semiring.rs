@@ -0,0 +1,276 @@
+//! Semirings for the weights of automata and grammars.
+//!
+//! A `Semiring` uses `+` and `Zero` as its addition, `*` and `One` as its
+//! multiplication, and `Ord` as its natural order, i.e. the order in which
+//! best-first algorithms (such as `recognisable::automaton::recognise` or the
+//! k-best parsing of `lcfrs::csparsing`) enumerate their results.
+//! The same algorithm can thus be used for
+//!
+//! * probabilities (`LogDomain<f64>`, whose addition is log-sum-exp),
+//! * Viterbi probabilities (`Viterbi<LogDomain<f64>>`),
+//! * costs (`Tropical<W>`, i.e. `Viterbi<util::reverse::Reverse<W>>`),
+//! * recognition (`Boolean`), and
+//! * counting (`usize`).
+
+use super::factorizable::Factorizable;
+use super::reverse::Reverse;
+use log_domain::LogDomain;
+use num_traits::{One, Zero};
+use std::cmp::{max, Ordering};
+use std::fmt::{Display, Error, Formatter};
+use std::iter::repeat;
+use std::ops::{Add, AddAssign, Mul, MulAssign};
+use std::str::FromStr;
+
+/// A semiring whose elements are ordered by the natural order of the
+/// semiring, i.e. `a ≤ a + b` for all `a` and `b`.
+pub trait Semiring: Copy + Ord + Zero + One + AddAssign + MulAssign {}
+
+impl Semiring for LogDomain<f64> {}
+
+macro_rules! counting_semiring {
+    ($($t:ty),*) => {
+        $(
+            /// Counts derivations or runs.
+            impl Semiring for $t {}
+        )*
+    };
+}
+
+counting_semiring!(u8, u16, u32, u64, usize);
+
+/// The Viterbi semiring over the weights `W`: the addition yields the greater
+/// of two weights, the multiplication is the one of `W`.
+/// The zero of `W` is the least element, regardless of the order of `W`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viterbi<W>(pub W);
+
+/// The tropical semiring: the addition yields the smaller of two weights, the
+/// multiplication is the one of `W`.
+/// For example, a cost `c` is represented by `LogDomain::new(c.exp())` in
+/// `Tropical<LogDomain<f64>>`, such that costs are summed along derivations
+/// and the derivation with the least cost is the best one.
+pub type Tropical<W> = Viterbi<Reverse<W>>;
+
+impl<W: Zero + Ord> PartialOrd for Viterbi<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<W: Zero + Ord> Ord for Viterbi<W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.0.is_zero(), other.0.is_zero()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self.0.cmp(&other.0),
+        }
+    }
+}
+
+impl<W: Zero + Ord> Add for Viterbi<W> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        max(self, other)
+    }
+}
+
+impl<W: Copy + Zero + Ord> AddAssign for Viterbi<W> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other
+    }
+}
+
+impl<W: Mul<Output = W>> Mul for Viterbi<W> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Viterbi(self.0 * other.0)
+    }
+}
+
+impl<W: MulAssign> MulAssign for Viterbi<W> {
+    fn mul_assign(&mut self, other: Self) {
+        self.0 *= other.0
+    }
+}
+
+impl<W: Zero + Ord> Zero for Viterbi<W> {
+    fn zero() -> Self {
+        Viterbi(W::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl<W: One> One for Viterbi<W> {
+    fn one() -> Self {
+        Viterbi(W::one())
+    }
+}
+
+/// Only the multiplication and the order of `W` are used, hence `W` may be
+/// ordered against its natural order, as in `Tropical<W>`.
+impl<W> Semiring for Viterbi<W> where W: Copy + Ord + Zero + One + Mul<Output = W> + MulAssign {}
+
+impl<W: Display> Display for Viterbi<W> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        self.0.fmt(f)
+    }
+}
+
+impl<W: FromStr> FromStr for Viterbi<W> {
+    type Err = W::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        W::from_str(s).map(Viterbi)
+    }
+}
+
+impl<W: Factorizable> Factorizable for Viterbi<W> {
+    fn factorize(self, n: usize) -> Vec<Self> {
+        self.0.factorize(n).into_iter().map(Viterbi).collect()
+    }
+}
+
+/// The Boolean semiring, i.e. disjunction and conjunction of truth values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Boolean(pub bool);
+
+impl Add for Boolean {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Boolean(self.0 || other.0)
+    }
+}
+
+impl AddAssign for Boolean {
+    fn add_assign(&mut self, other: Self) {
+        self.0 |= other.0
+    }
+}
+
+impl Mul for Boolean {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Boolean(self.0 && other.0)
+    }
+}
+
+impl MulAssign for Boolean {
+    fn mul_assign(&mut self, other: Self) {
+        self.0 &= other.0
+    }
+}
+
+impl Zero for Boolean {
+    fn zero() -> Self {
+        Boolean(false)
+    }
+
+    fn is_zero(&self) -> bool {
+        !self.0
+    }
+}
+
+impl One for Boolean {
+    fn one() -> Self {
+        Boolean(true)
+    }
+}
+
+impl Semiring for Boolean {}
+
+impl Display for Boolean {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Boolean {
+    type Err = <bool as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        bool::from_str(s).map(Boolean)
+    }
+}
+
+impl Factorizable for Boolean {
+    fn factorize(self, n: usize) -> Vec<Self> {
+        repeat(self).take(n).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+
+    fn sum<W: Semiring>(weights: &[W]) -> W {
+        weights.iter().fold(W::zero(), |acc, &w| acc + w)
+    }
+
+    fn product<W: Semiring>(weights: &[W]) -> W {
+        weights.iter().fold(W::one(), |acc, &w| acc * w)
+    }
+
+    /// Checks that `zero` is the least element and `a ≤ a + b` for all elements.
+    fn assert_natural_order<W: Semiring + Debug>(elements: &[W]) {
+        for &a in elements {
+            assert!(W::zero() <= a, "{:?} < zero", a);
+            for &b in elements {
+                assert!(a <= a + b, "{:?} > {:?} + {:?}", a, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn semirings() {
+        let (p, q) = (LogDomain::new(0.25).unwrap(), LogDomain::new(0.5).unwrap());
+        assert!((sum(&[p, p, q]).value() - 1.0).abs() < 1e-9);
+        assert_eq!(sum(&[Viterbi(p), Viterbi(q)]), Viterbi(q));
+        assert_eq!(product(&[Viterbi(p), Viterbi(q)]), Viterbi(p * q));
+
+        // the smaller cost is the better one, and zero is still the least element
+        let (cheap, expensive): (Tropical<_>, Tropical<_>) = (
+            Viterbi(Reverse::from(LogDomain::new(0.25).unwrap())),
+            Viterbi(Reverse::from(LogDomain::new(0.5).unwrap())),
+        );
+        assert!(cheap > expensive);
+        assert_eq!(sum(&[expensive, cheap]), cheap);
+        assert_eq!(sum(&[Tropical::zero(), expensive]), expensive);
+        assert!(Tropical::zero() < expensive);
+        assert!((product(&[cheap, expensive]).0.unwrap().value() - 0.125).abs() < 1e-9);
+
+        assert_eq!(sum(&[Boolean(false), Boolean(true)]), Boolean(true));
+        assert_eq!(product(&[Boolean(false), Boolean(true)]), Boolean::zero());
+        assert!(Boolean::one() > Boolean::zero());
+
+        assert_eq!(sum(&[product(&[2usize, 3]), 1]), 7);
+    }
+
+    #[test]
+    fn natural_order() {
+        let weights: Vec<LogDomain<f64>> = [0.0, 0.25, 0.5, 1.0, 2.0]
+            .iter()
+            .map(|&w| LogDomain::new(w).unwrap())
+            .collect();
+
+        assert_natural_order(&weights);
+        assert_natural_order(&weights.iter().map(|&w| Viterbi(w)).collect::<Vec<_>>());
+        assert_natural_order(
+            &weights
+                .iter()
+                .map(|&w| Viterbi(Reverse::from(w)))
+                .collect::<Vec<Tropical<_>>>(),
+        );
+        assert_natural_order(&[Boolean(false), Boolean(true)]);
+        assert_natural_order(&[0u8, 1, 2, 3]);
+        assert_natural_order(&[0u16, 1, 2, 3]);
+        assert_natural_order(&[0u32, 1, 2, 3]);
+        assert_natural_order(&[0u64, 1, 2, 3]);
+        assert_natural_order(&[0usize, 1, 2, 3]);
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::btree_map;
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
 use std::borrow::Borrow;
+use std::rc::Rc;
 
 /// A tree map where each node has a _Gorn address_, i.e. a sequence of integers that describes the
 /// path one has to follow to get from the root to the particular node.
@@ -97,6 +98,31 @@ impl<'a, V: 'a + Clone> GornTree<&'a V> {
     }
 }
 
+/// A tree of rule indices whose subtrees may be shared with other trees, e.g. by the items of a
+/// chart parser.
+#[derive(Debug)]
+pub(crate) struct SharedTree {
+    pub rule: usize,
+    pub children: Vec<Rc<SharedTree>>,
+}
+
+impl SharedTree {
+    /// Replaces each rule index by the corresponding element of `rules`.
+    pub fn to_gorn_tree<'a, R>(&self, rules: &'a [R]) -> GornTree<&'a R> {
+        let mut tree = GornTree::new();
+        let mut stack = vec![(Vec::new(), self)];
+        while let Some((address, node)) = stack.pop() {
+            for (i, child) in node.children.iter().enumerate() {
+                let mut child_address = address.clone();
+                child_address.push(i);
+                stack.push((child_address, &**child));
+            }
+            tree.insert(address, &rules[node.rule]);
+        }
+        tree
+    }
+}
+
 impl<V> IntoIterator for GornTree<V> {
     type Item = (Vec<usize>, V);
     type IntoIter = btree_map::IntoIter<Vec<usize>, V>;
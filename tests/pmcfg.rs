@@ -12,10 +12,12 @@ use rustomata::approximation::equivalence_classes::EquivalenceRelation;
 use rustomata::approximation::relabel::RlbElement;
 use rustomata::approximation::tts::TTSElement;
 use rustomata::approximation::ApproximationStrategy;
+use rustomata::automata::finite_state_automaton::FiniteStateAutomaton;
 use rustomata::automata::tree_stack_automaton::*;
 use rustomata::grammars::pmcfg::negra::{to_negra, DumpMode};
 use rustomata::grammars::pmcfg::*;
 use rustomata::recognisable::coarse_to_fine::CoarseToFineRecogniser;
+use rustomata::recognisable::lattice::Lattice;
 use rustomata::recognisable::*;
 
 fn pmcfg_from_file(grammar_file_path: &str) -> PMCFG<String, String, LogDomain<f64>> {
@@ -564,3 +566,34 @@ fn test_pmcfg_recognise_illegal_terminal_symbols() {
         assert!(automaton.recognise(illegal_word).next().is_none());
     }
 }
+
+#[test]
+fn test_pmcfg_recognise_lattice() {
+    let automaton = TreeStackAutomaton::from(pmcfg_from_file("examples/example.pmcfg"));
+
+    let word: Vec<_> = String::from("aabccd")
+        .chars()
+        .map(|x| x.to_string())
+        .collect();
+    let lattice = Lattice::from_word(&word);
+    let (parse, path) = automaton.recognise_lattice(&lattice).next().unwrap();
+    assert_eq!(path, (0..6).collect::<Vec<_>>());
+    assert_eq!(Some(parse), automaton.recognise(word).next());
+
+    let fsa: FiniteStateAutomaton<usize, String, LogDomain<f64>> = "initial: 0\n\
+                                                                    final: [4]\n\
+                                                                    Transition [a] (0 → 1) # 1\n\
+                                                                    Transition [b] (1 → 2) # 1\n\
+                                                                    Transition [c] (1 → 2) # 1\n\
+                                                                    Transition [b] (2 → 3) # 1\n\
+                                                                    Transition [c] (2 → 3) # 1\n\
+                                                                    Transition [d] (3 → 4) # 1"
+        .parse()
+        .unwrap();
+    let lattice = Lattice::from_automaton(&fsa).unwrap();
+    let paths: Vec<_> = automaton
+        .recognise_lattice(&lattice)
+        .map(|(_, path)| lattice.word(&path).into_iter().cloned().collect::<String>())
+        .collect();
+    assert_eq!(paths, vec![String::from("abcd")]);
+}